@@ -0,0 +1,241 @@
+//! The diff semantics of lcov-diff without the IO, for `no_std + alloc` targets such as
+//! on-device test harnesses producing lcov-like data.
+//!
+//! The diff of a record keeps what only the first report covers: a count (or a taken branch)
+//! is zeroed where the second report covers the record too. Records of the first report the
+//! second one doesn't have are kept as is, records only the second one has are dropped. The
+//! `lcov-diff` crate diffs `lcov::Report` with the same functions: `diff_line`, `diff_function`,
+//! `taken` and `diff_map` are the whole diff, the `Diff` impls only pass the fields to them.
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use core::fmt;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DiffError {
+    /// The function starts on different lines in the reports.
+    UnmatchedFunctionLine,
+    /// The line has different checksums in the reports.
+    UnmatchedChecksum,
+}
+
+impl fmt::Display for DiffError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiffError::UnmatchedFunctionLine => write!(f, "unmatched function line"),
+            DiffError::UnmatchedChecksum => write!(f, "unmatched checksum"),
+        }
+    }
+}
+
+/// Count of a line or a function in the diff.
+pub fn count(current: u64, baseline: u64) -> u64 {
+    // Only whether the record is covered matters, not the exact count
+    if baseline > 0 {
+        0
+    } else {
+        current
+    }
+}
+
+/// Taken count of a branch in the diff, `None` (`-`) if the baseline took it.
+pub fn taken(current: Option<u64>, baseline: Option<u64>) -> Option<u64> {
+    match baseline {
+        Some(taken) if taken > 0 => None,
+        _ => current,
+    }
+}
+
+/// Fails if both reports have the value and they differ.
+pub fn check_match<T: PartialEq>(
+    current: Option<&T>,
+    baseline: Option<&T>,
+    error: DiffError,
+) -> Result<(), DiffError> {
+    match (current, baseline) {
+        (Some(current), Some(baseline)) if current != baseline => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// Diffs the count of a line, failing if both reports have checksums of it and they differ.
+pub fn diff_line<T: PartialEq>(
+    current_count: &mut u64,
+    current_checksum: Option<&T>,
+    baseline_count: u64,
+    baseline_checksum: Option<&T>,
+) -> Result<(), DiffError> {
+    check_match(
+        current_checksum,
+        baseline_checksum,
+        DiffError::UnmatchedChecksum,
+    )?;
+    *current_count = count(*current_count, baseline_count);
+    Ok(())
+}
+
+/// Diffs the count of a function, failing if it starts on different lines in the reports.
+pub fn diff_function(
+    current_count: &mut u64,
+    current_start_line: Option<u32>,
+    baseline_count: u64,
+    baseline_start_line: Option<u32>,
+) -> Result<(), DiffError> {
+    check_match(
+        current_start_line.as_ref(),
+        baseline_start_line.as_ref(),
+        DiffError::UnmatchedFunctionLine,
+    )?;
+    *current_count = count(*current_count, baseline_count);
+    Ok(())
+}
+
+/// Diffs the records the baseline has too with `diff`, the others are left as is.
+pub fn diff_map<K, V, E, F>(
+    current: &mut BTreeMap<K, V>,
+    baseline: &BTreeMap<K, V>,
+    mut diff: F,
+) -> Result<(), E>
+where
+    K: Ord,
+    F: FnMut(&mut V, &V) -> Result<(), E>,
+{
+    for (key, value) in baseline {
+        if let Some(current) = current.get_mut(key) {
+            diff(current, value)?;
+        }
+    }
+    Ok(())
+}
+
+pub trait Diff {
+    fn diff(&mut self, other: &Self) -> Result<(), DiffError>;
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct LineValue {
+    pub count: u64,
+    pub checksum: Option<String>,
+}
+
+impl Diff for LineValue {
+    fn diff(&mut self, other: &Self) -> Result<(), DiffError> {
+        diff_line(
+            &mut self.count,
+            self.checksum.as_ref(),
+            other.count,
+            other.checksum.as_ref(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct FunctionValue {
+    pub start_line: Option<u32>,
+    pub count: u64,
+}
+
+impl Diff for FunctionValue {
+    fn diff(&mut self, other: &Self) -> Result<(), DiffError> {
+        diff_function(
+            &mut self.count,
+            self.start_line,
+            other.count,
+            other.start_line,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct BranchValue {
+    pub taken: Option<u64>,
+}
+
+impl Diff for BranchValue {
+    fn diff(&mut self, other: &Self) -> Result<(), DiffError> {
+        self.taken = taken(self.taken, other.taken);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct BranchKey {
+    pub line: u32,
+    pub block: u32,
+    pub branch: u32,
+}
+
+/// Records of a source file.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SectionValue {
+    pub lines: BTreeMap<u32, LineValue>,
+    pub functions: BTreeMap<String, FunctionValue>,
+    pub branches: BTreeMap<BranchKey, BranchValue>,
+}
+
+impl Diff for SectionValue {
+    fn diff(&mut self, other: &Self) -> Result<(), DiffError> {
+        self.functions.diff(&other.functions)?;
+        self.branches.diff(&other.branches)?;
+        self.lines.diff(&other.lines)
+    }
+}
+
+impl<K: Ord, V: Diff> Diff for BTreeMap<K, V> {
+    fn diff(&mut self, other: &Self) -> Result<(), DiffError> {
+        diff_map(self, other, V::diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BranchKey, BranchValue, Diff, DiffError, FunctionValue, LineValue, SectionValue};
+    use alloc::string::ToString;
+
+    #[test]
+    fn diff_section() {
+        let line = |count| LineValue {
+            count,
+            checksum: None,
+        };
+        let key = BranchKey {
+            line: 1,
+            block: 0,
+            branch: 0,
+        };
+        let mut first = SectionValue::default();
+        first.lines.insert(1, line(3));
+        first.lines.insert(2, line(1));
+        first.functions.insert(
+            "main".to_string(),
+            FunctionValue {
+                start_line: Some(1),
+                count: 1,
+            },
+        );
+        first.branches.insert(key, BranchValue { taken: Some(2) });
+        let mut second = SectionValue::default();
+        second.lines.insert(1, line(1));
+        second.lines.insert(3, line(1));
+        second.branches.insert(key, BranchValue { taken: Some(1) });
+
+        first.diff(&second).unwrap();
+        assert_eq!(first.lines[&1].count, 0);
+        assert_eq!(first.lines[&2].count, 1);
+        assert!(!first.lines.contains_key(&3));
+        assert_eq!(first.functions["main"].count, 1);
+        assert_eq!(first.branches[&key].taken, None);
+
+        second.functions.insert(
+            "main".to_string(),
+            FunctionValue {
+                start_line: Some(2),
+                count: 0,
+            },
+        );
+        assert_eq!(first.diff(&second), Err(DiffError::UnmatchedFunctionLine));
+    }
+}
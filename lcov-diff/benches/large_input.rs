@@ -0,0 +1,54 @@
+//! Timings of the sequential diff against `DiffOptions::large_input()` on synthetic reports of
+//! 10k and 100k sections, run with `cargo bench -p lcov-diff`. The timings are the medians of
+//! a few runs, measured with `std::time` rather than a benchmark framework.
+use std::time::{Duration, Instant};
+
+use lcov::Report;
+use lcov_diff::builder::ReportBuilder;
+use lcov_diff::{diff_reports, diff_reports_with, DiffOptions};
+
+const LINES: u32 = 20;
+const RUNS: usize = 5;
+
+/// Report of `sections` files, a line out of `stride` uncovered.
+fn synthetic(sections: usize, stride: u32) -> Report {
+    let mut builder = ReportBuilder::file("src/0.c");
+    for section in 0..sections {
+        if section > 0 {
+            builder = builder.next_file(format!("src/{}.c", section));
+        }
+        builder = builder.function("f", 1, 1);
+        for line in 1..=LINES {
+            builder = builder.line(line, u64::from(line % stride != 0));
+        }
+        builder = builder.branch(1, 0, 0, Some(1));
+    }
+    builder.build()
+}
+
+fn median<F: FnMut() -> Report>(mut run: F) -> Duration {
+    let mut timings: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let diff = run();
+            let elapsed = start.elapsed();
+            assert!(!diff.sections.is_empty());
+            elapsed
+        })
+        .collect();
+    timings.sort();
+    timings[RUNS / 2]
+}
+
+fn main() {
+    let options = DiffOptions::large_input();
+    for sections in [10_000, 100_000] {
+        let (current, baseline) = (synthetic(sections, 3), synthetic(sections, 5));
+        let sequential = median(|| diff_reports(&current, &baseline).unwrap());
+        let parallel = median(|| diff_reports_with(&current, &baseline, &options).unwrap());
+        println!(
+            "{} sections: sequential {:?}, large_input ({} threads) {:?}",
+            sections, sequential, options.threads, parallel
+        );
+    }
+}
@@ -0,0 +1,28 @@
+/* C interface of lcov-diff, the `ffi` feature of the library. */
+#ifndef LCOV_DIFF_H
+#define LCOV_DIFF_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Writes the lcov diff of the current report `path_a` against the baseline `path_b` to
+ * `out_path`. `options_json` is a json object of the options or NULL.
+ *
+ * Returns 0 on success, -1 on error and -2 if the diff panicked.
+ */
+int lcov_diff_run(const char *options_json, const char *path_a, const char *path_b,
+                  const char *out_path);
+
+/*
+ * Message of the last failed lcov_diff_run of the thread, NULL after a success. It's valid
+ * until the next call of the thread.
+ */
+const char *lcov_diff_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* LCOV_DIFF_H */
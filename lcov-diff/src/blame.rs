@@ -0,0 +1,200 @@
+//! Attribution of the uncovered new lines to the commits and authors which introduced them,
+//! with `git blame`.
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::summary::Comparison;
+
+/// Last commit of a line.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Blame {
+    pub commit: String,
+    pub author: String,
+    pub email: String,
+    pub summary: String,
+}
+
+/// Uncovered new lines of a commit.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CommitStats {
+    pub commit: String,
+    pub summary: String,
+    pub lines: usize,
+}
+
+/// Uncovered new lines of an author, by commit, the most lines first.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AuthorStats {
+    pub author: String,
+    pub email: String,
+    pub lines: usize,
+    pub commits: Vec<CommitStats>,
+}
+
+/// Blames the lines of the file, the lines git doesn't know (untracked file, lines out of the
+/// file) are missing in the result.
+pub fn blame_lines(repo: &Path, path: &Path, lines: &[u32]) -> io::Result<BTreeMap<u32, Blame>> {
+    let path = path.strip_prefix(repo).unwrap_or(path);
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(repo)
+        .args(["blame", "--line-porcelain"]);
+    for line in lines {
+        command.arg("-L").arg(format!("{},{}", line, line));
+    }
+    let output = command.arg("--").arg(path).stderr(Stdio::null()).output()?;
+    if !output.status.success() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(parse_porcelain(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses `--line-porcelain` output: every line has the full set of headers.
+fn parse_porcelain(output: &str) -> BTreeMap<u32, Blame> {
+    let mut blames = BTreeMap::new();
+    let mut current: Option<(u32, Blame)> = None;
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            if let Some((number, blame)) = current.take() {
+                blames.insert(number, blame);
+            }
+        } else if let Some((_, blame)) = &mut current {
+            if let Some(author) = line.strip_prefix("author ") {
+                blame.author = author.to_owned();
+            } else if let Some(email) = line.strip_prefix("author-mail ") {
+                blame.email = email.trim_matches(|c| c == '<' || c == '>').to_owned();
+            } else if let Some(summary) = line.strip_prefix("summary ") {
+                blame.summary = summary.to_owned();
+            }
+        } else {
+            let mut fields = line.split(' ');
+            let commit = fields.next().unwrap_or_default();
+            let number = fields.nth(1).and_then(|number| number.parse().ok());
+            if let Some(number) = number {
+                let blame = Blame {
+                    commit: commit.to_owned(),
+                    author: String::new(),
+                    email: String::new(),
+                    summary: String::new(),
+                };
+                current = Some((number, blame));
+            }
+        }
+    }
+    blames
+}
+
+/// Blames the uncovered new lines of the comparison and groups them by author and commit.
+pub fn by_author(comparison: &Comparison, repo: &Path) -> io::Result<Vec<AuthorStats>> {
+    let mut authors: BTreeMap<(String, String), BTreeMap<String, CommitStats>> = BTreeMap::new();
+    for (path, lines) in comparison.uncovered_new_lines_by_file() {
+        for blame in blame_lines(repo, path, &lines)?.into_values() {
+            let Blame {
+                commit,
+                author,
+                email,
+                summary,
+            } = blame;
+            authors
+                .entry((author, email))
+                .or_default()
+                .entry(commit.clone())
+                .or_insert_with(|| CommitStats {
+                    commit,
+                    summary,
+                    lines: 0,
+                })
+                .lines += 1;
+        }
+    }
+    let mut stats: Vec<AuthorStats> = authors
+        .into_iter()
+        .map(|((author, email), commits)| {
+            let mut commits: Vec<CommitStats> = commits.into_values().collect();
+            commits.sort_by_key(|commit| std::cmp::Reverse(commit.lines));
+            AuthorStats {
+                author,
+                email,
+                lines: commits.iter().map(|commit| commit.lines).sum(),
+                commits,
+            }
+        })
+        .collect();
+    stats.sort_by_key(|author| std::cmp::Reverse(author.lines));
+    Ok(stats)
+}
+
+/// Markdown report of the uncovered new lines by author and commit.
+pub fn markdown(stats: &[AuthorStats]) -> String {
+    let mut out = String::from("### Uncovered new lines by author\n\n");
+    if stats.is_empty() {
+        out.push_str("No uncovered new lines.\n");
+        return out;
+    }
+    out.push_str("| Author | Commit | Lines |\n|---|---|---:|\n");
+    for author in stats {
+        let _ = writeln!(
+            out,
+            "| **{}** <{}> | | **{}** |",
+            author.author, author.email, author.lines
+        );
+        for commit in &author.commits {
+            let _ = writeln!(
+                out,
+                "| | `{}` {} | {} |",
+                &commit.commit[..commit.commit.len().min(8)],
+                commit.summary.replace('|', "\\|"),
+                commit.lines
+            );
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{markdown, parse_porcelain, AuthorStats, CommitStats};
+
+    #[test]
+    fn porcelain() {
+        let output = "\
+0123456789abcdef0123456789abcdef01234567 3 3 1
+author Jane Doe
+author-mail <jane@example.com>
+author-time 1700000000
+summary Add parser
+filename src/a.c
+\tint x;
+fedcba9876543210fedcba9876543210fedcba98 7 9 1
+author Not Committed Yet
+author-mail <not.committed.yet>
+summary Version of src/a.c from src/a.c
+filename src/a.c
+\treturn x;
+";
+        let blames = parse_porcelain(output);
+        assert_eq!(blames.len(), 2);
+        assert_eq!(blames[&3].author, "Jane Doe");
+        assert_eq!(blames[&3].email, "jane@example.com");
+        assert_eq!(
+            blames[&9].commit,
+            "fedcba9876543210fedcba9876543210fedcba98"
+        );
+
+        let stats = vec![AuthorStats {
+            author: "Jane Doe".to_owned(),
+            email: "jane@example.com".to_owned(),
+            lines: 1,
+            commits: vec![CommitStats {
+                commit: blames[&3].commit.clone(),
+                summary: blames[&3].summary.clone(),
+                lines: 1,
+            }],
+        }];
+        assert!(markdown(&stats).ends_with("| | `01234567` Add parser | 1 |\n"));
+    }
+}
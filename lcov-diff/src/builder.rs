@@ -0,0 +1,143 @@
+//! Programmatic construction of the reports, for the tests and the synthesized reports:
+//!
+//! ```
+//! use lcov_diff::builder::ReportBuilder;
+//!
+//! let report = ReportBuilder::file("a.c")
+//!     .function("main", 1, 3)
+//!     .line(1, 3)
+//!     .line(2, 0)
+//!     .branch(2, 0, 0, Some(1))
+//!     .next_file("b.c")
+//!     .line(1, 1)
+//!     .build();
+//! assert_eq!(report.sections.len(), 2);
+//! ```
+use std::path::PathBuf;
+
+use lcov::report::section::branch::{Key as BranchKey, Value as BranchValue};
+use lcov::report::section::function::{Key as FunctionKey, Value as FunctionValue};
+use lcov::report::section::line::{Key as LineKey, Value as LineValue};
+use lcov::report::section::{Key as SectionKey, Value as SectionValue};
+use lcov::Report;
+
+/// Builds the sections one after another, the records go to the last started one.
+#[derive(Debug, Clone)]
+pub struct ReportBuilder {
+    report: Report,
+    key: SectionKey,
+}
+
+impl ReportBuilder {
+    /// Starts the report with the section of the source file, without a test name.
+    pub fn file<P: Into<PathBuf>>(path: P) -> Self {
+        let key = SectionKey {
+            test_name: String::new(),
+            source_file: path.into(),
+        };
+        let mut report = Report::new();
+        report.sections.insert(key.clone(), SectionValue::default());
+        ReportBuilder { report, key }
+    }
+
+    /// Starts the section of the next source file, with the test name of the current one.
+    pub fn next_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.key = SectionKey {
+            test_name: self.key.test_name.clone(),
+            source_file: path.into(),
+        };
+        self.report.sections.entry(self.key.clone()).or_default();
+        self
+    }
+
+    /// Sets the test name of the current section, it's merged with the section of the test if
+    /// there is one already.
+    pub fn test<S: Into<String>>(mut self, name: S) -> Self {
+        let section = self.report.sections.remove(&self.key).unwrap_or_default();
+        self.key.test_name = name.into();
+        let mut report = Report::new();
+        report.sections.insert(self.key.clone(), section);
+        // Sections of the builder never have conflicting function lines or checksums
+        let _ = self.report.merge(report);
+        self
+    }
+
+    pub fn line(mut self, line: u32, count: u64) -> Self {
+        self.section().lines.insert(
+            LineKey { line },
+            LineValue {
+                count,
+                checksum: None,
+            },
+        );
+        self
+    }
+
+    /// Function starting at `start_line`, executed `count` times.
+    pub fn function<S: Into<String>>(mut self, name: S, start_line: u32, count: u64) -> Self {
+        self.section().functions.insert(
+            FunctionKey { name: name.into() },
+            FunctionValue {
+                start_line: Some(start_line),
+                count,
+            },
+        );
+        self
+    }
+
+    /// Branch of the block, `taken` is `None` when the block was never executed.
+    pub fn branch(mut self, line: u32, block: u32, branch: u32, taken: Option<u64>) -> Self {
+        self.section().branches.insert(
+            BranchKey {
+                line,
+                block,
+                branch,
+            },
+            BranchValue { taken },
+        );
+        self
+    }
+
+    pub fn build(self) -> Report {
+        self.report
+    }
+
+    fn section(&mut self) -> &mut SectionValue {
+        self.report.sections.entry(self.key.clone()).or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReportBuilder;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn build() {
+        let input = "\
+TN:unit
+SF:a.c
+FN:1,main
+FNDA:3,main
+BRDA:2,0,0,-
+DA:1,3
+DA:2,0
+end_of_record
+TN:unit
+SF:b.c
+DA:1,1
+end_of_record
+";
+        let expected = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let report = ReportBuilder::file("a.c")
+            .function("main", 1, 3)
+            .line(1, 3)
+            .line(2, 0)
+            .branch(2, 0, 0, None)
+            .test("unit")
+            .next_file("b.c")
+            .line(1, 1)
+            .build();
+        assert_eq!(report, expected);
+    }
+}
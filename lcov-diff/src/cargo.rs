@@ -0,0 +1,100 @@
+//! Grouping of the source files by the Cargo workspace members, for per-crate summaries
+//! and checks.
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use lcov::Report;
+
+use crate::json;
+use crate::summary::{self, Comparison};
+
+/// Workspace member and the directory of its manifest.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Package {
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+/// Members of the workspace of the directory, from `cargo metadata`.
+pub fn workspace_packages(dir: &Path) -> io::Result<Vec<Package>> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .current_dir(dir)
+        .stderr(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "cargo metadata failed: {}",
+            output.status
+        )));
+    }
+    parse_metadata(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid cargo metadata"))
+}
+
+/// Packages of the `cargo metadata` json.
+pub fn parse_metadata(metadata: &str) -> Option<Vec<Package>> {
+    let metadata = json::parse(metadata).ok()?;
+    metadata
+        .get("packages")?
+        .as_array()?
+        .iter()
+        .map(|package| {
+            let manifest = Path::new(package.get("manifest_path")?.as_str()?);
+            Some(Package {
+                name: package.get("name")?.as_str()?.to_owned(),
+                dir: manifest.parent()?.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Package of the source file, the innermost one for the nested packages.
+pub fn package_of<'a>(packages: &'a [Package], path: &Path) -> Option<&'a Package> {
+    packages
+        .iter()
+        .filter(|package| path.starts_with(&package.dir))
+        .max_by_key(|package| package.dir.components().count())
+}
+
+/// Comparison of every package which has files in either report, ordered by name.
+/// Relative source paths are resolved against `root`.
+pub fn by_package(
+    packages: &[Package],
+    root: &Path,
+    baseline: &Report,
+    current: &Report,
+) -> Vec<(String, Comparison)> {
+    summary::by_group(baseline, current, |path| {
+        package_of(packages, &root.join(path))
+            .map(|package| package.name.clone())
+            .into_iter()
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{by_package, parse_metadata};
+    use lcov::{Reader, Report};
+    use std::path::Path;
+
+    #[test]
+    fn group() {
+        let metadata = r#"{"packages": [
+            {"name": "app", "manifest_path": "/ws/Cargo.toml"},
+            {"name": "core", "manifest_path": "/ws/crates/core/Cargo.toml"}
+        ], "workspace_root": "/ws"}"#;
+        let packages = parse_metadata(metadata).unwrap();
+        assert_eq!(packages[1].dir, Path::new("/ws/crates/core"));
+
+        let input = "SF:src/main.rs\nDA:1,1\nend_of_record\nSF:/ws/crates/core/src/lib.rs\nDA:1,0\nend_of_record\n";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let groups = by_package(&packages, Path::new("/ws"), &Report::new(), &report);
+        let names: Vec<&str> = groups.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["app", "core"]);
+        assert_eq!(groups[1].1.current.lines.hit, 0);
+        assert_eq!(groups[0].1.current.lines.hit, 1);
+    }
+}
@@ -0,0 +1,214 @@
+//! Lines changed since the merge base, from `git diff -U0 -M`, for the patch coverage of the
+//! actually changed lines instead of the newly instrumented ones.
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use lcov::report::section::Key as SectionKey;
+use lcov::Report;
+
+/// Added and modified lines by the new path, relative to the repository root.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Changes {
+    pub lines: BTreeMap<PathBuf, BTreeSet<u32>>,
+    /// Renamed files, old path to new path.
+    pub renames: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Changes {
+    /// Parses the unified diff, the context lines (if any) are not counted as changed.
+    pub fn parse(diff: &str) -> Self {
+        let mut changes = Changes::default();
+        let mut rename_from = None;
+        let mut path: Option<PathBuf> = None;
+        let mut line = 0;
+        // `+++`/`---` are file headers only before the first hunk of the file
+        let mut header = true;
+        for text in diff.lines() {
+            if text.starts_with("diff --git ") {
+                rename_from = None;
+                path = None;
+                header = true;
+            } else if !header {
+                if text.starts_with('+') {
+                    if let Some(path) = &path {
+                        changes.lines.entry(path.clone()).or_default().insert(line);
+                    }
+                    line += 1;
+                } else if text.starts_with(' ') {
+                    line += 1;
+                } else if let Some(hunk) = text.strip_prefix("@@ ") {
+                    line = hunk_start(hunk);
+                }
+            } else if let Some(from) = text.strip_prefix("rename from ") {
+                rename_from = Some(PathBuf::from(from));
+            } else if let Some(to) = text.strip_prefix("rename to ") {
+                if let Some(from) = rename_from.take() {
+                    changes.renames.push((from, PathBuf::from(to)));
+                }
+            } else if let Some(new) = text.strip_prefix("+++ ") {
+                path = new.strip_prefix("b/").map(PathBuf::from);
+            } else if let Some(hunk) = text.strip_prefix("@@ ") {
+                line = hunk_start(hunk);
+                header = false;
+            }
+        }
+        changes
+    }
+
+    /// Changed lines of the report path, which may be absolute or have a prefix.
+    pub fn lines_of(&self, path: &Path) -> Option<&BTreeSet<u32>> {
+        self.lines
+            .iter()
+            .find(|(changed, _)| path.ends_with(changed))
+            .map(|(_, lines)| lines)
+    }
+
+    /// Report (the baseline) with the sections of the renamed files moved to their new paths,
+    /// so a moved file is compared with itself.
+    pub fn follow_renames(&self, report: &Report) -> Report {
+        let mut renamed = Report::new();
+        for (key, section) in &report.sections {
+            let source_file = self
+                .renames
+                .iter()
+                .find_map(|(from, to)| {
+                    if !key.source_file.ends_with(from) {
+                        return None;
+                    }
+                    let prefix = key.source_file.components().count() - from.components().count();
+                    let prefix: PathBuf = key.source_file.components().take(prefix).collect();
+                    Some(prefix.join(to))
+                })
+                .unwrap_or_else(|| key.source_file.clone());
+            let mut single = Report::new();
+            single.sections.insert(
+                SectionKey {
+                    test_name: key.test_name.clone(),
+                    source_file,
+                },
+                section.clone(),
+            );
+            renamed.merge_lossy(single);
+        }
+        renamed
+    }
+
+    /// Changed files, the renamed ones without changes included.
+    pub fn files(&self) -> Vec<PathBuf> {
+        let files: BTreeSet<&PathBuf> = self
+            .lines
+            .keys()
+            .chain(self.renames.iter().map(|(_, to)| to))
+            .collect();
+        files.into_iter().cloned().collect()
+    }
+}
+
+/// First new line of the `-a,b +c,d @@` hunk header.
+fn hunk_start(hunk: &str) -> u32 {
+    hunk.split(' ')
+        .find_map(|range| range.strip_prefix('+'))
+        .and_then(|range| range.split(',').next()?.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Merge base of `HEAD` and `base`.
+pub fn merge_base(repo: &Path, base: &str) -> io::Result<Option<String>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["merge-base", "HEAD", base])
+        .stderr(Stdio::null())
+        .output()?;
+    Ok(if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    } else {
+        None
+    })
+}
+
+/// Changes of the working tree (committed or not) since the merge base of `HEAD` and `base`.
+pub fn since_merge_base(repo: &Path, base: &str) -> io::Result<Changes> {
+    let merge_base = merge_base(repo, base)?
+        .ok_or_else(|| io::Error::other(format!("no merge base of HEAD and {}", base)))?;
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["diff", "-U0", "-M", "--no-color", "--no-ext-diff"])
+        .arg(&merge_base)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git diff {} failed: {}",
+            merge_base, output.status
+        )));
+    }
+    Ok(Changes::parse(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Changes;
+    use lcov::{Reader, Report};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn parse() {
+        let diff = "\
+diff --git a/src/a.c b/src/a.c
+index 1111111..2222222 100644
+--- a/src/a.c
++++ b/src/a.c
+@@ -3 +3,2 @@ int main() {
+-  return 0;
++  int x = f();
++  return x;
+@@ -10,0 +12 @@
++++ counter;
+diff --git a/old.c b/new.c
+similarity index 90%
+rename from old.c
+rename to new.c
+--- a/old.c
++++ b/new.c
+@@ -1 +1 @@
+-a
++b
+diff --git a/gone.c b/gone.c
+deleted file mode 100644
+--- a/gone.c
++++ /dev/null
+@@ -1 +0,0 @@
+-x
+";
+        let changes = Changes::parse(diff);
+        assert_eq!(
+            changes.lines_of(Path::new("/repo/src/a.c")),
+            Some(&vec![3, 4, 12].into_iter().collect())
+        );
+        assert_eq!(
+            changes.renames,
+            vec![(PathBuf::from("old.c"), PathBuf::from("new.c"))]
+        );
+        assert_eq!(
+            changes.files(),
+            vec![PathBuf::from("new.c"), PathBuf::from("src/a.c")]
+        );
+
+        let input =
+            "SF:/repo/old.c\nDA:1,1\nend_of_record\nSF:/repo/src/a.c\nDA:1,1\nend_of_record\n";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let paths: Vec<PathBuf> = changes
+            .follow_renames(&report)
+            .sections
+            .keys()
+            .map(|key| key.source_file.clone())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("/repo/new.c"), PathBuf::from("/repo/src/a.c")]
+        );
+    }
+}
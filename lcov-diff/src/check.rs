@@ -0,0 +1,128 @@
+//! Coverage thresholds checks.
+use std::fmt;
+
+use crate::render::number::Numbers;
+use crate::summary::Comparison;
+
+/// Limits the new coverage must satisfy, unset limits aren't checked.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Thresholds {
+    /// Minimal total line coverage in percents.
+    pub min_lines: Option<f64>,
+    /// Maximal drop of the total line coverage in percentage points.
+    pub max_drop: Option<f64>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Violation {
+    LinesBelow { coverage: f64, min: f64 },
+    LinesDropped { drop: f64, max: f64 },
+}
+
+impl Violation {
+    /// Snake case name of the check.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Violation::LinesBelow { .. } => "lines_below",
+            Violation::LinesDropped { .. } => "lines_dropped",
+        }
+    }
+
+    /// Measured value and the limit it violates.
+    pub fn value_and_limit(&self) -> (f64, f64) {
+        match *self {
+            Violation::LinesBelow { coverage, min } => (coverage, min),
+            Violation::LinesDropped { drop, max } => (drop, max),
+        }
+    }
+
+    /// Message of the violation with the percents in the number format.
+    pub fn message(&self, numbers: &Numbers) -> String {
+        match *self {
+            Violation::LinesBelow { coverage, min } => format!(
+                "Line coverage {} is below the threshold {}",
+                numbers.percent(coverage),
+                numbers.percent(min)
+            ),
+            Violation::LinesDropped { drop, max } => format!(
+                "Line coverage dropped by {} which is more than {}",
+                numbers.percent(drop),
+                numbers.percent(max)
+            ),
+        }
+    }
+}
+
+/// The message in the default number format.
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message(&Numbers::default()))
+    }
+}
+
+impl Thresholds {
+    pub fn check(&self, comparison: &Comparison) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        if let Some(min) = self.min_lines {
+            let coverage = comparison.current.lines.percent().unwrap_or(100.0);
+            if coverage < min {
+                violations.push(Violation::LinesBelow { coverage, min });
+            }
+        }
+        if let Some(max) = self.max_drop {
+            let drop = -comparison.lines_delta();
+            if drop > max {
+                violations.push(Violation::LinesDropped { drop, max });
+            }
+        }
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Thresholds, Violation};
+    use crate::render::number::{Numbers, Rounding};
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn check() {
+        let baseline = "SF:a.c\nDA:1,1\nDA:2,1\nend_of_record\n";
+        let current = "SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&baseline, &current);
+
+        let thresholds = Thresholds {
+            min_lines: Some(40.0),
+            max_drop: Some(10.0),
+        };
+        assert_eq!(
+            thresholds.check(&comparison),
+            vec![Violation::LinesDropped {
+                drop: 50.0,
+                max: 10.0
+            }]
+        );
+        assert!(Thresholds::default().check(&comparison).is_empty());
+
+        let violation = Violation::LinesBelow {
+            coverage: 66.666,
+            min: 80.0,
+        };
+        assert_eq!(
+            violation.to_string(),
+            "Line coverage 66.67% is below the threshold 80.00%"
+        );
+        let numbers = Numbers {
+            precision: 1,
+            rounding: Rounding::Floor,
+            decimal_separator: ',',
+        };
+        assert_eq!(
+            violation.message(&numbers),
+            "Line coverage 66,6% is below the threshold 80,0%"
+        );
+    }
+}
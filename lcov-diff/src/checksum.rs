@@ -0,0 +1,135 @@
+//! SHA-256 manifests of the written artifacts in the `sha256sum` format (`<digest>  <path>`),
+//! so `sha256sum -c` checks them too. The paths are relative to the directory of the
+//! manifest, the directories (html reports) are listed file by file.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::hash::sha256;
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Manifest {
+    /// Digests by the path.
+    pub entries: BTreeMap<PathBuf, String>,
+}
+
+/// Directory the paths of the manifest are relative to.
+fn base_dir(manifest: &Path) -> PathBuf {
+    match manifest.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_owned(),
+        _ => PathBuf::from("."),
+    }
+}
+
+impl Manifest {
+    pub fn new() -> Self {
+        Manifest::default()
+    }
+
+    /// Adds the file, or every file of the directory, with the path relative to `base` if
+    /// it's inside of it.
+    pub fn add(&mut self, base: &Path, path: &Path) -> io::Result<()> {
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.sort();
+            for entry in entries {
+                self.add(base, &entry)?;
+            }
+            return Ok(());
+        }
+        let digest = sha256::hex_digest(&fs::read(path)?);
+        let (base, path) = (fs::canonicalize(base)?, fs::canonicalize(path)?);
+        let relative = path
+            .strip_prefix(&base)
+            .map_or(path.clone(), Path::to_owned);
+        self.entries.insert(relative, digest);
+        Ok(())
+    }
+
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut manifest = Manifest::new();
+        for line in input.lines().filter(|line| !line.trim().is_empty()) {
+            let (digest, path) = line
+                .split_once("  ")
+                .ok_or_else(|| format!("invalid checksum line: {}", line))?;
+            let path = path.strip_prefix('*').unwrap_or(path);
+            manifest
+                .entries
+                .insert(PathBuf::from(path), digest.to_ascii_lowercase());
+        }
+        Ok(manifest)
+    }
+
+    /// Messages of the files of `base` which are missing or don't match.
+    pub fn verify(&self, base: &Path) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        for (path, digest) in &self.entries {
+            match fs::read(base.join(path)) {
+                Ok(data) if sha256::hex_digest(&data) == *digest => {}
+                Ok(_) => mismatches.push(format!("{}: checksum mismatch", path.display())),
+                Err(e) => mismatches.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        mismatches
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (path, digest) in &self.entries {
+            writeln!(f, "{}  {}", digest, path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the manifest of the artifacts to `path`.
+pub fn write_manifest<P: AsRef<Path>>(path: &Path, artifacts: &[P]) -> io::Result<()> {
+    let base = base_dir(path);
+    let mut manifest = Manifest::new();
+    for artifact in artifacts {
+        manifest.add(&base, artifact.as_ref())?;
+    }
+    fs::write(path, manifest.to_string())
+}
+
+/// Messages of the artifacts of the manifest at `path` which are missing or don't match.
+pub fn verify_manifest(path: &Path) -> io::Result<Vec<String>> {
+    let manifest = Manifest::parse(&fs::read_to_string(path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(manifest.verify(&base_dir(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{verify_manifest, write_manifest};
+    use std::fs;
+
+    #[test]
+    fn write_and_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let (diff, html) = (dir.path().join("diff.info"), dir.path().join("html"));
+        fs::write(&diff, "").unwrap();
+        fs::create_dir(&html).unwrap();
+        fs::write(html.join("index.html"), "<html>").unwrap();
+        let manifest = dir.path().join("SHA256SUMS");
+        write_manifest(&manifest, &[&diff, &html]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&manifest).unwrap(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  diff.info\n\
+             b7d082ee12e91b756ea22e8513b8594eebcf5d39fab813da3cb55794dc888ad7  html/index.html\n"
+        );
+        assert!(verify_manifest(&manifest).unwrap().is_empty());
+
+        fs::write(&diff, "TN:\n").unwrap();
+        fs::remove_file(html.join("index.html")).unwrap();
+        let mismatches = verify_manifest(&manifest).unwrap();
+        assert_eq!(mismatches.len(), 2);
+        assert_eq!(mismatches[0], "diff.info: checksum mismatch");
+        assert!(mismatches[1].starts_with("html/index.html: "));
+    }
+}
@@ -0,0 +1,171 @@
+//! Detection of the CI provider from the environment: the built commit, the base of the
+//! pull (merge) request and the changed files.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::json;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Provider {
+    GithubActions,
+    Gitlab,
+    Buildkite,
+    Jenkins,
+}
+
+/// Build environment, the pull request fields are `None` for branch builds.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CiEnv {
+    pub provider: Provider,
+    /// Built commit.
+    pub commit: Option<String>,
+    /// Target branch of the pull request.
+    pub base_ref: Option<String>,
+    /// Target commit of the pull request, if the provider knows it.
+    pub base_commit: Option<String>,
+    pub pull_request: Option<u64>,
+    /// `owner/name` of the repository.
+    pub repository: Option<String>,
+}
+
+impl CiEnv {
+    /// Detects the provider from the process environment.
+    pub fn detect() -> Option<Self> {
+        CiEnv::from_vars(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+    }
+
+    /// Detects the provider from the variables returned by `var`.
+    pub fn from_vars<F: Fn(&str) -> Option<String>>(var: F) -> Option<Self> {
+        let number = |name: &str| var(name).and_then(|value| value.parse().ok());
+        if var("GITHUB_ACTIONS").as_deref() == Some("true") {
+            // The base commit is only in the event payload
+            let base_commit = var("GITHUB_EVENT_PATH")
+                .and_then(|path| fs::read_to_string(path).ok())
+                .and_then(|event| json::parse(&event).ok())
+                .and_then(|event| {
+                    let sha = event.get("pull_request")?.get("base")?.get("sha")?;
+                    sha.as_str().map(str::to_owned)
+                });
+            let pull_request = var("GITHUB_REF").and_then(|git_ref| {
+                git_ref
+                    .strip_prefix("refs/pull/")?
+                    .split('/')
+                    .next()?
+                    .parse()
+                    .ok()
+            });
+            Some(CiEnv {
+                provider: Provider::GithubActions,
+                commit: var("GITHUB_SHA"),
+                base_ref: var("GITHUB_BASE_REF"),
+                base_commit,
+                pull_request,
+                repository: var("GITHUB_REPOSITORY"),
+            })
+        } else if var("GITLAB_CI").is_some() {
+            Some(CiEnv {
+                provider: Provider::Gitlab,
+                commit: var("CI_COMMIT_SHA"),
+                base_ref: var("CI_MERGE_REQUEST_TARGET_BRANCH_NAME"),
+                base_commit: var("CI_MERGE_REQUEST_DIFF_BASE_SHA"),
+                pull_request: number("CI_MERGE_REQUEST_IID"),
+                repository: var("CI_PROJECT_PATH"),
+            })
+        } else if var("BUILDKITE").as_deref() == Some("true") {
+            // `BUILDKITE_PULL_REQUEST` is `false` for branch builds
+            Some(CiEnv {
+                provider: Provider::Buildkite,
+                commit: var("BUILDKITE_COMMIT"),
+                base_ref: var("BUILDKITE_PULL_REQUEST_BASE_BRANCH"),
+                base_commit: None,
+                pull_request: number("BUILDKITE_PULL_REQUEST"),
+                repository: var("BUILDKITE_REPO").and_then(|url| repository_of(&url)),
+            })
+        } else if var("JENKINS_URL").is_some() {
+            Some(CiEnv {
+                provider: Provider::Jenkins,
+                commit: var("GIT_COMMIT"),
+                base_ref: var("CHANGE_TARGET"),
+                base_commit: None,
+                pull_request: number("CHANGE_ID"),
+                repository: var("GIT_URL").and_then(|url| repository_of(&url)),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Git revision the changes are compared against: the base commit, or the remote
+    /// branch of the base ref.
+    pub fn base(&self) -> Option<String> {
+        self.base_commit
+            .clone()
+            .or_else(|| Some(format!("origin/{}", self.base_ref.as_ref()?)))
+    }
+}
+
+/// `owner/name` of the `git@host:owner/name.git` or `https://host/owner/name.git` url.
+fn repository_of(url: &str) -> Option<String> {
+    let path = url.trim_end_matches('/').trim_end_matches(".git");
+    let path = match path.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?.1,
+        None => path.split_once(':')?.1,
+    };
+    Some(path.to_owned())
+}
+
+/// Files changed since the merge base of `base`, relative to the repository root,
+/// `None` if `git diff` fails.
+pub fn changed_files(repo: &Path, base: &str) -> Option<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["diff", "--name-only"])
+        .arg(format!("{}...HEAD", base))
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(PathBuf::from)
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CiEnv, Provider};
+    use std::collections::HashMap;
+
+    #[test]
+    fn detect() {
+        let vars: HashMap<&str, &str> = vec![
+            ("GITLAB_CI", "true"),
+            ("CI_COMMIT_SHA", "abc"),
+            ("CI_MERGE_REQUEST_TARGET_BRANCH_NAME", "main"),
+            ("CI_MERGE_REQUEST_IID", "12"),
+            ("CI_PROJECT_PATH", "group/app"),
+        ]
+        .into_iter()
+        .collect();
+        let env = CiEnv::from_vars(|name| vars.get(name).map(|v| v.to_string())).unwrap();
+        assert_eq!(env.provider, Provider::Gitlab);
+        assert_eq!(env.pull_request, Some(12));
+        assert_eq!(env.base().as_deref(), Some("origin/main"));
+
+        let vars: HashMap<&str, &str> = vec![
+            ("BUILDKITE", "true"),
+            ("BUILDKITE_PULL_REQUEST", "false"),
+            ("BUILDKITE_REPO", "git@github.com:owner/app.git"),
+        ]
+        .into_iter()
+        .collect();
+        let env = CiEnv::from_vars(|name| vars.get(name).map(|v| v.to_string())).unwrap();
+        assert_eq!(env.pull_request, None);
+        assert_eq!(env.base(), None);
+        assert_eq!(env.repository.as_deref(), Some("owner/app"));
+        assert!(CiEnv::from_vars(|_| None).is_none());
+    }
+}
@@ -0,0 +1,126 @@
+//! Clusters of the tests with nearly identical coverage, the candidates for removal.
+use std::collections::BTreeMap;
+
+use lcov::Report;
+
+use crate::json::Value;
+use crate::similarity::similarity;
+use crate::summary::Coverage;
+
+/// Tests whose covered lines are pairwise at least as similar as the threshold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cluster {
+    /// Test covering the most lines, kept when the others are removed.
+    pub representative: String,
+    /// Other tests of the cluster.
+    pub redundant: Vec<String>,
+    /// Smallest line Jaccard similarity of a pair of the tests.
+    pub min_similarity: f64,
+}
+
+/// Clusters of more than one test by the line Jaccard similarity `threshold` (complete linkage,
+/// every pair of the cluster is similar enough), ordered by the representative.
+pub fn clusters(tests: &BTreeMap<String, Report>, threshold: f64) -> Vec<Cluster> {
+    let names: Vec<&String> = tests.keys().collect();
+    let mut groups: Vec<(Vec<usize>, f64)> = Vec::new();
+    for idx in 0..names.len() {
+        let mut joined = false;
+        for (members, min) in &mut groups {
+            let similarities: Vec<f64> = members
+                .iter()
+                .map(|&member| similarity(&tests[names[member]], &tests[names[idx]]).jaccard_lines)
+                .collect();
+            if similarities.iter().all(|&s| s >= threshold) {
+                members.push(idx);
+                *min = similarities.into_iter().fold(*min, f64::min);
+                joined = true;
+                break;
+            }
+        }
+        if !joined {
+            groups.push((vec![idx], 1.0));
+        }
+    }
+
+    let mut clusters: Vec<Cluster> = groups
+        .into_iter()
+        .filter(|(members, _)| members.len() > 1)
+        .map(|(members, min_similarity)| {
+            let covered = |idx: &usize| Coverage::of_report(&tests[names[*idx]]).lines.hit;
+            let representative = *members
+                .iter()
+                .max_by_key(|idx| (covered(idx), std::cmp::Reverse(**idx)))
+                .expect("cluster isn't empty");
+            Cluster {
+                representative: names[representative].clone(),
+                redundant: members
+                    .into_iter()
+                    .filter(|&idx| idx != representative)
+                    .map(|idx| names[idx].clone())
+                    .collect(),
+                min_similarity,
+            }
+        })
+        .collect();
+    clusters.sort_by(|a, b| a.representative.cmp(&b.representative));
+    clusters
+}
+
+/// `{"threshold": .., "clusters": [{"tests": [..], "representative": .., "redundant": [..],
+/// "min_similarity": ..}]}`
+pub fn to_json(clusters: &[Cluster], threshold: f64) -> Value {
+    let clusters = clusters
+        .iter()
+        .map(|cluster| {
+            let names = |names: &mut dyn Iterator<Item = &String>| {
+                Value::Array(names.map(|name| Value::from(name.as_str())).collect())
+            };
+            let mut tests = std::iter::once(&cluster.representative).chain(&cluster.redundant);
+            Value::object(vec![
+                ("tests", names(&mut tests)),
+                (
+                    "representative",
+                    Value::from(cluster.representative.as_str()),
+                ),
+                ("redundant", names(&mut cluster.redundant.iter())),
+                (
+                    "min_similarity",
+                    Value::from((cluster.min_similarity * 1000.0).round() / 1000.0),
+                ),
+            ])
+        })
+        .collect();
+    Value::object(vec![
+        ("threshold", Value::from(threshold)),
+        ("clusters", Value::Array(clusters)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clusters, to_json};
+    use crate::summary::by_test;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn cluster_tests() {
+        let input = "\
+TN:login\nSF:a.c\nDA:1,1\nDA:2,1\nDA:3,1\nend_of_record
+TN:login_again\nSF:a.c\nDA:1,1\nDA:2,1\nDA:3,0\nend_of_record
+TN:logout\nSF:b.c\nDA:1,1\nend_of_record
+";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let tests = by_test(&report);
+        assert_eq!(tests.len(), 3);
+
+        let clusters = clusters(&tests, 0.6);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].representative, "login");
+        assert_eq!(clusters[0].redundant, vec!["login_again"]);
+        assert_eq!(
+            to_json(&clusters, 0.6).to_string(),
+            r#"{"threshold":0.6,"clusters":[{"tests":["login","login_again"],"representative":"login","redundant":["login_again"],"min_similarity":0.667}]}"#
+        );
+        assert!(super::clusters(&tests, 0.9).is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+//! Coverage debt: the persisted list of the known uncovered lines, so the gate only fails on
+//! the new ones while the legacy code is covered bit by bit.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use lcov::Report;
+
+use crate::snippet;
+use crate::summary;
+
+/// Uncovered lines by the repository relative path.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Debt {
+    pub lines: BTreeMap<PathBuf, BTreeSet<u32>>,
+}
+
+impl Debt {
+    /// Never executed lines of the report, absolute source paths are made relative to `root`.
+    pub fn of_report(report: &Report, root: &Path) -> Self {
+        let mut debt = Debt::default();
+        for (path, section) in summary::by_file(report) {
+            let uncovered: BTreeSet<u32> = section
+                .lines
+                .iter()
+                .filter(|(_, value)| value.count == 0)
+                .map(|(key, _)| key.line)
+                .collect();
+            if !uncovered.is_empty() {
+                let path = path.strip_prefix(root).unwrap_or(&path).to_owned();
+                debt.lines.insert(path, uncovered);
+            }
+        }
+        debt
+    }
+
+    /// Parses the `path: 1,3-5` lines written by `Display`, `#` starts a comment.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut debt = Debt::default();
+        for (number, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || format!("invalid debt line {}: {}", number + 1, line);
+            let (path, ranges) = line.rsplit_once(": ").ok_or_else(invalid)?;
+            let lines = debt.lines.entry(PathBuf::from(path)).or_default();
+            for range in ranges.split(',') {
+                let (start, end) = range.split_once('-').unwrap_or((range, range));
+                let start: u32 = start.trim().parse().map_err(|_| invalid())?;
+                let end: u32 = end.trim().parse().map_err(|_| invalid())?;
+                lines.extend(start..=end);
+            }
+        }
+        Ok(debt)
+    }
+
+    /// Lines of `current` which aren't in the debt, by path.
+    pub fn new_lines(&self, current: &Debt) -> Vec<(PathBuf, u32)> {
+        let mut new = Vec::new();
+        for (path, lines) in &current.lines {
+            let known = self.lines.get(path);
+            for &line in lines {
+                if !known.is_some_and(|known| known.contains(&line)) {
+                    new.push((path.clone(), line));
+                }
+            }
+        }
+        new
+    }
+
+    /// Debt without the lines which aren't uncovered in `current` any more.
+    pub fn shrink(&self, current: &Debt) -> Debt {
+        let lines = self
+            .lines
+            .iter()
+            .filter_map(|(path, lines)| {
+                let uncovered = current.lines.get(path)?;
+                let lines: BTreeSet<u32> = lines.intersection(uncovered).copied().collect();
+                Some((path.clone(), lines)).filter(|(_, lines)| !lines.is_empty())
+            })
+            .collect();
+        Debt { lines }
+    }
+
+    /// Total number of the lines.
+    pub fn len(&self) -> usize {
+        self.lines.values().map(BTreeSet::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+impl fmt::Display for Debt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# Known uncovered lines, maintained by lcov-diff --debt")?;
+        for (path, lines) in &self.lines {
+            let lines: Vec<u32> = lines.iter().copied().collect();
+            let ranges: Vec<String> = snippet::ranges(&lines)
+                .into_iter()
+                .map(|(start, end)| {
+                    if start == end {
+                        start.to_string()
+                    } else {
+                        format!("{}-{}", start, end)
+                    }
+                })
+                .collect();
+            writeln!(f, "{}: {}", path.display(), ranges.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Debt;
+    use lcov::{Reader, Report};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn debt() {
+        let known = Debt::parse("# legacy\nsrc/a.c: 1-3,7\nsrc/b.c: 2\n").unwrap();
+        assert_eq!(known.len(), 5);
+        assert_eq!(
+            known.to_string(),
+            "# Known uncovered lines, maintained by lcov-diff --debt\nsrc/a.c: 1-3,7\nsrc/b.c: 2\n"
+        );
+        assert!(Debt::parse("src/a.c 1").is_err());
+
+        let input = "SF:/repo/src/a.c\nDA:1,0\nDA:2,5\nDA:3,0\nDA:8,0\nend_of_record\nSF:/repo/src/b.c\nDA:2,1\nend_of_record\n";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let current = Debt::of_report(&report, Path::new("/repo"));
+        assert_eq!(
+            known.new_lines(&current),
+            vec![(PathBuf::from("src/a.c"), 8)]
+        );
+        assert_eq!(
+            known.shrink(&current).to_string().lines().nth(1),
+            Some("src/a.c: 1,3")
+        );
+    }
+}
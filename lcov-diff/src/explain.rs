@@ -0,0 +1,320 @@
+//! Trace of why a line has its status in the diff, to make the reports of a wrong diff
+//! debuggable.
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use lcov::report::section::line::{Key as LineKey, Value as LineValue};
+use lcov::report::section::Key as SectionKey;
+use lcov::Report;
+
+use crate::json::Value;
+use crate::summary::Comparison;
+use crate::Diff;
+
+/// Status of the line in the diff.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Status {
+    /// The new report has no record of the line.
+    NotInstrumented,
+    /// Executed in the new report and not in the baseline, the diff keeps the count.
+    NewlyCovered,
+    /// Executed in the baseline too, the diff has a zero count.
+    AlreadyCovered,
+    /// Never executed in the new report.
+    Uncovered,
+}
+
+impl Status {
+    pub fn id(self) -> &'static str {
+        match self {
+            Status::NotInstrumented => "not_instrumented",
+            Status::NewlyCovered => "newly_covered",
+            Status::AlreadyCovered => "already_covered",
+            Status::Uncovered => "uncovered",
+        }
+    }
+}
+
+/// Checksums of the line records, which the diff requires to match.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Checksum {
+    /// Either record has no checksum.
+    Missing,
+    Matching,
+    /// The diff of the reports fails.
+    Mismatched,
+}
+
+impl Checksum {
+    pub fn id(self) -> &'static str {
+        match self {
+            Checksum::Missing => "missing",
+            Checksum::Matching => "matching",
+            Checksum::Mismatched => "mismatched",
+        }
+    }
+}
+
+/// Records of the line of one section (test name and source file), the diff only compares the
+/// records of the same section.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Record {
+    pub test_name: String,
+    pub source_file: PathBuf,
+    pub baseline: Option<u64>,
+    pub current: Option<u64>,
+    /// Count in the diff, `None` if the diff has no record.
+    pub diff: Option<u64>,
+    pub checksum: Checksum,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Explanation {
+    pub path: PathBuf,
+    pub line: u32,
+    pub status: Status,
+    pub records: Vec<Record>,
+    /// Settings and filters which affected the line.
+    pub notes: Vec<String>,
+}
+
+/// Explains the line of the source files whose path ends with `path` in the diff of `current`
+/// against `baseline`, and in the comparison if given (after its patch settings and filters).
+pub fn explain(
+    baseline: &Report,
+    current: &Report,
+    comparison: Option<&Comparison>,
+    path: &Path,
+    line: u32,
+) -> Explanation {
+    let mut keys: Vec<&SectionKey> = baseline
+        .sections
+        .keys()
+        .chain(current.sections.keys())
+        .filter(|key| key.source_file.ends_with(path))
+        .collect();
+    keys.sort();
+    keys.dedup();
+
+    let line_key = LineKey { line };
+    let mut records = Vec::new();
+    for key in keys {
+        let value = |report: &Report| -> Option<LineValue> {
+            report.sections.get(key)?.lines.get(&line_key).cloned()
+        };
+        let (old, new) = (value(baseline), value(current));
+        if old.is_none() && new.is_none() {
+            continue;
+        }
+        let checksum = match (
+            old.as_ref().and_then(|v| v.checksum.as_ref()),
+            new.as_ref().and_then(|v| v.checksum.as_ref()),
+        ) {
+            (Some(old), Some(new)) if old != new => Checksum::Mismatched,
+            (Some(_), Some(_)) => Checksum::Matching,
+            _ => Checksum::Missing,
+        };
+        let diff = new.clone().map(|mut diff| {
+            if let Some(old) = &old {
+                // A mismatched checksum fails the whole diff, the count is still of interest
+                let _ = diff.diff(&LineValue {
+                    checksum: None,
+                    ..old.clone()
+                });
+            }
+            diff.count
+        });
+        records.push(Record {
+            test_name: key.test_name.clone(),
+            source_file: key.source_file.clone(),
+            baseline: old.map(|v| v.count),
+            current: new.map(|v| v.count),
+            diff,
+            checksum,
+        });
+    }
+
+    let status = if records.iter().any(|r| r.diff.unwrap_or(0) > 0) {
+        Status::NewlyCovered
+    } else if records.iter().any(|r| r.current.unwrap_or(0) > 0) {
+        Status::AlreadyCovered
+    } else if records.iter().any(|r| r.current.is_some()) {
+        Status::Uncovered
+    } else {
+        Status::NotInstrumented
+    };
+
+    let mut notes = Vec::new();
+    for record in &records {
+        if record.diff.unwrap_or(0) > 0 {
+            let elsewhere: Vec<&str> = records
+                .iter()
+                .filter(|other| {
+                    other.source_file == record.source_file
+                        && other.test_name != record.test_name
+                        && other.baseline.unwrap_or(0) > 0
+                })
+                .map(|other| other.test_name.as_str())
+                .collect();
+            if !elsewhere.is_empty() {
+                notes.push(format!(
+                    "executed in the baseline under other test names ({}), the diff only \
+                     compares the records of the same test name",
+                    elsewhere.join(", ")
+                ));
+            }
+        }
+        if record.checksum == Checksum::Mismatched {
+            notes.push(format!(
+                "checksums of test `{}` differ, the diff of the reports fails",
+                record.test_name
+            ));
+        }
+    }
+    if let Some(comparison) = comparison {
+        let listed = |p: &Path| p.ends_with(path);
+        if !records.is_empty() && !comparison.files.iter().any(|file| listed(&file.path)) {
+            notes.push("file filtered out of the comparison (--changed-only)".to_owned());
+        }
+        let uncovered = comparison
+            .uncovered_new_lines
+            .iter()
+            .any(|(p, l)| *l == line && listed(p));
+        if uncovered {
+            notes.push("reported as an uncovered new line".to_owned());
+        } else if status == Status::Uncovered && records.iter().all(|r| r.baseline.is_none()) {
+            notes.push(
+                "new line not reported as uncovered (outside of the patch or suppressed)"
+                    .to_owned(),
+            );
+        }
+    }
+
+    Explanation {
+        path: path.to_owned(),
+        line,
+        status,
+        records,
+        notes,
+    }
+}
+
+/// Parses `path:line` location.
+pub fn parse_location(location: &str) -> Result<(PathBuf, u32), String> {
+    location
+        .rsplit_once(':')
+        .and_then(|(path, line)| Some((PathBuf::from(path), line.parse().ok()?)))
+        .ok_or_else(|| format!("`path:line` expected: {}", location))
+}
+
+impl Explanation {
+    pub fn to_json(&self) -> Value {
+        let count = |count: Option<u64>| Value::from(count.map(|count| count as f64));
+        let records = self
+            .records
+            .iter()
+            .map(|record| {
+                Value::object(vec![
+                    ("test_name", Value::from(record.test_name.as_str())),
+                    (
+                        "source_file",
+                        Value::from(record.source_file.to_string_lossy().as_ref()),
+                    ),
+                    ("baseline", count(record.baseline)),
+                    ("current", count(record.current)),
+                    ("diff", count(record.diff)),
+                    ("checksum", Value::from(record.checksum.id())),
+                ])
+            })
+            .collect();
+        Value::object(vec![
+            ("path", Value::from(self.path.to_string_lossy().as_ref())),
+            ("line", Value::from(self.line)),
+            ("status", Value::from(self.status.id())),
+            ("records", Value::Array(records)),
+            (
+                "notes",
+                Value::Array(self.notes.iter().map(|n| Value::from(n.as_str())).collect()),
+            ),
+        ])
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{}:{}: {}",
+            self.path.display(),
+            self.line,
+            self.status.id()
+        )?;
+        let count = |count: Option<u64>| count.map_or("-".to_owned(), |count| count.to_string());
+        for record in &self.records {
+            writeln!(
+                f,
+                "  test `{}` {}: baseline {}, new {}, diff {}, checksum {}",
+                record.test_name,
+                record.source_file.display(),
+                count(record.baseline),
+                count(record.current),
+                count(record.diff),
+                record.checksum.id()
+            )?;
+        }
+        for note in &self.notes {
+            writeln!(f, "  note: {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{explain, parse_location, Checksum, Status};
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+    use std::path::Path;
+
+    #[test]
+    fn explain_line() {
+        let baseline = "TN:unit\nSF:/repo/a.c\nDA:1,1\nDA:2,0,abc\nend_of_record\nTN:e2e\nSF:/repo/a.c\nDA:3,2\nend_of_record\n";
+        let current = "TN:unit\nSF:/repo/a.c\nDA:1,1\nDA:2,4,abd\nDA:3,1\nDA:4,0\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&baseline, &current);
+        let explain = |line| {
+            explain(
+                &baseline,
+                &current,
+                Some(&comparison),
+                Path::new("a.c"),
+                line,
+            )
+        };
+
+        assert_eq!(explain(1).status, Status::AlreadyCovered);
+        let second = explain(2);
+        assert_eq!(second.status, Status::NewlyCovered);
+        assert_eq!(second.records[0].checksum, Checksum::Mismatched);
+        assert_eq!(second.records[0].diff, Some(4));
+        // Covered by `e2e` in the baseline, and `unit` now
+        let third = explain(3);
+        assert_eq!(third.status, Status::NewlyCovered);
+        assert!(third.notes[0].contains("(e2e)"));
+        assert_eq!(explain(4).notes, vec!["reported as an uncovered new line"]);
+        assert_eq!(explain(5).status, Status::NotInstrumented);
+        assert!(explain(4)
+            .to_string()
+            .starts_with("a.c:4: uncovered\n  test `unit` /repo/a.c: baseline -, new 0, diff 0"));
+        assert_eq!(
+            parse_location("src/a.c:12"),
+            Ok((Path::new("src/a.c").to_owned(), 12))
+        );
+        assert!(parse_location("src/a.c").is_err());
+        assert!(explain(2)
+            .to_json()
+            .to_string()
+            .contains(r#""records":[{"test_name":"unit""#));
+    }
+}
@@ -0,0 +1,112 @@
+//! Records of the lcov files which the `lcov` crate doesn't know (`VER:` of lcov 2, vendor
+//! extensions, comments), kept aside so they can be written back with the output.
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use lcov::record::RecordKind;
+use lcov::report::section::Key as SectionKey;
+
+/// Unknown records in their order, by the section they were found in.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Extras {
+    /// Records outside of the sections, such as the `VER:` header.
+    pub header: Vec<String>,
+    pub sections: BTreeMap<SectionKey, Vec<String>>,
+}
+
+impl Extras {
+    /// Takes the unknown records out of the lcov input, returns the input the `lcov` crate can
+    /// read. Blank lines are dropped too.
+    pub fn split(input: &str) -> (String, Extras) {
+        let mut extras = Extras::default();
+        let mut known = String::with_capacity(input.len());
+        let mut test_name = String::new();
+        let mut source_file: Option<PathBuf> = None;
+        for line in input.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let (kind, value) = trimmed.split_once(':').unwrap_or((trimmed, ""));
+            if kind.parse::<RecordKind>().is_err() && trimmed != "end_of_record" {
+                match &source_file {
+                    Some(source_file) => extras
+                        .sections
+                        .entry(SectionKey {
+                            test_name: test_name.clone(),
+                            source_file: source_file.clone(),
+                        })
+                        .or_default()
+                        .push(line.to_owned()),
+                    None => extras.header.push(line.to_owned()),
+                }
+                continue;
+            }
+            match kind {
+                "TN" => test_name = value.to_owned(),
+                "SF" => source_file = Some(PathBuf::from(value)),
+                "end_of_record" => {
+                    test_name.clear();
+                    source_file = None;
+                }
+                _ => {}
+            }
+            known.push_str(line);
+            known.push('\n');
+        }
+        (known, extras)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.header.is_empty() && self.sections.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Extras;
+    use crate::write::WriteOptions;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn round_trip() {
+        let input = "\
+VER:2.0
+TN:unit
+SF:a.c
+DA:1,1
+# vendor comment
+XR:1,mutant
+end_of_record
+
+SF:b.c
+DA:2,0
+end_of_record
+";
+        let (known, extras) = Extras::split(input);
+        assert_eq!(extras.header, vec!["VER:2.0"]);
+        assert_eq!(extras.sections.len(), 1);
+        let report = Report::from_reader(Reader::new(known.as_bytes())).unwrap();
+        assert_eq!(
+            WriteOptions::default()
+                .lines(report.into_records(), &extras)
+                .join("\n"),
+            "\
+VER:2.0
+TN:
+SF:b.c
+DA:2,0
+LF:1
+LH:0
+end_of_record
+TN:unit
+SF:a.c
+DA:1,1
+LF:1
+LH:1
+# vendor comment
+XR:1,mutant
+end_of_record"
+        );
+    }
+}
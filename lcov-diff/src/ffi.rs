@@ -0,0 +1,231 @@
+//! C interface of the diff (the `ffi` feature), for the tooling which can't link Rust:
+//!
+//! ```c
+//! int lcov_diff_run(const char *options_json, const char *path_a, const char *path_b,
+//!                   const char *out_path);
+//! const char *lcov_diff_last_error(void);
+//! ```
+//!
+//! `lcov_diff_run` writes the lcov diff of the current report `path_a` against the baseline
+//! `path_b` to `out_path` and returns 0, -1 with the message of `lcov_diff_last_error`, or -2 if
+//! the diff panicked (the panic doesn't unwind into the caller). The
+//! options are a json object (or `NULL`) of `keep_unknown`, `lenient`, `large_input` (bools),
+//! `include`, `exclude` and `lcov_omit` (arrays of strings), as the options of the tool.
+//!
+//! The declarations are in `include/lcov_diff.h`, the shared library is built in the directory of
+//! the crate with `cargo build --features ffi --release`.
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::ptr;
+
+use crate::filter::PathFilter;
+use crate::import::{self, LoadOptions};
+use crate::json::{self, Value};
+use crate::write::WriteOptions;
+use crate::{diff_reports_with, DiffOptions};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Default)]
+struct Options {
+    load: LoadOptions,
+    diff: DiffOptions,
+    write: WriteOptions,
+}
+
+fn bool_option(value: &Value, key: &str) -> Result<bool, String> {
+    match value {
+        Value::Bool(value) => Ok(*value),
+        _ => Err(format!("option {} must be a bool", key)),
+    }
+}
+
+fn strings_option(value: &Value, key: &str) -> Result<Vec<String>, String> {
+    value
+        .as_array()
+        .and_then(|values| {
+            values
+                .iter()
+                .map(|value| value.as_str().map(str::to_owned))
+                .collect()
+        })
+        .ok_or_else(|| format!("option {} must be an array of strings", key))
+}
+
+fn parse_options(input: &str) -> Result<Options, String> {
+    let value = json::parse(input).map_err(|e| e.to_string())?;
+    let fields = value.as_object().ok_or("options must be a json object")?;
+    let mut options = Options::default();
+    let mut filter = PathFilter::default();
+    for (key, value) in fields {
+        match key.as_str() {
+            "keep_unknown" => options.load.keep_unknown = bool_option(value, key)?,
+            "lenient" => options.load.lenient = bool_option(value, key)?,
+            "large_input" => {
+                if bool_option(value, key)? {
+                    options.diff = DiffOptions::large_input();
+                }
+            }
+            "include" => filter.include = strings_option(value, key)?,
+            "exclude" => filter.exclude = strings_option(value, key)?,
+            "lcov_omit" => {
+                for what in strings_option(value, key)? {
+                    options.write.omit(&what)?;
+                }
+            }
+            _ => return Err(format!("unknown option: {}", key)),
+        }
+    }
+    options.load.filter = filter;
+    Ok(options)
+}
+
+/// # Safety
+///
+/// The strings must be valid and nul-terminated, `options_json` may be null.
+unsafe fn string(value: *const c_char, name: &str) -> Result<String, String> {
+    if value.is_null() {
+        return Err(format!("{} is null", name));
+    }
+    CStr::from_ptr(value)
+        .to_str()
+        .map(str::to_owned)
+        .map_err(|_| format!("{} isn't utf-8", name))
+}
+
+fn run(options: Option<String>, first: String, second: String, out: String) -> Result<(), String> {
+    let options = match options {
+        Some(options) => parse_options(&options)?,
+        None => Options::default(),
+    };
+    let load = |path: &str| {
+        import::load_file_with(PathBuf::from(path), &options.load)
+            .map_err(|e| format!("{}: {}", path, e))
+    };
+    let (first, second) = (load(&first)?, load(&second)?);
+    let diff = diff_reports_with(&first.report, &second.report, &options.diff)
+        .map_err(|e| format!("{:?}", e))?;
+    let mut lines = options.write.lines(diff.into_records(), &first.extras);
+    lines.push(String::new());
+    fs::write(&out, lines.join("\n")).map_err(|e| format!("{}: {}", out, e))
+}
+
+/// Runs `call` and sets the last error of the thread, without unwinding a panic into the caller.
+fn guarded(call: impl FnOnce() -> Result<(), String>) -> c_int {
+    let (code, message) = match panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => (0, None),
+        Ok(Err(message)) => (-1, Some(message)),
+        Err(payload) => {
+            let reason = payload
+                .downcast_ref::<&str>()
+                .map(|reason| reason.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            (-2, Some(format!("panicked: {}", reason)))
+        }
+    };
+    let error = message.and_then(|message| CString::new(message.replace('\0', " ")).ok());
+    LAST_ERROR.with(|last| *last.borrow_mut() = error);
+    code
+}
+
+/// Writes the diff of `path_a` against `path_b` to `out_path`, 0 on success, -1 on error and -2
+/// on a panic.
+///
+/// # Safety
+///
+/// The paths must be valid nul-terminated strings, `options_json` too or null.
+#[no_mangle]
+pub unsafe extern "C" fn lcov_diff_run(
+    options_json: *const c_char,
+    path_a: *const c_char,
+    path_b: *const c_char,
+    out_path: *const c_char,
+) -> c_int {
+    guarded(|| {
+        let options = if options_json.is_null() {
+            None
+        } else {
+            Some(string(options_json, "options_json")?)
+        };
+        run(
+            options,
+            string(path_a, "path_a")?,
+            string(path_b, "path_b")?,
+            string(out_path, "out_path")?,
+        )
+    })
+}
+
+/// Message of the last failed `lcov_diff_run` of the thread, null after a success. It's valid
+/// until the next call of the thread.
+#[no_mangle]
+pub extern "C" fn lcov_diff_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{guarded, lcov_diff_last_error, lcov_diff_run};
+    use std::ffi::{CStr, CString};
+    use std::fs;
+
+    #[test]
+    fn run_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| CString::new(dir.path().join(name).to_str().unwrap()).unwrap();
+        fs::write(
+            dir.path().join("a.info"),
+            "TN:\nSF:a.c\nDA:1,1\nDA:2,1\nend_of_record\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("b.info"),
+            "TN:\nSF:a.c\nDA:1,1\nDA:2,0\nend_of_record\n",
+        )
+        .unwrap();
+        let options = CString::new(r#"{"lcov_omit": ["summaries"]}"#).unwrap();
+        let code = unsafe {
+            lcov_diff_run(
+                options.as_ptr(),
+                path("a.info").as_ptr(),
+                path("b.info").as_ptr(),
+                path("out.info").as_ptr(),
+            )
+        };
+        assert_eq!(code, 0);
+        assert!(lcov_diff_last_error().is_null());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("out.info")).unwrap(),
+            "TN:\nSF:a.c\nDA:1,0\nDA:2,1\nend_of_record\n"
+        );
+
+        let options = CString::new(r#"{"colour": true}"#).unwrap();
+        let code = unsafe {
+            lcov_diff_run(
+                options.as_ptr(),
+                path("a.info").as_ptr(),
+                path("b.info").as_ptr(),
+                path("out.info").as_ptr(),
+            )
+        };
+        assert_eq!(code, -1);
+        let error = unsafe { CStr::from_ptr(lcov_diff_last_error()) };
+        assert_eq!(error.to_str().unwrap(), "unknown option: colour");
+    }
+
+    #[test]
+    fn panic() {
+        assert_eq!(guarded(|| panic!("diff thread panicked")), -2);
+        let error = unsafe { CStr::from_ptr(lcov_diff_last_error()) };
+        assert_eq!(error.to_str().unwrap(), "panicked: diff thread panicked");
+        assert_eq!(guarded(|| Ok(())), 0);
+        assert!(lcov_diff_last_error().is_null());
+    }
+}
@@ -0,0 +1,175 @@
+//! Include and exclude filters of the source paths. The lcov inputs are filtered before the
+//! parse: the sections of the excluded files are skipped from their `SF:` to the
+//! `end_of_record`, so a monorepo report is only parsed for the subtree of interest.
+//!
+//! A manifest restricts a report to the exact file set of a release instead.
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use lcov::Report;
+
+/// Patterns are matched as the text of the path with `/` separators.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct PathFilter {
+    /// The paths containing any of them are kept, all of them if empty.
+    pub include: Vec<String>,
+    /// The paths containing any of them are dropped, even if included.
+    pub exclude: Vec<String>,
+}
+
+impl PathFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let contains = |pattern: &String| path.contains(pattern.as_str());
+        (self.include.is_empty() || self.include.iter().any(contains))
+            && !self.exclude.iter().any(contains)
+    }
+
+    /// Drops the sections of the report which don't match.
+    pub fn retain(&self, report: &mut Report) {
+        if !self.is_empty() {
+            report
+                .sections
+                .retain(|key, _| self.matches(&key.source_file));
+        }
+    }
+
+    /// The lcov input without the sections which don't match, with their `TN:` lines. The
+    /// skipped sections aren't looked into.
+    pub fn skip_sections(&self, input: &str) -> String {
+        if self.is_empty() {
+            return input.to_owned();
+        }
+        self.skip_sections_numbered(input).0
+    }
+
+    /// `skip_sections` with the number (from 1) of every kept line in the input, so the later
+    /// messages point at the lines of the file.
+    pub fn skip_sections_numbered(&self, input: &str) -> (String, Vec<usize>) {
+        let mut out = String::with_capacity(input.len());
+        let mut numbers = Vec::new();
+        let mut keep = |out: &mut String, (idx, line): (usize, &str)| {
+            out.push_str(line);
+            out.push('\n');
+            numbers.push(idx + 1);
+        };
+        let mut test_name: Option<(usize, &str)> = None;
+        let mut lines = input.lines().enumerate();
+        while let Some((idx, line)) = lines.next() {
+            let record = line.trim();
+            if record.starts_with("TN:") {
+                test_name = Some((idx, line));
+                continue;
+            }
+            if let Some(path) = record.strip_prefix("SF:") {
+                if !self.matches(Path::new(path)) {
+                    test_name = None;
+                    for (_, line) in lines.by_ref() {
+                        if line.trim() == "end_of_record" {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+            }
+            for line in test_name.take().into_iter().chain(Some((idx, line))) {
+                keep(&mut out, line);
+            }
+        }
+        if let Some(line) = test_name {
+            keep(&mut out, line);
+        }
+        (out, numbers)
+    }
+}
+
+/// Paths of a manifest, one per line; the empty lines and the `#` comments are skipped.
+pub fn parse_manifest(input: &str) -> HashSet<PathBuf> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Keeps the sections of the files of the manifest. A relative path of the manifest matches
+/// the report paths ending with it, so `src/a.c` keeps `/ci/build/src/a.c`.
+pub fn retain_files(report: &mut Report, manifest: &HashSet<PathBuf>) {
+    report.sections.retain(|key, _| {
+        let components: Vec<_> = key.source_file.components().collect();
+        (0..components.len()).any(|start| {
+            let suffix: PathBuf = components[start..].iter().collect();
+            manifest.contains(&suffix)
+        })
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_manifest, retain_files, PathFilter};
+    use crate::builder::ReportBuilder;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn filter_sections() {
+        let input = "\
+TN:unit
+SF:/repo/app/a.c
+DA:1,1
+end_of_record
+TN:unit
+SF:/repo/lib/b.c
+DA:1,1
+end_of_record
+SF:/repo/app/tests/c.c
+DA:1,1
+end_of_record
+";
+        let filter = PathFilter {
+            include: vec!["app/".to_owned()],
+            exclude: vec!["tests/".to_owned()],
+        };
+        assert_eq!(
+            filter.skip_sections(input),
+            "TN:unit\nSF:/repo/app/a.c\nDA:1,1\nend_of_record\n"
+        );
+        assert_eq!(PathFilter::default().skip_sections(input), input);
+        let (_, numbers) = filter.skip_sections_numbered(input);
+        assert_eq!(numbers, vec![1, 2, 3, 4]);
+
+        let mut report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        filter.retain(&mut report);
+        let parsed = filter.skip_sections(input);
+        assert_eq!(
+            report,
+            Report::from_reader(Reader::new(parsed.as_bytes())).unwrap()
+        );
+    }
+
+    #[test]
+    fn manifest() {
+        let manifest = parse_manifest("# release 1.2\nsrc/a.c\n\n/abs/b.c\n");
+        assert_eq!(manifest.len(), 2);
+        let mut report = ReportBuilder::file("/ci/build/src/a.c")
+            .line(1, 1)
+            .next_file("/abs/b.c")
+            .line(1, 1)
+            .next_file("/ci/build/src/c.c")
+            .line(1, 1)
+            .next_file("/ci/build/other/src/a.c.orig")
+            .line(1, 1)
+            .build();
+        retain_files(&mut report, &manifest);
+        let paths: Vec<_> = report
+            .sections
+            .keys()
+            .map(|key| key.source_file.display().to_string())
+            .collect();
+        assert_eq!(paths, vec!["/abs/b.c", "/ci/build/src/a.c"]);
+    }
+}
@@ -0,0 +1,57 @@
+//! Stable hash of the coverage shape of a report: which lines, branches and functions are
+//! instrumented and whether they are covered, ignoring the counts and the test names.
+use std::fmt::Write as _;
+
+use lcov::Report;
+
+use crate::hash::md5;
+use crate::summary;
+
+/// Lowercase hex MD5 of the canonical listing of the report, equal for the reports covering
+/// the same.
+pub fn fingerprint(report: &Report) -> String {
+    let mut canonical = String::new();
+    for (path, section) in summary::by_file(report) {
+        let _ = writeln!(canonical, "SF:{}", path.display());
+        for (key, value) in &section.functions {
+            let _ = writeln!(
+                canonical,
+                "FN:{},{},{}",
+                key.name,
+                value.start_line.unwrap_or(0),
+                (value.count > 0) as u8
+            );
+        }
+        for (key, value) in &section.branches {
+            let _ = writeln!(
+                canonical,
+                "BR:{},{},{},{}",
+                key.line,
+                key.block,
+                key.branch,
+                (value.taken.unwrap_or(0) > 0) as u8
+            );
+        }
+        for (key, value) in &section.lines {
+            let _ = writeln!(canonical, "DA:{},{}", key.line, (value.count > 0) as u8);
+        }
+    }
+    md5::hex_digest(canonical.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fingerprint;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn shape() {
+        let parse = |input: &str| Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let a = parse("TN:unit\nSF:a.c\nDA:1,3\nDA:2,0\nend_of_record\n");
+        let b = parse("SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\n");
+        let c = parse("SF:a.c\nDA:1,1\nDA:2,1\nend_of_record\n");
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+        assert_ne!(fingerprint(&b), fingerprint(&c));
+        assert_eq!(fingerprint(&a).len(), 32);
+    }
+}
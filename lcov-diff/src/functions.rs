@@ -0,0 +1,311 @@
+//! Line coverage of the functions: the lines from the `FN` start line up to the end line (lcov
+//! 2.x `FN:<start>,<end>,<name>`) or, without it, up to the start of the next function of the
+//! file are attributed to the function.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use lcov::report::section::Value as SectionValue;
+use lcov::Report;
+
+use crate::summary::{self, Counter};
+use crate::version::FunctionEnds;
+
+/// Line coverage of a function in both reports, `None` if it's missing in the report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDelta {
+    pub path: PathBuf,
+    pub name: String,
+    pub baseline: Option<Counter>,
+    pub current: Option<Counter>,
+}
+
+impl FunctionDelta {
+    /// Change of the line coverage in percentage points, missing functions are counted as 0%.
+    pub fn lines_delta(&self) -> f64 {
+        let percent =
+            |counter: Option<Counter>| counter.and_then(|c| c.percent()).unwrap_or_default();
+        percent(self.current) - percent(self.baseline)
+    }
+}
+
+/// Function of a source file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Function {
+    pub name: String,
+    /// `None` if the report has only the execution count (`FNDA`) of the function.
+    pub start: Option<u32>,
+    /// Last line, given by lcov 2.x reports only.
+    pub end: Option<u32>,
+    pub count: u64,
+}
+
+/// Functions of the section ordered by the start line, the ones without it last. The lcov
+/// crate reads the unshimmed lcov 2.x `FN:<start>,<end>,<name>` as the function `<end>,<name>`,
+/// it's split back and joined with the `FNDA` count of `<name>`.
+pub fn functions_of(section: &SectionValue) -> Vec<Function> {
+    functions_with_ends(section, &BTreeMap::new())
+}
+
+/// `functions_of` with the end lines the importers keep aside (`Loaded::function_ends`) of
+/// the source file.
+pub fn functions_with_ends(section: &SectionValue, ends: &BTreeMap<String, u32>) -> Vec<Function> {
+    let mut functions: BTreeMap<&str, Function> = BTreeMap::new();
+    for (key, value) in &section.functions {
+        let (name, end) = match key.name.split_once(',') {
+            Some((end, name)) if end.bytes().all(|b| b.is_ascii_digit()) => {
+                (name, end.parse().ok())
+            }
+            _ => (key.name.as_str(), None),
+        };
+        let function = functions.entry(name).or_insert_with(|| Function {
+            name: name.to_owned(),
+            start: None,
+            end: None,
+            count: 0,
+        });
+        function.start = function.start.or(value.start_line);
+        function.end = function.end.or(end).or_else(|| ends.get(name).copied());
+        function.count += value.count;
+    }
+    let mut functions: Vec<Function> = functions.into_values().collect();
+    functions.sort_by_key(|function| (function.start.is_none(), function.start));
+    functions
+}
+
+/// Name of the function of every line of the section. A line belongs to the innermost
+/// function, the one with the last start line, containing it; a function without the end line
+/// ends before the next one starts.
+pub fn attribute(section: &SectionValue) -> BTreeMap<u32, String> {
+    attribute_with_ends(section, &BTreeMap::new())
+}
+
+/// `attribute` with the end lines of `functions_with_ends`.
+pub fn attribute_with_ends(
+    section: &SectionValue,
+    ends: &BTreeMap<String, u32>,
+) -> BTreeMap<u32, String> {
+    let functions: Vec<(u32, Option<u32>, String)> = functions_with_ends(section, ends)
+        .into_iter()
+        .filter_map(|function| Some((function.start?, function.end, function.name)))
+        .collect();
+    let ranges: Vec<(u32, u32, &str)> = functions
+        .iter()
+        .enumerate()
+        .map(|(idx, (start, end, name))| {
+            let end = end.unwrap_or_else(|| {
+                functions
+                    .get(idx + 1)
+                    .map_or(u32::MAX, |next| next.0.saturating_sub(1).max(*start))
+            });
+            (*start, end, name.as_str())
+        })
+        .collect();
+    let mut lines = BTreeMap::new();
+    for key in section.lines.keys() {
+        let function = ranges
+            .iter()
+            .rev()
+            .find(|(start, end, _)| (*start..=*end).contains(&key.line));
+        if let Some((_, _, name)) = function {
+            lines.insert(key.line, name.to_string());
+        }
+    }
+    lines
+}
+
+/// Line coverage of every function with the start line, by name.
+fn function_lines(
+    section: &SectionValue,
+    ends: &BTreeMap<String, u32>,
+) -> BTreeMap<String, Counter> {
+    let mut functions: BTreeMap<String, Counter> = BTreeMap::new();
+    for function in functions_with_ends(section, ends) {
+        if function.start.is_some() {
+            functions.insert(function.name, Counter::default());
+        }
+    }
+    let attributed = attribute_with_ends(section, ends);
+    for (key, value) in &section.lines {
+        let counter = match attributed.get(&key.line) {
+            Some(name) => functions.get_mut(name).expect("attributed to a function"),
+            None => continue,
+        };
+        counter.found += 1;
+        if value.count > 0 {
+            counter.hit += 1;
+        }
+    }
+    functions
+}
+
+/// Functions of either report, ordered by path and name.
+pub fn function_deltas(baseline: &Report, current: &Report) -> Vec<FunctionDelta> {
+    let none = FunctionEnds::new();
+    function_deltas_with_ends(baseline, current, &none, &none)
+}
+
+/// `function_deltas` with the end lines of the functions of both reports.
+pub fn function_deltas_with_ends(
+    baseline: &Report,
+    current: &Report,
+    baseline_ends: &FunctionEnds,
+    current_ends: &FunctionEnds,
+) -> Vec<FunctionDelta> {
+    let lines = |files: &BTreeMap<PathBuf, SectionValue>, ends: &FunctionEnds, path| {
+        let none = BTreeMap::new();
+        files
+            .get(path)
+            .map(|section| function_lines(section, ends.get(path).unwrap_or(&none)))
+            .unwrap_or_default()
+    };
+    let baseline = summary::by_file(baseline);
+    let current = summary::by_file(current);
+    let paths: BTreeSet<&PathBuf> = baseline.keys().chain(current.keys()).collect();
+    let mut deltas = Vec::new();
+    for path in paths {
+        let old = lines(&baseline, baseline_ends, path);
+        let new = lines(&current, current_ends, path);
+        let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+        for name in names {
+            deltas.push(FunctionDelta {
+                path: path.clone(),
+                name: name.clone(),
+                baseline: old.get(name).copied(),
+                current: new.get(name).copied(),
+            });
+        }
+    }
+    deltas
+}
+
+/// Functions whose line coverage has changed, worst regressions first.
+pub fn changed_functions(deltas: &[FunctionDelta]) -> Vec<&FunctionDelta> {
+    let mut changed: Vec<&FunctionDelta> = deltas
+        .iter()
+        .filter(|function| function.baseline != function.current)
+        .collect();
+    changed.sort_by(|a, b| {
+        a.lines_delta()
+            .partial_cmp(&b.lines_delta())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    changed
+}
+
+/// Functions never executed in any of the reports, ordered by path and start line; the files
+/// for which `exclude` is true (e.g. the tests) are skipped.
+pub fn dead_functions<F>(reports: &[&Report], mut exclude: F) -> Vec<(PathBuf, Function)>
+where
+    F: FnMut(&Path) -> bool,
+{
+    let mut merged = Report::new();
+    for report in reports {
+        merged.merge_lossy((*report).clone());
+    }
+    let mut dead = Vec::new();
+    for (path, section) in summary::by_file(&merged) {
+        if exclude(&path) {
+            continue;
+        }
+        for function in functions_of(&section) {
+            if function.count == 0 {
+                dead.push((path.clone(), function));
+            }
+        }
+    }
+    dead
+}
+
+/// Markdown list of the dead code candidates.
+pub fn dead_markdown(dead: &[(PathBuf, Function)]) -> String {
+    let mut out = String::from("### Possibly dead code\n\n");
+    if dead.is_empty() {
+        out.push_str("Every function is executed.\n");
+        return out;
+    }
+    let _ = writeln!(out, "{} functions are never executed:\n", dead.len());
+    for (path, function) in dead {
+        let _ = match function.start {
+            Some(start) => writeln!(out, "- `{}` `{}:{}`", function.name, path.display(), start),
+            None => writeln!(out, "- `{}` `{}`", function.name, path.display()),
+        };
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        attribute, attribute_with_ends, changed_functions, dead_functions, function_deltas,
+        functions_of, functions_with_ends,
+    };
+    use crate::summary::by_file;
+    use crate::summary::Counter;
+    use crate::version::shim_with_ends;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn deltas() {
+        let baseline = "SF:a.c\nFN:1,parse\nFN:10,main\nDA:1,1\nDA:2,1\nDA:3,1\nDA:10,1\nDA:11,1\nend_of_record\n";
+        let current = "SF:a.c\nFN:1,parse\nFN:10,main\nDA:1,1\nDA:2,0\nDA:3,0\nDA:10,1\nDA:11,1\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+
+        let deltas = function_deltas(&baseline, &current);
+        assert_eq!(deltas.len(), 2);
+        let changed = changed_functions(&deltas);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].name, "parse");
+        assert_eq!(changed[0].baseline, Some(Counter { found: 3, hit: 3 }));
+        assert_eq!(changed[0].current, Some(Counter { found: 3, hit: 1 }));
+
+        let other = "SF:a.c\nFN:10,main\nFNDA:1,main\nend_of_record\nSF:tests/t.c\nFN:1,helper\nend_of_record\n";
+        let other = Report::from_reader(Reader::new(other.as_bytes())).unwrap();
+        let dead = dead_functions(&[&current, &other], |path| path.starts_with("tests"));
+        let names: Vec<&str> = dead.iter().map(|(_, f)| f.name.as_str()).collect();
+        assert_eq!(names, vec!["parse"]);
+        assert!(super::dead_markdown(&dead).ends_with("- `parse` `a.c:1`\n"));
+    }
+
+    #[test]
+    fn end_lines() {
+        // lcov 2.x: `callback` is nested into `run`, the lines after `run` belong to no function
+        let input = "SF:a.c\nFN:1,6,run\nFN:3,4,callback\nFNDA:2,run\nFNDA:0,callback\nDA:1,1\nDA:3,0\nDA:4,0\nDA:5,1\nDA:9,1\nend_of_record\n";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let section = &by_file(&report)[&std::path::PathBuf::from("a.c")];
+        let functions = functions_of(section);
+        assert_eq!(functions.len(), 2);
+        assert_eq!(
+            (
+                functions[0].name.as_str(),
+                functions[0].end,
+                functions[0].count
+            ),
+            ("run", Some(6), 2)
+        );
+        let lines: Vec<(u32, String)> = attribute(section).into_iter().collect();
+        assert_eq!(
+            lines,
+            vec![
+                (1, "run".to_owned()),
+                (3, "callback".to_owned()),
+                (4, "callback".to_owned()),
+                (5, "run".to_owned()),
+            ]
+        );
+
+        // The shimmed input has the end lines aside
+        let (shimmed, ends) = shim_with_ends(input, false);
+        let shimmed = Report::from_reader(Reader::new(shimmed.as_bytes())).unwrap();
+        let section = &by_file(&shimmed)[&std::path::PathBuf::from("a.c")];
+        let ends = &ends[&std::path::PathBuf::from("a.c")];
+        assert_eq!(functions_with_ends(section, ends), functions);
+        assert_eq!(
+            attribute_with_ends(section, ends)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            lines
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Async loading of the reports (the `async` feature), for the diffs embedded in an async
+//! orchestrator. The loads run on a thread of their own and the futures work with any
+//! executor, tokio included.
+use std::any::Any;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use crate::import::{self, ImportError, LoadOptions, Loaded};
+
+struct State<T> {
+    result: Option<thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// Future of the result of a closure run on its own thread, a panic of the closure is resumed
+/// when polled.
+pub struct Blocking<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+impl<T: Send + 'static> Blocking<T> {
+    pub fn spawn<F: FnOnce() -> T + Send + 'static>(f: F) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            result: None,
+            waker: None,
+        }));
+        let shared = Arc::clone(&state);
+        thread::spawn(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            let mut state = shared.lock().unwrap_or_else(|e| e.into_inner());
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+        Blocking { state }
+    }
+}
+
+impl<T> Future for Blocking<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.result.take() {
+            Some(Ok(value)) => Poll::Ready(value),
+            Some(Err(panic)) => resume(panic),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+fn resume<T>(panic: Box<dyn Any + Send>) -> T {
+    panic::resume_unwind(panic)
+}
+
+/// `import::load_file_with` without blocking the executor.
+pub fn load_report_async(
+    path: PathBuf,
+    options: LoadOptions,
+) -> Blocking<Result<Loaded, ImportError>> {
+    Blocking::spawn(move || import::load_file_with(path, &options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_report_async, Blocking};
+    use crate::import::LoadOptions;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake};
+    use std::thread::{self, Thread};
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Arc::new(Unpark(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn load_async() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.info");
+        std::fs::write(&path, "SF:a.c\nDA:1,1\nend_of_record\n").unwrap();
+        let loaded = block_on(load_report_async(path, LoadOptions::default())).unwrap();
+        assert_eq!(loaded.report.sections.len(), 1);
+        let missing = dir.path().join("missing.info");
+        assert!(block_on(load_report_async(missing, LoadOptions::default())).is_err());
+        assert_eq!(block_on(Blocking::spawn(|| 2 + 2)), 4);
+    }
+}
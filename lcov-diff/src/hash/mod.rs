@@ -0,0 +1,4 @@
+//! Digests of the source files and the outputs, for the fingerprints, the Coveralls source
+//! digests and the checksum manifests.
+pub(crate) mod md5;
+pub(crate) mod sha256;
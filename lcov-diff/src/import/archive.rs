@@ -0,0 +1,275 @@
+//! Archives of coverage fragments (`.zip`, `.tar`, `.tar.gz`), as the CI systems hand the
+//! artifacts over. The `.info` and `.lcov` members are read in memory and merged, the other
+//! members are ignored. Tar archives are read here after `gzip` unpacks them, zip archives are
+//! read by `unzip`; both have to be in `PATH`. Fragments with conflicting records (a function
+//! starting on different lines, different checksums) fail the merge.
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use lcov::Report;
+
+use super::{parse_str, ImportError};
+
+const TAR_BLOCK: usize = 512;
+
+/// Whether the input starts with the zip local file header.
+pub fn is_zip(input: &[u8]) -> bool {
+    input.starts_with(b"PK\x03\x04")
+}
+
+/// Whether the input starts with the ustar header.
+pub fn is_tar(input: &[u8]) -> bool {
+    input.get(257..262) == Some(b"ustar")
+}
+
+fn is_fragment(name: &str) -> bool {
+    name.ends_with(".info") || name.ends_with(".lcov")
+}
+
+/// Output of the command, at most `limit` bytes of it: the command is killed past them.
+fn run(command: &mut Command, what: &str, limit: Option<u64>) -> Result<Vec<u8>, ImportError> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdout = Vec::new();
+    if let Some(pipe) = child.stdout.take() {
+        pipe.take(limit.map_or(u64::MAX, |limit| limit + 1))
+            .read_to_end(&mut stdout)?;
+    }
+    if let Some(limit) = limit.filter(|&limit| stdout.len() as u64 > limit) {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(ImportError::TooLargeUnpacked { limit });
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(ImportError::Io(io::Error::other(format!(
+            "{} failed: {}",
+            what,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))));
+    }
+    Ok(stdout)
+}
+
+/// Unpacked content of the gzipped file.
+pub fn gunzip(path: &Path) -> Result<Vec<u8>, ImportError> {
+    gunzip_limited(path, None)
+}
+
+/// Unpacked content of the gzipped file, failing past `limit` unpacked bytes.
+pub fn gunzip_limited(path: &Path, limit: Option<u64>) -> Result<Vec<u8>, ImportError> {
+    run(
+        Command::new("gzip").arg("-dc").arg(path),
+        &format!("gzip unpacking {:?}", path),
+        limit,
+    )
+}
+
+/// Regular files of the tar archive, with their paths.
+pub fn tar_entries(input: &[u8]) -> Result<Vec<(String, Vec<u8>)>, ImportError> {
+    let invalid = |message: &str| ImportError::Invalid(format!("tar archive: {}", message));
+    let field = |header: &[u8], start: usize, end: usize| {
+        let field = &header[start..end];
+        let len = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+        String::from_utf8_lossy(&field[..len]).into_owned()
+    };
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while let Some(header) = input.get(offset..offset + TAR_BLOCK) {
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let size = field(header, 124, 136);
+        let size = usize::from_str_radix(size.trim(), 8)
+            .map_err(|_| invalid(&format!("invalid size {:?}", size)))?;
+        let start = offset + TAR_BLOCK;
+        let content = input
+            .get(start..start + size)
+            .ok_or_else(|| invalid("truncated"))?;
+        if matches!(header[156], b'0' | 0) {
+            let (prefix, name) = (field(header, 345, 500), field(header, 0, 100));
+            let path = if prefix.is_empty() {
+                name
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+            entries.push((path, content.to_vec()));
+        }
+        offset = start + size.div_ceil(TAR_BLOCK) * TAR_BLOCK;
+    }
+    Ok(entries)
+}
+
+/// Merges the fragments of the archive members.
+pub fn merge_fragments<I: IntoIterator<Item = (String, Vec<u8>)>>(
+    entries: I,
+) -> Result<Report, ImportError> {
+    Ok(merge_fragments_text(entries)?.0)
+}
+
+/// Merges the fragments of the archive members, with their lcov text one after another.
+pub fn merge_fragments_text<I: IntoIterator<Item = (String, Vec<u8>)>>(
+    entries: I,
+) -> Result<(Report, String), ImportError> {
+    merge_fragments_timed(entries).map(|(report, text, _)| (report, text))
+}
+
+/// `merge_fragments_text` with the time the merges of the parsed fragments took.
+pub fn merge_fragments_timed<I: IntoIterator<Item = (String, Vec<u8>)>>(
+    entries: I,
+) -> Result<(Report, String, Duration), ImportError> {
+    let mut report = Report::new();
+    let mut text = String::new();
+    let mut merge_time = Duration::ZERO;
+    for (name, content) in entries {
+        if !is_fragment(&name) {
+            continue;
+        }
+        let content = String::from_utf8(content)
+            .map_err(|_| ImportError::Invalid(format!("{} isn't utf-8", name)))?;
+        let fragment =
+            parse_str(&content).map_err(|e| ImportError::Invalid(format!("{}: {}", name, e)))?;
+        let start = Instant::now();
+        report.merge(fragment)?;
+        merge_time += start.elapsed();
+        text.push_str(&content);
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+    }
+    Ok((report, text, merge_time))
+}
+
+/// The zip member name as an `unzip` pattern matching only itself.
+fn literal_pattern(name: &str) -> String {
+    let mut pattern = String::with_capacity(name.len());
+    for c in name.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern
+}
+
+/// Merges the fragments of the zip archive.
+pub fn read_zip(path: &Path) -> Result<Report, ImportError> {
+    read_zip_limited(path, None)
+}
+
+/// Merges the fragments of the zip archive, failing past `limit` unpacked bytes of them all.
+pub fn read_zip_limited(path: &Path, limit: Option<u64>) -> Result<Report, ImportError> {
+    merge_fragments(zip_fragments(path, limit)?)
+}
+
+/// The `.info` and `.lcov` members of the zip archive, failing past `limit` unpacked bytes of
+/// them all.
+pub fn zip_fragments(
+    path: &Path,
+    limit: Option<u64>,
+) -> Result<Vec<(String, Vec<u8>)>, ImportError> {
+    let what = format!("unzip reading {:?}", path);
+    let names = run(Command::new("unzip").arg("-Z1").arg(path), &what, None)?;
+    let mut entries = Vec::new();
+    let mut left = limit;
+    for name in String::from_utf8_lossy(&names).lines() {
+        if is_fragment(name) {
+            let content = run(
+                Command::new("unzip")
+                    .arg("-p")
+                    .arg(path)
+                    .arg(literal_pattern(name)),
+                &what,
+                left,
+            )
+            .map_err(|e| match (e, limit) {
+                (ImportError::TooLargeUnpacked { .. }, Some(limit)) => {
+                    ImportError::TooLargeUnpacked { limit }
+                }
+                (e, _) => e,
+            })?;
+            left = left.map(|left| left - content.len() as u64);
+            entries.push((name.to_owned(), content));
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_tar, literal_pattern, merge_fragments, merge_fragments_text, tar_entries};
+    use crate::builder::ReportBuilder;
+    use crate::testing::assert_reports_equal;
+
+    fn header(name: &str, size: usize) -> Vec<u8> {
+        let mut header = vec![0; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        let size = format!("{:011o}\0", size);
+        header[124..136].copy_from_slice(size.as_bytes());
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        header
+    }
+
+    #[test]
+    fn tar_fragments() {
+        let mut tar = Vec::new();
+        for (name, content) in [
+            ("unit/lcov.info", "SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\n"),
+            ("README.md", "not coverage"),
+            ("it/lcov.info", "SF:a.c\nDA:2,1\nend_of_record\n"),
+        ] {
+            tar.extend(header(name, content.len()));
+            tar.extend(content.as_bytes());
+            tar.resize(tar.len().div_ceil(512) * 512, 0);
+        }
+        tar.extend([0; 1024]);
+        assert!(is_tar(&tar));
+
+        let entries = tar_entries(&tar).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[1],
+            ("README.md".to_owned(), b"not coverage".to_vec())
+        );
+        let (_, text) = merge_fragments_text(entries.clone()).unwrap();
+        assert_eq!(
+            text,
+            "SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\nSF:a.c\nDA:2,1\nend_of_record\n"
+        );
+        assert_reports_equal(
+            &merge_fragments(entries).unwrap(),
+            &ReportBuilder::file("a.c").line(1, 1).line(2, 1).build(),
+        );
+        assert!(tar_entries(&tar[..520]).is_err());
+    }
+
+    #[test]
+    fn conflicting_fragments() {
+        let entries = vec![
+            (
+                "a.info".to_owned(),
+                b"SF:a.c\nFN:1,f\nend_of_record\n".to_vec(),
+            ),
+            (
+                "b.info".to_owned(),
+                b"SF:a.c\nFN:2,f\nend_of_record\n".to_vec(),
+            ),
+        ];
+        assert_eq!(
+            merge_fragments(entries).unwrap_err().to_string(),
+            "unmatched start line of function"
+        );
+    }
+
+    #[test]
+    fn unzip_patterns() {
+        assert_eq!(literal_pattern("unit/lcov.info"), "unit/lcov.info");
+        assert_eq!(literal_pattern("a[1]*?.info"), "a\\[1\\]\\*\\?.info");
+        assert_eq!(literal_pattern("a\\b.info"), "a\\\\b.info");
+    }
+}
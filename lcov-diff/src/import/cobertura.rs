@@ -0,0 +1,180 @@
+//! Cobertura xml importer.
+//!
+//! Every `<class>` becomes a section of its `filename` (joined to the first `<source>`),
+//! `<line>` elements become line records and their `condition-coverage` becomes branches:
+//! the covered ones are taken once, since Cobertura doesn't keep the exact counts.
+use std::path::PathBuf;
+
+use lcov::report::section::{branch, function, line, Key as SectionKey};
+use lcov::Report;
+
+use super::{number_attr, ImportError};
+use crate::xml::{Event, Reader};
+
+pub fn parse(input: &str) -> Result<Report, ImportError> {
+    let mut report = Report::new();
+    let mut source: Option<PathBuf> = None;
+    let mut in_source = false;
+    let mut file: Option<SectionKey> = None;
+    let mut method: Option<(String, Option<u32>, u64)> = None;
+    let mut seen_root = false;
+
+    for event in Reader::new(input) {
+        let event = event?;
+        match &event {
+            Event::Start { name, .. } if !seen_root => {
+                if name != "coverage" {
+                    return Err(ImportError::Invalid(format!(
+                        "`coverage` root element expected, found `{}`",
+                        name
+                    )));
+                }
+                seen_root = true;
+            }
+            Event::Start { name, .. } if name == "source" => in_source = true,
+            Event::Text(text) if in_source && source.is_none() && text != "." => {
+                source = Some(PathBuf::from(text));
+            }
+            Event::End { name } if name == "source" => in_source = false,
+            Event::Start { name, .. } if name == "class" => {
+                let filename = event.attr("filename").ok_or_else(|| {
+                    ImportError::Invalid("`filename` attribute is missing".to_owned())
+                })?;
+                let path = match &source {
+                    Some(source) => source.join(filename),
+                    None => PathBuf::from(filename),
+                };
+                file = Some(SectionKey {
+                    test_name: String::new(),
+                    source_file: path,
+                });
+            }
+            Event::End { name } if name == "class" => file = None,
+            Event::Start { name, .. } if name == "method" => {
+                let name = event.attr("name").unwrap_or_default().to_owned();
+                method = Some((name, None, 0));
+            }
+            Event::End { name } if name == "method" => {
+                if let (Some(key), Some((name, start_line, count))) = (&file, method.take()) {
+                    let section = report.sections.entry(key.clone()).or_default();
+                    section.functions.insert(
+                        function::Key { name },
+                        function::Value { start_line, count },
+                    );
+                }
+            }
+            Event::Start { name, .. } if name == "line" => {
+                let number: u32 = number_attr(&event, "number")?;
+                let hits: u64 = number_attr(&event, "hits")?;
+                if let Some((_, start_line, count)) = &mut method {
+                    // Lines of the method are repeated in the class, the first one is the start
+                    if start_line.is_none() {
+                        *start_line = Some(number);
+                        *count = hits;
+                    }
+                    continue;
+                }
+                let key = match &file {
+                    Some(key) => key,
+                    None => continue,
+                };
+                let section = report.sections.entry(key.clone()).or_default();
+                let value = section.lines.entry(line::Key { line: number }).or_default();
+                value.count += hits;
+
+                if event.attr("branch") == Some("true") {
+                    let (covered, total) = event
+                        .attr("condition-coverage")
+                        .and_then(condition_coverage)
+                        .unwrap_or((0, 0));
+                    for idx in 0..total {
+                        let taken = if hits == 0 {
+                            None
+                        } else if idx < covered {
+                            Some(1)
+                        } else {
+                            Some(0)
+                        };
+                        section.branches.insert(
+                            branch::Key {
+                                line: number,
+                                block: 0,
+                                branch: idx,
+                            },
+                            branch::Value { taken },
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if !seen_root {
+        return Err(ImportError::Invalid("empty document".to_owned()));
+    }
+    Ok(report)
+}
+
+/// Parses `50% (1/2)` into covered and total numbers.
+fn condition_coverage(value: &str) -> Option<(u32, u32)> {
+    let ratio = value.split('(').nth(1)?.trim_end_matches(')');
+    let mut parts = ratio.split('/');
+    let covered = parts.next()?.trim().parse().ok()?;
+    let total = parts.next()?.trim().parse().ok()?;
+    Some((covered, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn import() {
+        let input = r#"<?xml version="1.0" ?>
+<coverage line-rate="0.5" version="1.9">
+  <sources><source>/repo</source></sources>
+  <packages><package name="src"><classes>
+    <class name="a.py" filename="src/a.py">
+      <methods>
+        <method name="main" signature="()">
+          <lines><line number="2" hits="3"/></lines>
+        </method>
+      </methods>
+      <lines>
+        <line number="2" hits="3"/>
+        <line number="3" hits="1" branch="true" condition-coverage="50% (1/2)"/>
+        <line number="4" hits="0"/>
+      </lines>
+    </class>
+  </classes></package></packages>
+</coverage>
+"#;
+        let records: Vec<String> = parse(input)
+            .unwrap()
+            .into_records()
+            .map(|rec| rec.to_string())
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                "TN:",
+                "SF:/repo/src/a.py",
+                "FN:2,main",
+                "FNDA:3,main",
+                "FNF:1",
+                "FNH:1",
+                "BRDA:3,0,0,1",
+                "BRDA:3,0,1,0",
+                "BRF:2",
+                "BRH:1",
+                "DA:2,3",
+                "DA:3,1",
+                "DA:4,0",
+                "LF:3",
+                "LH:2",
+                "end_of_record",
+            ]
+        );
+        assert!(parse("<report/>").is_err());
+    }
+}
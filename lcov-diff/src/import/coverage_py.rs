@@ -0,0 +1,185 @@
+//! coverage.py json importer (`coverage json`), its xml output is Cobertura.
+//!
+//! Executed lines are counted once since coverage.py doesn't keep hit counts. Arcs
+//! (`executed_branches`/`missing_branches`) become branches of their source line, numbered
+//! in the order of the destination lines, and functions of the `functions` map (coverage.py
+//! 7.5+) start at their first line.
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use lcov::report::section::{branch, function, line, Key as SectionKey, Value as SectionValue};
+use lcov::Report;
+
+use super::ImportError;
+use crate::json::Value;
+
+/// Whether the document looks like coverage.py report: `meta` and `files` objects.
+pub fn matches(input: &Value) -> bool {
+    input.get("meta").is_some() && input.get("files").is_some()
+}
+
+pub fn parse(input: &Value) -> Result<Report, ImportError> {
+    let files = input
+        .get("files")
+        .and_then(Value::as_object)
+        .ok_or_else(|| ImportError::Invalid("`files` object is missing".to_owned()))?;
+    let mut report = Report::new();
+    for (path, file) in files {
+        report.sections.insert(
+            SectionKey {
+                test_name: String::new(),
+                source_file: PathBuf::from(path),
+            },
+            section(file)?,
+        );
+    }
+    Ok(report)
+}
+
+fn section(file: &Value) -> Result<SectionValue, ImportError> {
+    let mut section = SectionValue::default();
+    for line in numbers(file, "executed_lines")? {
+        section.lines.insert(
+            line::Key { line },
+            line::Value {
+                count: 1,
+                ..Default::default()
+            },
+        );
+    }
+    for line in numbers(file, "missing_lines")? {
+        section.lines.entry(line::Key { line }).or_default();
+    }
+
+    // Destinations of the arcs by source line, negative ones are exits of the code object
+    let mut arcs: BTreeMap<u32, Vec<(i64, bool)>> = BTreeMap::new();
+    for (field, taken) in &[("executed_branches", true), ("missing_branches", false)] {
+        for arc in array(file, field)? {
+            let pair = arc.as_array().unwrap_or_default();
+            match pair {
+                [Value::Number(from), Value::Number(to)] if *from > 0.0 => arcs
+                    .entry(*from as u32)
+                    .or_default()
+                    .push((*to as i64, *taken)),
+                _ => return Err(ImportError::Invalid(format!("invalid `{}` arc", field))),
+            }
+        }
+    }
+    for (line, mut destinations) in arcs {
+        destinations.sort_unstable();
+        let executed = section
+            .lines
+            .get(&line::Key { line })
+            .is_some_and(|value| value.count > 0);
+        for (idx, (_, taken)) in destinations.into_iter().enumerate() {
+            let taken = match (executed, taken) {
+                (false, _) => None,
+                (true, true) => Some(1),
+                (true, false) => Some(0),
+            };
+            section.branches.insert(
+                branch::Key {
+                    line,
+                    block: 0,
+                    branch: idx as u32,
+                },
+                branch::Value { taken },
+            );
+        }
+    }
+
+    if let Some(functions) = file.get("functions").and_then(Value::as_object) {
+        for (name, function) in functions {
+            // The empty name is the module level code
+            if name.is_empty() {
+                continue;
+            }
+            let executed = numbers(function, "executed_lines")?;
+            let missing = numbers(function, "missing_lines")?;
+            let start_line = executed.iter().chain(&missing).min().copied();
+            section.functions.insert(
+                function::Key { name: name.clone() },
+                function::Value {
+                    start_line,
+                    count: if executed.is_empty() { 0 } else { 1 },
+                },
+            );
+        }
+    }
+    Ok(section)
+}
+
+/// Optional array field, missing ones are empty.
+fn array<'a>(value: &'a Value, field: &str) -> Result<&'a [Value], ImportError> {
+    match value.get(field) {
+        None => Ok(&[]),
+        Some(value) => value
+            .as_array()
+            .ok_or_else(|| ImportError::Invalid(format!("`{}` must be an array", field))),
+    }
+}
+
+fn numbers(value: &Value, field: &str) -> Result<Vec<u32>, ImportError> {
+    array(value, field)?
+        .iter()
+        .map(|line| {
+            line.as_u64()
+                .map(|line| line as u32)
+                .ok_or_else(|| ImportError::Invalid(format!("invalid line in `{}`", field)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::json;
+
+    #[test]
+    fn import() {
+        let input = r#"{
+  "meta": {"format": 3, "version": "7.6.1", "branch_coverage": true},
+  "files": {
+    "pkg/a.py": {
+      "executed_lines": [1, 2, 3],
+      "missing_lines": [5],
+      "excluded_lines": [],
+      "executed_branches": [[3, 5], [3, -1]],
+      "missing_branches": [[3, 4]],
+      "functions": {
+        "main": {"executed_lines": [2, 3], "missing_lines": [5]},
+        "": {"executed_lines": [1], "missing_lines": []}
+      }
+    }
+  }
+}"#;
+        let records: Vec<String> = parse(&json::parse(input).unwrap())
+            .unwrap()
+            .into_records()
+            .map(|rec| rec.to_string())
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                "TN:",
+                "SF:pkg/a.py",
+                "FN:2,main",
+                "FNDA:1,main",
+                "FNF:1",
+                "FNH:1",
+                "BRDA:3,0,0,1",
+                "BRDA:3,0,1,0",
+                "BRDA:3,0,2,1",
+                "BRF:3",
+                "BRH:2",
+                "DA:1,1",
+                "DA:2,1",
+                "DA:3,1",
+                "DA:5,0",
+                "LF:4",
+                "LH:3",
+                "end_of_record",
+            ]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! gcov json intermediate format importer (`gcov --json-format`, gcc 9+).
+//!
+//! gcov writes a file per object file, `load_dir` merges all of them found in a directory.
+//! Compressed `.gcov.json.gz` files are unpacked by `gzip`, which has to be in `PATH`.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use lcov::report::section::{branch, function, line, Key as SectionKey};
+use lcov::Report;
+
+use super::{archive, ImportError};
+use crate::json::{self, Value};
+
+/// Whether the document is gcov output: it has `gcc_version`.
+pub fn matches(input: &Value) -> bool {
+    input.get("gcc_version").is_some()
+}
+
+pub fn parse(input: &Value) -> Result<Report, ImportError> {
+    let cwd = input
+        .get("current_working_directory")
+        .and_then(Value::as_str)
+        .map(PathBuf::from);
+    let mut report = Report::new();
+    for file in array(input, "files")? {
+        let name = file
+            .get("file")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ImportError::Invalid("`file` is missing".to_owned()))?;
+        let source_file = match &cwd {
+            Some(cwd) => cwd.join(name),
+            None => PathBuf::from(name),
+        };
+        let section = report
+            .sections
+            .entry(SectionKey {
+                test_name: String::new(),
+                source_file,
+            })
+            .or_default();
+
+        for function in array(file, "functions")? {
+            let name = function
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or_else(|| ImportError::Invalid("function `name` is missing".to_owned()))?;
+            let start_line = function
+                .get("start_line")
+                .and_then(Value::as_u64)
+                .map(|line| line as u32);
+            let count = number(function, "execution_count")?;
+            section
+                .functions
+                .entry(function::Key {
+                    name: name.to_owned(),
+                })
+                .or_insert(function::Value {
+                    start_line,
+                    count: 0,
+                })
+                .count += count;
+        }
+
+        for value in array(file, "lines")? {
+            let line = number(value, "line_number")? as u32;
+            let count = number(value, "count")?;
+            section.lines.entry(line::Key { line }).or_default().count += count;
+
+            let branches = match value.get("branches").and_then(Value::as_array) {
+                Some(branches) => branches,
+                None => continue,
+            };
+            // Lines of inlined or template code are repeated, their branches are appended
+            let first = section
+                .branches
+                .keys()
+                .filter(|key| key.line == line)
+                .count() as u32;
+            for (idx, branch) in branches.iter().enumerate() {
+                let taken = number(branch, "count")?;
+                section.branches.insert(
+                    branch::Key {
+                        line,
+                        block: 0,
+                        branch: first + idx as u32,
+                    },
+                    branch::Value {
+                        taken: if count > 0 { Some(taken) } else { None },
+                    },
+                );
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Reads `.gcov.json` or `.gcov.json.gz` file.
+pub fn read_file(path: &Path) -> Result<Report, ImportError> {
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        read_gz(path)
+    } else {
+        parse(&json::parse(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// Reads the gzipped gcov json file whatever its extension.
+pub fn read_gz(path: &Path) -> Result<Report, ImportError> {
+    parse_unpacked(archive::gunzip(path)?)
+}
+
+/// Parses the unpacked content of a gzipped gcov json file.
+pub(crate) fn parse_unpacked(input: Vec<u8>) -> Result<Report, ImportError> {
+    let input = String::from_utf8(input)
+        .map_err(|_| ImportError::Invalid("gcov json isn't utf-8".to_owned()))?;
+    parse(&json::parse(&input)?)
+}
+
+/// Merges all gcov json files of the directory and its subdirectories.
+pub fn load_dir(dir: &Path) -> Result<Report, ImportError> {
+    let mut report = Report::new();
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = fs::read_dir(&dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+        for path in entries {
+            let name = path.to_string_lossy();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if name.ends_with(".gcov.json") || name.ends_with(".gcov.json.gz") {
+                report.merge_lossy(read_file(&path)?);
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn array<'a>(value: &'a Value, field: &str) -> Result<&'a [Value], ImportError> {
+    value
+        .get(field)
+        .and_then(Value::as_array)
+        .ok_or_else(|| ImportError::Invalid(format!("`{}` array is missing", field)))
+}
+
+fn number(value: &Value, field: &str) -> Result<u64, ImportError> {
+    value
+        .get(field)
+        .and_then(Value::as_u64)
+        .ok_or_else(|| ImportError::Invalid(format!("`{}` number is missing", field)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::load_dir;
+    use std::fs;
+
+    #[test]
+    fn merge_dir() {
+        let a = r#"{"format_version": "1", "gcc_version": "11.4.0",
+  "current_working_directory": "/repo", "data_file": "a.gcda",
+  "files": [{"file": "src/a.c",
+    "functions": [{"name": "main", "demangled_name": "main", "start_line": 1, "end_line": 5,
+      "execution_count": 1, "blocks": 4, "blocks_executed": 3}],
+    "lines": [
+      {"line_number": 2, "count": 1, "unexecuted_block": false, "function_name": "main",
+       "branches": [{"count": 1, "fallthrough": true, "throw": false},
+                    {"count": 0, "fallthrough": false, "throw": false}]},
+      {"line_number": 3, "count": 0, "unexecuted_block": true, "function_name": "main",
+       "branches": []}
+    ]}]}"#;
+        let b = r#"{"format_version": "1", "gcc_version": "11.4.0",
+  "current_working_directory": "/repo", "data_file": "b.gcda",
+  "files": [{"file": "src/a.c", "functions": [],
+    "lines": [{"line_number": 3, "count": 2, "unexecuted_block": false, "function_name": "main"}]}]}"#;
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("obj")).unwrap();
+        fs::write(dir.path().join("a.gcov.json"), a).unwrap();
+        fs::write(dir.path().join("obj/b.gcov.json"), b).unwrap();
+        fs::write(dir.path().join("notes.json"), "{}").unwrap();
+
+        let records: Vec<String> = load_dir(dir.path())
+            .unwrap()
+            .into_records()
+            .map(|rec| rec.to_string())
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                "TN:",
+                "SF:/repo/src/a.c",
+                "FN:1,main",
+                "FNDA:1,main",
+                "FNF:1",
+                "FNH:1",
+                "BRDA:2,0,0,1",
+                "BRDA:2,0,1,0",
+                "BRF:2",
+                "BRH:1",
+                "DA:2,1",
+                "DA:3,2",
+                "LF:2",
+                "LH:2",
+                "end_of_record",
+            ]
+        );
+    }
+}
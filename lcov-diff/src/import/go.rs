@@ -0,0 +1,120 @@
+//! Go coverprofile importer (`go test -coverprofile`).
+//!
+//! Every line of a block gets its count, lines shared by several blocks get the biggest one.
+//! Repeated blocks of concatenated profiles are summed up (or-ed in `set` mode).
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use lcov::report::section::{line, Key as SectionKey};
+use lcov::Report;
+
+use super::ImportError;
+
+/// File, start line and column, end line and column of the block.
+type Block<'a> = (&'a str, u32, u32, u32, u32);
+
+/// Whether the text is a coverprofile: it starts with the `mode:` line.
+pub fn matches(input: &str) -> bool {
+    input.trim_start().starts_with("mode:")
+}
+
+pub fn parse(input: &str) -> Result<Report, ImportError> {
+    let mut lines = input.lines().filter(|line| !line.trim().is_empty());
+    let mode = lines
+        .next()
+        .and_then(|line| line.strip_prefix("mode:"))
+        .map(str::trim)
+        .ok_or_else(|| ImportError::Invalid("`mode:` line expected".to_owned()))?;
+    if !matches!(mode, "set" | "count" | "atomic") {
+        return Err(ImportError::Invalid(format!("unknown mode: {}", mode)));
+    }
+
+    let mut blocks: BTreeMap<Block<'_>, u64> = BTreeMap::new();
+    for text in lines {
+        // Concatenated profiles repeat the mode line
+        if text.starts_with("mode:") {
+            continue;
+        }
+        let block =
+            block(text).ok_or_else(|| ImportError::Invalid(format!("invalid block: {}", text)))?;
+        let count = blocks.entry(block.0).or_default();
+        *count = if mode == "set" {
+            (*count).max(block.1)
+        } else {
+            *count + block.1
+        };
+    }
+
+    let mut report = Report::new();
+    for ((file, start_line, _, end_line, _), count) in blocks {
+        let section = report
+            .sections
+            .entry(SectionKey {
+                test_name: String::new(),
+                source_file: PathBuf::from(file),
+            })
+            .or_default();
+        for line in start_line..=end_line {
+            let value = section.lines.entry(line::Key { line }).or_default();
+            value.count = value.count.max(count);
+        }
+    }
+    Ok(report)
+}
+
+/// Parses `file.go:10.2,12.16 2 1` into the position and the count.
+fn block(text: &str) -> Option<(Block<'_>, u64)> {
+    let (file, rest) = text.rsplit_once(':')?;
+    let mut fields = rest.split_whitespace();
+    let (start, end) = fields.next()?.split_once(',')?;
+    let _statements = fields.next()?;
+    let count = fields.next()?.parse().ok()?;
+    let position = |value: &str| -> Option<(u32, u32)> {
+        let (line, column) = value.split_once('.')?;
+        Some((line.parse().ok()?, column.parse().ok()?))
+    };
+    let (start_line, start_column) = position(start)?;
+    let (end_line, end_column) = position(end)?;
+    Some((
+        (file, start_line, start_column, end_line, end_column),
+        count,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matches, parse};
+
+    #[test]
+    fn import() {
+        let input = "\
+mode: count
+example.com/app/main.go:3.13,5.2 1 2
+example.com/app/main.go:5.2,7.3 2 0
+mode: count
+example.com/app/main.go:3.13,5.2 1 1
+";
+        assert!(matches(input));
+        let records: Vec<String> = parse(input)
+            .unwrap()
+            .into_records()
+            .map(|rec| rec.to_string())
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                "TN:",
+                "SF:example.com/app/main.go",
+                "DA:3,3",
+                "DA:4,3",
+                "DA:5,3",
+                "DA:6,0",
+                "DA:7,0",
+                "LF:5",
+                "LH:3",
+                "end_of_record",
+            ]
+        );
+        assert!(parse("mode: fast\n").is_err());
+    }
+}
@@ -0,0 +1,198 @@
+//! Istanbul/NYC `coverage-final.json` importer.
+//!
+//! Lines get the biggest count of the statements starting there, like `nyc report` does.
+//! Every entry of `branchMap` is a block of its line and keeps the order of its locations,
+//! so branches keep the identity lcov reporter of nyc would give them.
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use lcov::report::section::{branch, function, line, Key as SectionKey, Value as SectionValue};
+use lcov::Report;
+
+use super::ImportError;
+use crate::json::Value;
+
+/// Whether the document looks like Istanbul coverage: file coverage objects by path, at least
+/// one of them.
+pub fn matches(input: &Value) -> bool {
+    input.as_object().is_some_and(|files| {
+        !files.is_empty()
+            && files
+                .iter()
+                .all(|(_, file)| file.get("statementMap").is_some())
+    })
+}
+
+pub fn parse(input: &Value) -> Result<Report, ImportError> {
+    let files = input
+        .as_object()
+        .ok_or_else(|| ImportError::Invalid("object of file coverages expected".to_owned()))?;
+    let mut report = Report::new();
+    for (path, file) in files {
+        // `path` is the absolute one, keys are usually the same
+        let path = file.get("path").and_then(Value::as_str).unwrap_or(path);
+        report.sections.insert(
+            SectionKey {
+                test_name: String::new(),
+                source_file: PathBuf::from(path),
+            },
+            section(file)?,
+        );
+    }
+    Ok(report)
+}
+
+fn section(file: &Value) -> Result<SectionValue, ImportError> {
+    let mut section = SectionValue::default();
+
+    let counts = counts_by_id(file, "s")?;
+    for (id, statement) in object(file, "statementMap")? {
+        let line = start_line(statement)
+            .ok_or_else(|| ImportError::Invalid(format!("invalid statement `{}`", id)))?;
+        let count = count(&counts, id)?;
+        let value = section.lines.entry(line::Key { line }).or_default();
+        value.count = value.count.max(count);
+    }
+
+    let counts = counts_by_id(file, "f")?;
+    for (id, function) in object(file, "fnMap")? {
+        let name = function
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ImportError::Invalid(format!("function `{}` has no name", id)))?;
+        let start_line = function.get("decl").and_then(start_line).or_else(|| {
+            function
+                .get("line")
+                .and_then(Value::as_u64)
+                .map(|l| l as u32)
+        });
+        section.functions.insert(
+            function::Key {
+                name: name.to_owned(),
+            },
+            function::Value {
+                start_line,
+                count: count(&counts, id)?,
+            },
+        );
+    }
+
+    let counts = counts_by_id(file, "b")?;
+    for (block, (id, branch)) in object(file, "branchMap")?.iter().enumerate() {
+        let line = branch
+            .get("line")
+            .and_then(Value::as_u64)
+            .map(|l| l as u32)
+            .or_else(|| branch.get("loc").and_then(start_line))
+            .ok_or_else(|| ImportError::Invalid(format!("invalid branch `{}`", id)))?;
+        let taken = counts
+            .get(id.as_str())
+            .and_then(|counts| counts.as_array())
+            .ok_or_else(|| {
+                ImportError::Invalid(format!("counts of branch `{}` are missing", id))
+            })?;
+        for (idx, taken) in taken.iter().enumerate() {
+            section.branches.insert(
+                branch::Key {
+                    line,
+                    block: block as u32,
+                    branch: idx as u32,
+                },
+                branch::Value {
+                    taken: taken.as_u64(),
+                },
+            );
+        }
+    }
+    Ok(section)
+}
+
+fn object<'a>(value: &'a Value, field: &str) -> Result<&'a [(String, Value)], ImportError> {
+    value
+        .get(field)
+        .and_then(Value::as_object)
+        .ok_or_else(|| ImportError::Invalid(format!("`{}` object is missing", field)))
+}
+
+/// Counts of the `field` object by id, looked up once per statement, function and branch.
+fn counts_by_id<'a>(
+    file: &'a Value,
+    field: &str,
+) -> Result<HashMap<&'a str, &'a Value>, ImportError> {
+    Ok(object(file, field)?
+        .iter()
+        .map(|(id, count)| (id.as_str(), count))
+        .collect())
+}
+
+fn count(counts: &HashMap<&str, &Value>, id: &str) -> Result<u64, ImportError> {
+    counts
+        .get(id)
+        .and_then(|count| count.as_u64())
+        .ok_or_else(|| ImportError::Invalid(format!("count of `{}` is missing", id)))
+}
+
+/// Line of the `{"start": {"line": ..}}` location.
+fn start_line(location: &Value) -> Option<u32> {
+    location
+        .get("start")?
+        .get("line")?
+        .as_u64()
+        .map(|line| line as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matches, parse};
+    use crate::json;
+
+    #[test]
+    fn import() {
+        let input = r#"{
+  "/repo/src/a.js": {
+    "path": "/repo/src/a.js",
+    "statementMap": {
+      "0": {"start": {"line": 1, "column": 0}, "end": {"line": 3, "column": 1}},
+      "1": {"start": {"line": 2, "column": 2}, "end": {"line": 2, "column": 20}},
+      "2": {"start": {"line": 2, "column": 22}, "end": {"line": 2, "column": 30}}
+    },
+    "fnMap": {
+      "0": {"name": "main", "decl": {"start": {"line": 1, "column": 9}, "end": {"line": 1, "column": 13}}, "line": 1}
+    },
+    "branchMap": {
+      "0": {"loc": {"start": {"line": 2, "column": 2}}, "type": "if", "locations": [{}, {}], "line": 2}
+    },
+    "s": {"0": 1, "1": 1, "2": 0},
+    "f": {"0": 1},
+    "b": {"0": [1, 0]}
+  }
+}"#;
+        let input = json::parse(input).unwrap();
+        assert!(matches(&input));
+        let records: Vec<String> = parse(&input)
+            .unwrap()
+            .into_records()
+            .map(|rec| rec.to_string())
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                "TN:",
+                "SF:/repo/src/a.js",
+                "FN:1,main",
+                "FNDA:1,main",
+                "FNF:1",
+                "FNH:1",
+                "BRDA:2,0,0,1",
+                "BRDA:2,0,1,0",
+                "BRF:2",
+                "BRH:1",
+                "DA:1,1",
+                "DA:2,1",
+                "LF:2",
+                "LH:2",
+                "end_of_record",
+            ]
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! JaCoCo xml importer.
+//!
+//! A section is created per `<sourcefile>` (`SF:` is `package/file.java`). Covered instructions
+//! of the line are used as its execution count and the covered branches are taken once,
+//! since JaCoCo doesn't count executions. Methods are named `Class.method(descriptor)`
+//! to keep the overloads apart.
+use std::collections::HashMap;
+
+use lcov::report::section::{branch, function, line, Key as SectionKey};
+use lcov::Report;
+
+use super::{number_attr, ImportError};
+use crate::xml::{Event, Reader};
+
+pub fn parse(input: &str) -> Result<Report, ImportError> {
+    let mut report = Report::new();
+    let mut package = String::new();
+    let mut class: Option<(String, String)> = None;
+    // name, start line and covered methods counter of the current method
+    let mut method: Option<(String, Option<u32>, u64)> = None;
+    let mut source_file: Option<SectionKey> = None;
+    let mut functions: HashMap<String, function::Functions> = HashMap::new();
+    let mut seen_root = false;
+
+    let key = |package: &str, file: &str| SectionKey {
+        test_name: String::new(),
+        source_file: if package.is_empty() {
+            file.into()
+        } else {
+            format!("{}/{}", package, file).into()
+        },
+    };
+
+    for event in Reader::new(input) {
+        let event = event?;
+        match &event {
+            Event::Start { name, .. } if !seen_root => {
+                if name != "report" {
+                    return Err(ImportError::Invalid(format!(
+                        "`report` root element expected, found `{}`",
+                        name
+                    )));
+                }
+                seen_root = true;
+            }
+            Event::Start { name, .. } if name == "package" => {
+                package = event.attr("name").unwrap_or_default().to_owned();
+            }
+            Event::End { name } if name == "package" => {
+                for (file, functions) in functions.drain() {
+                    let section = report.sections.entry(key(&package, &file)).or_default();
+                    section.functions.extend(functions);
+                }
+                package.clear();
+            }
+            Event::Start { name, .. } if name == "class" => {
+                let name = event.attr("name").unwrap_or_default();
+                let simple_name = name.rsplit('/').next().unwrap_or(name).to_owned();
+                let file = event.attr("sourcefilename").unwrap_or_default().to_owned();
+                class = Some((simple_name, file));
+            }
+            Event::End { name } if name == "class" => class = None,
+            Event::Start { name, .. } if name == "method" => {
+                let name = format!(
+                    "{}.{}{}",
+                    class.as_ref().map(|(name, _)| name.as_str()).unwrap_or(""),
+                    event.attr("name").unwrap_or_default(),
+                    event.attr("desc").unwrap_or_default()
+                );
+                let start_line = event.attr("line").and_then(|line| line.parse().ok());
+                method = Some((name, start_line, 0));
+            }
+            Event::End { name } if name == "method" => {
+                if let (Some((_, file)), Some((name, start_line, count))) = (&class, method.take())
+                {
+                    functions.entry(file.clone()).or_default().insert(
+                        function::Key { name },
+                        function::Value { start_line, count },
+                    );
+                }
+            }
+            Event::Start { name, .. } if name == "counter" => {
+                if let Some((_, _, count)) = &mut method {
+                    if event.attr("type") == Some("METHOD") {
+                        *count = number_attr(&event, "covered")?;
+                    }
+                }
+            }
+            Event::Start { name, .. } if name == "sourcefile" => {
+                let file = event.attr("name").unwrap_or_default();
+                source_file = Some(key(&package, file));
+            }
+            Event::End { name } if name == "sourcefile" => source_file = None,
+            Event::Start { name, .. } if name == "line" => {
+                let key = match &source_file {
+                    Some(key) => key,
+                    None => continue,
+                };
+                let number: u32 = number_attr(&event, "nr")?;
+                let covered: u64 = number_attr(&event, "ci")?;
+                let missed_branches: u32 = number_attr(&event, "mb")?;
+                let covered_branches: u32 = number_attr(&event, "cb")?;
+
+                let section = report.sections.entry(key.clone()).or_default();
+                section.lines.insert(
+                    line::Key { line: number },
+                    line::Value {
+                        count: covered,
+                        checksum: None,
+                    },
+                );
+                for idx in 0..missed_branches + covered_branches {
+                    let taken = if covered == 0 {
+                        None
+                    } else if idx < covered_branches {
+                        Some(1)
+                    } else {
+                        Some(0)
+                    };
+                    section.branches.insert(
+                        branch::Key {
+                            line: number,
+                            block: 0,
+                            branch: idx,
+                        },
+                        branch::Value { taken },
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    if !seen_root {
+        return Err(ImportError::Invalid("empty document".to_owned()));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn import() {
+        let input = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<!DOCTYPE report PUBLIC "-//JACOCO//DTD Report 1.1//EN" "report.dtd">
+<report name="service">
+  <sessioninfo id="host" start="1" dump="2"/>
+  <package name="com/example">
+    <class name="com/example/Foo" sourcefilename="Foo.java">
+      <method name="bar" desc="(I)V" line="3">
+        <counter type="INSTRUCTION" missed="0" covered="4"/>
+        <counter type="METHOD" missed="0" covered="1"/>
+      </method>
+      <counter type="CLASS" missed="0" covered="1"/>
+    </class>
+    <sourcefile name="Foo.java">
+      <line nr="3" mi="0" ci="2" mb="0" cb="0"/>
+      <line nr="4" mi="1" ci="2" mb="1" cb="1"/>
+      <line nr="5" mi="3" ci="0" mb="0" cb="0"/>
+      <counter type="LINE" missed="1" covered="2"/>
+    </sourcefile>
+  </package>
+</report>
+"#;
+        let records: Vec<String> = parse(input)
+            .unwrap()
+            .into_records()
+            .map(|rec| rec.to_string())
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                "TN:",
+                "SF:com/example/Foo.java",
+                "FN:3,Foo.bar(I)V",
+                "FNDA:1,Foo.bar(I)V",
+                "FNF:1",
+                "FNH:1",
+                "BRDA:4,0,0,1",
+                "BRDA:4,0,1,0",
+                "BRF:2",
+                "BRH:1",
+                "DA:3,2",
+                "DA:4,2",
+                "DA:5,0",
+                "LF:3",
+                "LH:2",
+                "end_of_record",
+            ]
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Recovery from the malformed lines of lcov files, such as the garbage of the files
+//! concatenated by shell scripts.
+use std::fmt;
+
+use lcov::record::RecordKind;
+use lcov::Record;
+
+/// Skipped or fixed line of the input.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Warning {
+    /// Number of the line, from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Drops the lines which aren't valid records or are out of place, closes the sections missing
+/// `end_of_record`. Lines of unknown kinds are kept with `keep_unknown`, for `Extras::split`.
+pub fn clean(input: &str, keep_unknown: bool) -> (String, Vec<Warning>) {
+    clean_numbered(input, &[], keep_unknown)
+}
+
+/// `clean` of the input made of the lines of a file, such as the kept sections of a filtered
+/// one: the warnings have the numbers of the lines in the file, `numbers` has the one of every
+/// input line. Empty `numbers` are the line numbers of the input.
+pub fn clean_numbered(
+    input: &str,
+    numbers: &[usize],
+    keep_unknown: bool,
+) -> (String, Vec<Warning>) {
+    let mut out = String::with_capacity(input.len());
+    let mut warnings = Vec::new();
+    let mut in_section = false;
+    let mut last = 0;
+    let mut warn = |line: usize, message: String| warnings.push(Warning { line, message });
+    for (idx, line) in input.lines().enumerate() {
+        let number = numbers.get(idx).copied().unwrap_or(idx + 1);
+        last = number;
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let kind = trimmed.split(':').next().unwrap_or(trimmed);
+        let unknown = kind.parse::<RecordKind>().is_err() && trimmed != "end_of_record";
+        if unknown && keep_unknown {
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        }
+        match trimmed.parse::<Record>() {
+            Err(e) => {
+                warn(number, format!("{}: {}", e, trimmed));
+                continue;
+            }
+            Ok(Record::SourceFile { .. }) => {
+                if in_section {
+                    warn(number, "missing end_of_record before SF".to_owned());
+                    out.push_str("end_of_record\n");
+                }
+                in_section = true;
+            }
+            Ok(Record::TestName { .. }) if in_section => {
+                warn(number, format!("TN inside of a section: {}", trimmed));
+                continue;
+            }
+            Ok(Record::EndOfRecord) if !in_section => {
+                warn(number, "end_of_record outside of a section".to_owned());
+                continue;
+            }
+            Ok(Record::EndOfRecord) => in_section = false,
+            Ok(Record::TestName { .. }) => {}
+            Ok(_) if !in_section => {
+                warn(number, format!("record outside of a section: {}", trimmed));
+                continue;
+            }
+            Ok(_) => {}
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    if in_section {
+        warn(last, "missing end_of_record at the end".to_owned());
+        out.push_str("end_of_record\n");
+    }
+    (out, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clean, clean_numbered};
+
+    #[test]
+    fn recover() {
+        let input = "\
+garbage from echo
+DA:1,1
+SF:a.c
+DA:1,x
+DA:2,1
+SF:b.c
+DA:1,1
+VER:2
+";
+        let (out, warnings) = clean(input, false);
+        assert_eq!(
+            out,
+            "SF:a.c\nDA:2,1\nend_of_record\nSF:b.c\nDA:1,1\nend_of_record\n"
+        );
+        let lines: Vec<usize> = warnings.iter().map(|w| w.line).collect();
+        assert_eq!(lines, vec![1, 2, 4, 6, 8, 8]);
+        assert_eq!(
+            warnings[1].to_string(),
+            "line 2: record outside of a section: DA:1,1"
+        );
+        let (out, warnings) = clean(input, true);
+        assert!(out.contains("VER:2\nend_of_record\n"));
+        assert_eq!(warnings.len(), 4);
+    }
+
+    #[test]
+    fn line_numbers_of_the_file() {
+        // Lines 1 to 3 were a filtered out section, the shim wrote line 5 as two lines
+        let input = "SF:a.c\nFN:1,main\nFNDA:1,main\nDA:1,x\n";
+        let (out, warnings) = clean_numbered(input, &[4, 5, 5, 6], false);
+        assert_eq!(out, "SF:a.c\nFN:1,main\nFNDA:1,main\nend_of_record\n");
+        let lines: Vec<usize> = warnings.iter().map(|w| w.line).collect();
+        assert_eq!(lines, vec![6, 6]);
+    }
+}
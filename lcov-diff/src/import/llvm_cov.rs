@@ -0,0 +1,254 @@
+//! `llvm-cov export -format=json` importer.
+//!
+//! Line counts are folded from the segments the way `llvm-cov` itself does it: a line is
+//! mapped if a region starts there or a region with a count wraps it (skipped regions aren't
+//! mapped), and its count is the biggest one of these regions. Branches of a line are
+//! numbered in pairs, true and false outcome, like `llvm-cov export -format=lcov` does.
+use std::path::PathBuf;
+
+use lcov::report::section::{branch, function, line, Key as SectionKey, Value as SectionValue};
+use lcov::Report;
+
+use super::ImportError;
+use crate::json::Value;
+
+/// Whether the document is llvm-cov export: `type` is `llvm.coverage.json.export`.
+pub fn matches(input: &Value) -> bool {
+    input.get("type").and_then(Value::as_str) == Some("llvm.coverage.json.export")
+}
+
+pub fn parse(input: &Value) -> Result<Report, ImportError> {
+    let mut report = Report::new();
+    for export in array(input, "data")? {
+        for file in array(export, "files")? {
+            let filename = file
+                .get("filename")
+                .and_then(Value::as_str)
+                .ok_or_else(|| ImportError::Invalid("`filename` is missing".to_owned()))?;
+            let section = report.sections.entry(key(filename)).or_default();
+            lines(section, array(file, "segments")?)?;
+            branches(section, array(file, "branches").unwrap_or_default())?;
+        }
+        for function in array(export, "functions")? {
+            let name = function
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or_else(|| ImportError::Invalid("function `name` is missing".to_owned()))?;
+            // The first file is the one with the definition, the rest are expansions
+            let filename = match array(function, "filenames")?
+                .first()
+                .and_then(Value::as_str)
+            {
+                Some(filename) => filename,
+                None => continue,
+            };
+            let start_line = array(function, "regions")?
+                .first()
+                .and_then(|region| numbers(region).first().map(|&line| line as u32));
+            let count = function.get("count").and_then(Value::as_u64).unwrap_or(0);
+            let section = report.sections.entry(key(filename)).or_default();
+            let value = section
+                .functions
+                .entry(function::Key {
+                    name: name.to_owned(),
+                })
+                .or_insert(function::Value {
+                    start_line,
+                    count: 0,
+                });
+            value.count += count;
+        }
+    }
+    Ok(report)
+}
+
+/// `[line, column, count, has_count, is_region_entry, is_gap_region]`
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    line: u32,
+    count: u64,
+    has_count: bool,
+    is_region_entry: bool,
+    is_gap_region: bool,
+}
+
+impl Segment {
+    fn is_start_of_region(&self) -> bool {
+        !self.is_gap_region && self.has_count && self.is_region_entry
+    }
+}
+
+fn lines(section: &mut SectionValue, segments: &[Value]) -> Result<(), ImportError> {
+    let segments = segments
+        .iter()
+        .map(|segment| match segment.as_array().unwrap_or_default() {
+            [line, _, count, has_count, is_region_entry, rest @ ..] => Some(Segment {
+                line: line.as_u64()? as u32,
+                count: count.as_u64()?,
+                has_count: *has_count == Value::Bool(true),
+                is_region_entry: *is_region_entry == Value::Bool(true),
+                is_gap_region: rest.first() == Some(&Value::Bool(true)),
+            }),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| ImportError::Invalid("invalid segment".to_owned()))?;
+    let (first, last) = match (segments.first(), segments.last()) {
+        (Some(first), Some(last)) => (first.line, last.line),
+        _ => return Ok(()),
+    };
+
+    let mut wrapped: Option<Segment> = None;
+    let mut next = 0;
+    for line in first..=last {
+        let start = next;
+        while next < segments.len() && segments[next].line == line {
+            next += 1;
+        }
+        let line_segments = &segments[start..next];
+
+        let starts_skipped = line_segments
+            .first()
+            .is_some_and(|s| !s.has_count && s.is_region_entry);
+        let regions: Vec<&Segment> = line_segments
+            .iter()
+            .filter(|s| s.is_start_of_region())
+            .collect();
+        let mapped =
+            !starts_skipped && (wrapped.is_some_and(|s| s.has_count) || !regions.is_empty());
+        if mapped {
+            let count = regions
+                .iter()
+                .map(|s| s.count)
+                .chain(wrapped.map(|s| s.count))
+                .max()
+                .unwrap_or(0);
+            section.lines.entry(line::Key { line }).or_default().count += count;
+        }
+        if let Some(segment) = line_segments.last() {
+            wrapped = Some(*segment);
+        }
+    }
+    Ok(())
+}
+
+fn branches(section: &mut SectionValue, branches: &[Value]) -> Result<(), ImportError> {
+    for value in branches {
+        // `[line_start, column_start, line_end, column_end, true_count, false_count, ..]`
+        let fields = numbers(value);
+        let (line, true_count, false_count) = match fields.as_slice() {
+            [line, _, _, _, true_count, false_count, ..] => {
+                (*line as u32, *true_count, *false_count)
+            }
+            _ => return Err(ImportError::Invalid("invalid branch".to_owned())),
+        };
+        let idx = section
+            .branches
+            .keys()
+            .filter(|key| key.line == line)
+            .count() as u32;
+        let executed = true_count > 0 || false_count > 0;
+        for (offset, count) in [true_count, false_count].iter().enumerate() {
+            section.branches.insert(
+                branch::Key {
+                    line,
+                    block: 0,
+                    branch: idx + offset as u32,
+                },
+                branch::Value {
+                    taken: if executed { Some(*count) } else { None },
+                },
+            );
+        }
+    }
+    Ok(())
+}
+
+fn key(filename: &str) -> SectionKey {
+    SectionKey {
+        test_name: String::new(),
+        source_file: PathBuf::from(filename),
+    }
+}
+
+fn array<'a>(value: &'a Value, field: &str) -> Result<&'a [Value], ImportError> {
+    value
+        .get(field)
+        .and_then(Value::as_array)
+        .ok_or_else(|| ImportError::Invalid(format!("`{}` array is missing", field)))
+}
+
+/// Leading numbers of the array.
+fn numbers(value: &Value) -> Vec<u64> {
+    value
+        .as_array()
+        .unwrap_or_default()
+        .iter()
+        .map_while(Value::as_u64)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matches, parse};
+    use crate::json;
+
+    #[test]
+    fn import() {
+        let input = r#"{
+  "type": "llvm.coverage.json.export",
+  "version": "2.0.1",
+  "data": [{
+    "files": [{
+      "filename": "/repo/src/main.rs",
+      "segments": [
+        [1, 11, 1, true, true, false],
+        [2, 8, 0, true, true, false],
+        [4, 6, 1, true, false, false],
+        [5, 2, 0, false, false, false]
+      ],
+      "branches": [[2, 8, 2, 12, 0, 1, 0, 0, 4]],
+      "summary": {}
+    }],
+    "functions": [{
+      "name": "_RNvCs1_4main4main",
+      "count": 1,
+      "regions": [[1, 11, 5, 2, 1, 0, 0, 0]],
+      "filenames": ["/repo/src/main.rs"],
+      "branches": []
+    }],
+    "totals": {}
+  }]
+}"#;
+        let input = json::parse(input).unwrap();
+        assert!(matches(&input));
+        let records: Vec<String> = parse(&input)
+            .unwrap()
+            .into_records()
+            .map(|rec| rec.to_string())
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                "TN:",
+                "SF:/repo/src/main.rs",
+                "FN:1,_RNvCs1_4main4main",
+                "FNDA:1,_RNvCs1_4main4main",
+                "FNF:1",
+                "FNH:1",
+                "BRDA:2,0,0,0",
+                "BRDA:2,0,1,1",
+                "BRF:2",
+                "BRH:1",
+                "DA:1,1",
+                "DA:2,1",
+                "DA:3,0",
+                "DA:4,0",
+                "DA:5,1",
+                "LF:5",
+                "LH:3",
+                "end_of_record",
+            ]
+        );
+    }
+}
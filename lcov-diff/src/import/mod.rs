@@ -0,0 +1,500 @@
+//! Importers of the coverage formats other than lcov into `lcov::Report`.
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::Duration;
+
+use lcov::report::{MergeError, ParseError};
+use lcov::Report;
+
+use crate::extras::Extras;
+use crate::filter::PathFilter;
+use crate::version::{self, FunctionEnds, Version};
+use crate::{json, memory, xml};
+use lenient::Warning;
+
+pub mod archive;
+pub mod cobertura;
+pub mod coverage_py;
+pub mod gcov;
+pub mod go;
+pub mod istanbul;
+#[cfg(feature = "jacoco")]
+pub mod jacoco;
+pub mod lenient;
+pub mod llvm_cov;
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    Lcov(ParseError),
+    Xml(xml::Error),
+    Json(json::ParseError),
+    /// The document is well-formed but doesn't match the format.
+    Invalid(String),
+    /// The fragments of an archive conflict.
+    Merge(MergeError),
+    /// The file is larger than `LoadOptions::max_input_size`.
+    TooLarge {
+        size: u64,
+        limit: u64,
+    },
+    /// The unpacked archive or gzipped file is larger than `LoadOptions::max_input_size`.
+    TooLargeUnpacked {
+        limit: u64,
+    },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "{}", e),
+            ImportError::Lcov(e) => write!(f, "{}", e),
+            ImportError::Xml(e) => write!(f, "invalid xml at {}: {}", e.offset, e.message),
+            ImportError::Json(e) => write!(f, "{}", e),
+            ImportError::Merge(e) => write!(f, "{}", e),
+            ImportError::Invalid(message) => write!(f, "invalid coverage data: {}", message),
+            ImportError::TooLarge { size, limit } => write!(
+                f,
+                "input of {} exceeds the limit of {}",
+                memory::format_size(*size),
+                memory::format_size(*limit)
+            ),
+            ImportError::TooLargeUnpacked { limit } => write!(
+                f,
+                "unpacked input exceeds the limit of {}",
+                memory::format_size(*limit)
+            ),
+        }
+    }
+}
+
+impl Error for ImportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImportError::Io(e) => Some(e),
+            ImportError::Lcov(e) => Some(e),
+            ImportError::Json(e) => Some(e),
+            ImportError::Merge(e) => Some(e),
+            ImportError::Xml(_)
+            | ImportError::Invalid(_)
+            | ImportError::TooLarge { .. }
+            | ImportError::TooLargeUnpacked { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for ImportError {
+    fn from(e: io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+impl From<ParseError> for ImportError {
+    fn from(e: ParseError) -> Self {
+        ImportError::Lcov(e)
+    }
+}
+
+impl From<xml::Error> for ImportError {
+    fn from(e: xml::Error) -> Self {
+        ImportError::Xml(e)
+    }
+}
+
+impl From<json::ParseError> for ImportError {
+    fn from(e: json::ParseError) -> Self {
+        ImportError::Json(e)
+    }
+}
+
+impl From<MergeError> for ImportError {
+    fn from(e: MergeError) -> Self {
+        ImportError::Merge(e)
+    }
+}
+
+/// Format of an input, sniffed from the content by `sniff`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InputFormat {
+    Lcov,
+    /// Go coverprofile, starting with the `mode:` line.
+    Go,
+    Xml,
+    Json,
+    /// Gzipped gcov json or tar archive.
+    Gzip,
+    Zip,
+    Tar,
+}
+
+/// Sniffs the format from the first bytes of the input, anything unknown is lcov.
+pub fn sniff(input: &[u8]) -> InputFormat {
+    if input.starts_with(&[0x1f, 0x8b]) {
+        return InputFormat::Gzip;
+    }
+    if archive::is_zip(input) {
+        return InputFormat::Zip;
+    }
+    if archive::is_tar(input) {
+        return InputFormat::Tar;
+    }
+    let input = input.strip_prefix(b"\xef\xbb\xbf").unwrap_or(input);
+    let start = input
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(input.len());
+    match &input[start..] {
+        [b'<', ..] => InputFormat::Xml,
+        [b'{', ..] => InputFormat::Json,
+        rest if rest.starts_with(b"mode:") => InputFormat::Go,
+        _ => InputFormat::Lcov,
+    }
+}
+
+/// Format of the file, sniffed from its first bytes.
+fn sniff_file(path: &Path) -> io::Result<InputFormat> {
+    let mut head = Vec::with_capacity(512);
+    fs::File::open(path)?.take(512).read_to_end(&mut head)?;
+    Ok(sniff(&head))
+}
+
+/// Loads the report of the format sniffed from the content, whatever the extension: lcov,
+/// Go coverprofile, xml, json, gzipped gcov json or an archive of lcov fragments.
+///
+/// The format of xml files is chosen by the root element: `<coverage>` of Cobertura
+/// or `<report>` of JaCoCo (with `jacoco` feature). Json files are coverage.py reports,
+/// Istanbul `coverage-final.json`, `llvm-cov export` or gcov ones. Directories are read
+/// as trees of gcov json files, which are merged together.
+pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Report, ImportError> {
+    load_file_limited(path.as_ref(), None)
+}
+
+/// Loads the report as `load_file`, the gzipped files and the archives failing past `limit`
+/// unpacked bytes.
+fn load_file_limited(path: &Path, limit: Option<u64>) -> Result<Report, ImportError> {
+    Ok(load_report(path, limit)?.0)
+}
+
+/// `load_file_limited` with the lcov text of the archives, their fragments one after another,
+/// and the time the fragments took to merge.
+fn load_report(path: &Path, limit: Option<u64>) -> Result<ReportParts, ImportError> {
+    if path.is_dir() {
+        return Ok((gcov::load_dir(path)?, None, None));
+    }
+    let fragments = |entries: Vec<(String, Vec<u8>)>| {
+        archive::merge_fragments_timed(entries)
+            .map(|(report, text, merge_time)| (report, Some(text), Some(merge_time)))
+    };
+    match sniff_file(path)? {
+        InputFormat::Gzip => {
+            let unpacked = archive::gunzip_limited(path, limit)?;
+            if archive::is_tar(&unpacked) {
+                fragments(archive::tar_entries(&unpacked)?)
+            } else {
+                Ok((gcov::parse_unpacked(unpacked)?, None, None))
+            }
+        }
+        InputFormat::Zip => fragments(archive::zip_fragments(path, limit)?),
+        InputFormat::Tar => fragments(archive::tar_entries(&fs::read(path)?)?),
+        _ => Ok((parse_str(&fs::read_to_string(path)?)?, None, None)),
+    }
+}
+
+type ReportParts = (Report, Option<String>, Option<Duration>);
+
+/// Settings of `load_file_with`, the unknown records and the lenient parse are for the lcov
+/// files only.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct LoadOptions {
+    /// Keeps the unknown records aside instead of failing the parse.
+    pub keep_unknown: bool,
+    /// Skips the malformed lines with a warning instead of failing the parse.
+    pub lenient: bool,
+    /// Bytes of the largest file read, the larger ones fail before reading. The gzipped files
+    /// and the archives also fail once their unpacked content exceeds it. The directories of
+    /// gcov files aren't limited.
+    pub max_input_size: Option<u64>,
+    /// Sections of the files to keep, the lcov inputs skip the others without parsing them.
+    pub filter: PathFilter,
+    /// Keeps the lcov text of the input in `Loaded::text`.
+    pub keep_text: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Loaded {
+    pub report: Report,
+    pub extras: Extras,
+    pub warnings: Vec<Warning>,
+    /// Version of the lcov format of the input, `None` for the other formats.
+    pub version: Option<Version>,
+    /// End lines of the functions of the lcov 2.x input.
+    pub function_ends: FunctionEnds,
+    /// With `LoadOptions::keep_text`, the text of the lcov input as it's read, before the
+    /// filter, or the fragments of an archive one after another. `None` for the other formats.
+    pub text: Option<String>,
+    /// Time the fragments of an archive took to merge, part of the load. `None` for the inputs
+    /// which aren't archives.
+    pub merge_time: Option<Duration>,
+}
+
+/// Loads the report as `load_file` with the options.
+pub fn load_file_with<P: AsRef<Path>>(
+    path: P,
+    options: &LoadOptions,
+) -> Result<Loaded, ImportError> {
+    let path = path.as_ref();
+    if let (Some(limit), false) = (options.max_input_size, path.is_dir()) {
+        let size = fs::metadata(path)?.len();
+        if size > limit {
+            return Err(ImportError::TooLarge { size, limit });
+        }
+    }
+    if !path.is_dir() && sniff_file(path)? == InputFormat::Lcov {
+        let input = fs::read_to_string(path)?;
+        let text = options.keep_text.then(|| input.clone());
+        let version = version::detect(&input);
+        // The excluded sections are skipped first, the later steps only see the kept ones. The
+        // warnings keep the line numbers of the file.
+        let (input, numbers) = if options.filter.is_empty() {
+            (input, Vec::new())
+        } else {
+            options.filter.skip_sections_numbered(&input)
+        };
+        let (input, function_ends, numbers) =
+            version::shim_numbered(&input, &numbers, version, options.keep_unknown);
+        let (input, warnings) = if options.lenient {
+            lenient::clean_numbered(&input, &numbers, options.keep_unknown)
+        } else {
+            (input, Vec::new())
+        };
+        let (input, extras) = if options.keep_unknown {
+            Extras::split(&input)
+        } else {
+            (input, Extras::default())
+        };
+        let report = Report::from_reader(lcov::Reader::new(input.as_bytes()))?;
+        return Ok(Loaded {
+            report,
+            extras,
+            warnings,
+            version: Some(version),
+            function_ends,
+            text,
+            merge_time: None,
+        });
+    }
+    let (mut report, text, merge_time) = load_report(path, options.max_input_size)?;
+    options.filter.retain(&mut report);
+    Ok(Loaded {
+        report,
+        text: text.filter(|_| options.keep_text),
+        merge_time,
+        ..Loaded::default()
+    })
+}
+
+/// Parses the report of any supported text format, sniffed by the content: xml, json,
+/// Go coverprofile or lcov.
+pub fn parse_str(input: &str) -> Result<Report, ImportError> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    match sniff(input.as_bytes()) {
+        InputFormat::Xml => parse_xml(input),
+        InputFormat::Json => parse_json(input),
+        InputFormat::Go => go::parse(input),
+        InputFormat::Lcov => parse_lcov(input),
+        InputFormat::Gzip | InputFormat::Zip | InputFormat::Tar => {
+            Err(ImportError::Invalid("binary input".to_owned()))
+        }
+    }
+}
+
+/// Parses the lcov input of either version.
+fn parse_lcov(input: &str) -> Result<Report, ImportError> {
+    let input = version::shim(input, false);
+    Ok(Report::from_reader(lcov::Reader::new(input.as_bytes()))?)
+}
+
+fn parse_xml(input: &str) -> Result<Report, ImportError> {
+    let root = xml::Reader::new(input).find_map(|event| match event {
+        Ok(xml::Event::Start { name, .. }) => Some(Ok(name)),
+        Ok(_) => None,
+        Err(e) => Some(Err(e)),
+    });
+    match root.transpose()?.as_deref() {
+        #[cfg(feature = "jacoco")]
+        Some("report") => jacoco::parse(input),
+        #[cfg(not(feature = "jacoco"))]
+        Some("report") => Err(ImportError::Invalid(
+            "JaCoCo support requires the `jacoco` feature".to_owned(),
+        )),
+        _ => cobertura::parse(input),
+    }
+}
+
+fn parse_json(input: &str) -> Result<Report, ImportError> {
+    let value = json::parse(input)?;
+    if coverage_py::matches(&value) {
+        coverage_py::parse(&value)
+    } else if gcov::matches(&value) {
+        gcov::parse(&value)
+    } else if llvm_cov::matches(&value) {
+        llvm_cov::parse(&value)
+    } else if istanbul::matches(&value) {
+        istanbul::parse(&value)
+    } else {
+        Err(ImportError::Invalid(
+            "unknown json coverage format".to_owned(),
+        ))
+    }
+}
+
+/// Integer attribute of the xml element.
+pub(crate) fn number_attr<T: std::str::FromStr>(
+    event: &xml::Event,
+    name: &str,
+) -> Result<T, ImportError> {
+    let value = event
+        .attr(name)
+        .ok_or_else(|| ImportError::Invalid(format!("`{}` attribute is missing", name)))?;
+    value
+        .parse()
+        .map_err(|_| ImportError::Invalid(format!("invalid `{}` value: {}", name, value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        load_file, load_file_with, parse_str, sniff, ImportError, InputFormat, LoadOptions,
+    };
+    use crate::filter::PathFilter;
+    use std::fs;
+
+    #[test]
+    fn sniff_formats() {
+        assert_eq!(sniff(b"TN:\nSF:a.c\n"), InputFormat::Lcov);
+        assert_eq!(sniff(b"\xef\xbb\xbf\n  <?xml version"), InputFormat::Xml);
+        assert_eq!(sniff(b" {\"data\": []}"), InputFormat::Json);
+        assert_eq!(sniff(b"mode: set\n"), InputFormat::Go);
+        assert_eq!(sniff(&[0x1f, 0x8b, 8]), InputFormat::Gzip);
+        assert_eq!(sniff(b""), InputFormat::Lcov);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("coverage.info");
+        fs::write(
+            &path,
+            r#"{"meta": {"version": "7.2"}, "files": {"a.py": {"executed_lines": [1], "missing_lines": [2]}}}"#,
+        )
+        .unwrap();
+        let report = load_file(&path).unwrap();
+        assert_eq!(report.sections.len(), 1);
+        let loaded = load_file_with(&path, &LoadOptions::default()).unwrap();
+        assert_eq!(loaded.report, report);
+        assert!(loaded.version.is_none());
+    }
+
+    #[test]
+    fn unknown_json() {
+        for input in ["{}", "{\"a.js\": {}}"] {
+            assert!(
+                matches!(
+                    parse_str(input),
+                    Err(ImportError::Invalid(ref message)) if message == "unknown json coverage format"
+                ),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[cfg(not(feature = "jacoco"))]
+    #[test]
+    fn jacoco_without_feature() {
+        let input =
+            "<?xml version=\"1.0\"?>\n<report name=\"app\"><package name=\"a\"/></report>\n";
+        assert!(matches!(
+            parse_str(input),
+            Err(ImportError::Invalid(ref message)) if message == "JaCoCo support requires the `jacoco` feature"
+        ));
+    }
+
+    #[test]
+    fn filter_before_parse() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("coverage.info");
+        fs::write(
+            &path,
+            "SF:skip/a.c\nDA:1,1\nend_of_record\nSF:keep/b.c\nDA:1,1\nend_of_record\nSF:keep/c.c\ngarbage\nDA:1,1\nend_of_record\n",
+        )
+        .unwrap();
+        let options = LoadOptions {
+            lenient: true,
+            filter: PathFilter {
+                include: vec!["keep/".to_owned()],
+                ..PathFilter::default()
+            },
+            ..LoadOptions::default()
+        };
+        let loaded = load_file_with(&path, &options).unwrap();
+        assert_eq!(loaded.report.sections.len(), 2);
+        assert_eq!(loaded.warnings.len(), 1);
+        assert_eq!(loaded.warnings[0].line, 8);
+        assert!(loaded.text.is_none());
+        let options = LoadOptions {
+            keep_text: true,
+            ..options
+        };
+        let text = load_file_with(&path, &options).unwrap().text;
+        assert_eq!(text.as_deref(), fs::read_to_string(&path).ok().as_deref());
+    }
+
+    #[test]
+    fn lenient_lcov2_line_numbers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("coverage.info");
+        fs::write(
+            &path,
+            "VER:2.0\nSF:skip/a.c\nDA:1,1\nend_of_record\nSF:keep/b.c\nFNL:0,1,3\nFNA:0,1,main\nDA:x\nend_of_record\n",
+        )
+        .unwrap();
+        let options = LoadOptions {
+            lenient: true,
+            filter: PathFilter {
+                exclude: vec!["skip/".to_owned()],
+                ..PathFilter::default()
+            },
+            ..LoadOptions::default()
+        };
+        let loaded = load_file_with(&path, &options).unwrap();
+        let lines: Vec<usize> = loaded.warnings.iter().map(|w| w.line).collect();
+        assert_eq!(lines, vec![8]);
+        assert_eq!(loaded.function_ends.len(), 1);
+    }
+
+    #[test]
+    fn unpacked_size_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("coverage.json");
+        let mut gcov = String::from(r#"{"format_version": "1", "files": []}"#);
+        gcov.push_str(&" ".repeat(100_000));
+        fs::write(&path, gcov).unwrap();
+        let status = std::process::Command::new("gzip")
+            .arg(&path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let path = dir.path().join("coverage.json.gz");
+        let options = LoadOptions {
+            max_input_size: Some(10_000),
+            ..LoadOptions::default()
+        };
+        assert!(fs::metadata(&path).unwrap().len() < 10_000);
+        assert!(matches!(
+            load_file_with(&path, &options),
+            Err(ImportError::TooLargeUnpacked { limit: 10_000 })
+        ));
+        assert!(load_file_with(&path, &LoadOptions::default()).is_ok());
+    }
+}
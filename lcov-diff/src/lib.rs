@@ -4,11 +4,68 @@ use std::collections::BTreeMap;
 use lcov::Report;
 
 use lcov::report::MergeError;
+use lcov_diff_core::{self as core_diff, DiffError};
 
-use lcov::report::section::branch::Value as BranchValue;
-use lcov::report::section::function::Value as FunctionValue;
-use lcov::report::section::line::Value as LineValue;
-use lcov::report::section::Value as SectionValue;
+use lcov::report::section::branch::{Key as BranchKey, Value as BranchValue};
+use lcov::report::section::function::{Key as FunctionKey, Value as FunctionValue};
+use lcov::report::section::line::{Key as LineKey, Value as LineValue};
+use lcov::report::section::{Key as SectionKey, Value as SectionValue};
+
+pub mod blame;
+pub mod builder;
+pub mod cargo;
+pub mod changes;
+pub mod check;
+pub mod checksum;
+pub mod ci;
+pub mod clusters;
+pub mod debt;
+pub mod explain;
+pub mod extras;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod filter;
+pub mod fingerprint;
+pub mod functions;
+#[cfg(feature = "async")]
+pub mod future;
+pub(crate) mod hash;
+pub mod import;
+pub mod json;
+pub mod memory;
+pub mod notes;
+pub mod order;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod owners;
+pub mod permalink;
+pub mod pipeline;
+pub mod process;
+pub mod proto;
+pub mod render;
+pub mod rollup;
+pub mod selection;
+pub mod serve;
+pub mod session;
+pub mod severity;
+pub mod shard;
+pub mod signature;
+pub mod similarity;
+pub mod snippet;
+pub mod sourcemap;
+pub mod statsd;
+pub mod summary;
+pub mod suppress;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod textdiff;
+pub mod timings;
+pub mod trend;
+pub mod version;
+pub mod visit;
+pub mod weights;
+pub mod write;
+pub mod xml;
 
 pub fn diff_reports(first: &Report, second: &Report) -> Result<Report, MergeError> {
     let mut rep = Report::new();
@@ -17,6 +74,263 @@ pub fn diff_reports(first: &Report, second: &Report) -> Result<Report, MergeErro
     Ok(rep)
 }
 
+/// How `diff_merged` merges the reports of a side.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MergeStrategy {
+    /// The counts are added up, as `lcov -a` does.
+    Sum,
+    /// The largest count of the reports is kept, for the shards running the same tests.
+    Max,
+}
+
+impl MergeStrategy {
+    pub const VARIANTS: &'static [&'static str] = &["sum", "max"];
+}
+
+impl std::str::FromStr for MergeStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sum" => Ok(MergeStrategy::Sum),
+            "max" => Ok(MergeStrategy::Max),
+            _ => Err(format!("unknown merge strategy: {}", s)),
+        }
+    }
+}
+
+/// How `diff_reports_with` diffs the reports.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DiffOptions {
+    /// Threads diffing the sections, with 1 they're diffed on the calling thread.
+    pub threads: usize,
+    /// Merge of the reports of a side in `diff_merged`.
+    pub merge: MergeStrategy,
+    /// Times the diff and the comparison of every `diff_pairs` pair.
+    pub timings: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            threads: 1,
+            merge: MergeStrategy::Sum,
+            timings: false,
+        }
+    }
+}
+
+impl DiffOptions {
+    /// The mode of the reports with thousands of sections: the sections are split between a
+    /// thread per core. It's the parallel diff only, there are no hashmap or interning paths
+    /// since the records are the `BTreeMap`s of the lcov crate. `benches/large_input.rs` times it.
+    pub fn large_input() -> Self {
+        DiffOptions {
+            threads: std::thread::available_parallelism().map_or(1, usize::from),
+            ..DiffOptions::default()
+        }
+    }
+}
+
+/// `diff_reports` with the options, the result is the same.
+pub fn diff_reports_with(
+    first: &Report,
+    second: &Report,
+    options: &DiffOptions,
+) -> Result<Report, MergeError> {
+    if options.threads <= 1 {
+        return diff_reports(first, second);
+    }
+    let mut diff = first.clone();
+    diff_parallel(&mut diff, second, options.threads)?;
+    Ok(diff)
+}
+
+/// Diffs the first report in place, its sections split between the threads.
+fn diff_parallel(first: &mut Report, second: &Report, threads: usize) -> Result<(), MergeError> {
+    let mut sections: Vec<_> = std::mem::take(&mut first.sections).into_iter().collect();
+    let chunk = sections.len().div_ceil(threads).max(1);
+    let result = std::thread::scope(|scope| {
+        let threads: Vec<_> = sections
+            .chunks_mut(chunk)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    for (key, value) in chunk {
+                        if let Some(other) = second.sections.get(key) {
+                            value.diff(other)?;
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        threads
+            .into_iter()
+            .try_for_each(|thread| thread.join().expect("diff thread panicked"))
+    });
+    first.sections = sections.into_iter().collect();
+    result
+}
+
+/// Merges the reports of a side with the strategy.
+fn merge_side(reports: &[Report], strategy: MergeStrategy) -> Result<Report, MergeError> {
+    let mut merged = Report::new();
+    for report in reports {
+        match strategy {
+            MergeStrategy::Sum => merged.merge(report.clone())?,
+            MergeStrategy::Max => {
+                for (key, section) in &report.sections {
+                    let target = merged.sections.entry(key.clone()).or_default();
+                    merge_max(target, section).map_err(merge_error)?;
+                }
+            }
+        }
+    }
+    Ok(merged)
+}
+
+/// Keeps the largest count of every record, the reports have to match like with `merge`.
+fn merge_max(target: &mut SectionValue, section: &SectionValue) -> Result<(), DiffError> {
+    for (key, line) in &section.lines {
+        let value = target.lines.entry(*key).or_insert_with(|| line.clone());
+        core_diff::check_match(
+            value.checksum.as_ref(),
+            line.checksum.as_ref(),
+            DiffError::UnmatchedChecksum,
+        )?;
+        value.count = value.count.max(line.count);
+    }
+    for (name, function) in &section.functions {
+        let value = target.functions.entry(name.clone()).or_insert(*function);
+        core_diff::check_match(
+            value.start_line.as_ref(),
+            function.start_line.as_ref(),
+            DiffError::UnmatchedFunctionLine,
+        )?;
+        value.start_line = value.start_line.or(function.start_line);
+        value.count = value.count.max(function.count);
+    }
+    for (key, branch) in &section.branches {
+        let value = target.branches.entry(*key).or_default();
+        value.taken = value.taken.max(branch.taken);
+    }
+    Ok(())
+}
+
+/// Merges the first reports and the second ones with `options.merge`, then diffs the merged
+/// reports; the diff is made in the merged first report instead of a copy of it, on
+/// `options.threads` threads too.
+pub fn diff_merged(
+    firsts: &[Report],
+    seconds: &[Report],
+    options: &DiffOptions,
+) -> Result<Report, MergeError> {
+    let mut first = merge_side(firsts, options.merge)?;
+    let second = merge_side(seconds, options.merge)?;
+    if options.threads > 1 {
+        diff_parallel(&mut first, &second, options.threads)?;
+    } else {
+        first.diff(&second)?;
+    }
+    Ok(first)
+}
+
+/// Diff of a `diff_pairs` pair, with the comparison of the first report against the second one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffOutcome {
+    pub diff: Result<Report, MergeError>,
+    pub comparison: summary::Comparison,
+    /// The `diff` and `compare` phases with the records of the diff and the compared files,
+    /// with `DiffOptions::timings`.
+    pub timings: Option<timings::Timings>,
+}
+
+/// Diffs the `(first, second)` pairs on a pool of `options.threads` threads, each pair on one
+/// of them. The outcomes are in the order of the pairs.
+pub fn diff_pairs(pairs: &[(Report, Report)], options: &DiffOptions) -> Vec<DiffOutcome> {
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let outcome = |(first, second): &(Report, Report)| {
+        let mut phases = options.timings.then(timings::Timings::new);
+        let diff = diff_reports(first, second);
+        if let Some(phases) = &mut phases {
+            phases.lap("diff", diff.as_ref().map_or(0, timings::records));
+        }
+        let comparison = summary::Comparison::new(second, first);
+        if let Some(phases) = &mut phases {
+            phases.lap("compare", comparison.files.len());
+        }
+        DiffOutcome {
+            diff,
+            comparison,
+            timings: phases,
+        }
+    };
+    let threads = options.threads.clamp(1, pairs.len().max(1));
+    let mut outcomes: Vec<(usize, DiffOutcome)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        match pairs.get(i) {
+                            Some(pair) => done.push((i, outcome(pair))),
+                            None => return done,
+                        }
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("diff thread panicked"))
+            .collect()
+    });
+    outcomes.sort_by_key(|(i, _)| *i);
+    outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+}
+
+/// Diffed section passed to the `diff_reports_each` callback.
+#[derive(Debug, Copy, Clone)]
+pub struct SectionDelta<'a> {
+    /// The section of the diff report.
+    pub diff: &'a SectionValue,
+    /// Coverage of the section in the second report, `None` if it doesn't have the section.
+    pub baseline: Option<summary::Coverage>,
+    /// Coverage of the section in the first report.
+    pub current: summary::Coverage,
+}
+
+/// `diff_reports` calling `on_section` with every section as soon as it's diffed, in the order
+/// of the section keys, so the consumers can render and upload before the diff finishes.
+pub fn diff_reports_each<F>(
+    first: &Report,
+    second: &Report,
+    mut on_section: F,
+) -> Result<Report, MergeError>
+where
+    F: FnMut(&SectionKey, SectionDelta),
+{
+    let mut report = Report::new();
+    for (key, section) in &first.sections {
+        let baseline = second.sections.get(key);
+        let mut diff = section.clone();
+        if let Some(baseline) = baseline {
+            diff.diff(baseline)?;
+        }
+        on_section(
+            key,
+            SectionDelta {
+                diff: &diff,
+                baseline: baseline.map(summary::Coverage::of_section),
+                current: summary::Coverage::of_section(section),
+            },
+        );
+        report.sections.insert(key.clone(), diff);
+    }
+    Ok(report)
+}
+
 pub trait Diff {
     fn diff(&mut self, other: &Self) -> Result<(), MergeError>;
 }
@@ -27,14 +341,17 @@ impl Diff for Report {
     }
 }
 
+/// The `MergeError` of the core diff error, which has the same cases.
+fn merge_error(e: DiffError) -> MergeError {
+    match e {
+        DiffError::UnmatchedFunctionLine => MergeError::UnmatchedFunctionLine,
+        DiffError::UnmatchedChecksum => MergeError::UnmatchedChecksum,
+    }
+}
+
 impl Diff for BranchValue {
     fn diff(&mut self, other: &Self) -> Result<(), MergeError> {
-        if let BranchValue { taken: Some(taken) } = *other {
-            // We don't care about exact count. It's only important is the branch covered or not
-            if taken > 0 {
-                self.taken = None;
-            }
-        };
+        self.taken = core_diff::taken(self.taken, other.taken);
         Ok(())
     }
 }
@@ -50,48 +367,228 @@ impl Diff for SectionValue {
 
 impl Diff for FunctionValue {
     fn diff(&mut self, other: &Self) -> Result<(), MergeError> {
-        if let Some(start_line) = other.start_line.as_ref() {
-            if let Some(my_start_line) = self.start_line.as_ref() {
-                if start_line != my_start_line {
-                    return Err(MergeError::UnmatchedFunctionLine);
-                }
-            }
-        }
-        // As for branch it's only important if it covered or not
-        if other.count > 0 {
-            self.count = 0;
-        }
-        Ok(())
+        core_diff::diff_function(
+            &mut self.count,
+            self.start_line,
+            other.count,
+            other.start_line,
+        )
+        .map_err(merge_error)
     }
 }
 
 impl Diff for LineValue {
     fn diff(&mut self, other: &Self) -> Result<(), MergeError> {
-        if let Some(checksum) = other.checksum.as_ref() {
-            if let Some(my_checksum) = self.checksum.as_ref() {
-                if checksum != my_checksum {
-                    return Err(MergeError::UnmatchedChecksum);
+        core_diff::diff_line(
+            &mut self.count,
+            self.checksum.as_ref(),
+            other.count,
+            other.checksum.as_ref(),
+        )
+        .map_err(merge_error)
+    }
+}
+
+impl<K: Ord, V: Diff> Diff for BTreeMap<K, V> {
+    fn diff(&mut self, other: &Self) -> Result<(), MergeError> {
+        core_diff::diff_map(self, other, V::diff)
+    }
+}
+
+/// Adds the coverage of the diff to the base report, the records missing in the base are
+/// inserted and returned. Counts saturate at the maximum, branches the diff has no count of
+/// (taken in the baseline of the diff) are left as is.
+pub fn apply_diff(base: &mut Report, diff: &Report) -> Result<Inserted, MergeError> {
+    let inserted = Inserted::new(base, diff);
+    base.sections.apply(&diff.sections, Direction::Add)?;
+    Ok(inserted)
+}
+
+/// Subtracts the coverage of the diff from the base report and removes the records
+/// `apply_diff` inserted, reverting it. Counts saturate at zero, the records missing in the
+/// base are skipped.
+pub fn revert_diff(
+    base: &mut Report,
+    diff: &Report,
+    inserted: &Inserted,
+) -> Result<(), MergeError> {
+    base.sections.apply(&diff.sections, Direction::Subtract)?;
+    inserted.remove(base);
+    Ok(())
+}
+
+/// Records and branch counts `apply_diff` inserted into the base report. A record the base had
+/// with a zero count and an inserted one look the same after the apply, so the revert needs them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Inserted {
+    sections: Vec<SectionKey>,
+    functions: Vec<(SectionKey, FunctionKey)>,
+    branches: Vec<(SectionKey, BranchKey)>,
+    lines: Vec<(SectionKey, LineKey)>,
+    /// Branches of the base without a taken count (`-`) which the diff has one of.
+    taken: Vec<(SectionKey, BranchKey)>,
+}
+
+impl Inserted {
+    /// Records of the diff the base doesn't have.
+    fn new(base: &Report, diff: &Report) -> Self {
+        fn missing<K: Ord + Clone, V>(
+            section: &SectionKey,
+            diff: &BTreeMap<K, V>,
+            base: &BTreeMap<K, V>,
+        ) -> Vec<(SectionKey, K)> {
+            diff.keys()
+                .filter(|key| !base.contains_key(key))
+                .map(|key| (section.clone(), key.clone()))
+                .collect()
+        }
+
+        let mut inserted = Inserted::default();
+        for (key, section) in &diff.sections {
+            match base.sections.get(key) {
+                Some(base) => {
+                    inserted
+                        .functions
+                        .extend(missing(key, &section.functions, &base.functions));
+                    inserted
+                        .branches
+                        .extend(missing(key, &section.branches, &base.branches));
+                    inserted
+                        .lines
+                        .extend(missing(key, &section.lines, &base.lines));
+                    inserted.taken.extend(
+                        section
+                            .branches
+                            .iter()
+                            .filter(|(branch, value)| {
+                                value.taken.is_some()
+                                    && base.branches.get(branch).is_some_and(|b| b.taken.is_none())
+                            })
+                            .map(|(branch, _)| (key.clone(), *branch)),
+                    );
                 }
+                None => inserted.sections.push(key.clone()),
+            }
+        }
+        inserted
+    }
+
+    /// Whether the apply inserted nothing.
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+            && self.functions.is_empty()
+            && self.branches.is_empty()
+            && self.lines.is_empty()
+            && self.taken.is_empty()
+    }
+
+    fn remove(&self, report: &mut Report) {
+        for key in &self.sections {
+            report.sections.remove(key);
+        }
+        for (key, function) in &self.functions {
+            if let Some(section) = report.sections.get_mut(key) {
+                section.functions.remove(function);
             }
         }
-        // As for branch it's only important if it covered or not
-        if other.count > 0 {
-            self.count = 0;
+        for (key, branch) in &self.branches {
+            if let Some(section) = report.sections.get_mut(key) {
+                section.branches.remove(branch);
+            }
+        }
+        for (key, line) in &self.lines {
+            if let Some(section) = report.sections.get_mut(key) {
+                section.lines.remove(line);
+            }
+        }
+        for (key, branch) in &self.taken {
+            if let Some(value) = report
+                .sections
+                .get_mut(key)
+                .and_then(|section| section.branches.get_mut(branch))
+            {
+                value.taken = None;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Direction {
+    Add,
+    Subtract,
+}
+
+impl Direction {
+    fn count(self, count: u64, delta: u64) -> u64 {
+        match self {
+            Direction::Add => count.saturating_add(delta),
+            Direction::Subtract => count.saturating_sub(delta),
+        }
+    }
+}
+
+trait Apply: Sized {
+    fn apply(&mut self, diff: &Self, direction: Direction) -> Result<(), MergeError>;
+}
+
+impl Apply for BranchValue {
+    fn apply(&mut self, diff: &Self, direction: Direction) -> Result<(), MergeError> {
+        if let Some(taken) = diff.taken {
+            self.taken = Some(direction.count(self.taken.unwrap_or(0), taken));
         }
         Ok(())
     }
 }
 
-impl<K, V> Diff for BTreeMap<K, V>
+impl Apply for SectionValue {
+    fn apply(&mut self, diff: &Self, direction: Direction) -> Result<(), MergeError> {
+        self.functions.apply(&diff.functions, direction)?;
+        self.branches.apply(&diff.branches, direction)?;
+        self.lines.apply(&diff.lines, direction)?;
+        Ok(())
+    }
+}
+
+impl Apply for FunctionValue {
+    fn apply(&mut self, diff: &Self, direction: Direction) -> Result<(), MergeError> {
+        core_diff::check_match(
+            self.start_line.as_ref(),
+            diff.start_line.as_ref(),
+            DiffError::UnmatchedFunctionLine,
+        )
+        .map_err(merge_error)?;
+        self.count = direction.count(self.count, diff.count);
+        Ok(())
+    }
+}
+
+impl Apply for LineValue {
+    fn apply(&mut self, diff: &Self, direction: Direction) -> Result<(), MergeError> {
+        core_diff::check_match(
+            self.checksum.as_ref(),
+            diff.checksum.as_ref(),
+            DiffError::UnmatchedChecksum,
+        )
+        .map_err(merge_error)?;
+        self.count = direction.count(self.count, diff.count);
+        Ok(())
+    }
+}
+
+impl<K, V> Apply for BTreeMap<K, V>
 where
     K: Ord + Clone,
-    V: Diff,
+    V: Apply + Clone,
 {
-    fn diff(&mut self, other: &Self) -> Result<(), MergeError> {
-        for (key, value) in other {
-            match self.entry(key.clone()) {
-                Entry::Vacant(_) => {}
-                Entry::Occupied(mut e) => e.get_mut().diff(value)?,
+    fn apply(&mut self, diff: &Self, direction: Direction) -> Result<(), MergeError> {
+        for (key, value) in diff {
+            match (self.entry(key.clone()), direction) {
+                (Entry::Occupied(mut e), _) => e.get_mut().apply(value, direction)?,
+                (Entry::Vacant(e), Direction::Add) => {
+                    e.insert(value.clone());
+                }
+                (Entry::Vacant(_), Direction::Subtract) => {}
             }
         }
         Ok(())
@@ -100,9 +597,18 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::diff_reports;
+    use crate::builder::ReportBuilder;
+    use crate::summary::Comparison;
+    use crate::testing::assert_reports_equal;
+    use crate::testing::Generator;
+    use crate::timings;
+    use crate::{
+        apply_diff, diff_merged, diff_pairs, diff_reports, diff_reports_each, diff_reports_with,
+        revert_diff, DiffOptions, Inserted, MergeStrategy,
+    };
     use lcov::report::MergeError;
     use lcov::{Reader, Report};
+    use std::path::PathBuf;
 
     #[test]
     fn diff_report() -> Result<(), MergeError> {
@@ -178,9 +684,233 @@ end_of_record
 
         let diff_rep = diff_reports(&report2, &report1).unwrap();
 
-        for pair in diff_rep.into_records().zip(expected_report.into_records()) {
-            assert_eq!(pair.0, pair.1)
+        assert_reports_equal(&diff_rep, &expected_report);
+        Ok(())
+    }
+
+    #[test]
+    fn diff_large_input() {
+        let options = DiffOptions {
+            threads: 3,
+            ..DiffOptions::default()
+        };
+        for seed in 0..50 {
+            let (first, second) = Generator::new(seed).pair();
+            assert_reports_equal(
+                &diff_reports_with(&first, &second, &options).unwrap(),
+                &diff_reports(&first, &second).unwrap(),
+            );
+        }
+        assert!(DiffOptions::large_input().threads >= 1);
+    }
+
+    #[test]
+    fn diff_many_pairs() {
+        let pairs: Vec<_> = (0..20).map(|seed| Generator::new(seed).pair()).collect();
+        let outcomes = diff_pairs(
+            &pairs,
+            &DiffOptions {
+                threads: 4,
+                ..DiffOptions::default()
+            },
+        );
+        assert_eq!(outcomes.len(), pairs.len());
+        for ((first, second), outcome) in pairs.iter().zip(&outcomes) {
+            assert_reports_equal(
+                outcome.diff.as_ref().unwrap(),
+                &diff_reports(first, second).unwrap(),
+            );
+            assert_eq!(outcome.comparison, Comparison::new(second, first));
+            assert_eq!(outcome.timings, None);
+        }
+        assert!(diff_pairs(&[], &DiffOptions::default()).is_empty());
+
+        let options = DiffOptions {
+            timings: true,
+            ..DiffOptions::default()
+        };
+        let outcome = &diff_pairs(&pairs[..1], &options)[0];
+        let phases: Vec<_> = outcome
+            .timings
+            .as_ref()
+            .unwrap()
+            .phases
+            .iter()
+            .map(|phase| (phase.name, phase.records))
+            .collect();
+        assert_eq!(
+            phases,
+            vec![
+                ("diff", timings::records(outcome.diff.as_ref().unwrap())),
+                ("compare", outcome.comparison.files.len()),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_merged_shards() {
+        let firsts = [
+            ReportBuilder::file("a.c").line(1, 2).line(2, 0).build(),
+            ReportBuilder::file("a.c")
+                .line(1, 1)
+                .line(2, 1)
+                .next_file("b.c")
+                .line(1, 0)
+                .build(),
+        ];
+        let seconds = [
+            ReportBuilder::file("a.c").line(1, 0).build(),
+            ReportBuilder::file("a.c").line(2, 3).build(),
+        ];
+        let mut first = Report::new();
+        let mut second = Report::new();
+        for report in &firsts {
+            first.merge(report.clone()).unwrap();
+        }
+        for report in &seconds {
+            second.merge(report.clone()).unwrap();
         }
+        let expected = diff_reports(&first, &second).unwrap();
+        for threads in [1, 3] {
+            let options = DiffOptions {
+                threads,
+                ..DiffOptions::default()
+            };
+            let merged = diff_merged(&firsts, &seconds, &options).unwrap();
+            assert_reports_equal(&merged, &expected);
+        }
+
+        let options = DiffOptions {
+            merge: "max".parse().unwrap(),
+            ..DiffOptions::default()
+        };
+        assert_reports_equal(
+            &diff_merged(&firsts, &seconds, &options).unwrap(),
+            &ReportBuilder::file("a.c")
+                .line(1, 2)
+                .line(2, 0)
+                .next_file("b.c")
+                .line(1, 0)
+                .build(),
+        );
+        let moved = [
+            ReportBuilder::file("a.c").function("main", 1, 1).build(),
+            ReportBuilder::file("a.c").function("main", 2, 1).build(),
+        ];
+        assert_eq!(
+            diff_merged(&moved, &[], &options),
+            Err(MergeError::UnmatchedFunctionLine)
+        );
+        assert!("min".parse::<MergeStrategy>().is_err());
+    }
+
+    #[test]
+    fn diff_section_by_section() {
+        let first = ReportBuilder::file("a.c")
+            .line(1, 1)
+            .line(2, 1)
+            .next_file("b.c")
+            .line(1, 0)
+            .build();
+        let second = ReportBuilder::file("a.c").line(1, 1).line(2, 0).build();
+        let mut seen = Vec::new();
+        let report = diff_reports_each(&first, &second, |key, delta| {
+            seen.push((
+                key.source_file.clone(),
+                delta.diff.lines.len(),
+                delta.baseline.map(|coverage| coverage.lines.hit),
+                delta.current.lines.hit,
+            ));
+        })
+        .unwrap();
+        assert_reports_equal(&report, &diff_reports(&first, &second).unwrap());
+        assert_eq!(
+            seen,
+            vec![
+                (PathBuf::from("a.c"), 2, Some(1), 2),
+                (PathBuf::from("b.c"), 1, None, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn revert_inserted_records() -> Result<(), MergeError> {
+        let base = ReportBuilder::file("a.c")
+            .branch(1, 0, 0, None)
+            .line(1, 0)
+            .build();
+        let diff = ReportBuilder::file("a.c")
+            .branch(1, 0, 0, Some(2))
+            .line(1, 2)
+            .line(2, 1)
+            .function("f", 2, 1)
+            .branch(2, 0, 0, Some(1))
+            .next_file("b.c")
+            .line(1, 1)
+            .function("g", 1, 1)
+            .branch(1, 0, 0, Some(1))
+            .build();
+
+        let mut applied = base.clone();
+        let inserted = apply_diff(&mut applied, &diff)?;
+        assert!(!inserted.is_empty());
+        assert_eq!(applied.sections.len(), 2);
+        revert_diff(&mut applied, &diff, &inserted)?;
+        // The zero count line of the base stays, the inserted records are gone
+        assert_eq!(applied, base);
+
+        let mut applied = base.clone();
+        assert!(apply_diff(&mut applied, &base)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn apply_and_revert() -> Result<(), MergeError> {
+        let base = ReportBuilder::file("a.c")
+            .function("main", 1, 1)
+            .branch(1, 0, 0, Some(2))
+            .branch(1, 0, 1, None)
+            .line(1, 1)
+            .line(2, 0)
+            .build();
+        let diff = ReportBuilder::file("a.c")
+            .function("main", 1, 1)
+            .branch(1, 0, 1, Some(3))
+            .line(2, 5)
+            .line(3, 1)
+            .next_file("b.c")
+            .line(1, 1)
+            .build();
+
+        let mut applied = base.clone();
+        let inserted = apply_diff(&mut applied, &diff)?;
+        let counts = |report: &Report, file: &str| -> Vec<(u32, u64)> {
+            let section = report
+                .sections
+                .iter()
+                .find(|(key, _)| key.source_file.ends_with(file))
+                .map(|(_, section)| section.clone())
+                .unwrap_or_default();
+            section
+                .lines
+                .iter()
+                .map(|(key, value)| (key.line, value.count))
+                .collect()
+        };
+        assert_eq!(counts(&applied, "a.c"), vec![(1, 1), (2, 5), (3, 1)]);
+        assert_eq!(counts(&applied, "b.c"), vec![(1, 1)]);
+
+        revert_diff(&mut applied, &diff, &inserted)?;
+        assert_eq!(counts(&applied, "a.c"), vec![(1, 1), (2, 0)]);
+        assert_reports_equal(&applied, &base);
+        let section = applied.sections.values().next().unwrap();
+        let taken: Vec<Option<u64>> = section.branches.values().map(|b| b.taken).collect();
+        assert_eq!(taken, vec![Some(2), None]);
+        assert_eq!(section.functions.values().next().unwrap().count, 1);
+
+        // Saturates at zero
+        revert_diff(&mut applied, &diff, &Inserted::default())?;
+        assert_eq!(counts(&applied, "a.c"), vec![(1, 1), (2, 0)]);
         Ok(())
     }
 }
@@ -0,0 +1,97 @@
+//! Approximate memory accounting of the reports, so the size of the inputs can be capped
+//! before a CI box runs out of memory.
+use std::mem::size_of;
+
+use lcov::report::section::branch::{Key as BranchKey, Value as BranchValue};
+use lcov::report::section::function::{Key as FunctionKey, Value as FunctionValue};
+use lcov::report::section::line::{Key as LineKey, Value as LineValue};
+use lcov::report::section::{Key as SectionKey, Value as SectionValue};
+use lcov::Report;
+
+/// Bookkeeping of a `BTreeMap` entry besides the key and the value, about the share of a node.
+const ENTRY_OVERHEAD: usize = 16;
+
+/// Bytes the report takes in memory, counting the entries of the maps and the strings on the
+/// heap. It's an estimate within the allocator slack.
+pub fn approximate_size(report: &Report) -> usize {
+    let mut size = size_of::<Report>();
+    for (key, section) in &report.sections {
+        size += size_of::<SectionKey>() + size_of::<SectionValue>() + ENTRY_OVERHEAD;
+        size += key.test_name.capacity() + key.source_file.as_os_str().len();
+        size +=
+            section.lines.len() * (size_of::<LineKey>() + size_of::<LineValue>() + ENTRY_OVERHEAD);
+        size += section
+            .lines
+            .values()
+            .filter_map(|line| line.checksum.as_ref())
+            .map(String::capacity)
+            .sum::<usize>();
+        size += section.functions.len()
+            * (size_of::<FunctionKey>() + size_of::<FunctionValue>() + ENTRY_OVERHEAD);
+        size += section
+            .functions
+            .keys()
+            .map(|key| key.name.capacity())
+            .sum::<usize>();
+        size += section.branches.len()
+            * (size_of::<BranchKey>() + size_of::<BranchValue>() + ENTRY_OVERHEAD);
+    }
+    size
+}
+
+/// Bytes of `1024`, `512K`, `64M` or `2G` (binary multiples).
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let (number, shift) = match size.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&size[..i], 10),
+        Some((i, 'M' | 'm')) => (&size[..i], 20),
+        Some((i, 'G' | 'g')) => (&size[..i], 30),
+        _ => (size, 0),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(1 << shift))
+        .ok_or_else(|| format!("invalid size: {}", size))
+}
+
+/// `1.5 MiB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{approximate_size, format_size, parse_size};
+    use crate::builder::ReportBuilder;
+
+    #[test]
+    fn sizes() {
+        let small = ReportBuilder::file("a.c").line(1, 1).build();
+        let mut builder = ReportBuilder::file("a.c");
+        for line in 1..=1000 {
+            builder = builder.line(line, 1);
+        }
+        let large = builder.function("main", 1, 1).build();
+        assert!(approximate_size(&small) < approximate_size(&large));
+        assert!(approximate_size(&large) > 1000 * 16);
+
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("64M"), Ok(64 << 20));
+        assert_eq!(parse_size("2g"), Ok(2 << 30));
+        assert!(parse_size("lots").is_err());
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(3 << 19), "1.5 MiB");
+    }
+}
@@ -0,0 +1,273 @@
+//! Coverage snapshots stored as git notes, so the baselines travel with the repository.
+//!
+//! Every snapshot is the lcov report of a commit, kept in the notes ref (`refs/notes/coverage`
+//! by default), which is shared with `git push origin refs/notes/coverage` and
+//! `git fetch origin refs/notes/coverage:refs/notes/coverage`. The pull request of a snapshot,
+//! if it's known when it's recorded, is kept in a `# pull-request: N` line before the records.
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use lcov::Report;
+
+use crate::import::ImportError;
+
+/// Number of the ancestors looked through for a snapshot.
+const MAX_ANCESTORS: usize = 1000;
+
+const PULL_REQUEST: &str = "# pull-request: ";
+
+/// Snapshot of a commit with the commit time (unix seconds) and author.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub commit: String,
+    pub time: u64,
+    pub author: String,
+    pub pull_request: Option<u64>,
+    pub report: Report,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Notes {
+    pub repo: PathBuf,
+    pub notes_ref: String,
+}
+
+impl Notes {
+    pub fn new<P: Into<PathBuf>>(repo: P) -> Self {
+        Notes {
+            repo: repo.into(),
+            notes_ref: "refs/notes/coverage".to_owned(),
+        }
+    }
+
+    fn git(&self) -> Command {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(&self.repo);
+        command
+    }
+
+    /// Output of the git command, `None` if it fails.
+    fn output(&self, args: &[&str]) -> io::Result<Option<String>> {
+        let output = self.git().args(args).stderr(Stdio::null()).output()?;
+        Ok(if output.status.success() {
+            Some(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            None
+        })
+    }
+
+    /// Stores the snapshot of the commit, replacing the previous one.
+    pub fn save(&self, commit: &str, report: &Report) -> io::Result<()> {
+        self.save_with(commit, report, None)
+    }
+
+    /// `save` with the pull request the commit was made in.
+    pub fn save_with(
+        &self,
+        commit: &str,
+        report: &Report,
+        pull_request: Option<u64>,
+    ) -> io::Result<()> {
+        let mut child = self
+            .git()
+            .args([
+                "notes",
+                "--ref",
+                &self.notes_ref,
+                "add",
+                "-f",
+                "-F",
+                "-",
+                commit,
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+        {
+            let mut stdin = child.stdin.take().expect("stdin is piped");
+            if let Some(pull_request) = pull_request {
+                writeln!(stdin, "{}{}", PULL_REQUEST, pull_request)?;
+            }
+            for record in report.clone().into_records() {
+                writeln!(stdin, "{}", record)?;
+            }
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "git notes add failed: {}",
+                status
+            )));
+        }
+        Ok(())
+    }
+
+    /// Snapshot of the commit itself.
+    pub fn load(&self, commit: &str) -> Result<Option<Report>, ImportError> {
+        Ok(self.load_note(commit)?.map(|(report, _)| report))
+    }
+
+    /// Snapshot and pull request of the commit.
+    fn load_note(&self, commit: &str) -> Result<Option<(Report, Option<u64>)>, ImportError> {
+        match self.show(commit)? {
+            Some(note) => Ok(Some(parse_note(&note)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Text of the note of the commit, as `git notes show` prints it.
+    fn show(&self, commit: &str) -> io::Result<Option<String>> {
+        self.output(&["notes", "--ref", &self.notes_ref, "show", commit])
+    }
+
+    /// Commits which have a snapshot.
+    fn annotated(&self) -> io::Result<HashSet<String>> {
+        Ok(self
+            .output(&["notes", "--ref", &self.notes_ref, "list"])?
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| Some(line.split_once(' ')?.1.to_owned()))
+            .collect())
+    }
+
+    /// Snapshot of the commit or of its nearest ancestor which has one, with that commit.
+    pub fn find(&self, commit: &str) -> Result<Option<(String, Report)>, ImportError> {
+        match self.find_note(commit)? {
+            Some((commit, note)) => Ok(Some((commit, parse_note(&note)?.0))),
+            None => Ok(None),
+        }
+    }
+
+    /// Text of the note `find` reads the snapshot from, with its commit. Signatures of the
+    /// snapshots are made of this text, from `git notes show`.
+    pub fn find_note(&self, commit: &str) -> io::Result<Option<(String, String)>> {
+        let annotated = self.annotated()?;
+        if annotated.is_empty() {
+            return Ok(None);
+        }
+        let max_count = format!("--max-count={}", MAX_ANCESTORS);
+        let ancestors = self
+            .output(&["rev-list", &max_count, commit])?
+            .unwrap_or_default();
+        for ancestor in ancestors.lines() {
+            if annotated.contains(ancestor) {
+                if let Some(note) = self.show(ancestor)? {
+                    return Ok(Some((ancestor.to_owned(), note)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Up to `max` latest snapshots of the commit and its ancestors, the oldest first, with the
+    /// commit metadata.
+    pub fn history(&self, commit: &str, max: usize) -> Result<Vec<Snapshot>, ImportError> {
+        let annotated = self.annotated()?;
+        let max_count = format!("--max-count={}", MAX_ANCESTORS);
+        let log = self
+            .output(&["log", "--format=%H %ct %an", &max_count, commit])?
+            .unwrap_or_default();
+        let mut snapshots = Vec::new();
+        for line in log.lines() {
+            if snapshots.len() == max {
+                break;
+            }
+            let mut fields = line.splitn(3, ' ');
+            let (commit, time, author) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(commit), Some(time), author) if annotated.contains(commit) => {
+                    (commit, time, author.unwrap_or_default())
+                }
+                _ => continue,
+            };
+            if let Some((report, pull_request)) = self.load_note(commit)? {
+                snapshots.push(Snapshot {
+                    commit: commit.to_owned(),
+                    time: time.parse().unwrap_or_default(),
+                    author: author.to_owned(),
+                    pull_request,
+                    report,
+                });
+            }
+        }
+        snapshots.reverse();
+        Ok(snapshots)
+    }
+}
+
+/// Snapshot of the note text of `Notes::find_note`.
+pub fn parse_snapshot(note: &str) -> Result<Report, ImportError> {
+    Ok(parse_note(note)?.0)
+}
+
+/// Snapshot and pull request of the note text.
+fn parse_note(note: &str) -> Result<(Report, Option<u64>), ImportError> {
+    let (pull_request, records) = match note.strip_prefix(PULL_REQUEST) {
+        Some(rest) => {
+            let (number, records) = rest.split_once('\n').unwrap_or((rest, ""));
+            (number.trim().parse().ok(), records)
+        }
+        None => (None, note),
+    };
+    let report = Report::from_reader(lcov::Reader::new(records.as_bytes()))?;
+    Ok((report, pull_request))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_snapshot, Notes};
+    use lcov::{Reader, Report};
+    use std::process::Command;
+
+    #[test]
+    fn save_and_find() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {:?} failed", args);
+        };
+        git(&["init", "-q"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "first"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "second"]);
+
+        let notes = Notes::new(dir.path());
+        let input = "SF:a.c\nDA:1,1\nend_of_record\n";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        assert!(notes.find("HEAD").unwrap().is_none());
+        // `git notes add` needs the committer identity of the repository
+        git(&["config", "user.name", "test"]);
+        git(&["config", "user.email", "test@example.com"]);
+        notes.save("HEAD~1", &report).unwrap();
+
+        assert!(notes.load("HEAD").unwrap().is_none());
+        let (commit, found) = notes.find("HEAD").unwrap().unwrap();
+        assert_eq!(commit.len(), 40);
+        assert_eq!(found, report);
+        let (_, note) = notes.find_note("HEAD").unwrap().unwrap();
+        assert_eq!(note, "TN:\nSF:a.c\nDA:1,1\nLF:1\nLH:1\nend_of_record\n");
+        assert_eq!(parse_snapshot(&note).unwrap(), report);
+
+        let input = "SF:a.c\nDA:1,0\nend_of_record\n";
+        let head = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        notes.save_with("HEAD", &head, Some(12)).unwrap();
+        let history = notes.history("HEAD", 10).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].commit, commit);
+        assert_eq!(history[0].report, report);
+        assert!(history[1].time >= history[0].time);
+        assert_eq!(history[1].author, "test");
+        assert_eq!(
+            (history[0].pull_request, history[1].pull_request),
+            (None, Some(12))
+        );
+        assert_eq!(notes.load("HEAD").unwrap().unwrap(), head);
+        assert_eq!(notes.history("HEAD", 1).unwrap()[0].report, head);
+    }
+}
@@ -0,0 +1,97 @@
+//! Order of the written sections. By default it's the order of `Report::sections`: by the test
+//! name, then by the source path, both compared byte by byte, with the records of a section by
+//! the line (and the function name, block and branch) number, so the output is byte-stable
+//! between runs. The index keeps the order of the sections of an input instead.
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use lcov::report::section::Key as SectionKey;
+use lcov::{Record, Report};
+
+/// Positions of the sections in the input.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SectionOrder {
+    index: BTreeMap<SectionKey, usize>,
+}
+
+impl SectionOrder {
+    /// Index of the sections of the lcov input by their first appearance.
+    pub fn of_input(input: &str) -> Self {
+        let mut index = BTreeMap::new();
+        let mut test_name = String::new();
+        for line in input.lines() {
+            let (kind, value) = line.trim().split_once(':').unwrap_or((line.trim(), ""));
+            match kind {
+                "TN" => test_name = value.to_owned(),
+                "SF" => {
+                    let key = SectionKey {
+                        test_name: test_name.clone(),
+                        source_file: PathBuf::from(value),
+                    };
+                    let position = index.len();
+                    index.entry(key).or_insert(position);
+                }
+                "end_of_record" => test_name.clear(),
+                _ => {}
+            }
+        }
+        SectionOrder { index }
+    }
+
+    /// Records of the report with the indexed sections first in their order, then the others
+    /// in the default order.
+    pub fn records(&self, mut report: Report) -> Vec<Record> {
+        let mut indexed: Vec<(usize, SectionKey)> = report
+            .sections
+            .keys()
+            .filter_map(|key| Some((*self.index.get(key)?, key.clone())))
+            .collect();
+        indexed.sort();
+        let mut records = Vec::new();
+        for (_, key) in indexed {
+            let mut section = Report::new();
+            if let Some(value) = report.sections.remove(&key) {
+                section.sections.insert(key, value);
+            }
+            records.extend(section.into_records());
+        }
+        records.extend(report.into_records());
+        records
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SectionOrder;
+    use lcov::{Reader, Record, Report};
+
+    #[test]
+    fn input_order() {
+        let input = "TN:\nSF:z.c\nDA:1,1\nend_of_record\nTN:\nSF:a.c\nDA:1,1\nend_of_record\n";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let mut other = report.clone();
+        let extra = "SF:m.c\nDA:1,1\nend_of_record\n";
+        other
+            .merge(Report::from_reader(Reader::new(extra.as_bytes())).unwrap())
+            .unwrap();
+        let paths = |records: Vec<Record>| -> Vec<String> {
+            records
+                .into_iter()
+                .filter_map(|record| match record {
+                    Record::SourceFile { path } => Some(path.display().to_string()),
+                    _ => None,
+                })
+                .collect()
+        };
+        let order = SectionOrder::of_input(input);
+        assert_eq!(
+            paths(report.clone().into_records().collect()),
+            vec!["a.c", "z.c"]
+        );
+        assert_eq!(paths(order.records(other)), vec!["z.c", "a.c", "m.c"]);
+        assert_eq!(
+            order.records(report.clone()),
+            SectionOrder::of_input(input).records(report)
+        );
+    }
+}
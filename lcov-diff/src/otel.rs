@@ -0,0 +1,278 @@
+//! OpenTelemetry span and metrics of a diff run, in the OTLP/HTTP json encoding.
+//!
+//! The span joins the trace of the W3C `traceparent` (set by the CI tracing integrations),
+//! otherwise a new trace is started.
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::hash::md5;
+use crate::json::Value;
+use crate::summary::Comparison;
+
+const SCOPE: &str = "lcov-diff";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run {
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_span_id: Option<String>,
+    pub start: SystemTime,
+    pub duration: Duration,
+    /// Sizes of the current and the baseline inputs in bytes.
+    pub input_bytes: [u64; 2],
+    /// Sections of the current and the baseline reports.
+    pub sections: [usize; 2],
+    /// Sections of the diff report.
+    pub diff_sections: usize,
+    /// Failed threshold checks.
+    pub violations: usize,
+}
+
+impl Run {
+    /// Run started at `start`, in the trace of the `traceparent` if it is valid.
+    pub fn new(start: SystemTime, traceparent: Option<&str>) -> Self {
+        let seed = format!("{:?}-{}", start, process::id());
+        let digest = md5::hex_digest(seed.as_bytes());
+        let (trace_id, parent_span_id) = match traceparent.and_then(parse_traceparent) {
+            Some((trace_id, parent)) => (trace_id.to_owned(), Some(parent.to_owned())),
+            None => (digest.clone(), None),
+        };
+        Run {
+            trace_id,
+            span_id: digest[16..].to_owned(),
+            parent_span_id,
+            start,
+            duration: Duration::default(),
+            input_bytes: [0; 2],
+            sections: [0; 2],
+            diff_sections: 0,
+            violations: 0,
+        }
+    }
+
+    /// `ExportTraceServiceRequest` with the span of the run.
+    pub fn traces(&self, comparison: &Comparison) -> Value {
+        let mut attributes = vec![
+            int("lcov_diff.input.current.bytes", self.input_bytes[0]),
+            int("lcov_diff.input.baseline.bytes", self.input_bytes[1]),
+            int("lcov_diff.sections.current", self.sections[0] as u64),
+            int("lcov_diff.sections.baseline", self.sections[1] as u64),
+            int("lcov_diff.sections.diff", self.diff_sections as u64),
+            int("lcov_diff.files.dropped", dropped_files(comparison)),
+            int("lcov_diff.violations", self.violations as u64),
+        ];
+        attributes.extend(
+            gauges(comparison)
+                .into_iter()
+                .map(|(name, value)| double(name, value)),
+        );
+        let mut span = vec![
+            ("traceId", Value::from(self.trace_id.as_str())),
+            ("spanId", Value::from(self.span_id.as_str())),
+            ("name", Value::from("lcov-diff")),
+            ("kind", Value::from(1u32)),
+            ("startTimeUnixNano", nanos(self.start)),
+            ("endTimeUnixNano", nanos(self.start + self.duration)),
+            ("attributes", Value::Array(attributes)),
+            (
+                "status",
+                Value::object(vec![(
+                    "code",
+                    Value::from(if self.violations == 0 { 1u32 } else { 2 }),
+                )]),
+            ),
+        ];
+        if let Some(parent) = &self.parent_span_id {
+            span.insert(2, ("parentSpanId", Value::from(parent.as_str())));
+        }
+        Value::object(vec![(
+            "resourceSpans",
+            Value::Array(vec![Value::object(vec![
+                ("resource", resource()),
+                (
+                    "scopeSpans",
+                    Value::Array(vec![Value::object(vec![
+                        ("scope", scope()),
+                        ("spans", Value::Array(vec![Value::object(span)])),
+                    ])]),
+                ),
+            ])]),
+        )])
+    }
+
+    /// `ExportMetricsServiceRequest` with the coverage gauges and the run duration.
+    pub fn metrics(&self, comparison: &Comparison) -> Value {
+        let time = nanos(self.start + self.duration);
+        let gauge = |name: &str, unit: &str, value: f64| {
+            Value::object(vec![
+                ("name", Value::from(name)),
+                ("unit", Value::from(unit)),
+                (
+                    "gauge",
+                    Value::object(vec![(
+                        "dataPoints",
+                        Value::Array(vec![Value::object(vec![
+                            ("timeUnixNano", time.clone()),
+                            ("asDouble", Value::from(value)),
+                        ])]),
+                    )]),
+                ),
+            ])
+        };
+        let mut metrics: Vec<Value> = gauges(comparison)
+            .into_iter()
+            .map(|(name, value)| gauge(name, "%", value))
+            .collect();
+        metrics.push(gauge(
+            "lcov_diff.files.dropped",
+            "{file}",
+            dropped_files(comparison) as f64,
+        ));
+        metrics.push(gauge(
+            "lcov_diff.run.duration",
+            "s",
+            self.duration.as_secs_f64(),
+        ));
+        Value::object(vec![(
+            "resourceMetrics",
+            Value::Array(vec![Value::object(vec![
+                ("resource", resource()),
+                (
+                    "scopeMetrics",
+                    Value::Array(vec![Value::object(vec![
+                        ("scope", scope()),
+                        ("metrics", Value::Array(metrics)),
+                    ])]),
+                ),
+            ])]),
+        )])
+    }
+}
+
+/// Trace id and parent span id of the `00-<trace-id>-<span-id>-<flags>` header.
+fn parse_traceparent(header: &str) -> Option<(&str, &str)> {
+    let mut parts = header.trim().split('-');
+    let (version, trace_id, span_id) = (parts.next()?, parts.next()?, parts.next()?);
+    let hex = |s: &str, len| s.len() == len && s.bytes().all(|b| b.is_ascii_hexdigit());
+    if version != "00" || !hex(trace_id, 32) || !hex(span_id, 16) {
+        return None;
+    }
+    Some((trace_id, span_id))
+}
+
+fn gauges(comparison: &Comparison) -> Vec<(&'static str, f64)> {
+    let mut gauges = Vec::new();
+    if let Some(percent) = comparison.current.lines.percent() {
+        gauges.push(("lcov_diff.lines.percent", percent));
+    }
+    gauges.push(("lcov_diff.lines.delta", comparison.lines_delta()));
+    if let Some(percent) = comparison.patch.percent() {
+        gauges.push(("lcov_diff.patch.percent", percent));
+    }
+    gauges
+}
+
+fn dropped_files(comparison: &Comparison) -> u64 {
+    comparison
+        .files
+        .iter()
+        .filter(|file| file.lines_delta() < 0.0)
+        .count() as u64
+}
+
+fn resource() -> Value {
+    Value::object(vec![(
+        "attributes",
+        Value::Array(vec![string("service.name", SCOPE)]),
+    )])
+}
+
+fn scope() -> Value {
+    Value::object(vec![
+        ("name", Value::from(SCOPE)),
+        ("version", Value::from(env!("CARGO_PKG_VERSION"))),
+    ])
+}
+
+/// Int64 values are strings in the json encoding.
+fn nanos(time: SystemTime) -> Value {
+    let nanos = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    Value::from(nanos.as_nanos().to_string())
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    Value::object(vec![("key", Value::from(key)), ("value", value)])
+}
+
+fn string(key: &str, value: &str) -> Value {
+    attribute(
+        key,
+        Value::object(vec![("stringValue", Value::from(value))]),
+    )
+}
+
+fn int(key: &str, value: u64) -> Value {
+    attribute(
+        key,
+        Value::object(vec![("intValue", Value::from(value.to_string()))]),
+    )
+}
+
+fn double(key: &str, value: f64) -> Value {
+    attribute(
+        key,
+        Value::object(vec![("doubleValue", Value::from(value))]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Run;
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn span() {
+        let report = "SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\n";
+        let report = Report::from_reader(Reader::new(report.as_bytes())).unwrap();
+        let comparison = Comparison::new(&report, &report);
+        let traceparent = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+        let mut run = Run::new(UNIX_EPOCH + Duration::from_secs(1), Some(traceparent));
+        run.duration = Duration::from_millis(1500);
+        run.input_bytes = [30, 30];
+        run.sections = [1, 1];
+
+        let traces = run.traces(&comparison);
+        let span = &traces.get("resourceSpans").unwrap().as_array().unwrap()[0]
+            .get("scopeSpans")
+            .unwrap()
+            .as_array()
+            .unwrap()[0]
+            .get("spans")
+            .unwrap()
+            .as_array()
+            .unwrap()[0];
+        assert_eq!(
+            span.get("traceId").and_then(|v| v.as_str()),
+            Some("0af7651916cd43dd8448eb211c80319c")
+        );
+        assert_eq!(
+            span.get("parentSpanId").and_then(|v| v.as_str()),
+            Some("b7ad6b7169203331")
+        );
+        assert_eq!(run.span_id.len(), 16);
+        assert_eq!(
+            span.get("endTimeUnixNano").and_then(|v| v.as_str()),
+            Some("2500000000")
+        );
+        let attributes = span.get("attributes").unwrap().pretty();
+        assert!(attributes.contains("\"lcov_diff.lines.percent\""));
+
+        let metrics = run.metrics(&comparison).pretty();
+        assert!(metrics.contains("\"lcov_diff.run.duration\""));
+        assert!(Run::new(UNIX_EPOCH, Some("garbage"))
+            .parent_span_id
+            .is_none());
+    }
+}
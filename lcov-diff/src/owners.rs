@@ -0,0 +1,177 @@
+//! Ownership of the source files from CODEOWNERS, for the summaries and checks per team.
+//!
+//! Custom mapping files use the same syntax: a gitignore-like pattern followed by the owners,
+//! the last matching rule wins.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use lcov::Report;
+
+use crate::summary::{self, Comparison};
+
+/// Places where GitHub and GitLab look for the file.
+const LOCATIONS: [&str; 4] = [
+    ".github/CODEOWNERS",
+    "CODEOWNERS",
+    "docs/CODEOWNERS",
+    ".gitlab/CODEOWNERS",
+];
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct CodeOwners {
+    rules: Vec<(String, Vec<String>)>,
+}
+
+impl CodeOwners {
+    pub fn parse(input: &str) -> Self {
+        let rules = input
+            .lines()
+            .map(str::trim)
+            // GitLab sections `[Section]` only group the rules
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('['))
+            .filter_map(|line| {
+                let line = line.split(" #").next().unwrap_or(line);
+                let mut fields = line.split_whitespace();
+                let pattern = fields.next()?.to_owned();
+                Some((pattern, fields.map(str::to_owned).collect()))
+            })
+            .collect();
+        CodeOwners { rules }
+    }
+
+    /// Reads the CODEOWNERS file of the repository from the standard locations.
+    pub fn find(repo: &Path) -> io::Result<Option<Self>> {
+        for location in &LOCATIONS {
+            match fs::read_to_string(repo.join(location)) {
+                Ok(input) => return Ok(Some(CodeOwners::parse(&input))),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Owners of the repository relative path, empty if no rule matches (or the matching
+    /// rule has no owners).
+    pub fn owners_of(&self, path: &Path) -> &[String] {
+        let path = path.to_string_lossy().replace('\\', "/");
+        self.rules
+            .iter()
+            .rev()
+            .find(|(pattern, _)| matches(pattern, &path))
+            .map_or(&[], |(_, owners)| owners.as_slice())
+    }
+
+    /// Comparison of every owner which has files in either report, ordered by name.
+    /// Absolute source paths are made relative to `root`.
+    pub fn by_owner(
+        &self,
+        root: &Path,
+        baseline: &Report,
+        current: &Report,
+    ) -> Vec<(String, Comparison)> {
+        summary::by_group(baseline, current, |path| {
+            let path: PathBuf = path.strip_prefix(root).unwrap_or(path).to_owned();
+            self.owners_of(&path).to_vec()
+        })
+    }
+}
+
+/// Matches the gitignore-like pattern: patterns with a slash (other than the trailing one) are
+/// anchored to the root, the others match at any depth, a directory matches everything below.
+pub(crate) fn matches(pattern: &str, path: &str) -> bool {
+    let dir = pattern.ends_with('/');
+    let pattern = pattern.trim_end_matches('/');
+    let anchored = pattern.contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    let path = path.trim_start_matches("./");
+    let segments: Vec<&str> = path.split('/').collect();
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let starts: Vec<usize> = if anchored {
+        vec![0]
+    } else {
+        (0..segments.len()).collect()
+    };
+    starts.into_iter().any(|start| {
+        (start..=segments.len()).any(|end| {
+            // A directory pattern has to leave something below it
+            let below = end < segments.len();
+            (below || !dir) && glob_segments(&pattern, &segments[start..end])
+        })
+    })
+}
+
+/// Matches the pattern segments against the path segments, `**` matches any number of them.
+fn glob_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| glob_segments(rest, &path[skip..])),
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, path)) => {
+                glob(segment.as_bytes(), name.as_bytes()) && glob_segments(rest, path)
+            }
+            None => false,
+        },
+    }
+}
+
+/// `*` and `?` wildcards within a segment.
+fn glob(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| glob(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && glob(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && glob(rest, &name[1..]),
+    }
+}
+
+/// Parses `owner=percent` limit.
+pub fn parse_limit(limit: &str) -> Result<(String, f64), String> {
+    limit
+        .rsplit_once('=')
+        .and_then(|(owner, percent)| Some((owner.to_owned(), percent.parse().ok()?)))
+        .ok_or_else(|| format!("`owner=percent` limit expected: {}", limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_limit, CodeOwners};
+    use lcov::{Reader, Report};
+    use std::path::Path;
+
+    #[test]
+    fn owners() {
+        let owners = CodeOwners::parse(
+            "\
+# default owners
+*           @org/core
+*.md        @org/docs
+/src/ui/    @org/frontend @alice
+docs/**/api.md @org/api
+[Generated]
+/gen/
+",
+        );
+        let owners_of = |path: &str| owners.owners_of(Path::new(path)).to_vec();
+        assert_eq!(owners_of("src/lib.rs"), vec!["@org/core"]);
+        assert_eq!(
+            owners_of("src/ui/button/mod.rs"),
+            vec!["@org/frontend", "@alice"]
+        );
+        assert_eq!(owners_of("lib/src/ui/x.rs"), vec!["@org/core"]);
+        assert_eq!(owners_of("README.md"), vec!["@org/docs"]);
+        assert_eq!(owners_of("docs/v1/http/api.md"), vec!["@org/api"]);
+        assert!(owners_of("gen/a.rs").is_empty());
+
+        let input = "SF:/repo/src/ui/a.rs\nDA:1,1\nend_of_record\nSF:/repo/src/lib.rs\nDA:1,0\nend_of_record\n";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let groups = owners.by_owner(Path::new("/repo"), &Report::new(), &report);
+        let names: Vec<&str> = groups.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["@alice", "@org/core", "@org/frontend"]);
+        assert_eq!(
+            parse_limit("@org/core=80"),
+            Ok(("@org/core".to_owned(), 80.0))
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Links to the source lines on a source hosting.
+//!
+//! The link is built from the url template with `{commit}`, `{path}` and `{line}` placeholders,
+//! e.g. `https://github.com/org/repo/blob/{commit}/{path}#L{line}`.
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::render::relative_path;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Permalink {
+    template: String,
+    commit: String,
+    root: Option<PathBuf>,
+}
+
+impl Permalink {
+    pub fn new<T: Into<String>, C: Into<String>>(template: T, commit: C) -> Self {
+        Permalink {
+            template: template.into(),
+            commit: commit.into(),
+            root: None,
+        }
+    }
+
+    /// Root of the repository, the report paths are made relative to it. Without it the paths
+    /// are expected to be relative already.
+    pub fn root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Link to the `line` of the file.
+    pub fn link(&self, path: &Path, line: u32) -> String {
+        let path = match &self.root {
+            Some(root) => relative_path(root, path),
+            None => path.to_owned(),
+        };
+        let path = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        self.template
+            .replace("{commit}", &self.commit)
+            .replace("{path}", path.trim_start_matches('/'))
+            .replace("{line}", &line.to_string())
+    }
+}
+
+/// Commit id of the revision (`HEAD`, a branch, ...) of the repository, so the links stay
+/// valid when the revision moves.
+pub fn resolve_commit(repo: &Path, rev: &str) -> io::Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{}^{{commit}}", rev))
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("unknown revision {}", rev)));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve_commit, Permalink};
+    use std::fs;
+    use std::path::Path;
+    use std::process::Command;
+
+    #[test]
+    fn link() {
+        let permalink = Permalink::new(
+            "https://github.com/org/repo/blob/{commit}/{path}#L{line}",
+            "abc123",
+        );
+        assert_eq!(
+            permalink.link(Path::new("src/main.rs"), 42),
+            "https://github.com/org/repo/blob/abc123/src/main.rs#L42"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/a.c"), "int a;\n").unwrap();
+        let permalink = Permalink::new("https://h/{commit}/{path}#L{line}", "c0").root(dir.path());
+        // The report was made on another machine
+        assert_eq!(
+            permalink.link(Path::new("/ci/build/src/a.c"), 2),
+            "https://h/c0/src/a.c#L2"
+        );
+        assert_eq!(
+            permalink.link(&dir.path().join("src/b.c"), 2),
+            "https://h/c0/src/b.c#L2"
+        );
+    }
+
+    #[test]
+    fn commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(["-c", "user.name=t", "-c", "user.email=t@t"])
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success());
+            String::from_utf8(output.stdout).unwrap()
+        };
+        git(&["init", "-q"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "first"]);
+        let head = resolve_commit(dir.path(), "HEAD").unwrap();
+        assert_eq!(head, git(&["rev-parse", "HEAD"]).trim());
+        assert!(resolve_commit(dir.path(), "missing").is_err());
+    }
+}
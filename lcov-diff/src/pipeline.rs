@@ -0,0 +1,329 @@
+//! The load → normalize → diff → render flow of the tool as a builder:
+//!
+//! ```no_run
+//! use lcov_diff::pipeline::{DiffPipeline, Format};
+//! use lcov_diff::process::DropZeros;
+//!
+//! let markdown = DiffPipeline::new()
+//!     .load("current.info")
+//!     .load_baseline("baseline.info")
+//!     .rewrite_paths(|path| path.strip_prefix("/ci/build").unwrap_or(path).to_owned())
+//!     .diff()?
+//!     .post_process(&DropZeros)
+//!     .render(Format::Markdown);
+//! # Ok::<(), lcov_diff::pipeline::PipelineError>(())
+//! ```
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use lcov::report::MergeError;
+use lcov::Report;
+
+use crate::extras::Extras;
+use crate::filter::PathFilter;
+use crate::import::lenient::Warning;
+use crate::import::{self, ImportError, LoadOptions, Loaded};
+use crate::process::{DiffContext, PostProcessor};
+use crate::render::html::SideBySide;
+use crate::render::markdown::Markdown;
+use crate::render::number::Numbers;
+use crate::summary::Comparison;
+use crate::visit::{self, DiffVisitor};
+use crate::write::WriteOptions;
+use crate::{diff_reports_with, render, DiffOptions};
+
+#[derive(Debug)]
+pub enum PipelineError {
+    /// `load` or `load_baseline` wasn't called.
+    MissingInput(&'static str),
+    Import(PathBuf, ImportError),
+    Merge(MergeError),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PipelineError::MissingInput(input) => write!(f, "no {} report to load", input),
+            PipelineError::Import(path, e) => write!(f, "{}: {}", path.display(), e),
+            PipelineError::Merge(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for PipelineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PipelineError::Import(_, e) => Some(e),
+            PipelineError::Merge(e) => Some(e),
+            PipelineError::MissingInput(_) => None,
+        }
+    }
+}
+
+impl From<MergeError> for PipelineError {
+    fn from(e: MergeError) -> Self {
+        PipelineError::Merge(e)
+    }
+}
+
+/// Outputs of `Diffed::render`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Format {
+    Lcov,
+    Markdown,
+    /// The summary json of `--summary-json`, the totals of the comparison rather than the diff.
+    SummaryJson,
+}
+
+type Rewrite = Box<dyn Fn(&Path) -> PathBuf>;
+
+#[derive(Default)]
+pub struct DiffPipeline {
+    load_options: LoadOptions,
+    diff_options: DiffOptions,
+    current: Option<PathBuf>,
+    baseline: Option<PathBuf>,
+    rewrites: Vec<Rewrite>,
+}
+
+impl DiffPipeline {
+    pub fn new() -> Self {
+        DiffPipeline::default()
+    }
+
+    pub fn load_options(mut self, options: LoadOptions) -> Self {
+        self.load_options = options;
+        self
+    }
+
+    pub fn diff_options(mut self, options: DiffOptions) -> Self {
+        self.diff_options = options;
+        self
+    }
+
+    /// The current report.
+    pub fn load<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.current = Some(path.into());
+        self
+    }
+
+    pub fn load_baseline<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.baseline = Some(path.into());
+        self
+    }
+
+    /// Rewrites the source paths of both reports, after the earlier rewrites. The sections
+    /// which end up with the same path are merged, `diff` fails if their records conflict.
+    pub fn rewrite_paths<F: Fn(&Path) -> PathBuf + 'static>(mut self, rewrite: F) -> Self {
+        self.rewrites.push(Box::new(rewrite));
+        self
+    }
+
+    /// Sections of the files to keep, the lcov inputs skip the others without parsing them.
+    pub fn filter(mut self, filter: PathFilter) -> Self {
+        self.load_options.filter = filter;
+        self
+    }
+
+    fn load_input(
+        &self,
+        path: Option<&PathBuf>,
+        input: &'static str,
+    ) -> Result<Loaded, PipelineError> {
+        let path = path.ok_or(PipelineError::MissingInput(input))?;
+        import::load_file_with(path, &self.load_options)
+            .map_err(|e| PipelineError::Import(path.clone(), e))
+    }
+
+    fn rewrite(&self, report: Report) -> Result<Report, MergeError> {
+        if self.rewrites.is_empty() {
+            return Ok(report);
+        }
+        let mut rewritten = Report::new();
+        for (mut key, section) in report.sections {
+            for rewrite in &self.rewrites {
+                key.source_file = rewrite(&key.source_file);
+            }
+            let mut single = Report::new();
+            single.sections.insert(key, section);
+            rewritten.merge(single)?;
+        }
+        Ok(rewritten)
+    }
+
+    /// Loads the reports, rewrites the paths and diffs. The importers shim the lcov 2.x
+    /// inputs, so the functions of both lcov versions are read the same.
+    pub fn diff(self) -> Result<Diffed, PipelineError> {
+        let current = self.load_input(self.current.as_ref(), "current")?;
+        let baseline = self.load_input(self.baseline.as_ref(), "baseline")?;
+        let current_report = self.rewrite(current.report)?;
+        let baseline_report = self.rewrite(baseline.report)?;
+        let diff = diff_reports_with(&current_report, &baseline_report, &self.diff_options)?;
+        let mut warnings = current.warnings;
+        warnings.extend(baseline.warnings);
+        Ok(Diffed {
+            comparison: Comparison::new(&baseline_report, &current_report),
+            current: current_report,
+            baseline: baseline_report,
+            diff,
+            extras: current.extras,
+            warnings,
+        })
+    }
+}
+
+/// Result of `DiffPipeline::diff`.
+#[derive(Debug, Clone)]
+pub struct Diffed {
+    pub current: Report,
+    pub baseline: Report,
+    pub diff: Report,
+    pub comparison: Comparison,
+    /// Unknown records of the current report.
+    pub extras: Extras,
+    /// Warnings of the lenient parse of both reports.
+    pub warnings: Vec<Warning>,
+}
+
+impl Diffed {
+    pub fn post_process<P: PostProcessor + ?Sized>(mut self, processor: &P) -> Self {
+        let ctx = DiffContext {
+            first: &self.current,
+            second: &self.baseline,
+        };
+        processor.process(&mut self.diff, &ctx);
+        self
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Lcov => {
+                let mut lines =
+                    WriteOptions::default().lines(self.diff.clone().into_records(), &self.extras);
+                lines.push(String::new());
+                lines.join("\n")
+            }
+            Format::Markdown => Markdown::default().render(&self.comparison),
+            Format::SummaryJson => {
+                render::azure::summary_json(&self.comparison, &Numbers::default()).pretty()
+            }
+        }
+    }
+
+    /// Visits the changes from the baseline to the current report.
+    pub fn visit<V: DiffVisitor + ?Sized>(&self, visitor: &mut V) {
+        visit::visit(&self.baseline, &self.current, visitor)
+    }
+
+    /// Side-by-side html view of the reports with the sources of `root`.
+    pub fn write_html<P: AsRef<Path>, Q: Into<PathBuf>>(
+        &self,
+        out_dir: P,
+        root: Q,
+    ) -> io::Result<()> {
+        let root = root.into();
+        SideBySide::new(&self.baseline, &self.current)
+            .baseline_root(root.clone())
+            .current_root(root)
+            .write(out_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiffPipeline, Format, PipelineError};
+    use crate::filter::PathFilter;
+    use crate::process::DropZeros;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn pipeline() {
+        let dir = tempfile::tempdir().unwrap();
+        let (current, baseline) = (dir.path().join("a.info"), dir.path().join("b.info"));
+        fs::write(
+            &current,
+            "SF:/ci/build/a.c\nDA:1,1\nDA:2,1\nend_of_record\nSF:/ci/build/vendor/b.c\nDA:1,1\nend_of_record\n",
+        )
+        .unwrap();
+        fs::write(
+            &baseline,
+            "SF:/ci/build/a.c\nDA:1,1\nDA:2,0\nend_of_record\n",
+        )
+        .unwrap();
+        let diffed = DiffPipeline::new()
+            .load(&current)
+            .load_baseline(&baseline)
+            .filter(PathFilter {
+                exclude: vec!["vendor/".to_owned()],
+                ..PathFilter::default()
+            })
+            .rewrite_paths(|path| path.strip_prefix("/ci/build").unwrap_or(path).to_owned())
+            .diff()
+            .unwrap()
+            .post_process(&DropZeros);
+        assert_eq!(
+            diffed.render(Format::Lcov),
+            "TN:\nSF:a.c\nDA:2,1\nLF:1\nLH:1\nend_of_record\n"
+        );
+        assert!(diffed
+            .render(Format::Markdown)
+            .starts_with("### Coverage diff"));
+        assert!(diffed.render(Format::SummaryJson).contains("\"lines\""));
+        assert_eq!(diffed.comparison.files.len(), 1);
+        assert_eq!(diffed.comparison.files[0].path, Path::new("a.c"));
+
+        assert!(matches!(
+            DiffPipeline::new().load(&current).diff(),
+            Err(PipelineError::MissingInput("baseline"))
+        ));
+    }
+
+    #[test]
+    fn lcov2_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let (current, baseline) = (dir.path().join("a.info"), dir.path().join("b.info"));
+        fs::write(
+            &current,
+            "VER:2.0\nSF:a.c\nFN:1,3,main\nFNDA:2,main\nDA:1,2\nend_of_record\n",
+        )
+        .unwrap();
+        fs::write(
+            &baseline,
+            "VER:2.0\nSF:a.c\nFN:1,3,main\nFNDA:0,main\nDA:1,0\nend_of_record\n",
+        )
+        .unwrap();
+        let diffed = DiffPipeline::new()
+            .load(&current)
+            .load_baseline(&baseline)
+            .diff()
+            .unwrap();
+        assert_eq!(
+            diffed.render(Format::Lcov),
+            "TN:\nSF:a.c\nFN:1,main\nFNDA:2,main\nFNF:1\nFNH:1\nDA:1,2\nLF:1\nLH:1\nend_of_record\n"
+        );
+    }
+
+    #[test]
+    fn conflicting_rewrites() {
+        let dir = tempfile::tempdir().unwrap();
+        let (current, baseline) = (dir.path().join("a.info"), dir.path().join("b.info"));
+        fs::write(
+            &current,
+            "SF:unit/a.c\nFN:1,f\nend_of_record\nSF:it/a.c\nFN:2,f\nend_of_record\n",
+        )
+        .unwrap();
+        fs::write(&baseline, "SF:a.c\nDA:1,1\nend_of_record\n").unwrap();
+        let result = DiffPipeline::new()
+            .load(&current)
+            .load_baseline(&baseline)
+            .rewrite_paths(|path| path.file_name().map_or(path.into(), Into::into))
+            .diff();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "unmatched start line of function"
+        );
+    }
+}
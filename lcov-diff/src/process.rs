@@ -0,0 +1,173 @@
+//! Post-processors of the diff report, run in order after the diff. Custom transformations
+//! implement `PostProcessor`, closures of the same signature do too.
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use lcov::report::MergeError;
+use lcov::Report;
+
+use crate::{diff_reports_with, DiffOptions};
+
+/// Reports the diff was made of.
+#[derive(Debug, Copy, Clone)]
+pub struct DiffContext<'a> {
+    pub first: &'a Report,
+    pub second: &'a Report,
+}
+
+pub trait PostProcessor {
+    fn process(&self, report: &mut Report, ctx: &DiffContext);
+}
+
+impl<F: Fn(&mut Report, &DiffContext)> PostProcessor for F {
+    fn process(&self, report: &mut Report, ctx: &DiffContext) {
+        self(report, ctx)
+    }
+}
+
+/// Drops the records with a zero count, and the sections left empty, so the diff only has the
+/// newly covered records.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DropZeros;
+
+impl PostProcessor for DropZeros {
+    fn process(&self, report: &mut Report, _: &DiffContext) {
+        for section in report.sections.values_mut() {
+            section.lines.retain(|_, line| line.count > 0);
+            section.functions.retain(|_, function| function.count > 0);
+            section.branches.retain(|_, branch| branch.taken > Some(0));
+        }
+        report.sections.retain(|_, section| {
+            !section.lines.is_empty()
+                || !section.functions.is_empty()
+                || !section.branches.is_empty()
+        });
+    }
+}
+
+/// Handling of the sections without any `DA`, `FN` or `BRDA` record, which confuse genhtml.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum EmptySections {
+    Keep,
+    Drop,
+    /// Keeps them, the caller warns about the paths `apply` returns.
+    Warn,
+}
+
+impl EmptySections {
+    pub const VARIANTS: &'static [&'static str] = &["keep", "drop", "warn"];
+
+    /// Drops the empty sections with `Drop`, returns the source files of the empty sections.
+    pub fn apply(&self, report: &mut Report) -> Vec<PathBuf> {
+        let empty: Vec<PathBuf> = report
+            .sections
+            .iter()
+            .filter(|(_, section)| section.is_empty())
+            .map(|(key, _)| key.source_file.clone())
+            .collect();
+        if *self == EmptySections::Drop {
+            report.sections.retain(|_, section| !section.is_empty());
+        }
+        empty
+    }
+}
+
+impl FromStr for EmptySections {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(EmptySections::Keep),
+            "drop" => Ok(EmptySections::Drop),
+            "warn" => Ok(EmptySections::Warn),
+            _ => Err(format!("unknown empty sections policy: {}", s)),
+        }
+    }
+}
+
+impl PostProcessor for EmptySections {
+    fn process(&self, report: &mut Report, _: &DiffContext) {
+        self.apply(report);
+    }
+}
+
+/// `diff_reports_with`, then the post-processors in order.
+pub fn diff_and_process(
+    first: &Report,
+    second: &Report,
+    options: &DiffOptions,
+    processors: &[Box<dyn PostProcessor>],
+) -> Result<Report, MergeError> {
+    let mut report = diff_reports_with(first, second, options)?;
+    let ctx = DiffContext { first, second };
+    for processor in processors {
+        processor.process(&mut report, &ctx);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_and_process, DiffContext, DropZeros, EmptySections, PostProcessor};
+    use crate::builder::ReportBuilder;
+    use crate::testing::assert_reports_equal;
+    use crate::DiffOptions;
+    use lcov::Report;
+    use std::path::PathBuf;
+
+    #[test]
+    fn post_process() {
+        let first = ReportBuilder::file("a.c")
+            .line(1, 1)
+            .line(2, 1)
+            .branch(2, 0, 0, Some(1))
+            .next_file("b.c")
+            .line(1, 1)
+            .build();
+        let second = ReportBuilder::file("a.c")
+            .line(1, 1)
+            .line(2, 0)
+            .branch(2, 0, 0, Some(0))
+            .next_file("b.c")
+            .line(1, 1)
+            .build();
+        let rename = |report: &mut Report, ctx: &DiffContext| {
+            assert_eq!(ctx.first.sections.len(), 2);
+            let sections = std::mem::take(&mut report.sections);
+            for (mut key, section) in sections {
+                key.source_file = key.source_file.with_extension("h");
+                report.sections.insert(key, section);
+            }
+        };
+        let processors: Vec<Box<dyn PostProcessor>> = vec![Box::new(DropZeros), Box::new(rename)];
+        let report =
+            diff_and_process(&first, &second, &DiffOptions::default(), &processors).unwrap();
+        assert_reports_equal(
+            &report,
+            &ReportBuilder::file("a.h")
+                .line(2, 1)
+                .branch(2, 0, 0, Some(1))
+                .build(),
+        );
+    }
+
+    #[test]
+    fn empty_sections() {
+        let report = ReportBuilder::file("a.c")
+            .line(1, 1)
+            .next_file("b.c")
+            .next_file("c.c")
+            .function("main", 1, 0)
+            .build();
+        let mut kept = report.clone();
+        assert_eq!(
+            "warn".parse::<EmptySections>().unwrap().apply(&mut kept),
+            vec![PathBuf::from("b.c")]
+        );
+        assert_eq!(kept, report);
+        let mut dropped = report.clone();
+        EmptySections::Drop.apply(&mut dropped);
+        assert_eq!(dropped.sections.len(), 2);
+        assert!("remove".parse::<EmptySections>().is_err());
+    }
+}
@@ -0,0 +1,336 @@
+//! Protobuf encoding of the diff results, the schema is `proto/lcov_diff.proto`.
+//!
+//! The messages are small and flat, so the wire format is written by hand. Unknown fields
+//! are skipped by the decoder, like generated code does.
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::summary::{Comparison, Counter, Coverage, FileDelta};
+
+/// `DiffResult` message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffResult {
+    pub comparison: Comparison,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DecodeError {
+    pub offset: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid protobuf at {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl DiffResult {
+    pub fn encode(&self) -> Vec<u8> {
+        let comparison = &self.comparison;
+        let mut out = Vec::new();
+        message(&mut out, 1, &coverage(&comparison.baseline));
+        message(&mut out, 2, &coverage(&comparison.current));
+        for file in &comparison.files {
+            let mut delta = Vec::new();
+            string(&mut delta, 1, &file.path.to_string_lossy());
+            if let Some(baseline) = &file.baseline {
+                message(&mut delta, 2, &coverage(baseline));
+            }
+            if let Some(current) = &file.current {
+                message(&mut delta, 3, &coverage(current));
+            }
+            message(&mut out, 3, &delta);
+        }
+        message(&mut out, 4, &counter(&comparison.patch));
+        for (path, line) in &comparison.uncovered_new_lines {
+            let mut uncovered = Vec::new();
+            string(&mut uncovered, 1, &path.to_string_lossy());
+            uint(&mut uncovered, 2, u64::from(*line));
+            message(&mut out, 5, &uncovered);
+        }
+        for warning in &self.warnings {
+            string(&mut out, 6, warning);
+        }
+        out
+    }
+
+    pub fn decode(input: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = DiffResult {
+            comparison: Comparison {
+                baseline: Coverage::default(),
+                current: Coverage::default(),
+                files: Vec::new(),
+                patch: Counter::default(),
+                uncovered_new_lines: Vec::new(),
+            },
+            warnings: Vec::new(),
+        };
+        let comparison = &mut result.comparison;
+        for field in Fields::new(input, 0) {
+            let field = field?;
+            match (field.number, &field.value) {
+                (1, Wire::Bytes(bytes)) => {
+                    comparison.baseline = decode_coverage(bytes, field.offset)?
+                }
+                (2, Wire::Bytes(bytes)) => {
+                    comparison.current = decode_coverage(bytes, field.offset)?
+                }
+                (3, Wire::Bytes(bytes)) => {
+                    let mut delta = FileDelta {
+                        path: PathBuf::new(),
+                        baseline: None,
+                        current: None,
+                    };
+                    for field in Fields::new(bytes, field.offset) {
+                        let field = field?;
+                        match (field.number, &field.value) {
+                            (1, Wire::Bytes(bytes)) => {
+                                delta.path = PathBuf::from(text(bytes, field.offset)?)
+                            }
+                            (2, Wire::Bytes(bytes)) => {
+                                delta.baseline = Some(decode_coverage(bytes, field.offset)?)
+                            }
+                            (3, Wire::Bytes(bytes)) => {
+                                delta.current = Some(decode_coverage(bytes, field.offset)?)
+                            }
+                            _ => {}
+                        }
+                    }
+                    comparison.files.push(delta);
+                }
+                (4, Wire::Bytes(bytes)) => comparison.patch = decode_counter(bytes, field.offset)?,
+                (5, Wire::Bytes(bytes)) => {
+                    let (mut path, mut line) = (PathBuf::new(), 0);
+                    for field in Fields::new(bytes, field.offset) {
+                        let field = field?;
+                        match (field.number, &field.value) {
+                            (1, Wire::Bytes(bytes)) => {
+                                path = PathBuf::from(text(bytes, field.offset)?)
+                            }
+                            (2, Wire::Varint(value)) => line = *value as u32,
+                            _ => {}
+                        }
+                    }
+                    comparison.uncovered_new_lines.push((path, line));
+                }
+                (6, Wire::Bytes(bytes)) => result.warnings.push(text(bytes, field.offset)?),
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+}
+
+fn coverage(coverage: &Coverage) -> Vec<u8> {
+    let mut out = Vec::new();
+    message(&mut out, 1, &counter(&coverage.lines));
+    message(&mut out, 2, &counter(&coverage.functions));
+    message(&mut out, 3, &counter(&coverage.branches));
+    out
+}
+
+fn counter(counter: &Counter) -> Vec<u8> {
+    let mut out = Vec::new();
+    uint(&mut out, 1, counter.found);
+    uint(&mut out, 2, counter.hit);
+    out
+}
+
+fn decode_coverage(input: &[u8], offset: usize) -> Result<Coverage, DecodeError> {
+    let mut coverage = Coverage::default();
+    for field in Fields::new(input, offset) {
+        let field = field?;
+        if let Wire::Bytes(bytes) = field.value {
+            let counter = decode_counter(bytes, field.offset)?;
+            match field.number {
+                1 => coverage.lines = counter,
+                2 => coverage.functions = counter,
+                3 => coverage.branches = counter,
+                _ => {}
+            }
+        }
+    }
+    Ok(coverage)
+}
+
+fn decode_counter(input: &[u8], offset: usize) -> Result<Counter, DecodeError> {
+    let mut counter = Counter::default();
+    for field in Fields::new(input, offset) {
+        let field = field?;
+        match (field.number, field.value) {
+            (1, Wire::Varint(value)) => counter.found = value,
+            (2, Wire::Varint(value)) => counter.hit = value,
+            _ => {}
+        }
+    }
+    Ok(counter)
+}
+
+fn text(bytes: &[u8], offset: usize) -> Result<String, DecodeError> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError {
+        offset,
+        message: "string isn't utf-8",
+    })
+}
+
+fn varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Varint field, proto3 omits zeros.
+fn uint(out: &mut Vec<u8>, number: u32, value: u64) {
+    if value != 0 {
+        varint(out, u64::from(number) << 3);
+        varint(out, value);
+    }
+}
+
+fn message(out: &mut Vec<u8>, number: u32, bytes: &[u8]) {
+    varint(out, u64::from(number) << 3 | 2);
+    varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn string(out: &mut Vec<u8>, number: u32, value: &str) {
+    if !value.is_empty() {
+        message(out, number, value.as_bytes());
+    }
+}
+
+enum Wire<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+struct Field<'a> {
+    number: u32,
+    value: Wire<'a>,
+    /// Offset of the value in the whole message, for the errors.
+    offset: usize,
+}
+
+/// Iterator over the fields of the message.
+struct Fields<'a> {
+    input: &'a [u8],
+    pos: usize,
+    base: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(input: &'a [u8], base: usize) -> Self {
+        Fields {
+            input,
+            pos: 0,
+            base,
+        }
+    }
+
+    fn error(&self, message: &'static str) -> DecodeError {
+        DecodeError {
+            offset: self.base + self.pos,
+            message,
+        }
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self
+                .input
+                .get(self.pos)
+                .ok_or_else(|| self.error("truncated varint"))?;
+            self.pos += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.error("varint is too long"))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.input.len())
+            .ok_or_else(|| self.error("truncated field"))?;
+        let bytes = &self.input[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn field(&mut self) -> Result<Field<'a>, DecodeError> {
+        let key = self.varint()?;
+        let offset = self.base + self.pos;
+        let value = match key & 7 {
+            0 => Wire::Varint(self.varint()?),
+            1 => {
+                self.take(8)?;
+                Wire::Fixed
+            }
+            2 => {
+                let len = self.varint()? as usize;
+                Wire::Bytes(self.take(len)?)
+            }
+            5 => {
+                self.take(4)?;
+                Wire::Fixed
+            }
+            _ => return Err(self.error("unsupported wire type")),
+        };
+        Ok(Field {
+            number: (key >> 3) as u32,
+            value,
+            offset,
+        })
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<Field<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.input.len() {
+            return None;
+        }
+        let field = self.field();
+        if field.is_err() {
+            // Stop after the first error
+            self.pos = self.input.len();
+        }
+        Some(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DiffResult;
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn roundtrip() {
+        let baseline = "SF:a.c\nDA:1,1\nend_of_record\n";
+        let current = "SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\nSF:b.c\nFN:1,f\nFNDA:3,f\nDA:1,300\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let result = DiffResult {
+            comparison: Comparison::new(&baseline, &current),
+            warnings: vec!["Line coverage dropped".to_owned()],
+        };
+        let encoded = result.encode();
+        // Counter of the baseline lines: found 1, hit 1
+        assert_eq!(&encoded[..6], &[0x0a, 0x0a, 0x0a, 0x04, 0x08, 0x01]);
+        assert_eq!(DiffResult::decode(&encoded).unwrap(), result);
+        assert!(DiffResult::decode(&encoded[..encoded.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+//! Json payload of the threshold violations alert, with the files whose coverage dropped.
+use std::path::PathBuf;
+
+use super::number::Numbers;
+use super::regressions;
+use crate::check::Violation;
+use crate::json::Value;
+use crate::permalink::Permalink;
+use crate::snippet::{self, Sources};
+use crate::summary::{Comparison, Counter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    /// Maximum number of the regressed files listed.
+    pub max_files: usize,
+    /// Link to the full report.
+    pub report_url: Option<String>,
+    /// Source root and the context lines of the snippets of the uncovered new lines, `None`
+    /// leaves them out.
+    pub snippets: Option<(PathBuf, usize)>,
+    /// Precision and rounding of the percentages.
+    pub numbers: Numbers,
+    /// Links of the uncovered new lines, `None` leaves them out.
+    pub permalink: Option<Permalink>,
+}
+
+impl Default for Alert {
+    fn default() -> Self {
+        Alert {
+            max_files: 20,
+            report_url: None,
+            snippets: None,
+            numbers: Numbers::default(),
+            permalink: None,
+        }
+    }
+}
+
+impl Alert {
+    pub fn render(&self, comparison: &Comparison, violations: &[Violation]) -> Value {
+        let violations = violations
+            .iter()
+            .map(|violation| {
+                let (value, limit) = violation.value_and_limit();
+                Value::object(vec![
+                    ("kind", Value::from(violation.kind())),
+                    ("message", Value::from(violation.to_string())),
+                    ("value", Value::from(self.numbers.round(value))),
+                    ("limit", Value::from(limit)),
+                ])
+            })
+            .collect();
+        let uncovered = comparison.uncovered_new_lines_by_file();
+        let mut sources = self
+            .snippets
+            .as_ref()
+            .map(|(root, context)| Sources::new(root, *context));
+        let files = regressions(comparison, self.max_files)
+            .into_iter()
+            .map(|file| {
+                let percent = |coverage: Option<Counter>| {
+                    Value::from(
+                        coverage
+                            .and_then(|c| c.percent())
+                            .map(|p| self.numbers.round(p)),
+                    )
+                };
+                let lines = uncovered
+                    .get(file.path.as_path())
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                let mut fields = vec![
+                    ("path", Value::from(file.path.to_string_lossy().as_ref())),
+                    ("baseline", percent(file.baseline.map(|c| c.lines))),
+                    ("current", percent(file.current.map(|c| c.lines))),
+                    ("delta", Value::from(self.numbers.round(file.lines_delta()))),
+                    (
+                        "uncovered_new_lines",
+                        Value::Array(lines.iter().map(|line| Value::from(*line)).collect()),
+                    ),
+                ];
+                if let Some(permalink) = &self.permalink {
+                    let links = lines
+                        .iter()
+                        .map(|line| {
+                            Value::object(vec![
+                                ("line", Value::from(*line)),
+                                ("url", Value::from(permalink.link(&file.path, *line))),
+                            ])
+                        })
+                        .collect();
+                    fields.push(("uncovered_new_line_links", Value::Array(links)));
+                }
+                if let Some(sources) = &mut sources {
+                    let snippets = snippet::ranges(lines)
+                        .into_iter()
+                        .filter_map(|(start, end)| sources.snippet(&file.path, start, end))
+                        .map(|snippet| {
+                            Value::object(vec![
+                                ("start_line", Value::from(snippet.start_line)),
+                                ("end_line", Value::from(snippet.end_line)),
+                                ("text", Value::from(snippet.text.as_str())),
+                            ])
+                        })
+                        .collect();
+                    fields.push(("snippets", Value::Array(snippets)));
+                }
+                Value::object(fields)
+            })
+            .collect();
+        let mut fields = vec![
+            ("status", Value::from("failed")),
+            (
+                "lines",
+                Value::from(
+                    comparison
+                        .current
+                        .lines
+                        .percent()
+                        .map(|p| self.numbers.round(p)),
+                ),
+            ),
+            (
+                "lines_delta",
+                Value::from(self.numbers.round(comparison.lines_delta())),
+            ),
+            ("violations", Value::Array(violations)),
+            ("files", Value::Array(files)),
+        ];
+        if let Some(url) = &self.report_url {
+            fields.push(("report_url", Value::from(url.as_str())));
+        }
+        Value::object(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Alert;
+    use crate::check::Thresholds;
+    use crate::permalink::Permalink;
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn render() {
+        let baseline = "SF:a.c\nDA:1,1\nDA:2,1\nend_of_record\nSF:b.c\nDA:1,1\nend_of_record\n";
+        let current =
+            "SF:a.c\nDA:1,1\nDA:2,0\nDA:3,0\nend_of_record\nSF:b.c\nDA:1,1\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&baseline, &current);
+        let violations = Thresholds {
+            min_lines: None,
+            max_drop: Some(10.0),
+        }
+        .check(&comparison);
+
+        let json = Alert::default()
+            .render(&comparison, &violations)
+            .to_string();
+        assert!(json.starts_with(r#"{"status":"failed","lines":50,"lines_delta":-50,"#));
+        assert!(json.contains(r#""kind":"lines_dropped","#));
+        assert!(json.contains(
+            r#""files":[{"path":"a.c","baseline":100,"current":33.33,"delta":-66.67,"uncovered_new_lines":[3]}]"#
+        ));
+
+        let alert = Alert {
+            permalink: Some(Permalink::new("https://h/{commit}/{path}#L{line}", "c0")),
+            ..Alert::default()
+        };
+        assert!(alert.render(&comparison, &violations).to_string().contains(
+            r#""uncovered_new_lines":[3],"uncovered_new_line_links":[{"line":3,"url":"https://h/c0/a.c#L3"}]}"#
+        ));
+    }
+}
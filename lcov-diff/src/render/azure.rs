@@ -0,0 +1,103 @@
+//! Azure Pipelines coverage publishing.
+//!
+//! `PublishCodeCoverageResults` task takes the Cobertura xml of the new report, the summary json
+//! keeps the computed diff numbers next to it for the following steps of the pipeline.
+use super::number::Numbers;
+use crate::json::Value;
+use crate::permalink::Permalink;
+use crate::summary::{Comparison, Counter};
+
+/// Summary of the comparison, the percentages rounded by `numbers`.
+pub fn summary_json(comparison: &Comparison, numbers: &Numbers) -> Value {
+    summary_json_with(comparison, numbers, None)
+}
+
+/// Summary of the comparison with the links of the uncovered new lines when there's a
+/// permalink.
+pub fn summary_json_with(
+    comparison: &Comparison,
+    numbers: &Numbers,
+    permalink: Option<&Permalink>,
+) -> Value {
+    let mut fields = vec![
+        ("lines", counter(numbers, comparison.current.lines)),
+        ("functions", counter(numbers, comparison.current.functions)),
+        ("branches", counter(numbers, comparison.current.branches)),
+        (
+            "baseline_lines",
+            counter(numbers, comparison.baseline.lines),
+        ),
+        (
+            "lines_delta",
+            Value::from(numbers.round(comparison.lines_delta())),
+        ),
+        ("patch", counter(numbers, comparison.patch)),
+        (
+            "uncovered_new_lines",
+            Value::from(comparison.uncovered_new_lines.len()),
+        ),
+    ];
+    if let Some(permalink) = permalink {
+        let links = comparison
+            .uncovered_new_lines
+            .iter()
+            .map(|(path, line)| {
+                Value::object(vec![
+                    ("path", Value::from(path.to_string_lossy().as_ref())),
+                    ("line", Value::from(*line)),
+                    ("url", Value::from(permalink.link(path, *line))),
+                ])
+            })
+            .collect();
+        fields.push(("uncovered_new_line_links", Value::Array(links)));
+    }
+    Value::object(fields)
+}
+
+fn counter(numbers: &Numbers, counter: Counter) -> Value {
+    Value::object(vec![
+        ("covered", Value::from(counter.hit)),
+        ("total", Value::from(counter.found)),
+        (
+            "percent",
+            Value::from(counter.percent().map(|p| numbers.round(p))),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{summary_json, summary_json_with};
+    use crate::permalink::Permalink;
+    use crate::render::number::Numbers;
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn summary() {
+        let baseline = "SF:a.c\nDA:1,1\nend_of_record\n";
+        let current = "SF:a.c\nDA:1,1\nDA:2,0\nDA:3,0\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let json =
+            summary_json(&Comparison::new(&baseline, &current), &Numbers::default()).to_string();
+
+        assert!(json.starts_with(r#"{"lines":{"covered":1,"total":3,"percent":33.33},"#));
+        assert!(json.contains(r#""functions":{"covered":0,"total":0,"percent":null}"#));
+        assert!(json.contains(r#""lines_delta":-66.67,"#));
+        assert!(json.ends_with(r#""uncovered_new_lines":2}"#));
+    }
+
+    #[test]
+    fn links() {
+        let current = "SF:src/a.c\nDA:1,1\nDA:2,0\nend_of_record\n";
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&Report::new(), &current);
+        let permalink = Permalink::new("https://h/blob/{commit}/{path}#L{line}", "c0");
+        let json = summary_json_with(&comparison, &Numbers::default(), Some(&permalink));
+
+        assert!(json.to_string().ends_with(
+            r#""uncovered_new_line_links":[{"path":"src/a.c","line":2,"url":"https://h/blob/c0/src/a.c#L2"}]}"#
+        ));
+    }
+}
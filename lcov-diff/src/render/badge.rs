@@ -0,0 +1,196 @@
+//! Coverage badges: flat SVG like the shields.io ones and the shields endpoint json.
+use std::fmt;
+use std::str::FromStr;
+
+use super::escape;
+use crate::json::Value;
+use crate::summary::Comparison;
+
+/// Number shown by the badge.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Metric {
+    /// Line coverage of the new report.
+    Total,
+    /// Change of the line coverage in percentage points.
+    Delta,
+    /// Coverage of the new lines.
+    Patch,
+}
+
+impl Metric {
+    pub const VARIANTS: &'static [&'static str] = &["total", "delta", "patch"];
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "total" => Ok(Metric::Total),
+            "delta" => Ok(Metric::Delta),
+            "patch" => Ok(Metric::Patch),
+            _ => Err(format!("unknown badge metric: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Metric::Total => "coverage",
+            Metric::Delta => "coverage delta",
+            Metric::Patch => "patch coverage",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Badge {
+    pub metric: Metric,
+    /// Label on the left side, the metric name if `None`.
+    pub label: Option<String>,
+    /// Coverage below this is red.
+    pub red: f64,
+    /// Coverage below this is yellow, green otherwise.
+    pub yellow: f64,
+}
+
+impl Default for Badge {
+    fn default() -> Self {
+        Badge {
+            metric: Metric::Total,
+            label: None,
+            red: 50.0,
+            yellow: 80.0,
+        }
+    }
+}
+
+impl Badge {
+    fn label(&self) -> String {
+        self.label
+            .clone()
+            .unwrap_or_else(|| self.metric.to_string())
+    }
+
+    /// Message and the color of the badge.
+    fn value(&self, comparison: &Comparison) -> (String, &'static str) {
+        let percent = match self.metric {
+            Metric::Total => comparison.current.lines.percent(),
+            Metric::Patch => comparison.patch.percent(),
+            Metric::Delta => {
+                let delta = comparison.lines_delta();
+                let color = if delta < -0.005 {
+                    "red"
+                } else if delta > 0.005 {
+                    "brightgreen"
+                } else {
+                    "lightgrey"
+                };
+                return (format!("{:+.2}%", delta), color);
+            }
+        };
+        match percent {
+            None => ("unknown".to_owned(), "lightgrey"),
+            Some(percent) if percent < self.red => (format!("{:.2}%", percent), "red"),
+            Some(percent) if percent < self.yellow => (format!("{:.2}%", percent), "yellow"),
+            Some(percent) => (format!("{:.2}%", percent), "brightgreen"),
+        }
+    }
+
+    /// Shields.io endpoint json, https://shields.io/badges/endpoint-badge
+    pub fn endpoint_json(&self, comparison: &Comparison) -> Value {
+        let (message, color) = self.value(comparison);
+        Value::object(vec![
+            ("schemaVersion", Value::from(1u32)),
+            ("label", Value::from(self.label())),
+            ("message", Value::from(message)),
+            ("color", Value::from(color)),
+        ])
+    }
+
+    pub fn svg(&self, comparison: &Comparison) -> String {
+        let (message, color) = self.value(comparison);
+        let label = self.label();
+        let color = match color {
+            "red" => "#e05d44",
+            "yellow" => "#dfb317",
+            "brightgreen" => "#4c1",
+            _ => "#9f9f9f",
+        };
+        let left = text_width(&label) + 10;
+        let right = text_width(&message) + 10;
+        let width = left + right;
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">
+  <title>{label}: {message}</title>
+  <linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+  <clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+  <g clip-path="url(#r)">
+    <rect width="{left}" height="20" fill="#555"/>
+    <rect x="{left}" width="{right}" height="20" fill="{color}"/>
+    <rect width="{width}" height="20" fill="url(#s)"/>
+  </g>
+  <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+    <text x="{label_x}" y="15" fill="#010101" fill-opacity=".3">{label}</text>
+    <text x="{label_x}" y="14">{label}</text>
+    <text x="{message_x}" y="15" fill="#010101" fill-opacity=".3">{message}</text>
+    <text x="{message_x}" y="14">{message}</text>
+  </g>
+</svg>
+"##,
+            width = width,
+            left = left,
+            right = right,
+            color = color,
+            label = escape(&label),
+            message = escape(&message),
+            label_x = left / 2,
+            message_x = left + right / 2,
+        )
+    }
+}
+
+/// Approximate width of 11px Verdana text, there are no font metrics without the font.
+fn text_width(text: &str) -> usize {
+    text.chars()
+        .map(|ch| match ch {
+            'i' | 'l' | 'j' | '.' | ',' | ':' | '\'' | '|' | '!' => 4,
+            'f' | 'r' | 't' | ' ' | '(' | ')' | '-' => 5,
+            'm' | 'w' | 'M' | 'W' | '%' => 10,
+            _ => 7,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Badge, Metric};
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn badges() {
+        let baseline = "SF:a.c\nDA:1,1\nend_of_record\n";
+        let current = "SF:a.c\nDA:1,1\nDA:2,1\nDA:3,0\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&baseline, &current);
+
+        assert_eq!(
+            Badge::default().endpoint_json(&comparison).to_string(),
+            r#"{"schemaVersion":1,"label":"coverage","message":"66.67%","color":"yellow"}"#
+        );
+        let delta = Badge {
+            metric: Metric::Delta,
+            ..Badge::default()
+        };
+        assert_eq!(
+            delta.endpoint_json(&comparison).get("message"),
+            Some(&"-33.33%".into())
+        );
+        let svg = delta.svg(&comparison);
+        assert!(svg.contains("aria-label=\"coverage delta: -33.33%\""));
+        assert!(svg.contains("fill=\"#e05d44\""));
+    }
+}
@@ -0,0 +1,273 @@
+//! Cobertura xml, which is the only format some CI systems and their plugins ingest.
+//!
+//! Every directory becomes a package and every source file becomes a class of it.
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use lcov::report::section::Value as SectionValue;
+use lcov::Report;
+
+use super::{escape, relative_path};
+use crate::summary::{by_file, Counter, Coverage};
+
+#[derive(Debug, Clone)]
+pub struct Cobertura {
+    /// Directory `SF:` paths are relative to.
+    pub source: PathBuf,
+    /// Seconds since unix epoch.
+    pub timestamp: u64,
+}
+
+impl Default for Cobertura {
+    fn default() -> Self {
+        Cobertura {
+            source: PathBuf::from("."),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Cobertura {
+    pub fn render(&self, report: &Report) -> String {
+        let files = by_file(report);
+        let mut packages: BTreeMap<String, Vec<(PathBuf, &SectionValue)>> = BTreeMap::new();
+        for (path, section) in &files {
+            // Filenames are relative to the source directory, which `.` of absolute paths isn't
+            // a prefix of
+            let path = path
+                .strip_prefix(&self.source)
+                .map(Path::to_owned)
+                .unwrap_or_else(|_| relative_path(&self.source, path));
+            packages
+                .entry(package_name(&path))
+                .or_default()
+                .push((path, section));
+        }
+        let source = fs::canonicalize(&self.source).unwrap_or_else(|_| self.source.clone());
+        let total = Coverage::of_report(report);
+
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" ?>\n");
+        out.push_str(
+            "<!DOCTYPE coverage SYSTEM \"http://cobertura.sourceforge.net/xml/coverage-04.dtd\">\n",
+        );
+        let _ = writeln!(
+            out,
+            "<coverage line-rate=\"{}\" branch-rate=\"{}\" lines-covered=\"{}\" lines-valid=\"{}\" \
+             branches-covered=\"{}\" branches-valid=\"{}\" complexity=\"0\" version=\"lcov-diff\" timestamp=\"{}\">",
+            rate(total.lines),
+            rate(total.branches),
+            total.lines.hit,
+            total.lines.found,
+            total.branches.hit,
+            total.branches.found,
+            self.timestamp,
+        );
+        let _ = writeln!(
+            out,
+            "  <sources>\n    <source>{}</source>\n  </sources>\n  <packages>",
+            escape(&source.to_string_lossy())
+        );
+        for (name, classes) in &packages {
+            let mut coverage = Coverage::default();
+            for (_, section) in classes {
+                coverage += Coverage::of_section(section);
+            }
+            let _ = writeln!(
+                out,
+                "    <package name=\"{}\" line-rate=\"{}\" branch-rate=\"{}\" complexity=\"0\">\n      <classes>",
+                escape(name),
+                rate(coverage.lines),
+                rate(coverage.branches),
+            );
+            for (path, section) in classes {
+                class(&mut out, path, section);
+            }
+            out.push_str("      </classes>\n    </package>\n");
+        }
+        out.push_str("  </packages>\n</coverage>\n");
+        out
+    }
+}
+
+fn class(out: &mut String, path: &Path, section: &SectionValue) {
+    let coverage = Coverage::of_section(section);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let _ = writeln!(
+        out,
+        "        <class name=\"{}\" filename=\"{}\" line-rate=\"{}\" branch-rate=\"{}\" complexity=\"0\">",
+        escape(&name),
+        escape(&path.to_string_lossy()),
+        rate(coverage.lines),
+        rate(coverage.branches),
+    );
+
+    out.push_str("          <methods>\n");
+    for (key, function) in &section.functions {
+        let start_line = match function.start_line {
+            Some(line) => line,
+            None => continue,
+        };
+        let hit = if function.count > 0 { 1 } else { 0 };
+        let _ = writeln!(
+            out,
+            "            <method name=\"{}\" signature=\"\" line-rate=\"{}\" branch-rate=\"0\" complexity=\"0\">\n\
+             \x20             <lines>\n\
+             \x20               <line number=\"{}\" hits=\"{}\"/>\n\
+             \x20             </lines>\n\
+             \x20           </method>",
+            escape(&key.name),
+            hit,
+            start_line,
+            function.count,
+        );
+    }
+    out.push_str("          </methods>\n          <lines>\n");
+
+    let mut branches: BTreeMap<u32, Counter> = BTreeMap::new();
+    for (key, branch) in &section.branches {
+        let counter = branches.entry(key.line).or_default();
+        counter.found += 1;
+        if branch.taken.unwrap_or(0) > 0 {
+            counter.hit += 1;
+        }
+    }
+    for (key, line) in &section.lines {
+        match branches.get(&key.line) {
+            Some(counter) => {
+                let _ = writeln!(
+                    out,
+                    "            <line number=\"{}\" hits=\"{}\" branch=\"true\" condition-coverage=\"{:.0}% ({}/{})\"/>",
+                    key.line,
+                    line.count,
+                    counter.percent().unwrap_or_default(),
+                    counter.hit,
+                    counter.found,
+                );
+            }
+            None => {
+                let _ = writeln!(
+                    out,
+                    "            <line number=\"{}\" hits=\"{}\" branch=\"false\"/>",
+                    key.line, line.count,
+                );
+            }
+        }
+    }
+    out.push_str("          </lines>\n        </class>\n");
+}
+
+/// Name of the package is the dotted directory path.
+fn package_name(path: &Path) -> String {
+    let name = path
+        .parent()
+        .map(|dir| {
+            dir.components()
+                .filter_map(|c| match c {
+                    std::path::Component::Normal(name) => Some(name.to_string_lossy()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join(".")
+        })
+        .unwrap_or_default();
+    if name.is_empty() {
+        ".".to_owned()
+    } else {
+        name
+    }
+}
+
+fn rate(counter: Counter) -> String {
+    format!(
+        "{:.4}",
+        counter.percent().map(|p| p / 100.0).unwrap_or_default()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cobertura;
+    use lcov::{Reader, Report};
+    use std::path::PathBuf;
+
+    #[test]
+    fn render_diff() {
+        let first = "SF:/repo/src/a.c\nDA:1,1\nDA:2,1\nend_of_record\n";
+        let second = "SF:/repo/src/a.c\nDA:1,1\nDA:2,0\nend_of_record\n";
+        let first = Report::from_reader(Reader::new(first.as_bytes())).unwrap();
+        let second = Report::from_reader(Reader::new(second.as_bytes())).unwrap();
+        let diff = crate::diff_reports(&first, &second).unwrap();
+        let xml = Cobertura {
+            source: PathBuf::from("/repo"),
+            timestamp: 0,
+        }
+        .render(&diff);
+
+        assert!(xml.contains("lines-covered=\"1\" lines-valid=\"2\""));
+        assert!(xml.contains("<class name=\"a.c\" filename=\"src/a.c\" line-rate=\"0.5000\""));
+        assert!(xml.contains("<line number=\"1\" hits=\"0\" branch=\"false\"/>"));
+        assert!(xml.contains("<line number=\"2\" hits=\"1\" branch=\"false\"/>"));
+    }
+
+    #[test]
+    fn absolute_paths() {
+        let root = env!("CARGO_MANIFEST_DIR");
+        let input = format!(
+            "SF:{}/src/lib.rs\nDA:1,1\nend_of_record\nSF:{}/src/missing/a.c\nDA:1,0\nend_of_record\n",
+            root, root
+        );
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let xml = Cobertura {
+            source: PathBuf::from("."),
+            timestamp: 0,
+        }
+        .render(&report);
+
+        let source = std::fs::canonicalize(".").unwrap();
+        assert!(xml.contains(&format!("<source>{}</source>", source.display())));
+        assert!(xml.contains("<package name=\"src\""));
+        assert!(xml.contains("<class name=\"lib.rs\" filename=\"src/lib.rs\""));
+        assert!(xml.contains("<package name=\"src.missing\""));
+        assert!(xml.contains("filename=\"src/missing/a.c\""));
+    }
+
+    #[test]
+    fn render() {
+        let input = "\
+SF:src/a.c
+FN:1,main
+FNDA:1,main
+BRDA:2,0,0,1
+BRDA:2,0,1,0
+DA:1,1
+DA:2,1
+DA:3,0
+end_of_record
+";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let xml = Cobertura {
+            source: PathBuf::from("/repo"),
+            timestamp: 0,
+        }
+        .render(&report);
+
+        assert!(xml.contains("<coverage line-rate=\"0.6667\" branch-rate=\"0.5000\" lines-covered=\"2\" lines-valid=\"3\""));
+        assert!(xml.contains("<package name=\"src\" line-rate=\"0.6667\""));
+        assert!(xml.contains("<class name=\"a.c\" filename=\"src/a.c\""));
+        assert!(xml.contains("<method name=\"main\" signature=\"\" line-rate=\"1\""));
+        assert!(xml.contains(
+            "<line number=\"2\" hits=\"1\" branch=\"true\" condition-coverage=\"50% (1/2)\"/>"
+        ));
+        assert!(xml.contains("<line number=\"3\" hits=\"0\" branch=\"false\"/>"));
+    }
+}
@@ -0,0 +1,121 @@
+//! Codecov upload body: the network section (files of the repository) and the lcov report.
+//!
+//! Paths are fixed by the `before::after` prefix rules, like the `fixes` of `codecov.yml`,
+//! before the report is written, so the uploaded paths match the repository ones.
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use lcov::report::section::Key as SectionKey;
+use lcov::Report;
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Codecov {
+    /// Prefix replacements of the source paths, an empty `after` strips the prefix.
+    pub fixes: Vec<(String, String)>,
+    /// Files of the repository, Codecov matches report paths against them.
+    pub network: Vec<PathBuf>,
+    /// Name of the report file in the upload.
+    pub name: String,
+}
+
+/// Parses `before::after` fix rule.
+pub fn parse_fix(rule: &str) -> Result<(String, String), String> {
+    rule.split_once("::")
+        .map(|(before, after)| (before.to_owned(), after.to_owned()))
+        .ok_or_else(|| format!("`before::after` rule expected: {}", rule))
+}
+
+/// Tracked files of the git repository, empty if `git ls-files` fails.
+pub fn git_files(repo: &Path) -> Vec<PathBuf> {
+    Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .arg("ls-files")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl Codecov {
+    /// Report with the source paths rewritten by the fix rules, the first matching rule wins.
+    pub fn fix_paths(&self, report: &Report) -> Report {
+        let mut fixed = Report::new();
+        for (key, section) in &report.sections {
+            let path = key.source_file.to_string_lossy();
+            let source_file = self
+                .fixes
+                .iter()
+                .find_map(|(before, after)| {
+                    let rest = path.strip_prefix(before.as_str())?;
+                    Some(if after.is_empty() {
+                        PathBuf::from(rest.trim_start_matches('/'))
+                    } else {
+                        PathBuf::from(format!("{}{}", after, rest))
+                    })
+                })
+                .unwrap_or_else(|| key.source_file.clone());
+            let mut single = Report::new();
+            single.sections.insert(
+                SectionKey {
+                    test_name: key.test_name.clone(),
+                    source_file,
+                },
+                section.clone(),
+            );
+            fixed.merge_lossy(single);
+        }
+        fixed
+    }
+
+    pub fn render(&self, report: &Report) -> String {
+        let mut out = String::new();
+        for path in &self.network {
+            let _ = writeln!(out, "{}", path.display());
+        }
+        out.push_str("<<<<<< network\n");
+        let _ = writeln!(out, "# path={}", self.name);
+        for record in self.fix_paths(report).into_records() {
+            let _ = writeln!(out, "{}", record);
+        }
+        out.push_str("<<<<<< EOF\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_fix, Codecov};
+    use lcov::{Reader, Report};
+    use std::path::PathBuf;
+
+    #[test]
+    fn render() {
+        let input =
+            "SF:/build/src/a.c\nDA:1,1\nend_of_record\nSF:/other/b.c\nDA:1,0\nend_of_record\n";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let body = Codecov {
+            fixes: vec![
+                parse_fix("/build::").unwrap(),
+                parse_fix("/other::lib").unwrap(),
+            ],
+            network: vec![PathBuf::from("src/a.c"), PathBuf::from("lib/b.c")],
+            name: "coverage.info".to_owned(),
+        }
+        .render(&report);
+
+        assert!(body.starts_with(
+            "src/a.c\nlib/b.c\n<<<<<< network\n# path=coverage.info\nTN:\nSF:lib/b.c\n"
+        ));
+        assert!(body.contains("SF:src/a.c\n"));
+        assert!(body.ends_with("end_of_record\n<<<<<< EOF\n"));
+        assert!(parse_fix("src").is_err());
+    }
+}
@@ -0,0 +1,238 @@
+//! Coveralls job json, which is posted to `https://coveralls.io/api/v1/jobs`.
+//!
+//! Every source file gets the md5 digest of its content and the coverage array with an item
+//! per source line: the hit count, or `null` for the lines which aren't instrumented.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use lcov::Report;
+
+use super::resolve_source;
+use crate::hash::md5::hex_digest;
+use crate::json::Value;
+use crate::summary::by_file;
+
+/// Commit the job is run for.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Git {
+    pub id: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub committer_name: String,
+    pub committer_email: String,
+    pub message: String,
+    pub branch: String,
+    /// Names and urls of the remotes.
+    pub remotes: Vec<(String, String)>,
+}
+
+impl Git {
+    /// Reads the `HEAD` commit of the repository by `git`, `None` if it isn't a repository.
+    pub fn discover(repo: &Path) -> Option<Self> {
+        let git = |args: &[&str]| -> Option<String> {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(repo)
+                .args(args)
+                .output()
+                .ok()?;
+            if output.status.success() {
+                String::from_utf8(output.stdout).ok()
+            } else {
+                None
+            }
+        };
+        let head = git(&["log", "-1", "--format=%H%n%an%n%ae%n%cn%n%ce%n%s"])?;
+        let mut head = head.lines().map(str::to_owned);
+        let mut field = || head.next().unwrap_or_default();
+        let mut remotes: Vec<(String, String)> = git(&["remote", "-v"])
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| line.ends_with("(fetch)"))
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                Some((fields.next()?.to_owned(), fields.next()?.to_owned()))
+            })
+            .collect();
+        remotes.dedup();
+        Some(Git {
+            id: field(),
+            author_name: field(),
+            author_email: field(),
+            committer_name: field(),
+            committer_email: field(),
+            message: field(),
+            branch: git(&["rev-parse", "--abbrev-ref", "HEAD"])
+                .map(|branch| branch.trim().to_owned())
+                .unwrap_or_default(),
+            remotes,
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        Value::object(vec![
+            (
+                "head",
+                Value::object(vec![
+                    ("id", Value::from(self.id.as_str())),
+                    ("author_name", Value::from(self.author_name.as_str())),
+                    ("author_email", Value::from(self.author_email.as_str())),
+                    ("committer_name", Value::from(self.committer_name.as_str())),
+                    (
+                        "committer_email",
+                        Value::from(self.committer_email.as_str()),
+                    ),
+                    ("message", Value::from(self.message.as_str())),
+                ]),
+            ),
+            ("branch", Value::from(self.branch.as_str())),
+            (
+                "remotes",
+                Value::Array(
+                    self.remotes
+                        .iter()
+                        .map(|(name, url)| {
+                            Value::object(vec![
+                                ("name", Value::from(name.as_str())),
+                                ("url", Value::from(url.as_str())),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Coveralls {
+    /// Repository root, names of the source files are relative to it.
+    pub root: PathBuf,
+    pub repo_token: Option<String>,
+    pub service_name: String,
+    pub service_job_id: Option<String>,
+    pub git: Option<Git>,
+}
+
+impl Default for Coveralls {
+    fn default() -> Self {
+        Coveralls {
+            root: PathBuf::from("."),
+            repo_token: None,
+            service_name: "lcov-diff".to_owned(),
+            service_job_id: None,
+            git: None,
+        }
+    }
+}
+
+impl Coveralls {
+    pub fn render(&self, report: &Report) -> Value {
+        let mut source_files = Vec::new();
+        for (path, section) in by_file(report) {
+            let source =
+                resolve_source(&self.root, &path).and_then(|p| fs::read(&p).ok().map(|c| (p, c)));
+            let name = match &source {
+                Some((resolved, _)) => resolved.strip_prefix(&self.root).unwrap_or(resolved),
+                None => path.strip_prefix(&self.root).unwrap_or(&path),
+            };
+
+            // Sources could be missing, then the array ends at the last instrumented line
+            let lines_number = match &source {
+                Some((_, content)) => {
+                    let newlines = content.iter().filter(|b| **b == b'\n').count();
+                    newlines + usize::from(!content.is_empty() && !content.ends_with(b"\n"))
+                }
+                None => 0,
+            };
+            let last = section
+                .lines
+                .keys()
+                .map(|key| key.line as usize)
+                .max()
+                .unwrap_or(0);
+            let mut coverage = vec![Value::Null; lines_number.max(last)];
+            for (key, value) in &section.lines {
+                if let Some(item) = coverage.get_mut((key.line as usize).wrapping_sub(1)) {
+                    *item = Value::from(value.count);
+                }
+            }
+
+            let mut branches: BTreeMap<(u32, u32, u32), u64> = BTreeMap::new();
+            for (key, value) in &section.branches {
+                branches.insert((key.line, key.block, key.branch), value.taken.unwrap_or(0));
+            }
+            let branches = branches
+                .into_iter()
+                .flat_map(|((line, block, branch), hits)| {
+                    vec![
+                        Value::from(line),
+                        Value::from(block),
+                        Value::from(branch),
+                        Value::from(hits),
+                    ]
+                })
+                .collect();
+
+            let mut file = vec![("name", Value::from(name.to_string_lossy().into_owned()))];
+            if let Some((_, content)) = &source {
+                file.push(("source_digest", Value::from(hex_digest(content))));
+            }
+            file.push(("coverage", Value::Array(coverage)));
+            file.push(("branches", Value::Array(branches)));
+            source_files.push(Value::object(file));
+        }
+
+        let mut job = Vec::new();
+        if let Some(token) = &self.repo_token {
+            job.push(("repo_token", Value::from(token.as_str())));
+        }
+        job.push(("service_name", Value::from(self.service_name.as_str())));
+        if let Some(id) = &self.service_job_id {
+            job.push(("service_job_id", Value::from(id.as_str())));
+        }
+        if let Some(git) = &self.git {
+            job.push(("git", git.to_json()));
+        }
+        job.push(("source_files", Value::Array(source_files)));
+        Value::object(job)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Coveralls, Git};
+    use lcov::{Reader, Report};
+    use std::fs;
+
+    #[test]
+    fn render() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir(root.path().join("src")).unwrap();
+        fs::write(root.path().join("src/a.c"), "int a;\nint main() {\n}\n").unwrap();
+        let input = "SF:/build/src/a.c\nBRDA:2,0,0,1\nBRDA:2,0,1,-\nDA:2,3\nend_of_record\n";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let json = Coveralls {
+            root: root.path().to_owned(),
+            repo_token: Some("token".to_owned()),
+            git: Some(Git {
+                id: "abc".to_owned(),
+                branch: "main".to_owned(),
+                ..Git::default()
+            }),
+            ..Coveralls::default()
+        }
+        .render(&report)
+        .to_string();
+
+        assert!(json.starts_with(
+            r#"{"repo_token":"token","service_name":"lcov-diff","git":{"head":{"id":"abc","#
+        ));
+        assert!(json.contains(r#""branch":"main","remotes":[]}"#));
+        assert!(json.ends_with(
+            r#""source_files":[{"name":"src/a.c","source_digest":"3c15a0d11d7bef4b9e717df5b544cb5c","coverage":[null,3,null],"branches":[2,0,0,1,2,0,1,0]}]}"#
+        ));
+    }
+}
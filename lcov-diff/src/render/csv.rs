@@ -0,0 +1,209 @@
+//! Per-file coverage deltas as csv/tsv table for spreadsheets.
+//!
+//! `*_total` columns are the numbers of the new report, `*_delta` ones are the change of the
+//! coverage in percentage points. Files of a single report only have empty cells of the other.
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use crate::summary::{Comparison, Counter, Coverage, FileDelta};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Column {
+    File,
+    LinesTotal,
+    LinesCoveredBefore,
+    LinesCoveredAfter,
+    LinesDelta,
+    BranchesTotal,
+    BranchesCoveredBefore,
+    BranchesCoveredAfter,
+    BranchesDelta,
+    FunctionsTotal,
+    FunctionsCoveredBefore,
+    FunctionsCoveredAfter,
+    FunctionsDelta,
+}
+
+impl Column {
+    pub const VARIANTS: &'static [&'static str] = &[
+        "file",
+        "lines_total",
+        "lines_covered_before",
+        "lines_covered_after",
+        "lines_delta",
+        "branches_total",
+        "branches_covered_before",
+        "branches_covered_after",
+        "branches_delta",
+        "functions_total",
+        "functions_covered_before",
+        "functions_covered_after",
+        "functions_delta",
+    ];
+
+    pub const ALL: &'static [Column] = &[
+        Column::File,
+        Column::LinesTotal,
+        Column::LinesCoveredBefore,
+        Column::LinesCoveredAfter,
+        Column::LinesDelta,
+        Column::BranchesTotal,
+        Column::BranchesCoveredBefore,
+        Column::BranchesCoveredAfter,
+        Column::BranchesDelta,
+        Column::FunctionsTotal,
+        Column::FunctionsCoveredBefore,
+        Column::FunctionsCoveredAfter,
+        Column::FunctionsDelta,
+    ];
+
+    fn name(self) -> &'static str {
+        let idx = Column::ALL.iter().position(|c| *c == self).unwrap_or(0);
+        Column::VARIANTS[idx]
+    }
+
+    fn value(self, file: &FileDelta) -> String {
+        let lines = |c: &Coverage| c.lines;
+        let branches = |c: &Coverage| c.branches;
+        let functions = |c: &Coverage| c.functions;
+        let (counter, stat): (fn(&Coverage) -> Counter, Stat) = match self {
+            Column::File => return file.path.display().to_string(),
+            Column::LinesTotal => (lines, Stat::Total),
+            Column::LinesCoveredBefore => (lines, Stat::Before),
+            Column::LinesCoveredAfter => (lines, Stat::After),
+            Column::LinesDelta => (lines, Stat::Delta),
+            Column::BranchesTotal => (branches, Stat::Total),
+            Column::BranchesCoveredBefore => (branches, Stat::Before),
+            Column::BranchesCoveredAfter => (branches, Stat::After),
+            Column::BranchesDelta => (branches, Stat::Delta),
+            Column::FunctionsTotal => (functions, Stat::Total),
+            Column::FunctionsCoveredBefore => (functions, Stat::Before),
+            Column::FunctionsCoveredAfter => (functions, Stat::After),
+            Column::FunctionsDelta => (functions, Stat::Delta),
+        };
+        let before = file.baseline.as_ref().map(counter);
+        let after = file.current.as_ref().map(counter);
+        let number = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+        match stat {
+            Stat::Total => number(after.map(|c| c.found)),
+            Stat::Before => number(before.map(|c| c.hit)),
+            Stat::After => number(after.map(|c| c.hit)),
+            Stat::Delta => {
+                let percent = |c: Option<Counter>| c.and_then(|c| c.percent());
+                match (percent(before), percent(after)) {
+                    (None, None) => String::new(),
+                    (old, new) => {
+                        format!("{:.2}", new.unwrap_or_default() - old.unwrap_or_default())
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Stat {
+    Total,
+    Before,
+    After,
+    Delta,
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Column::VARIANTS
+            .iter()
+            .position(|name| *name == s)
+            .map(|idx| Column::ALL[idx])
+            .ok_or_else(|| format!("unknown column: {}", s))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    /// `,` for csv, `\t` for tsv.
+    pub delimiter: char,
+    pub columns: Vec<Column>,
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Table {
+            delimiter: ',',
+            columns: Column::ALL.to_vec(),
+        }
+    }
+}
+
+impl Table {
+    pub fn render(&self, comparison: &Comparison) -> String {
+        let mut out = String::new();
+        let names: Vec<&str> = self.columns.iter().map(|c| c.name()).collect();
+        self.row(&mut out, names.iter().map(|name| name.to_string()));
+        for file in &comparison.files {
+            self.row(
+                &mut out,
+                self.columns.iter().map(|column| column.value(file)),
+            );
+        }
+        out
+    }
+
+    fn row<I: Iterator<Item = String>>(&self, out: &mut String, cells: I) {
+        let cells: Vec<String> = cells.map(|cell| self.cell(&cell)).collect();
+        let _ = writeln!(out, "{}", cells.join(&self.delimiter.to_string()));
+    }
+
+    /// Quotes csv cells (RFC 4180), tsv can't quote so the delimiters are replaced by spaces.
+    fn cell(&self, value: &str) -> String {
+        if self.delimiter == '\t' {
+            return value.replace(['\t', '\n', '\r'], " ");
+        }
+        if value.contains([self.delimiter, '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Column, Table};
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn render() {
+        let baseline = "SF:a,b.c\nDA:1,1\nDA:2,1\nend_of_record\n";
+        let current =
+            "SF:a,b.c\nDA:1,1\nDA:2,0\nDA:3,1\nend_of_record\nSF:new.c\nDA:1,1\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&baseline, &current);
+
+        let table = Table {
+            delimiter: ',',
+            columns: "file,lines_total,lines_covered_before,lines_covered_after,lines_delta"
+                .split(',')
+                .map(|c| c.parse().unwrap())
+                .collect(),
+        };
+        assert_eq!(
+            table.render(&comparison),
+            "file,lines_total,lines_covered_before,lines_covered_after,lines_delta\n\
+             \"a,b.c\",3,2,2,-33.33\n\
+             new.c,1,,1,100.00\n"
+        );
+        let tsv = Table {
+            delimiter: '\t',
+            ..Table::default()
+        }
+        .render(&comparison);
+        assert!(tsv.starts_with("file\tlines_total\t"));
+        assert!(tsv.contains("\na,b.c\t3\t2\t2\t-33.33\t0\t0\t0\t\t0\t0\t0\t\n"));
+        assert!("size".parse::<Column>().is_err());
+    }
+}
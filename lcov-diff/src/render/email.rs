@@ -0,0 +1,185 @@
+//! Email report: html part with inline styles only (mail clients strip `<style>` and scripts)
+//! and the plaintext alternative, packed into a MIME message for `sendmail -t`.
+use std::fmt::Write as _;
+
+use super::number::Numbers;
+use super::{escape, patch_summary, percent_with_delta, regressions};
+use crate::summary::Comparison;
+
+const BOUNDARY: &str = "lcov-diff-alternative";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Email {
+    /// Maximum number of the regressed files listed.
+    pub max_files: usize,
+    /// Link to the full report.
+    pub report_url: Option<String>,
+    pub numbers: Numbers,
+}
+
+impl Default for Email {
+    fn default() -> Self {
+        Email {
+            max_files: 10,
+            report_url: None,
+            numbers: Numbers::default(),
+        }
+    }
+}
+
+impl Email {
+    fn totals(&self, comparison: &Comparison) -> Vec<(&'static str, String)> {
+        let (old, new) = (&comparison.baseline, &comparison.current);
+        vec![
+            (
+                "Lines",
+                percent_with_delta(&self.numbers, old.lines, new.lines),
+            ),
+            (
+                "Functions",
+                percent_with_delta(&self.numbers, old.functions, new.functions),
+            ),
+            (
+                "Branches",
+                percent_with_delta(&self.numbers, old.branches, new.branches),
+            ),
+            ("New lines", patch_summary(&self.numbers, comparison)),
+        ]
+    }
+
+    pub fn html(&self, comparison: &Comparison) -> String {
+        let cell = "padding:4px 12px;border-bottom:1px solid #ddd;text-align:left";
+        let mut out = String::new();
+        out.push_str(
+            "<!DOCTYPE html>\n<html><body style=\"font-family:Arial,Helvetica,sans-serif;font-size:14px;color:#222\">\n",
+        );
+        out.push_str("<h2 style=\"font-size:18px\">Coverage report</h2>\n");
+        out.push_str(
+            "<table cellpadding=\"0\" cellspacing=\"0\" style=\"border-collapse:collapse\">\n",
+        );
+        for (name, value) in self.totals(comparison) {
+            let _ = writeln!(
+                out,
+                "<tr><th style=\"{}\">{}</th><td style=\"{}\">{}</td></tr>",
+                cell,
+                name,
+                cell,
+                escape(&value)
+            );
+        }
+        out.push_str("</table>\n");
+
+        let regressions = regressions(comparison, self.max_files);
+        if !regressions.is_empty() {
+            out.push_str("<h3 style=\"font-size:16px\">Worst regressions</h3>\n");
+            out.push_str(
+                "<table cellpadding=\"0\" cellspacing=\"0\" style=\"border-collapse:collapse\">\n",
+            );
+            for file in regressions {
+                let _ = writeln!(
+                    out,
+                    "<tr><td style=\"{}\"><code>{}</code></td><td style=\"{};color:#c00\">{}</td></tr>",
+                    cell,
+                    escape(&file.path.to_string_lossy()),
+                    cell,
+                    self.numbers.delta(file.lines_delta())
+                );
+            }
+            out.push_str("</table>\n");
+        }
+        if let Some(url) = &self.report_url {
+            let _ = writeln!(
+                out,
+                "<p><a href=\"{}\" style=\"color:#0366d6\">Full report</a></p>",
+                escape(url)
+            );
+        }
+        out.push_str("</body></html>\n");
+        out
+    }
+
+    pub fn text(&self, comparison: &Comparison) -> String {
+        let mut out = String::from("Coverage report\n\n");
+        for (name, value) in self.totals(comparison) {
+            let _ = writeln!(out, "{:<10} {}", format!("{}:", name), value);
+        }
+        let regressions = regressions(comparison, self.max_files);
+        if !regressions.is_empty() {
+            out.push_str("\nWorst regressions:\n");
+            for file in regressions {
+                let _ = writeln!(
+                    out,
+                    "  {} {}",
+                    file.path.display(),
+                    self.numbers.delta(file.lines_delta())
+                );
+            }
+        }
+        if let Some(url) = &self.report_url {
+            let _ = writeln!(out, "\nFull report: {}", url);
+        }
+        out
+    }
+
+    /// MIME message with both parts, `headers` go first (`To`, `From`, ...).
+    pub fn mime(&self, comparison: &Comparison, subject: &str, headers: &[(&str, &str)]) -> String {
+        let mut out = String::new();
+        for (name, value) in headers {
+            let _ = write!(out, "{}: {}\r\n", name, value);
+        }
+        let _ = write!(
+            out,
+            "Subject: {}\r\nMIME-Version: 1.0\r\nContent-Type: multipart/alternative; boundary=\"{}\"\r\n\r\n",
+            subject, BOUNDARY
+        );
+        for (content_type, body) in &[
+            ("text/plain", self.text(comparison)),
+            ("text/html", self.html(comparison)),
+        ] {
+            let _ = write!(
+                out,
+                "--{}\r\nContent-Type: {}; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n{}\r\n",
+                BOUNDARY,
+                content_type,
+                body.replace('\n', "\r\n")
+            );
+        }
+        let _ = write!(out, "--{}--\r\n", BOUNDARY);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Email;
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn render() {
+        let baseline = "SF:a<b>.c\nDA:1,1\nDA:2,1\nend_of_record\n";
+        let current = "SF:a<b>.c\nDA:1,1\nDA:2,0\nDA:3,0\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&baseline, &current);
+        let email = Email::default();
+
+        let html = email.html(&comparison);
+        assert!(!html.contains("<style") && !html.contains("<script"));
+        assert!(html.contains("<code>a&lt;b&gt;.c</code></td><td style=\"padding:4px 12px;border-bottom:1px solid #ddd;text-align:left;color:#c00\">-66.67%</td>"));
+        assert_eq!(
+            email.text(&comparison),
+            "Coverage report\n\nLines:     33.33% (-66.67%)\nFunctions: -\nBranches:  -\n\
+             New lines: 0.00% (1 uncovered)\n\nWorst regressions:\n  a<b>.c -66.67%\n"
+        );
+        let mime = email.mime(
+            &comparison,
+            "Nightly coverage",
+            &[("To", "team@example.com")],
+        );
+        assert!(mime.starts_with(
+            "To: team@example.com\r\nSubject: Nightly coverage\r\nMIME-Version: 1.0\r\n"
+        ));
+        assert!(mime.ends_with("--lcov-diff-alternative--\r\n"));
+    }
+}
@@ -0,0 +1,76 @@
+//! GitHub Actions workflow commands, so findings are shown inline on the PR diff.
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::check::Violation;
+use crate::severity::{Finding, Severity};
+
+/// `::error`, `::warning` or `::notice` for every finding by its severity and `::error` for
+/// every threshold violation.
+pub fn render(findings: &[Finding], violations: &[Violation]) -> String {
+    let mut out = String::new();
+    for finding in findings {
+        let command = match finding.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info | Severity::Off => "notice",
+        };
+        let _ = write!(out, "::{} file={}", command, escape_property(&finding.path));
+        if let Some(line) = finding.line {
+            let _ = write!(out, ",line={}", line);
+        }
+        if let Some(end_line) = finding.end_line {
+            let _ = write!(out, ",endLine={}", end_line);
+        }
+        let _ = writeln!(out, "::{}", finding.kind.message());
+    }
+    for violation in violations {
+        let _ = writeln!(out, "::error::{}", escape_data(&violation.to_string()));
+    }
+    out
+}
+
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(path: &Path) -> String {
+    escape_data(&path.to_string_lossy())
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::check::Violation;
+    use crate::severity::{findings, Severities, Severity};
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn annotations() {
+        let baseline = "SF:gone.c\nDA:1,1\nend_of_record\n";
+        let current = "SF:src/a,b.c\nDA:1,1\nDA:2,0\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&baseline, &current);
+        let severities = Severities {
+            dropped_file: Severity::Info,
+            ..Severities::default()
+        };
+        let findings = findings(&baseline, &current, &comparison, &severities);
+        let violations = [Violation::LinesBelow {
+            coverage: 50.0,
+            min: 80.0,
+        }];
+        assert_eq!(
+            render(&findings, &violations),
+            "::notice file=gone.c::File is missing in the new report\n\
+             ::warning file=src/a%2Cb.c,line=2::Line not covered by tests\n\
+             ::error::Line coverage 50.00%25 is below the threshold 80.00%25\n"
+        );
+    }
+}
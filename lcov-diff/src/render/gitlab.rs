@@ -0,0 +1,40 @@
+//! GitLab CI integration.
+//!
+//! The coverage of the new report goes to the `coverage_report` Cobertura artifact, which is
+//! used to annotate MR diffs. The summary line is printed to the job log to be picked up by
+//! the `coverage` regex of the job, e.g. `/^Coverage: \d+\.\d+%/`.
+use super::number::Numbers;
+use crate::summary::Comparison;
+
+/// `Coverage: 81.25%` line with the line coverage of the new report, the number format should
+/// have the `.` separator the regex expects.
+pub fn summary_line(comparison: &Comparison, numbers: &Numbers) -> String {
+    format!(
+        "Coverage: {}",
+        numbers.percent(comparison.current.lines.percent().unwrap_or_default())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::summary_line;
+    use crate::render::number::Numbers;
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn summary() {
+        let current = "SF:a.c\nDA:1,1\nDA:2,1\nDA:3,0\nend_of_record\n";
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&Report::new(), &current);
+        assert_eq!(
+            summary_line(&comparison, &Numbers::default()),
+            "Coverage: 66.67%"
+        );
+        let numbers = Numbers {
+            precision: 0,
+            ..Numbers::default()
+        };
+        assert_eq!(summary_line(&comparison, &numbers), "Coverage: 67%");
+    }
+}
@@ -0,0 +1,441 @@
+//! Side-by-side html view of the baseline and the new coverage.
+//!
+//! Every source file gets its own page where the baseline version of the file is on the left
+//! and the new one is on the right. Lines of both versions are aligned, so the lines exercised
+//! only by the new run are easy to spot.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use lcov::Report;
+
+use super::number::Numbers;
+use super::{escape, line_hits, relative_path, resolve_source};
+use crate::permalink::Permalink;
+use crate::rollup::Directory;
+use crate::summary::{Comparison, Counter, Coverage};
+use crate::textdiff::{self, Op};
+
+const STYLE: &str = "\
+body { font-family: sans-serif; }
+table { border-collapse: collapse; width: 100%; }
+td { padding: 0 4px; vertical-align: top; }
+td.num, td.cnt { text-align: right; color: #666; white-space: nowrap; }
+td.src { font-family: monospace; white-space: pre; }
+td.split { border-left: 2px solid #999; }
+.hit { background: #dfd; }
+.miss { background: #fdd; }
+.gained { background: #9f9; }
+";
+
+/// Html renderer showing baseline coverage on the left and the new one on the right.
+#[derive(Debug, Clone)]
+pub struct SideBySide<'a> {
+    baseline: &'a Report,
+    current: &'a Report,
+    baseline_root: PathBuf,
+    current_root: PathBuf,
+    permalink: Option<Permalink>,
+    directory_depth: Option<usize>,
+    numbers: Numbers,
+}
+
+impl<'a> SideBySide<'a> {
+    /// Source files are searched inside of the current directory by default.
+    pub fn new(baseline: &'a Report, current: &'a Report) -> Self {
+        SideBySide {
+            baseline,
+            current,
+            baseline_root: PathBuf::from("."),
+            current_root: PathBuf::from("."),
+            permalink: None,
+            directory_depth: None,
+            numbers: Numbers::default(),
+        }
+    }
+
+    /// Directory with the sources the baseline report was collected for.
+    pub fn baseline_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.baseline_root = root.into();
+        self
+    }
+
+    /// Directory with the sources the new report was collected for.
+    pub fn current_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.current_root = root.into();
+        self
+    }
+
+    /// Links line numbers of the new sources to the source hosting.
+    pub fn permalink(mut self, permalink: Permalink) -> Self {
+        self.permalink = Some(permalink);
+        self
+    }
+
+    /// Folds the directories deeper than `depth` levels below the common root into their
+    /// ancestor in the directory table of the index, all levels are shown by default.
+    pub fn directory_depth(mut self, depth: usize) -> Self {
+        self.directory_depth = Some(depth);
+        self
+    }
+
+    /// Number format of the directory table.
+    pub fn numbers(mut self, numbers: Numbers) -> Self {
+        self.numbers = numbers;
+        self
+    }
+
+    /// Writes `index.html` and a page per source file into `out_dir`.
+    pub fn write<P: AsRef<Path>>(&self, out_dir: P) -> io::Result<()> {
+        let out_dir = out_dir.as_ref();
+        fs::create_dir_all(out_dir)?;
+        let baseline = line_hits(self.baseline);
+        let current = line_hits(self.current);
+        let files: BTreeSet<&PathBuf> = baseline.keys().chain(current.keys()).collect();
+        let empty = BTreeMap::new();
+
+        let mut index = String::new();
+        page_header(&mut index, "Coverage diff");
+        let comparison = Comparison::new(self.baseline, self.current);
+        directory_table(
+            &mut index,
+            &Directory::tree(&comparison, self.directory_depth),
+            &self.numbers,
+        );
+        index.push_str("<table>\n<tr><th>File</th><th>Baseline</th><th>New</th></tr>\n");
+        for (idx, path) in files.into_iter().enumerate() {
+            let old = baseline.get(path).unwrap_or(&empty);
+            let new = current.get(path).unwrap_or(&empty);
+            let page = format!("file{}.html", idx);
+            fs::write(out_dir.join(&page), self.render_file(path, old, new))?;
+            let _ = writeln!(
+                index,
+                "<tr><td><a href=\"{}\">{}</a></td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+                page,
+                escape(&path.to_string_lossy()),
+                ratio(old),
+                ratio(new),
+            );
+        }
+        index.push_str("</table>\n</body>\n</html>\n");
+        fs::write(out_dir.join("index.html"), index)
+    }
+
+    fn render_file(
+        &self,
+        path: &Path,
+        old: &BTreeMap<u32, u64>,
+        new: &BTreeMap<u32, u64>,
+    ) -> String {
+        let repo_path = relative_path(&self.current_root, path);
+        let old_src = read_source(&self.baseline_root, path, old);
+        let new_src = read_source(&self.current_root, path, new);
+        let old_lines: Vec<&str> = old_src.lines().collect();
+        let new_lines: Vec<&str> = new_src.lines().collect();
+
+        let mut page = String::new();
+        page_header(&mut page, &path.to_string_lossy());
+        page.push_str("<p><a href=\"index.html\">index</a></p>\n<table>\n");
+        for op in textdiff::diff(&old_lines, &new_lines) {
+            let (left, right) = match op {
+                Op::Equal { old, new } => (Some(old), Some(new)),
+                Op::Delete { old } => (Some(old), None),
+                Op::Insert { new } => (None, Some(new)),
+            };
+            let old_count = left.and_then(|idx| old.get(&(idx as u32 + 1)));
+            let new_count = right.and_then(|idx| new.get(&(idx as u32 + 1)));
+            let gained =
+                matches!((old_count, new_count), (None | Some(0), Some(count)) if *count > 0);
+
+            page.push_str("<tr>");
+            let link = right
+                .and_then(|idx| Some((idx, self.permalink.as_ref()?)))
+                .map(|(idx, permalink)| permalink.link(&repo_path, idx as u32 + 1));
+
+            cells(&mut page, left, &old_lines, old_count, "", false, None);
+            cells(
+                &mut page, right, &new_lines, new_count, " split", gained, link,
+            );
+            page.push_str("</tr>\n");
+        }
+        page.push_str("</table>\n</body>\n</html>\n");
+        page
+    }
+}
+
+/// Page of the comparison: the totals, every file whose coverage changed and the uncovered
+/// new lines.
+pub fn summary_page(comparison: &Comparison, numbers: &Numbers) -> String {
+    let percent = |counter: Counter| {
+        counter
+            .percent()
+            .map_or_else(|| "-".to_owned(), |p| numbers.percent(p))
+    };
+    let mut page = String::new();
+    page_header(&mut page, "Coverage diff");
+    page.push_str("<table>\n<tr><th></th><th>Baseline</th><th>New</th><th>Change</th></tr>\n");
+    let (old, new) = (&comparison.baseline, &comparison.current);
+    for (name, old, new) in [
+        ("Lines", old.lines, new.lines),
+        ("Functions", old.functions, new.functions),
+        ("Branches", old.branches, new.branches),
+    ] {
+        let delta = match (old.percent(), new.percent()) {
+            (Some(old), Some(new)) => numbers.delta(new - old),
+            _ => "-".to_owned(),
+        };
+        let _ = writeln!(
+            page,
+            "<tr><th>{}</th><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+            name,
+            percent(old),
+            percent(new),
+            delta
+        );
+    }
+    page.push_str("</table>\n");
+    directory_table(&mut page, &Directory::tree(comparison, None), numbers);
+
+    let changed: Vec<_> = comparison
+        .files
+        .iter()
+        .filter(|file| file.lines_delta() != 0.0)
+        .collect();
+    if !changed.is_empty() {
+        page.push_str("<h2>Changed files</h2>\n<table>\n<tr><th>File</th><th>Baseline</th><th>New</th><th>Change</th></tr>\n");
+        for file in changed {
+            let lines = |coverage: Option<Coverage>| {
+                coverage.map_or_else(|| "-".to_owned(), |c| percent(c.lines))
+            };
+            let class = if file.lines_delta() < 0.0 {
+                "miss"
+            } else {
+                "hit"
+            };
+            let _ = writeln!(
+                page,
+                "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num {}\">{}</td></tr>",
+                escape(&file.path.to_string_lossy()),
+                lines(file.baseline),
+                lines(file.current),
+                class,
+                numbers.delta(file.lines_delta())
+            );
+        }
+        page.push_str("</table>\n");
+    }
+    if !comparison.uncovered_new_lines.is_empty() {
+        page.push_str("<h2>Uncovered new lines</h2>\n<ul>\n");
+        for (path, line) in &comparison.uncovered_new_lines {
+            let _ = writeln!(
+                page,
+                "<li><code>{}:{}</code></li>",
+                escape(&path.to_string_lossy()),
+                line
+            );
+        }
+        page.push_str("</ul>\n");
+    }
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+/// Reads the source file, when it's not available the blank lines are used instead.
+fn read_source(root: &Path, path: &Path, lines: &BTreeMap<u32, u64>) -> String {
+    resolve_source(root, path)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .unwrap_or_else(|| {
+            let last = lines.keys().next_back().copied().unwrap_or(0);
+            "\n".repeat(last as usize)
+        })
+}
+
+fn cells(
+    page: &mut String,
+    idx: Option<usize>,
+    lines: &[&str],
+    count: Option<&u64>,
+    split: &str,
+    gained: bool,
+    link: Option<String>,
+) {
+    let idx = match idx {
+        Some(idx) => idx,
+        None => {
+            let _ = write!(page, "<td class=\"num{0}\"></td><td></td><td></td>", split);
+            return;
+        }
+    };
+    let class = match count {
+        Some(_) if gained => "gained",
+        Some(0) => "miss",
+        Some(_) => "hit",
+        None => "",
+    };
+    let number = match link {
+        Some(link) => format!("<a href=\"{}\">{}</a>", escape(&link), idx + 1),
+        None => (idx + 1).to_string(),
+    };
+    let _ = write!(
+        page,
+        "<td class=\"num{}\">{}</td><td class=\"cnt {}\">{}</td><td class=\"src {}\">{}</td>",
+        split,
+        number,
+        class,
+        count.map(u64::to_string).unwrap_or_default(),
+        class,
+        escape(lines[idx]),
+    );
+}
+
+/// Collapsible table of the line coverage by directory, the subdirectories are indented
+/// below their parent. Nothing is written when all files are in the same directory.
+fn directory_table(page: &mut String, tree: &Directory, numbers: &Numbers) {
+    if tree.children.is_empty() {
+        return;
+    }
+    let percent = |counter: Counter| {
+        counter
+            .percent()
+            .map_or_else(|| "-".to_owned(), |p| numbers.percent(p))
+    };
+    page.push_str("<details open>\n<summary>Directories</summary>\n<table>\n<tr><th>Directory</th><th>Files</th><th>Baseline</th><th>New</th><th>Change</th></tr>\n");
+    for (level, dir) in tree.walk() {
+        let name = match (level, dir.path.file_name()) {
+            _ if dir.path.as_os_str().is_empty() => ".".to_owned(),
+            (0, _) | (_, None) => dir.path.to_string_lossy().into_owned(),
+            (_, Some(name)) => name.to_string_lossy().into_owned(),
+        };
+        let (old, new) = (dir.baseline.lines, dir.current.lines);
+        let (delta, class) = match (old.percent(), new.percent()) {
+            (Some(old), Some(new)) if new < old => (numbers.delta(new - old), " miss"),
+            (Some(old), Some(new)) if new > old => (numbers.delta(new - old), " hit"),
+            (Some(old), Some(new)) => (numbers.delta(new - old), ""),
+            _ => ("-".to_owned(), ""),
+        };
+        let _ = writeln!(
+            page,
+            "<tr><td style=\"padding-left: {}em\">{}/</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num{}\">{}</td></tr>",
+            level * 2,
+            escape(&name),
+            dir.files,
+            percent(old),
+            percent(new),
+            class,
+            delta
+        );
+    }
+    page.push_str("</table>\n</details>\n");
+}
+
+fn page_header(page: &mut String, title: &str) {
+    let _ = write!(
+        page,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n<h1>{0}</h1>\n",
+        escape(title),
+        STYLE,
+    );
+}
+
+fn ratio(lines: &BTreeMap<u32, u64>) -> String {
+    let hit = lines.values().filter(|&&count| count > 0).count();
+    format!("{}/{}", hit, lines.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{summary_page, SideBySide};
+    use crate::permalink::Permalink;
+    use crate::render::number::Numbers;
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+    use std::fs;
+
+    #[test]
+    fn side_by_side() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old_root, new_root, out) = (
+            dir.path().join("old"),
+            dir.path().join("new"),
+            dir.path().join("web"),
+        );
+        fs::create_dir_all(&old_root).unwrap();
+        fs::create_dir_all(&new_root).unwrap();
+        fs::write(old_root.join("a.c"), "int a;\nint b;\n").unwrap();
+        fs::write(new_root.join("a.c"), "int a;\nint x;\nint b;\n").unwrap();
+
+        let baseline = "SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\n";
+        let current = "SF:a.c\nDA:1,1\nDA:2,1\nDA:3,4\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+
+        SideBySide::new(&baseline, &current)
+            .baseline_root(&old_root)
+            .current_root(&new_root)
+            .permalink(Permalink::new(
+                "https://example.com/{commit}/{path}#L{line}",
+                "c0ffee",
+            ))
+            .write(&out)
+            .unwrap();
+
+        assert!(fs::read_to_string(out.join("index.html"))
+            .unwrap()
+            .contains("1/2"));
+        let page = fs::read_to_string(out.join("file0.html")).unwrap();
+        assert!(page.contains("<td class=\"src gained\">int b;</td>"));
+        assert!(page.contains("<td class=\"src gained\">int x;</td>"));
+        assert!(page.contains("<a href=\"https://example.com/c0ffee/a.c#L2\">2</a>"));
+    }
+
+    #[test]
+    fn summary() {
+        let baseline = "SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\nSF:b.c\nDA:1,1\nend_of_record\n";
+        let current =
+            "SF:a.c\nDA:1,1\nDA:2,1\nDA:3,0\nend_of_record\nSF:b.c\nDA:1,1\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let page = summary_page(&Comparison::new(&baseline, &current), &Numbers::default());
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<tr><th>Lines</th><td class=\"num\">66.67%</td><td class=\"num\">75.00%</td><td class=\"num\">+8.33%</td></tr>"));
+        assert!(page.contains("<tr><td>a.c</td>"));
+        assert!(!page.contains("<tr><td>b.c</td>"));
+        assert!(page.contains("<li><code>a.c:3</code></li>"));
+        assert!(!page.contains("<summary>Directories</summary>"));
+    }
+
+    #[test]
+    fn directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let baseline = "\
+SF:src/a/x.c\nDA:1,1\nDA:2,0\nend_of_record
+SF:src/a/deep/y.c\nDA:1,1\nend_of_record
+SF:src/b/z.c\nDA:1,1\nend_of_record
+";
+        let current = "\
+SF:src/a/x.c\nDA:1,1\nDA:2,1\nend_of_record
+SF:src/a/deep/y.c\nDA:1,1\nend_of_record
+SF:src/b/z.c\nDA:1,0\nend_of_record
+";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+
+        let page = summary_page(&Comparison::new(&baseline, &current), &Numbers::default());
+        assert!(page.contains("<details open>\n<summary>Directories</summary>"));
+        assert!(page.contains("<tr><td style=\"padding-left: 0em\">src/</td><td class=\"num\">3</td><td class=\"num\">75.00%</td><td class=\"num\">75.00%</td><td class=\"num\">+0.00%</td></tr>"));
+        assert!(page.contains("<tr><td style=\"padding-left: 2em\">a/</td><td class=\"num\">2</td><td class=\"num\">66.67%</td><td class=\"num\">100.00%</td><td class=\"num hit\">+33.33%</td></tr>"));
+        assert!(page.contains("<td style=\"padding-left: 4em\">deep/</td>"));
+        assert!(page.contains("<td class=\"num miss\">-100.00%</td>"));
+
+        SideBySide::new(&baseline, &current)
+            .directory_depth(1)
+            .write(dir.path())
+            .unwrap();
+        let index = fs::read_to_string(dir.path().join("index.html")).unwrap();
+        assert!(index.contains("<td style=\"padding-left: 2em\">a/</td>"));
+        assert!(index.contains("<td style=\"padding-left: 2em\">b/</td>"));
+        assert!(!index.contains(">deep/</td>"));
+    }
+}
@@ -0,0 +1,146 @@
+//! JUnit xml, for the CI dashboards which can only show test results.
+//!
+//! Every configured threshold and every file with changed coverage is a test case,
+//! the regressed files and the violated thresholds are failed ones.
+use std::fmt::Write as _;
+
+use super::escape;
+use crate::check::{Thresholds, Violation};
+use crate::summary::Comparison;
+
+struct Case {
+    classname: &'static str,
+    name: String,
+    failure: Option<String>,
+}
+
+pub fn render(comparison: &Comparison, thresholds: &Thresholds) -> String {
+    let violations = thresholds.check(comparison);
+    let mut suites: Vec<(&str, Vec<Case>)> = Vec::new();
+
+    let mut cases = Vec::new();
+    if let Some(min) = thresholds.min_lines {
+        cases.push(Case {
+            classname: "lcov-diff.thresholds",
+            name: format!("line coverage is at least {:.2}%", min),
+            failure: violations
+                .iter()
+                .find(|v| matches!(v, Violation::LinesBelow { .. }))
+                .map(ToString::to_string),
+        });
+    }
+    if let Some(max) = thresholds.max_drop {
+        cases.push(Case {
+            classname: "lcov-diff.thresholds",
+            name: format!("line coverage dropped at most {:.2}%", max),
+            failure: violations
+                .iter()
+                .find(|v| matches!(v, Violation::LinesDropped { .. }))
+                .map(ToString::to_string),
+        });
+    }
+    suites.push(("coverage thresholds", cases));
+
+    let cases = comparison
+        .changed_files()
+        .into_iter()
+        .map(|file| {
+            let percent = |coverage: Option<crate::summary::Coverage>| {
+                coverage.and_then(|c| c.lines.percent()).unwrap_or_default()
+            };
+            Case {
+                classname: "lcov-diff.files",
+                name: file.path.to_string_lossy().into_owned(),
+                failure: if file.lines_delta() < 0.0 {
+                    Some(format!(
+                        "Line coverage dropped from {:.2}% to {:.2}%",
+                        percent(file.baseline),
+                        percent(file.current)
+                    ))
+                } else {
+                    None
+                },
+            }
+        })
+        .collect();
+    suites.push(("file coverage", cases));
+
+    let count = |cases: &[Case]| {
+        (
+            cases.len(),
+            cases.iter().filter(|case| case.failure.is_some()).count(),
+        )
+    };
+    let (tests, failures) = suites.iter().fold((0, 0), |(tests, failures), (_, cases)| {
+        let (t, f) = count(cases);
+        (tests + t, failures + f)
+    });
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<testsuites name=\"lcov-diff\" tests=\"{}\" failures=\"{}\">",
+        tests, failures
+    );
+    for (name, cases) in &suites {
+        let (tests, failures) = count(cases);
+        let _ = writeln!(
+            out,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">",
+            name, tests, failures
+        );
+        for case in cases {
+            let _ = write!(
+                out,
+                "    <testcase classname=\"{}\" name=\"{}\"",
+                case.classname,
+                escape(&case.name)
+            );
+            match &case.failure {
+                Some(message) => {
+                    let _ = writeln!(
+                        out,
+                        ">\n      <failure message=\"{}\"/>\n    </testcase>",
+                        escape(message)
+                    );
+                }
+                None => out.push_str("/>\n"),
+            }
+        }
+        out.push_str("  </testsuite>\n");
+    }
+    out.push_str("</testsuites>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::check::Thresholds;
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn test_cases() {
+        let baseline = "SF:a.c\nDA:1,1\nDA:2,1\nend_of_record\nSF:b.c\nDA:1,0\nend_of_record\n";
+        let current = "SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\nSF:b.c\nDA:1,1\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let thresholds = Thresholds {
+            min_lines: Some(50.0),
+            max_drop: None,
+        };
+        let xml = render(&Comparison::new(&baseline, &current), &thresholds);
+
+        assert!(xml.contains("<testsuites name=\"lcov-diff\" tests=\"3\" failures=\"1\">"));
+        assert!(xml.contains(
+            "<testcase classname=\"lcov-diff.thresholds\" name=\"line coverage is at least 50.00%\"/>"
+        ));
+        assert!(xml.contains(
+            "<testcase classname=\"lcov-diff.files\" name=\"a.c\">\n      \
+             <failure message=\"Line coverage dropped from 100.00% to 50.00%\"/>"
+        ));
+        assert!(xml.contains("<testcase classname=\"lcov-diff.files\" name=\"b.c\"/>"));
+    }
+}
@@ -0,0 +1,533 @@
+//! Compact markdown summary sized to fit into a GitHub/GitLab comment.
+//!
+//! The rows of the tables and the lists stop at `max_bytes` with the count of the ones left out,
+//! since GitHub rejects comments over 65536 characters.
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use super::number::Numbers;
+use crate::functions::{changed_functions, FunctionDelta};
+use crate::permalink::Permalink;
+use crate::rollup::Directory;
+use crate::snippet::{self, Sources};
+use crate::summary::{Comparison, Counter, FileDelta, Rank};
+use crate::weights::Score;
+
+#[derive(Debug, Clone)]
+pub struct Markdown {
+    /// Maximum number of rows in the per-file table.
+    pub max_files: usize,
+    /// Maximum number of uncovered new lines listed.
+    pub max_lines: usize,
+    /// Maximum length of the summary in bytes.
+    pub max_bytes: usize,
+    /// Files whose line coverage changed less than this (in percentage points) are skipped.
+    pub threshold: f64,
+    pub permalink: Option<Permalink>,
+    /// Depth of the directory table below the common root, `None` hides the table.
+    pub directory_depth: Option<usize>,
+    /// Length of the lists of the largest losses and gains, `None` hides the lists.
+    pub top: Option<usize>,
+    /// Measure the largest losses and gains are ranked by.
+    pub rank: Rank,
+    /// Weighted line coverage of the baseline and the new report, `None` hides the row.
+    pub weighted: Option<(Score, Score)>,
+    /// Source root and the context lines of the snippets of the uncovered new lines, `None`
+    /// hides the snippets.
+    pub snippets: Option<(PathBuf, usize)>,
+    pub numbers: Numbers,
+}
+
+impl Default for Markdown {
+    fn default() -> Self {
+        Markdown {
+            max_files: 10,
+            max_lines: 20,
+            max_bytes: 60_000,
+            threshold: 0.0,
+            permalink: None,
+            directory_depth: None,
+            top: None,
+            rank: Rank::Percent,
+            weighted: None,
+            snippets: None,
+            numbers: Numbers::default(),
+        }
+    }
+}
+
+impl Markdown {
+    pub fn render(&self, comparison: &Comparison) -> String {
+        let mut out = String::new();
+        out.push_str("### Coverage diff\n\n");
+        out.push_str("| | Baseline | New | Delta |\n|---|---:|---:|---:|\n");
+        for (name, old, new) in &[
+            ("Lines", comparison.baseline.lines, comparison.current.lines),
+            (
+                "Functions",
+                comparison.baseline.functions,
+                comparison.current.functions,
+            ),
+            (
+                "Branches",
+                comparison.baseline.branches,
+                comparison.current.branches,
+            ),
+        ] {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} |",
+                name,
+                counter(&self.numbers, old),
+                counter(&self.numbers, new),
+                delta(&self.numbers, old.percent(), new.percent()),
+            );
+        }
+        if let Some((old, new)) = self.weighted {
+            let percent = |score: Score| {
+                score
+                    .percent()
+                    .map_or_else(|| "-".to_owned(), |percent| self.numbers.percent(percent))
+            };
+            let _ = writeln!(
+                out,
+                "| Weighted lines | {} | {} | {} |",
+                percent(old),
+                percent(new),
+                delta(&self.numbers, old.percent(), new.percent()),
+            );
+        }
+
+        if let Some(depth) = self.directory_depth {
+            let tree = Directory::tree(comparison, Some(depth));
+            let changed: Vec<_> = tree
+                .walk()
+                .into_iter()
+                .filter(|(_, dir)| dir.baseline != dir.current)
+                .collect();
+            if !changed.is_empty() {
+                let _ = write!(
+                    out,
+                    "\n<details><summary>Directories with changed coverage ({})</summary>\n\n",
+                    changed.len()
+                );
+                out.push_str("| Directory | Baseline | New | Delta |\n|---|---:|---:|---:|\n");
+                let mut shown = 0;
+                for (level, dir) in changed.iter().take(self.max_files) {
+                    let name = match (level, dir.path.file_name()) {
+                        _ if dir.path.as_os_str().is_empty() => ".".to_owned(),
+                        (0, _) | (_, None) => dir.path.display().to_string(),
+                        (_, Some(name)) => name.to_string_lossy().into_owned(),
+                    };
+                    let (old, new) = (dir.baseline.lines, dir.current.lines);
+                    let row = format!(
+                        "| {}{} | {} | {} | {} |\n",
+                        "&nbsp;&nbsp;".repeat(*level),
+                        cell(&format!("{}/", name)),
+                        counter(&self.numbers, &old),
+                        counter(&self.numbers, &new),
+                        delta(&self.numbers, old.percent(), new.percent()),
+                    );
+                    if !self.fits(&out, &row) {
+                        break;
+                    }
+                    out.push_str(&row);
+                    shown += 1;
+                }
+                if changed.len() > shown {
+                    let _ = writeln!(out, "\n_and {} more directories_", changed.len() - shown);
+                }
+                out.push_str("\n</details>\n");
+            }
+        }
+
+        if let Some(n) = self.top {
+            let top = comparison.top_changes(n, self.rank);
+            for (title, files) in &[
+                ("Largest losses", &top.regressions),
+                ("Largest gains", &top.gains),
+            ] {
+                if files.is_empty() {
+                    continue;
+                }
+                let _ = writeln!(out, "\n**{}**\n", title);
+                let mut shown = 0;
+                for file in files.iter() {
+                    let item = format!(
+                        "- {} {}\n",
+                        code(&file.path.to_string_lossy()),
+                        change(&self.numbers, file)
+                    );
+                    if !self.fits(&out, &item) {
+                        break;
+                    }
+                    out.push_str(&item);
+                    shown += 1;
+                }
+                if files.len() > shown {
+                    let _ = writeln!(out, "\n_and {} more files_", files.len() - shown);
+                }
+            }
+        }
+
+        let files: Vec<_> = comparison
+            .changed_files()
+            .into_iter()
+            .filter(|file| file.lines_delta().abs() >= self.threshold)
+            .collect();
+        if !files.is_empty() {
+            let _ = write!(
+                out,
+                "\n<details><summary>Files with changed coverage ({})</summary>\n\n",
+                files.len()
+            );
+            out.push_str("| File | Baseline | New | Delta |\n|---|---:|---:|---:|\n");
+            let mut shown = 0;
+            for file in files.iter().take(self.max_files) {
+                let old = file.baseline.map(|c| c.lines);
+                let new = file.current.map(|c| c.lines);
+                let row = format!(
+                    "| {} | {} | {} | {} |\n",
+                    cell(&file.path.to_string_lossy()),
+                    old.as_ref()
+                        .map_or_else(|| "-".to_owned(), |c| counter(&self.numbers, c)),
+                    new.as_ref()
+                        .map_or_else(|| "-".to_owned(), |c| counter(&self.numbers, c)),
+                    delta(
+                        &self.numbers,
+                        old.and_then(|c| c.percent()),
+                        new.and_then(|c| c.percent())
+                    ),
+                );
+                if !self.fits(&out, &row) {
+                    break;
+                }
+                out.push_str(&row);
+                shown += 1;
+            }
+            if files.len() > shown {
+                let _ = writeln!(out, "\n_and {} more files_", files.len() - shown);
+            }
+            out.push_str("\n</details>\n");
+        }
+
+        let uncovered = comparison.uncovered_new_lines.len();
+        if uncovered > 0 {
+            let _ = write!(
+                out,
+                "\n<details><summary>Uncovered new lines ({})</summary>\n\n",
+                uncovered
+            );
+            let mut sources = self
+                .snippets
+                .as_ref()
+                .map(|(root, context)| Sources::new(root, *context));
+            let mut left = self.max_lines;
+            let mut listed = 0;
+            for (path, lines) in comparison.uncovered_new_lines_by_file() {
+                if left == 0 {
+                    break;
+                }
+                let shown: Vec<String> = lines
+                    .iter()
+                    .take(left)
+                    .map(|&line| match &self.permalink {
+                        Some(permalink) => format!("[{}]({})", line, permalink.link(path, line)),
+                        None => line.to_string(),
+                    })
+                    .collect();
+                let mut item = format!(
+                    "- {}: {}\n",
+                    code(&path.to_string_lossy()),
+                    shown.join(", ")
+                );
+                if let Some(sources) = &mut sources {
+                    for (start, end) in snippet::ranges(&lines[..shown.len()]) {
+                        if let Some(snippet) = sources.snippet(path, start, end) {
+                            // Indented to stay within the list item
+                            item.push_str("\n  ```\n");
+                            for line in snippet.numbered((start, end)).lines() {
+                                let _ = writeln!(item, "  {}", line);
+                            }
+                            item.push_str("  ```\n");
+                        }
+                    }
+                }
+                if !self.fits(&out, &item) {
+                    break;
+                }
+                out.push_str(&item);
+                left -= shown.len();
+                listed += shown.len();
+            }
+            if uncovered > listed {
+                let _ = writeln!(out, "\n_and {} more lines_", uncovered - listed);
+            }
+            out.push_str("\n</details>\n");
+        }
+        out
+    }
+
+    /// Whether the row keeps the summary within `max_bytes`, with room left for the headers,
+    /// the footers and the closing tags of the sections.
+    fn fits(&self, out: &str, row: &str) -> bool {
+        out.len() + row.len() + RESERVED <= self.max_bytes
+    }
+}
+
+/// Bytes of the summary kept for what's written after the rows of a section.
+const RESERVED: usize = 1024;
+
+/// Inline code span of the text, fenced by more backticks than the text has in a row.
+fn code(text: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat(longest + 1);
+    // The spaces keep a backtick of the text from joining the fence
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{} {} {}", fence, text, fence)
+    } else {
+        format!("{}{}{}", fence, text, fence)
+    }
+}
+
+/// Code span of the text for a table cell, the `|` escaped so it doesn't split the cell.
+fn cell(text: &str) -> String {
+    code(text).replace('|', "\\|")
+}
+
+/// Table of the line coverage by group (crate, owner), the `(name, comparison)` pairs, `title`
+/// is the header of the name column.
+pub fn groups_table(title: &str, groups: &[(String, Comparison)], numbers: &Numbers) -> String {
+    let mut out = format!(
+        "\n| {} | Baseline | New | Delta | New lines |\n|---|---:|---:|---:|---:|\n",
+        title
+    );
+    for (name, comparison) in groups {
+        let (old, new) = (comparison.baseline.lines, comparison.current.lines);
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            cell(name),
+            counter(numbers, &old),
+            counter(numbers, &new),
+            delta(numbers, old.percent(), new.percent()),
+            super::patch_summary(numbers, comparison),
+        );
+    }
+    out
+}
+
+/// Change of the file in both measures, `+1.50% (+3 lines)`.
+fn change(numbers: &Numbers, file: &FileDelta) -> String {
+    format!(
+        "{} ({:+} lines)",
+        numbers.delta(file.lines_delta()),
+        file.hit_delta()
+    )
+}
+
+/// Details table of the functions whose line coverage changed, worst first, at most `max_rows`.
+pub fn functions_table(deltas: &[FunctionDelta], max_rows: usize, numbers: &Numbers) -> String {
+    let changed = changed_functions(deltas);
+    if changed.is_empty() {
+        return String::new();
+    }
+    let mut out = format!(
+        "\n<details><summary>Functions with changed coverage ({})</summary>\n\n",
+        changed.len()
+    );
+    out.push_str("| Function | File | Baseline | New | Delta |\n|---|---|---:|---:|---:|\n");
+    for function in changed.iter().take(max_rows) {
+        let (old, new) = (function.baseline, function.current);
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            cell(&function.name),
+            cell(&function.path.to_string_lossy()),
+            old.as_ref()
+                .map_or_else(|| "-".to_owned(), |c| counter(numbers, c)),
+            new.as_ref()
+                .map_or_else(|| "-".to_owned(), |c| counter(numbers, c)),
+            delta(
+                numbers,
+                old.and_then(|c| c.percent()),
+                new.and_then(|c| c.percent())
+            ),
+        );
+    }
+    if changed.len() > max_rows {
+        let _ = writeln!(out, "\n_and {} more functions_", changed.len() - max_rows);
+    }
+    out.push_str("\n</details>\n");
+    out
+}
+
+fn counter(numbers: &Numbers, counter: &Counter) -> String {
+    match counter.percent() {
+        Some(percent) => format!(
+            "{} ({}/{})",
+            numbers.percent(percent),
+            counter.hit,
+            counter.found
+        ),
+        None => "-".to_owned(),
+    }
+}
+
+fn delta(numbers: &Numbers, old: Option<f64>, new: Option<f64>) -> String {
+    match (old, new) {
+        (None, None) => "-".to_owned(),
+        (old, new) => numbers.delta(new.unwrap_or_default() - old.unwrap_or_default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{functions_table, groups_table, Markdown};
+    use crate::functions::function_deltas;
+    use crate::render::number::Numbers;
+    use crate::summary::{Comparison, Rank};
+    use crate::weights::Score;
+    use lcov::{Reader, Report};
+
+    fn report(input: &str) -> Report {
+        Report::from_reader(Reader::new(input.as_bytes())).unwrap()
+    }
+
+    /// Comparison of a file which covers one more line and adds two uncovered ones.
+    fn comparison() -> Comparison {
+        let baseline = report("SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\n");
+        let current = report("SF:a.c\nDA:1,1\nDA:2,1\nDA:3,0\nDA:4,0\nend_of_record\n");
+        Comparison::new(&baseline, &current)
+    }
+
+    #[test]
+    fn totals() {
+        let markdown = Markdown::default().render(&comparison());
+        assert!(markdown.starts_with("### Coverage diff\n"));
+        assert!(markdown.contains("| Lines | 50.00% (1/2) | 50.00% (2/4) | +0.00% |"));
+        assert!(markdown.contains("| Branches | - | - | - |"));
+        assert!(!markdown.contains("Weighted lines"));
+    }
+
+    #[test]
+    fn weighted() {
+        let markdown = Markdown {
+            weighted: Some((
+                Score {
+                    found: 4.0,
+                    hit: 3.0,
+                },
+                Score::default(),
+            )),
+            ..Markdown::default()
+        }
+        .render(&comparison());
+        assert!(markdown.contains("| Weighted lines | 75.00% | - | -75.00% |"));
+    }
+
+    #[test]
+    fn uncovered_lines() {
+        let markdown = Markdown {
+            max_lines: 1,
+            ..Markdown::default()
+        }
+        .render(&comparison());
+        assert!(markdown.contains("- `a.c`: 3\n\n_and 1 more lines_"));
+    }
+
+    #[test]
+    fn escaped_cells() {
+        let baseline = report("SF:a|b`c.c\nDA:1,1\nend_of_record\n");
+        let current = report("SF:a|b`c.c\nDA:1,0\nDA:2,0\nend_of_record\n");
+        let markdown = Markdown::default().render(&Comparison::new(&baseline, &current));
+        assert!(markdown.contains("| ``a\\|b`c.c`` | 100.00% (1/1) | 0.00% (0/2) | -100.00% |\n"));
+        // The lists aren't tables, the `|` stays as is
+        assert!(markdown.contains("- ``a|b`c.c``: 2\n"));
+        assert_eq!(super::code("`a`"), "`` `a` ``");
+    }
+
+    #[test]
+    fn byte_budget() {
+        let mut baseline = String::new();
+        let mut current = String::new();
+        for i in 0..500 {
+            baseline += &format!("SF:src/file{}.c\nDA:1,1\nend_of_record\n", i);
+            current += &format!("SF:src/file{}.c\nDA:1,0\nend_of_record\n", i);
+        }
+        let comparison = Comparison::new(&report(&baseline), &report(&current));
+        let markdown = Markdown {
+            max_files: 1000,
+            max_bytes: 4096,
+            directory_depth: Some(1),
+            top: Some(3),
+            ..Markdown::default()
+        }
+        .render(&comparison);
+
+        assert!(markdown.len() <= 4096);
+        let rows = markdown.matches("| `src/file").count();
+        assert!(rows > 0);
+        assert!(markdown.contains(&format!("\n_and {} more files_\n\n</details>", 500 - rows)));
+        assert!(markdown.ends_with("</details>\n"));
+    }
+
+    #[test]
+    fn top_changes() {
+        let markdown = Markdown {
+            top: Some(3),
+            rank: Rank::Lines,
+            ..Markdown::default()
+        }
+        .render(&comparison());
+        assert!(markdown.contains("**Largest gains**\n\n- `a.c` +0.00% (+1 lines)\n"));
+        assert!(!markdown.contains("**Largest losses**"));
+    }
+
+    #[test]
+    fn directories() {
+        let markdown = Markdown {
+            directory_depth: Some(1),
+            ..Markdown::default()
+        }
+        .render(&comparison());
+        assert!(markdown.contains("| `./` | 50.00% (1/2) | 50.00% (2/4) | +0.00% |"));
+        assert!(!Markdown::default()
+            .render(&comparison())
+            .contains("Directories with changed coverage"));
+    }
+
+    #[test]
+    fn groups() {
+        let table = groups_table(
+            "Crate",
+            &[("app".to_owned(), comparison())],
+            &Numbers::default(),
+        );
+        assert!(table
+            .ends_with("| `app` | 50.00% (1/2) | 50.00% (2/4) | +0.00% | 0.00% (2 uncovered) |\n"));
+    }
+
+    #[test]
+    fn functions() {
+        let baseline = report("SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\n");
+        let current = report("SF:a.c\nFN:1,f\nDA:1,1\nDA:2,0\nend_of_record\n");
+        let table = functions_table(
+            &function_deltas(&baseline, &current),
+            10,
+            &Numbers::default(),
+        );
+        assert!(table.contains("| `f` | `a.c` | - | 50.00% (1/2) | +50.00% |\n"));
+        assert!(functions_table(
+            &function_deltas(&baseline, &baseline),
+            10,
+            &Numbers::default()
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,119 @@
+//! Renderers of the coverage reports.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use lcov::Report;
+
+use crate::summary::{Comparison, Counter, FileDelta, Rank};
+use number::Numbers;
+
+pub mod alert;
+pub mod azure;
+pub mod badge;
+pub mod cobertura;
+pub mod codecov;
+pub mod coveralls;
+pub mod csv;
+pub mod email;
+pub mod github;
+pub mod gitlab;
+pub mod html;
+pub mod junit;
+pub mod markdown;
+pub mod number;
+pub mod patch;
+pub mod pdf;
+pub mod prometheus;
+pub mod sarif;
+pub mod slack;
+pub mod teamcity;
+pub mod teams;
+pub mod verdict;
+
+/// Execution counts of every line by source file, test names are merged together.
+pub(crate) fn line_hits(report: &Report) -> BTreeMap<PathBuf, BTreeMap<u32, u64>> {
+    let mut files: BTreeMap<PathBuf, BTreeMap<u32, u64>> = BTreeMap::new();
+    for (key, section) in &report.sections {
+        let lines = files.entry(key.source_file.clone()).or_default();
+        for (line, value) in &section.lines {
+            *lines.entry(line.line).or_default() += value.count;
+        }
+    }
+    files
+}
+
+/// Finds the source file of `SF:` path inside of the `root` directory.
+///
+/// Relative paths are just joined to the root. For the absolute ones the longest suffix
+/// presented inside of the root is used, since reports are usually generated on another machine.
+pub(crate) fn resolve_source(root: &Path, source_file: &Path) -> Option<PathBuf> {
+    if source_file.is_relative() {
+        let path = root.join(source_file);
+        return if path.is_file() { Some(path) } else { None };
+    }
+    let components: Vec<_> = source_file.components().collect();
+    (1..components.len())
+        .map(|skip| root.join(components[skip..].iter().collect::<PathBuf>()))
+        .find(|path| path.is_file())
+        .or_else(|| Some(source_file.to_owned()).filter(|path| path.is_file()))
+}
+
+/// Path of the `SF:` file relative to the `root` directory, for the links and annotations
+/// which expect paths of the repository: the file `resolve_source` finds, or the path inside
+/// of the canonical root when the sources aren't there. Other paths are returned as is.
+pub(crate) fn relative_path(root: &Path, source_file: &Path) -> PathBuf {
+    let found = resolve_source(root, source_file)
+        .and_then(|source| source.strip_prefix(root).ok().map(Path::to_owned));
+    if let Some(relative) = found {
+        return relative;
+    }
+    std::fs::canonicalize(root)
+        .ok()
+        .and_then(|root| source_file.strip_prefix(root).ok().map(Path::to_owned))
+        .unwrap_or_else(|| source_file.to_owned())
+}
+
+/// `81.25% (+1.20%)` line coverage of the new report with the change, for the chat messages.
+pub(crate) fn percent_with_delta(numbers: &Numbers, old: Counter, new: Counter) -> String {
+    match new.percent() {
+        Some(percent) => format!(
+            "{} ({})",
+            numbers.percent(percent),
+            numbers.delta(percent - old.percent().unwrap_or_default())
+        ),
+        None => "-".to_owned(),
+    }
+}
+
+/// `75.00% (2 uncovered)` coverage of the new lines.
+pub(crate) fn patch_summary(numbers: &Numbers, comparison: &Comparison) -> String {
+    match comparison.patch.percent() {
+        Some(percent) => format!(
+            "{} ({} uncovered)",
+            numbers.percent(percent),
+            comparison.uncovered_new_lines.len()
+        ),
+        None => "-".to_owned(),
+    }
+}
+
+/// Files whose line coverage dropped, worst first.
+pub(crate) fn regressions(comparison: &Comparison, max_files: usize) -> Vec<&FileDelta> {
+    comparison.top_changes(max_files, Rank::Percent).regressions
+}
+
+/// Escapes special characters of html and xml.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,138 @@
+//! Formatting of the percentages, shared by the renderers so the markdown, html and json
+//! outputs show the same numbers on every platform.
+use std::str::FromStr;
+
+/// Rounding of the values to the precision.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Rounding {
+    /// Ties away from zero, `0.125` is `0.13`.
+    HalfUp,
+    /// Ties to the even digit, `0.125` is `0.12`.
+    HalfEven,
+    /// Towards negative infinity, a coverage never looks higher than it is.
+    Floor,
+    Ceil,
+}
+
+impl Rounding {
+    pub const VARIANTS: &'static [&'static str] = &["half-up", "half-even", "floor", "ceil"];
+}
+
+impl FromStr for Rounding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "half-up" => Ok(Rounding::HalfUp),
+            "half-even" => Ok(Rounding::HalfEven),
+            "floor" => Ok(Rounding::Floor),
+            "ceil" => Ok(Rounding::Ceil),
+            _ => Err(format!("unknown rounding: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Numbers {
+    /// Digits after the decimal separator.
+    pub precision: usize,
+    pub rounding: Rounding,
+    /// Decimal separator of the human outputs, json always has `.`.
+    pub decimal_separator: char,
+}
+
+impl Default for Numbers {
+    fn default() -> Self {
+        Numbers {
+            precision: 2,
+            rounding: Rounding::HalfUp,
+            decimal_separator: '.',
+        }
+    }
+}
+
+impl Numbers {
+    /// Value rounded to the precision, without the negative zero.
+    pub fn round(&self, value: f64) -> f64 {
+        let scale = 10f64.powi(self.precision as i32);
+        // The noise of the binary representation (`0.29 * 100` is `28.999999999999996`) isn't
+        // rounded down
+        let scaled = (value * scale * 1e6).round() / 1e6;
+        let rounded = match self.rounding {
+            Rounding::HalfUp => scaled.round(),
+            Rounding::HalfEven => scaled.round_ties_even(),
+            Rounding::Floor => scaled.floor(),
+            Rounding::Ceil => scaled.ceil(),
+        } / scale;
+        if rounded == 0.0 {
+            0.0
+        } else {
+            rounded
+        }
+    }
+
+    /// `81.25`
+    pub fn number(&self, value: f64) -> String {
+        self.localize(format!("{:.*}", self.precision, self.round(value)))
+    }
+
+    /// `81.25%`
+    pub fn percent(&self, value: f64) -> String {
+        self.number(value) + "%"
+    }
+
+    /// `+1.20%`, the change of a percentage.
+    pub fn delta(&self, value: f64) -> String {
+        self.localize(format!("{:+.*}%", self.precision, self.round(value)))
+    }
+
+    fn localize(&self, number: String) -> String {
+        if self.decimal_separator == '.' {
+            number
+        } else {
+            number.replace('.', &self.decimal_separator.to_string())
+        }
+    }
+}
+
+/// Decimal separator of the locale, `de_DE.UTF-8`, `fr` or `en-US`.
+pub fn decimal_separator(locale: &str) -> Result<char, String> {
+    let language = locale
+        .split(['_', '-', '.'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match language.as_str() {
+        "c" | "posix" | "en" | "ja" | "zh" | "ko" | "he" | "hi" | "th" => Ok('.'),
+        "de" | "fr" | "es" | "it" | "pt" | "nl" | "ru" | "uk" | "pl" | "cs" | "sk" | "sv"
+        | "da" | "nb" | "nn" | "fi" | "tr" | "el" | "hu" | "ro" | "bg" | "id" | "vi" => Ok(','),
+        _ => Err(format!("unknown locale: {}", locale)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decimal_separator, Numbers, Rounding};
+
+    #[test]
+    fn format_numbers() {
+        let numbers = Numbers::default();
+        assert_eq!(numbers.percent(0.125), "0.13%");
+        assert_eq!(numbers.delta(-0.001), "+0.00%");
+        assert_eq!(numbers.delta(-1.2), "-1.20%");
+        assert_eq!(numbers.round(2.0 / 3.0 * 100.0), 66.67);
+        let numbers = Numbers {
+            precision: 1,
+            rounding: Rounding::Floor,
+            decimal_separator: decimal_separator("de_DE.UTF-8").unwrap(),
+        };
+        assert_eq!(numbers.percent(79.99), "79,9%");
+        assert_eq!(numbers.number(0.29 * 100.0), "29,0");
+        let even = Numbers {
+            rounding: Rounding::HalfEven,
+            ..Numbers::default()
+        };
+        assert_eq!(even.percent(0.125), "0.12%");
+        assert!(decimal_separator("xx").is_err());
+    }
+}
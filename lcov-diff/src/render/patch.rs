@@ -0,0 +1,172 @@
+//! Unified text patch turning the lcov file of the new report into the diff, so the delta can be
+//! reviewed as a normal diff and applied with `patch`.
+//!
+//! Only the `DA`, `FNDA` and `BRDA` records with another count in the diff are rewritten, the
+//! summary records (`LF`, `LH`, ...) are kept as is.
+use std::fmt::Write as _;
+use std::path::Path;
+
+use lcov::report::section::branch::Key as BranchKey;
+use lcov::report::section::line::Key as LineKey;
+use lcov::report::section::Key as SectionKey;
+use lcov::Report;
+
+/// Unchanged lines around every change.
+const CONTEXT: usize = 3;
+
+/// Patch of the `original` lcov text (read from `path`) with the counts of `diff`, empty if
+/// nothing changed.
+pub fn render(original: &str, path: &Path, diff: &Report) -> String {
+    let lines: Vec<&str> = original.lines().collect();
+    let mut key = SectionKey {
+        test_name: String::new(),
+        source_file: Default::default(),
+    };
+    let mut changes: Vec<(usize, String)> = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        let (kind, fields) = line.split_once(':').unwrap_or((line, ""));
+        match kind {
+            "TN" => key.test_name = fields.to_owned(),
+            "SF" => key.source_file = fields.into(),
+            "end_of_record" => key.test_name.clear(),
+            "DA" | "FNDA" | "BRDA" => {
+                let section = match diff.sections.get(&key) {
+                    Some(section) => section,
+                    None => continue,
+                };
+                let fields: Vec<&str> = fields.split(',').collect();
+                let replacement = match (kind, fields.as_slice()) {
+                    ("DA", [number, count, rest @ ..]) => number
+                        .parse()
+                        .ok()
+                        .and_then(|line| section.lines.get(&LineKey { line }))
+                        .map(|value| value.count.to_string())
+                        .filter(|new| new != count)
+                        .map(|new| record("DA", &[&[*number, &new], rest].concat())),
+                    ("FNDA", [count, name @ ..]) => section
+                        .functions
+                        .iter()
+                        .find(|(key, _)| key.name == name.join(","))
+                        .map(|(_, value)| value.count.to_string())
+                        .filter(|new| new != count)
+                        .map(|new| record("FNDA", &[&[new.as_str()], name].concat())),
+                    ("BRDA", [number, block, branch, taken]) => (|| {
+                        let key = BranchKey {
+                            line: number.parse().ok()?,
+                            block: block.parse().ok()?,
+                            branch: branch.parse().ok()?,
+                        };
+                        let new = section.branches.get(&key)?.taken;
+                        let new = new.map_or("-".to_owned(), |taken| taken.to_string());
+                        Some(new)
+                            .filter(|new| new != taken)
+                            .map(|new| record("BRDA", &[*number, block, branch, &new]))
+                    })(),
+                    _ => None,
+                };
+                if let Some(replacement) = replacement {
+                    changes.push((idx, replacement));
+                }
+            }
+            _ => {}
+        }
+    }
+    unified(&lines, &changes, path)
+}
+
+fn record(kind: &str, fields: &[&str]) -> String {
+    format!("{}:{}", kind, fields.join(","))
+}
+
+/// Unified diff of the replacements of the lines, `changes` ordered by the index.
+fn unified(lines: &[&str], changes: &[(usize, String)], path: &Path) -> String {
+    let mut out = String::new();
+    if changes.is_empty() {
+        return out;
+    }
+    let path = path.to_string_lossy();
+    let _ = writeln!(out, "--- a/{}\n+++ b/{}", path, path);
+    let mut rest = changes;
+    while let Some(&(first, _)) = rest.first() {
+        // Changes closer than twice the context share a hunk
+        let count = rest
+            .windows(2)
+            .take_while(|pair| pair[1].0 - pair[0].0 <= 2 * CONTEXT)
+            .count()
+            + 1;
+        let (hunk, next) = rest.split_at(count);
+        rest = next;
+        let start = first.saturating_sub(CONTEXT);
+        let end = (hunk[hunk.len() - 1].0 + CONTEXT + 1).min(lines.len());
+        let _ = writeln!(
+            out,
+            "@@ -{},{} +{},{} @@",
+            start + 1,
+            end - start,
+            start + 1,
+            end - start
+        );
+        let mut hunk = hunk.iter().peekable();
+        for (idx, line) in lines.iter().enumerate().take(end).skip(start) {
+            match hunk.next_if(|(changed, _)| *changed == idx) {
+                Some((_, replacement)) => {
+                    let _ = writeln!(out, "-{}\n+{}", line, replacement);
+                }
+                None => {
+                    let _ = writeln!(out, " {}", line);
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::diff_reports;
+    use lcov::{Reader, Report};
+    use std::path::Path;
+
+    #[test]
+    fn patch() {
+        let current = "TN:\nSF:a.c\nFN:1,main\nFNDA:2,main\nBRDA:2,0,0,1\nBRDA:2,0,1,1\nDA:1,2\nDA:2,2\nDA:3,0\nDA:4,1\nDA:5,1\nDA:6,1\nDA:7,1\nDA:8,1\nDA:9,1\nDA:10,1\nDA:11,1\nDA:12,3\nend_of_record\n";
+        let baseline = "TN:\nSF:a.c\nFN:1,main\nFNDA:1,main\nBRDA:2,0,0,1\nDA:1,1\nDA:2,1\nDA:12,1\nend_of_record\n";
+        let first = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let second = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let diff = diff_reports(&first, &second).unwrap();
+
+        let patch = render(current, Path::new("new.info"), &diff);
+        assert_eq!(
+            patch,
+            "\
+--- a/new.info
++++ b/new.info
+@@ -1,11 +1,11 @@
+ TN:
+ SF:a.c
+ FN:1,main
+-FNDA:2,main
++FNDA:0,main
+-BRDA:2,0,0,1
++BRDA:2,0,0,-
+ BRDA:2,0,1,1
+-DA:1,2
++DA:1,0
+-DA:2,2
++DA:2,0
+ DA:3,0
+ DA:4,1
+ DA:5,1
+@@ -15,5 +15,5 @@
+ DA:9,1
+ DA:10,1
+ DA:11,1
+-DA:12,3
++DA:12,0
+ end_of_record
+"
+        );
+        assert!(render(current, Path::new("new.info"), &first).is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+//! PDF report: the summary and the regressed files as monospaced text pages.
+//!
+//! The document is written by hand with the standard Courier font, so it doesn't need any font
+//! files. It is kept ASCII only, other characters are replaced by `?`.
+use std::fmt::Write as _;
+
+use super::number::Numbers;
+use super::{patch_summary, percent_with_delta};
+use crate::summary::Comparison;
+
+const LINES_PER_PAGE: usize = 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pdf {
+    /// Title on the first page.
+    pub title: String,
+    /// Maximum number of the changed files listed, all of them if `None`.
+    pub max_files: Option<usize>,
+    pub numbers: Numbers,
+}
+
+impl Default for Pdf {
+    fn default() -> Self {
+        Pdf {
+            title: "Coverage report".to_owned(),
+            max_files: None,
+            numbers: Numbers::default(),
+        }
+    }
+}
+
+impl Pdf {
+    fn lines(&self, comparison: &Comparison) -> Vec<String> {
+        let (old, new) = (&comparison.baseline, &comparison.current);
+        let mut lines = vec![
+            self.title.clone(),
+            String::new(),
+            format!(
+                "Lines:     {}",
+                percent_with_delta(&self.numbers, old.lines, new.lines)
+            ),
+            format!(
+                "Functions: {}",
+                percent_with_delta(&self.numbers, old.functions, new.functions)
+            ),
+            format!(
+                "Branches:  {}",
+                percent_with_delta(&self.numbers, old.branches, new.branches)
+            ),
+            format!("New lines: {}", patch_summary(&self.numbers, comparison)),
+        ];
+        let files = comparison.changed_files();
+        if !files.is_empty() {
+            lines.push(String::new());
+            lines.push("Changed files, worst first:".to_owned());
+            let limit = self.max_files.unwrap_or(files.len());
+            for file in files.iter().take(limit) {
+                lines.push(format!(
+                    "  {:>9}  {}",
+                    self.numbers.delta(file.lines_delta()),
+                    file.path.display()
+                ));
+            }
+            if files.len() > limit {
+                lines.push(format!("  and {} more files", files.len() - limit));
+            }
+        }
+        lines
+    }
+
+    pub fn render(&self, comparison: &Comparison) -> String {
+        let lines = self.lines(comparison);
+        let pages: Vec<&[String]> = lines.chunks(LINES_PER_PAGE).collect();
+
+        // 1 catalog, 2 pages, 3 font, then page and content objects of every page
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_owned(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..pages.len())
+                    .map(|idx| format!("{} 0 R", 4 + idx * 2))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                pages.len()
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>"
+                .to_owned(),
+        ];
+        for (idx, page) in pages.iter().enumerate() {
+            let mut content = String::from("BT\n/F1 10 Tf\n12 TL\n50 800 Td\n");
+            for line in page.iter() {
+                let _ = writeln!(content, "({}) '", text(line));
+            }
+            content.push_str("ET\n");
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] \
+                 /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                5 + idx * 2
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                content.len(),
+                content
+            ));
+        }
+
+        let mut out = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (idx, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            let _ = write!(out, "{} 0 obj\n{}\nendobj\n", idx + 1, object);
+        }
+        let xref = out.len();
+        let _ = write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(out, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            out,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        );
+        out
+    }
+}
+
+/// Escapes the pdf string literal.
+fn text(line: &str) -> String {
+    line.chars()
+        .map(|ch| match ch {
+            '(' | ')' | '\\' => format!("\\{}", ch),
+            ' '..='~' => ch.to_string(),
+            _ => "?".to_owned(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pdf;
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn render() {
+        let baseline = "SF:src/(a).c\nDA:1,1\nDA:2,1\nend_of_record\n";
+        let current = "SF:src/(a).c\nDA:1,1\nDA:2,0\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let pdf = Pdf::default().render(&Comparison::new(&baseline, &current));
+
+        assert!(pdf.starts_with("%PDF-1.4\n1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>"));
+        assert!(pdf.contains("(Lines:     50.00% \\(-50.00%\\)) '"));
+        assert!(pdf.contains("(    -50.00%  src/\\(a\\).c) '"));
+        // xref offsets point to the objects
+        let xref = pdf.rsplit("startxref\n").next().unwrap();
+        let xref: usize = xref.lines().next().unwrap().parse().unwrap();
+        assert!(pdf[xref..].starts_with("xref\n0 6\n"));
+        let offset: usize = pdf[xref..].lines().nth(4).unwrap()[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with("2 0 obj"));
+    }
+}
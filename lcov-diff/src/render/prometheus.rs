@@ -0,0 +1,227 @@
+//! Prometheus text exposition format, for the pushgateway or the textfile collector.
+//!
+//! Totals are labeled by `report` (`baseline` or `current`), the same counters are repeated per
+//! directory cut to `depth` components with the `directory` label.
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Component, Path};
+
+use crate::summary::{Comparison, Coverage};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prometheus {
+    /// Number of the leading directory components files are grouped by, 0 disables grouping.
+    pub depth: usize,
+    /// Labels added to every sample, e.g. `project`.
+    pub labels: Vec<(String, String)>,
+}
+
+impl Default for Prometheus {
+    fn default() -> Self {
+        Prometheus {
+            depth: 1,
+            labels: Vec::new(),
+        }
+    }
+}
+
+/// Parses `name=value` label.
+pub fn parse_label(label: &str) -> Result<(String, String), String> {
+    match label.split_once('=') {
+        Some((name, value)) if is_label_name(name) => Ok((name.to_owned(), value.to_owned())),
+        _ => Err(format!("`name=value` label expected: {}", label)),
+    }
+}
+
+fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl Prometheus {
+    pub fn render(&self, comparison: &Comparison) -> String {
+        let mut directories: BTreeMap<String, (Coverage, Coverage)> = BTreeMap::new();
+        if self.depth > 0 {
+            for file in &comparison.files {
+                let entry = directories
+                    .entry(directory(&file.path, self.depth))
+                    .or_default();
+                entry.0 += file.baseline.unwrap_or_default();
+                entry.1 += file.current.unwrap_or_default();
+            }
+        }
+
+        let mut out = String::new();
+        for kind in &["lines", "functions", "branches"] {
+            for (stat, help) in &[("total", "Instrumented"), ("covered", "Executed")] {
+                let value = |coverage: &Coverage| {
+                    let counter = match *kind {
+                        "lines" => coverage.lines,
+                        "functions" => coverage.functions,
+                        _ => coverage.branches,
+                    };
+                    let value = if *stat == "total" {
+                        counter.found
+                    } else {
+                        counter.hit
+                    };
+                    value as f64
+                };
+                let name = format!("lcov_diff_{}_{}", kind, stat);
+                header(
+                    &mut out,
+                    &name,
+                    &format!("{} {} of the report.", help, kind),
+                );
+                for (report, coverage) in [
+                    ("baseline", &comparison.baseline),
+                    ("current", &comparison.current),
+                ] {
+                    self.sample(&mut out, &name, &[("report", report)], value(coverage));
+                }
+                for (dir, (baseline, current)) in &directories {
+                    for (report, coverage) in [("baseline", baseline), ("current", current)] {
+                        self.sample(
+                            &mut out,
+                            &name,
+                            &[("report", report), ("directory", dir)],
+                            value(coverage),
+                        );
+                    }
+                }
+            }
+        }
+
+        header(
+            &mut out,
+            "lcov_diff_line_coverage_delta",
+            "Change of the line coverage in percentage points.",
+        );
+        self.sample(
+            &mut out,
+            "lcov_diff_line_coverage_delta",
+            &[],
+            comparison.lines_delta(),
+        );
+        for (dir, (baseline, current)) in &directories {
+            let delta = current.lines.percent().unwrap_or_default()
+                - baseline.lines.percent().unwrap_or_default();
+            self.sample(
+                &mut out,
+                "lcov_diff_line_coverage_delta",
+                &[("directory", dir)],
+                delta,
+            );
+        }
+        header(
+            &mut out,
+            "lcov_diff_new_lines_total",
+            "Lines instrumented only in the current report.",
+        );
+        self.sample(
+            &mut out,
+            "lcov_diff_new_lines_total",
+            &[],
+            comparison.patch.found as f64,
+        );
+        header(
+            &mut out,
+            "lcov_diff_new_lines_covered",
+            "Executed new lines.",
+        );
+        self.sample(
+            &mut out,
+            "lcov_diff_new_lines_covered",
+            &[],
+            comparison.patch.hit as f64,
+        );
+        out
+    }
+
+    fn sample(&self, out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+        let labels: Vec<String> = self
+            .labels
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .chain(labels.iter().copied())
+            .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+            .collect();
+        if labels.is_empty() {
+            let _ = writeln!(out, "{} {}", name, value);
+        } else {
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels.join(","), value);
+        }
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge", name, help, name);
+}
+
+/// Leading `depth` components of the file directory, `.` for the top level files.
+fn directory(path: &Path, depth: usize) -> String {
+    let components: Vec<String> = path
+        .parent()
+        .map(|dir| {
+            dir.components()
+                .filter_map(|c| match c {
+                    Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                    _ => None,
+                })
+                .take(depth)
+                .collect()
+        })
+        .unwrap_or_default();
+    if components.is_empty() {
+        ".".to_owned()
+    } else {
+        components.join("/")
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_label, Prometheus};
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn render() {
+        let baseline = "SF:src/a/x.c\nDA:1,1\nend_of_record\n";
+        let current =
+            "SF:src/a/x.c\nDA:1,1\nDA:2,0\nend_of_record\nSF:main.c\nDA:1,1\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let text = Prometheus {
+            depth: 1,
+            labels: vec![parse_label("project=app").unwrap()],
+        }
+        .render(&Comparison::new(&baseline, &current));
+
+        assert!(text.starts_with(
+            "# HELP lcov_diff_lines_total Instrumented lines of the report.\n\
+             # TYPE lcov_diff_lines_total gauge\n\
+             lcov_diff_lines_total{project=\"app\",report=\"baseline\"} 1\n\
+             lcov_diff_lines_total{project=\"app\",report=\"current\"} 3\n\
+             lcov_diff_lines_total{project=\"app\",report=\"baseline\",directory=\".\"} 0\n"
+        ));
+        assert!(text.contains(
+            "lcov_diff_lines_covered{project=\"app\",report=\"current\",directory=\"src\"} 1\n"
+        ));
+        assert!(
+            text.contains("lcov_diff_line_coverage_delta{project=\"app\",directory=\"src\"} -50\n")
+        );
+        assert!(text.ends_with("lcov_diff_new_lines_covered{project=\"app\"} 1\n"));
+        assert!(parse_label("1x=y").is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! SARIF 2.1.0 log, so code scanning shows coverage gaps inline.
+//!
+//! Every finding is a result of its kind (`uncovered-new-line`, `lost-line`, ...), every
+//! threshold violation is a `coverage-regression` result without location. The relative paths
+//! of the findings (see `severity::relative_to`) are relative to `%SRCROOT%`, the root of the
+//! repository for code scanning. With a permalink every finding with a line has the `url` of
+//! the line in its `properties`.
+use std::path::Component;
+
+use crate::check::Violation;
+use crate::json::Value;
+use crate::permalink::Permalink;
+use crate::severity::{Finding, Kind, Severity};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+pub fn render(
+    findings: &[Finding],
+    violations: &[Violation],
+    permalink: Option<&Permalink>,
+) -> Value {
+    let mut results = Vec::new();
+    for finding in findings {
+        let level = match finding.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info | Severity::Off => "note",
+        };
+        let mut result = vec![
+            ("ruleId", Value::from(finding.kind.id())),
+            ("level", Value::from(level)),
+            ("message", message(finding.kind.message())),
+            ("locations", Value::Array(vec![location(finding)])),
+        ];
+        if let (Some(permalink), Some(line)) = (permalink, finding.line) {
+            let url = permalink.link(&finding.path, line);
+            result.push(("properties", Value::object(vec![("url", Value::from(url))])));
+        }
+        results.push(Value::object(result));
+    }
+    for violation in violations {
+        results.push(Value::object(vec![
+            ("ruleId", Value::from("coverage-regression")),
+            ("level", Value::from("error")),
+            ("message", message(&violation.to_string())),
+        ]));
+    }
+
+    let rules = vec![
+        rule(
+            Kind::UncoveredNewLine.id(),
+            "New line is not covered by tests",
+        ),
+        rule(
+            Kind::LostLine.id(),
+            "Line covered by the baseline is not covered",
+        ),
+        rule(
+            Kind::LostBranch.id(),
+            "Branch taken by the baseline is not taken",
+        ),
+        rule(Kind::DroppedFile.id(), "File of the baseline is missing"),
+        rule(
+            "coverage-regression",
+            "Coverage is out of the configured thresholds",
+        ),
+    ];
+    let driver = Value::object(vec![
+        ("name", Value::from("lcov-diff")),
+        (
+            "informationUri",
+            Value::from("https://github.com/capgelka/lcov-diff"),
+        ),
+        ("version", Value::from(env!("CARGO_PKG_VERSION"))),
+        ("rules", Value::Array(rules)),
+    ]);
+    Value::object(vec![
+        ("$schema", Value::from(SCHEMA)),
+        ("version", Value::from("2.1.0")),
+        (
+            "runs",
+            Value::Array(vec![Value::object(vec![
+                ("tool", Value::object(vec![("driver", driver)])),
+                ("results", Value::Array(results)),
+            ])]),
+        ),
+    ])
+}
+
+fn rule(id: &str, description: &str) -> Value {
+    Value::object(vec![
+        ("id", Value::from(id)),
+        ("shortDescription", message(description)),
+    ])
+}
+
+fn message(text: &str) -> Value {
+    Value::object(vec![("text", Value::from(text))])
+}
+
+fn location(finding: &Finding) -> Value {
+    let path = finding
+        .path
+        .components()
+        .filter(|c| !matches!(c, Component::RootDir | Component::CurDir))
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let artifact = if finding.path.is_absolute() {
+        vec![("uri", Value::from(format!("file:///{}", path)))]
+    } else {
+        vec![
+            ("uri", Value::from(path)),
+            ("uriBaseId", Value::from("%SRCROOT%")),
+        ]
+    };
+    let mut location = vec![("artifactLocation", Value::object(artifact))];
+    if let Some(line) = finding.line {
+        let mut region = vec![("startLine", Value::from(line))];
+        if let Some(end_line) = finding.end_line {
+            region.push(("endLine", Value::from(end_line)));
+        }
+        location.push(("region", Value::object(region)));
+    }
+    if let Some(snippet) = &finding.snippet {
+        location.push((
+            "contextRegion",
+            Value::object(vec![
+                ("startLine", Value::from(snippet.start_line)),
+                ("endLine", Value::from(snippet.end_line)),
+                (
+                    "snippet",
+                    Value::object(vec![("text", Value::from(snippet.text.as_str()))]),
+                ),
+            ]),
+        ));
+    }
+    Value::object(vec![("physicalLocation", Value::object(location))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::check::Violation;
+    use crate::permalink::Permalink;
+    use crate::severity::{findings, Severities};
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+    use std::path::PathBuf;
+
+    #[test]
+    fn results() {
+        let current = "SF:src/a.c\nDA:1,1\nDA:2,0\nend_of_record\n";
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let violations = [Violation::LinesBelow {
+            coverage: 50.0,
+            min: 80.0,
+        }];
+        let comparison = Comparison::new(&Report::new(), &current);
+        let findings = findings(
+            &Report::new(),
+            &current,
+            &comparison,
+            &Severities::default(),
+        );
+        let sarif = render(&findings, &violations, None).to_string();
+
+        assert!(sarif.contains(
+            r#"{"ruleId":"uncovered-new-line","level":"warning","message":{"text":"Line not covered by tests"},"locations":[{"physicalLocation":{"artifactLocation":{"uri":"src/a.c","uriBaseId":"%SRCROOT%"},"region":{"startLine":2}}}]}"#
+        ));
+        assert!(sarif.contains(r#"{"ruleId":"coverage-regression","level":"error","#));
+
+        let permalink = Permalink::new("https://h/blob/{commit}/{path}#L{line}", "c0");
+        assert!(render(&findings, &violations, Some(&permalink)).to_string().contains(
+            r#""region":{"startLine":2}}}],"properties":{"url":"https://h/blob/c0/src/a.c#L2"}}"#
+        ));
+
+        let mut findings = findings;
+        findings[0].path = PathBuf::from("/home/runner/work/r/r/src/a.c");
+        assert!(render(&findings, &[], None)
+            .to_string()
+            .contains(r#""artifactLocation":{"uri":"file:///home/runner/work/r/r/src/a.c"}"#));
+    }
+}
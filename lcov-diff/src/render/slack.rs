@@ -0,0 +1,146 @@
+//! Slack message with Block Kit blocks, posted to an incoming webhook.
+use super::number::Numbers;
+use super::{patch_summary, percent_with_delta, regressions};
+use crate::json::Value;
+use crate::summary::Comparison;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slack {
+    /// Maximum number of the regressed files listed.
+    pub max_files: usize,
+    /// Link to the full report.
+    pub report_url: Option<String>,
+    pub numbers: Numbers,
+}
+
+impl Default for Slack {
+    fn default() -> Self {
+        Slack {
+            max_files: 5,
+            report_url: None,
+            numbers: Numbers::default(),
+        }
+    }
+}
+
+impl Slack {
+    pub fn render(&self, comparison: &Comparison) -> Value {
+        let (old, new) = (&comparison.baseline, &comparison.current);
+        let field = |name: &str, value: String| {
+            Value::object(vec![
+                ("type", Value::from("mrkdwn")),
+                ("text", Value::from(format!("*{}*\n{}", name, value))),
+            ])
+        };
+        let mut blocks = vec![
+            Value::object(vec![
+                ("type", Value::from("header")),
+                ("text", plain_text("Coverage report")),
+            ]),
+            Value::object(vec![
+                ("type", Value::from("section")),
+                (
+                    "fields",
+                    Value::Array(vec![
+                        field(
+                            "Lines",
+                            percent_with_delta(&self.numbers, old.lines, new.lines),
+                        ),
+                        field(
+                            "Functions",
+                            percent_with_delta(&self.numbers, old.functions, new.functions),
+                        ),
+                        field(
+                            "Branches",
+                            percent_with_delta(&self.numbers, old.branches, new.branches),
+                        ),
+                        field("New lines", patch_summary(&self.numbers, comparison)),
+                    ]),
+                ),
+            ]),
+        ];
+
+        let regressions = regressions(comparison, self.max_files);
+        if !regressions.is_empty() {
+            let lines: Vec<String> = regressions
+                .iter()
+                .map(|file| {
+                    format!(
+                        "• `{}` {}",
+                        file.path.display(),
+                        self.numbers.delta(file.lines_delta())
+                    )
+                })
+                .collect();
+            blocks.push(Value::object(vec![
+                ("type", Value::from("section")),
+                (
+                    "text",
+                    Value::object(vec![
+                        ("type", Value::from("mrkdwn")),
+                        (
+                            "text",
+                            Value::from(format!("*Worst regressions*\n{}", lines.join("\n"))),
+                        ),
+                    ]),
+                ),
+            ]));
+        }
+        if let Some(url) = &self.report_url {
+            blocks.push(Value::object(vec![
+                ("type", Value::from("actions")),
+                (
+                    "elements",
+                    Value::Array(vec![Value::object(vec![
+                        ("type", Value::from("button")),
+                        ("text", plain_text("Full report")),
+                        ("url", Value::from(url.as_str())),
+                    ])]),
+                ),
+            ]));
+        }
+
+        Value::object(vec![
+            (
+                "text",
+                Value::from(format!(
+                    "Coverage: {}",
+                    percent_with_delta(&self.numbers, old.lines, new.lines)
+                )),
+            ),
+            ("blocks", Value::Array(blocks)),
+        ])
+    }
+}
+
+fn plain_text(text: &str) -> Value {
+    Value::object(vec![
+        ("type", Value::from("plain_text")),
+        ("text", Value::from(text)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Slack;
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn render() {
+        let baseline = "SF:a.c\nDA:1,1\nDA:2,1\nend_of_record\n";
+        let current = "SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let json = Slack {
+            report_url: Some("https://ci/report".to_owned()),
+            ..Slack::default()
+        }
+        .render(&Comparison::new(&baseline, &current))
+        .to_string();
+
+        assert!(json.starts_with(r#"{"text":"Coverage: 50.00% (-50.00%)","blocks":["#));
+        assert!(json.contains(r#""text":"*Worst regressions*\n• `a.c` -50.00%""#));
+        assert!(json.contains(r#""url":"https://ci/report""#));
+    }
+}
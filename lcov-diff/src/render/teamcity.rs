@@ -0,0 +1,123 @@
+//! TeamCity service messages: build statistics and inspections for the findings.
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+
+use super::number::Numbers;
+use crate::severity::{Finding, Severity};
+use crate::summary::Comparison;
+
+/// The statistic values are in the number format, which should have the `.` separator.
+pub fn render(comparison: &Comparison, findings: &[Finding], numbers: &Numbers) -> String {
+    let mut out = String::new();
+    let lines = comparison.current.lines;
+    let mut statistic = |key: &str, value: String| {
+        let _ = writeln!(
+            out,
+            "##teamcity[buildStatisticValue key='{}' value='{}']",
+            escape(key),
+            value
+        );
+    };
+    if let Some(percent) = lines.percent() {
+        statistic("CodeCoverageL", numbers.number(percent));
+    }
+    statistic("CodeCoverageAbsLCovered", lines.hit.to_string());
+    statistic("CodeCoverageAbsLTotal", lines.found.to_string());
+    statistic(
+        "lcovDiff.linesDelta",
+        numbers.number(comparison.lines_delta()),
+    );
+    if let Some(percent) = comparison.patch.percent() {
+        statistic("lcovDiff.patchCoverage", numbers.number(percent));
+    }
+
+    let kinds: BTreeSet<_> = findings.iter().map(|finding| finding.kind).collect();
+    for kind in kinds {
+        let name = kind.id().replace('-', " ");
+        let _ = writeln!(
+            out,
+            "##teamcity[inspectionType id='{}' name='{}{}' category='Coverage' description='{}']",
+            kind.id(),
+            name[..1].to_uppercase(),
+            &name[1..],
+            kind.message()
+        );
+    }
+    for finding in findings {
+        let severity = match finding.severity {
+            Severity::Error => "ERROR",
+            Severity::Warning => "WARNING",
+            Severity::Info | Severity::Off => "INFO",
+        };
+        let message = match (finding.line, finding.end_line) {
+            (Some(line), Some(end_line)) => {
+                format!("{} (lines {}-{})", finding.kind.message(), line, end_line)
+            }
+            _ => finding.kind.message().to_owned(),
+        };
+        let _ = write!(
+            out,
+            "##teamcity[inspection typeId='{}' message='{}' file='{}'",
+            finding.kind.id(),
+            escape(&message),
+            escape(&finding.path.to_string_lossy()),
+        );
+        if let Some(line) = finding.line {
+            let _ = write!(out, " line='{}'", line);
+        }
+        let _ = writeln!(out, " SEVERITY='{}']", severity);
+    }
+    out
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '|' => escaped.push_str("||"),
+            '\'' => escaped.push_str("|'"),
+            '\n' => escaped.push_str("|n"),
+            '\r' => escaped.push_str("|r"),
+            '[' => escaped.push_str("|["),
+            ']' => escaped.push_str("|]"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::render::number::Numbers;
+    use crate::severity::{findings, Severities};
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn service_messages() {
+        let baseline = "SF:a.c\nDA:1,1\nend_of_record\n";
+        let current = "SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\nSF:b[1].c\nDA:1,1\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&baseline, &current);
+        let findings = findings(&baseline, &current, &comparison, &Severities::default());
+        let out = render(&comparison, &findings, &Numbers::default());
+
+        assert!(out.contains("##teamcity[buildStatisticValue key='CodeCoverageL' value='66.67']\n"));
+        assert!(out.contains("key='lcovDiff.linesDelta' value='-33.33'"));
+        assert!(out.contains("key='lcovDiff.patchCoverage' value='50.00'"));
+        assert!(out.contains(
+            "##teamcity[inspection typeId='uncovered-new-line' message='Line not covered by tests' file='a.c' line='2' SEVERITY='WARNING']"
+        ));
+        assert_eq!(super::escape("b[1]'s|"), "b|[1|]|'s||");
+
+        let numbers = Numbers {
+            precision: 1,
+            ..Numbers::default()
+        };
+        let out = render(&comparison, &findings, &numbers);
+        assert!(out.contains("key='CodeCoverageL' value='66.7'"));
+        assert!(out.contains("key='lcovDiff.linesDelta' value='-33.3'"));
+    }
+}
@@ -0,0 +1,154 @@
+//! Microsoft Teams message with an Adaptive Card, posted to an incoming webhook or a workflow.
+use super::number::Numbers;
+use super::{patch_summary, percent_with_delta, regressions};
+use crate::json::Value;
+use crate::summary::Comparison;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Teams {
+    /// Maximum number of the regressed files listed.
+    pub max_files: usize,
+    /// Link to the full report.
+    pub report_url: Option<String>,
+    pub numbers: Numbers,
+}
+
+impl Default for Teams {
+    fn default() -> Self {
+        Teams {
+            max_files: 5,
+            report_url: None,
+            numbers: Numbers::default(),
+        }
+    }
+}
+
+impl Teams {
+    pub fn render(&self, comparison: &Comparison) -> Value {
+        let (old, new) = (&comparison.baseline, &comparison.current);
+        let mut body = vec![
+            text_block("Coverage report", true),
+            fact_set(vec![
+                (
+                    "Lines".to_owned(),
+                    percent_with_delta(&self.numbers, old.lines, new.lines),
+                ),
+                (
+                    "Functions".to_owned(),
+                    percent_with_delta(&self.numbers, old.functions, new.functions),
+                ),
+                (
+                    "Branches".to_owned(),
+                    percent_with_delta(&self.numbers, old.branches, new.branches),
+                ),
+                (
+                    "New lines".to_owned(),
+                    patch_summary(&self.numbers, comparison),
+                ),
+            ]),
+        ];
+        let regressions = regressions(comparison, self.max_files);
+        if !regressions.is_empty() {
+            body.push(text_block("Worst regressions", false));
+            body.push(fact_set(
+                regressions
+                    .iter()
+                    .map(|file| {
+                        (
+                            file.path.display().to_string(),
+                            self.numbers.delta(file.lines_delta()),
+                        )
+                    })
+                    .collect(),
+            ));
+        }
+
+        let mut card = vec![
+            (
+                "$schema",
+                Value::from("http://adaptivecards.io/schemas/adaptive-card.json"),
+            ),
+            ("type", Value::from("AdaptiveCard")),
+            ("version", Value::from("1.4")),
+            ("body", Value::Array(body)),
+        ];
+        if let Some(url) = &self.report_url {
+            card.push((
+                "actions",
+                Value::Array(vec![Value::object(vec![
+                    ("type", Value::from("Action.OpenUrl")),
+                    ("title", Value::from("Full report")),
+                    ("url", Value::from(url.as_str())),
+                ])]),
+            ));
+        }
+        Value::object(vec![
+            ("type", Value::from("message")),
+            (
+                "attachments",
+                Value::Array(vec![Value::object(vec![
+                    (
+                        "contentType",
+                        Value::from("application/vnd.microsoft.card.adaptive"),
+                    ),
+                    ("content", Value::object(card)),
+                ])]),
+            ),
+        ])
+    }
+}
+
+fn text_block(text: &str, header: bool) -> Value {
+    let mut block = vec![
+        ("type", Value::from("TextBlock")),
+        ("text", Value::from(text)),
+        ("weight", Value::from("Bolder")),
+    ];
+    if header {
+        block.push(("size", Value::from("Medium")));
+    }
+    Value::object(block)
+}
+
+fn fact_set(facts: Vec<(String, String)>) -> Value {
+    Value::object(vec![
+        ("type", Value::from("FactSet")),
+        (
+            "facts",
+            Value::Array(
+                facts
+                    .into_iter()
+                    .map(|(title, value)| {
+                        Value::object(vec![
+                            ("title", Value::from(title)),
+                            ("value", Value::from(value)),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Teams;
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn render() {
+        let baseline = "SF:a.c\nDA:1,1\nDA:2,1\nend_of_record\n";
+        let current = "SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let json = Teams::default()
+            .render(&Comparison::new(&baseline, &current))
+            .to_string();
+
+        assert!(json.starts_with(r#"{"type":"message","attachments":[{"contentType":"application/vnd.microsoft.card.adaptive","#));
+        assert!(json.contains(r#"{"title":"Lines","value":"50.00% (-50.00%)"}"#));
+        assert!(json.contains(r#"{"title":"a.c","value":"-50.00%"}"#));
+        assert!(!json.contains("actions"));
+    }
+}
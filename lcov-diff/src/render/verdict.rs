@@ -0,0 +1,91 @@
+//! Versioned json verdict of the checks, for the pipeline steps branching on the violations.
+use super::azure;
+use super::number::Numbers;
+use crate::check::Violation;
+use crate::json::Value;
+use crate::permalink::Permalink;
+use crate::summary::Comparison;
+
+/// Version of the verdict format, bumped on incompatible changes.
+pub const VERSION: u64 = 1;
+
+/// `failures` are the `(kind, message)` pairs of the failed checks other than the thresholds:
+/// per group thresholds, error findings, new coverage debt. The totals link the uncovered new
+/// lines with the `permalink`.
+pub fn render(
+    comparison: &Comparison,
+    violations: &[Violation],
+    failures: &[(String, String)],
+    numbers: &Numbers,
+    permalink: Option<&Permalink>,
+) -> Value {
+    let mut entries: Vec<Value> = violations
+        .iter()
+        .map(|violation| {
+            let (value, limit) = violation.value_and_limit();
+            Value::object(vec![
+                ("kind", Value::from(violation.kind())),
+                ("message", Value::from(violation.to_string())),
+                ("value", Value::from(numbers.round(value))),
+                ("limit", Value::from(limit)),
+            ])
+        })
+        .collect();
+    entries.extend(failures.iter().map(|(kind, message)| {
+        Value::object(vec![
+            ("kind", Value::from(kind.as_str())),
+            ("message", Value::from(message.as_str())),
+        ])
+    }));
+    let status = if entries.is_empty() {
+        "passed"
+    } else {
+        "failed"
+    };
+    Value::object(vec![
+        ("version", Value::from(VERSION)),
+        ("status", Value::from(status)),
+        ("violations", Value::Array(entries)),
+        (
+            "totals",
+            azure::summary_json_with(comparison, numbers, permalink),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::check::Violation;
+    use crate::render::number::Numbers;
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn verdict() {
+        let current = "SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\n";
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&Report::new(), &current);
+        assert!(render(&comparison, &[], &[], &Numbers::default(), None)
+            .to_string()
+            .starts_with(r#"{"version":1,"status":"passed","violations":[],"totals":{"lines":{"covered":1,"total":2,"percent":50},"#));
+
+        let violations = [Violation::LinesBelow {
+            coverage: 50.0,
+            min: 80.0,
+        }];
+        let failures = [(
+            "debt".to_owned(),
+            "a.c:2: uncovered line is not in the debt file".to_owned(),
+        )];
+        let json = render(
+            &comparison,
+            &violations,
+            &failures,
+            &Numbers::default(),
+            None,
+        )
+        .to_string();
+        assert!(json.contains(r#""status":"failed","violations":[{"kind":"lines_below","message":"Line coverage 50.00% is below the threshold 80.00%","value":50,"limit":80},{"kind":"debt","#));
+    }
+}
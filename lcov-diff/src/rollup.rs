@@ -0,0 +1,158 @@
+//! Coverage deltas rolled up by directory, for the reports of repositories too big to be read
+//! file by file.
+use std::path::{Component, Path, PathBuf};
+
+use crate::summary::{Comparison, Coverage};
+
+/// Directory with the coverage of all files below it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Directory {
+    pub path: PathBuf,
+    pub baseline: Coverage,
+    pub current: Coverage,
+    /// Number of the files below the directory.
+    pub files: usize,
+    /// Subdirectories ordered by path, empty at the depth limit.
+    pub children: Vec<Directory>,
+}
+
+impl Directory {
+    /// Tree of the files of the comparison. The root is the deepest directory common to all
+    /// files, the subdirectories deeper than `max_depth` levels below it are folded into
+    /// their ancestor.
+    pub fn tree(comparison: &Comparison, max_depth: Option<usize>) -> Self {
+        let root = common_dir(comparison.files.iter().map(|file| file.path.as_path()));
+        let mut tree = Directory {
+            path: root.clone(),
+            ..Directory::default()
+        };
+        for file in &comparison.files {
+            let dirs: Vec<Component> = file
+                .path
+                .parent()
+                .and_then(|dir| dir.strip_prefix(&root).ok())
+                .map(|dir| dir.components().collect())
+                .unwrap_or_default();
+            let dirs = match max_depth {
+                Some(depth) => &dirs[..dirs.len().min(depth)],
+                None => &dirs[..],
+            };
+            tree.insert(
+                dirs,
+                file.baseline.unwrap_or_default(),
+                file.current.unwrap_or_default(),
+            );
+        }
+        tree.sort();
+        tree
+    }
+
+    fn insert(&mut self, dirs: &[Component], baseline: Coverage, current: Coverage) {
+        self.baseline += baseline;
+        self.current += current;
+        self.files += 1;
+        if let Some((dir, rest)) = dirs.split_first() {
+            let path = self.path.join(dir);
+            let child = match self.children.iter().position(|child| child.path == path) {
+                Some(idx) => &mut self.children[idx],
+                None => {
+                    self.children.push(Directory {
+                        path,
+                        ..Directory::default()
+                    });
+                    self.children.last_mut().expect("just pushed")
+                }
+            };
+            child.insert(rest, baseline, current);
+        }
+    }
+
+    fn sort(&mut self) {
+        self.children.sort_by(|a, b| a.path.cmp(&b.path));
+        for child in &mut self.children {
+            child.sort();
+        }
+    }
+
+    /// Change of the line coverage in percentage points.
+    pub fn lines_delta(&self) -> f64 {
+        self.current.lines.percent().unwrap_or_default()
+            - self.baseline.lines.percent().unwrap_or_default()
+    }
+
+    /// The directory and all its subdirectories in the depth-first order, with their depth.
+    pub fn walk(&self) -> Vec<(usize, &Directory)> {
+        let mut out = Vec::new();
+        let mut stack = vec![(0, self)];
+        while let Some((depth, dir)) = stack.pop() {
+            out.push((depth, dir));
+            stack.extend(dir.children.iter().rev().map(|child| (depth + 1, child)));
+        }
+        out
+    }
+}
+
+/// Deepest directory containing all the paths.
+fn common_dir<'a, I: Iterator<Item = &'a Path>>(paths: I) -> PathBuf {
+    let mut common: Option<Vec<Component>> = None;
+    for path in paths {
+        let dir: Vec<Component> = path
+            .parent()
+            .map_or_else(Vec::new, |dir| dir.components().collect());
+        common = Some(match common {
+            None => dir,
+            Some(common) => common
+                .into_iter()
+                .zip(dir)
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    common.unwrap_or_default().into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Directory;
+    use crate::summary::{Comparison, Counter};
+    use lcov::{Reader, Report};
+    use std::path::Path;
+
+    #[test]
+    fn tree() {
+        let baseline = "\
+SF:/repo/src/a/x.c\nDA:1,1\nDA:2,1\nend_of_record
+SF:/repo/src/a/deep/y.c\nDA:1,1\nend_of_record
+SF:/repo/src/b/z.c\nDA:1,0\nend_of_record
+";
+        let current = "\
+SF:/repo/src/a/x.c\nDA:1,1\nDA:2,0\nend_of_record
+SF:/repo/src/a/deep/y.c\nDA:1,1\nend_of_record
+SF:/repo/src/b/z.c\nDA:1,1\nend_of_record
+";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&baseline, &current);
+
+        let tree = Directory::tree(&comparison, Some(1));
+        assert_eq!(tree.path, Path::new("/repo/src"));
+        assert_eq!(tree.files, 3);
+        let dirs: Vec<(usize, &Path, usize)> = tree
+            .walk()
+            .into_iter()
+            .map(|(depth, dir)| (depth, dir.path.as_path(), dir.files))
+            .collect();
+        assert_eq!(
+            dirs,
+            vec![
+                (0, Path::new("/repo/src"), 3),
+                (1, Path::new("/repo/src/a"), 2),
+                (1, Path::new("/repo/src/b"), 1),
+            ]
+        );
+        assert_eq!(tree.children[0].current.lines, Counter { found: 3, hit: 2 });
+        assert_eq!(tree.children[1].lines_delta(), 100.0);
+        assert_eq!(Directory::tree(&comparison, None).walk().len(), 4);
+    }
+}
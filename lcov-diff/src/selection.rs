@@ -0,0 +1,121 @@
+//! Greedy set cover: the small subset of the tests keeping the line coverage of all of them.
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use lcov::Report;
+
+use crate::json::Value;
+use crate::summary;
+
+/// Selected tests in the order of the selection, with the number of the lines each one added.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    pub selected: Vec<(String, usize)>,
+    /// Tests whose covered lines are all covered by the selected ones.
+    pub redundant: Vec<String>,
+    /// Lines covered by the tests.
+    pub lines: usize,
+}
+
+/// Lines covered by the report.
+fn covered_lines(report: &Report) -> BTreeSet<(PathBuf, u32)> {
+    summary::by_file(report)
+        .into_iter()
+        .flat_map(|(path, section)| {
+            section
+                .lines
+                .into_iter()
+                .filter(|(_, value)| value.count > 0)
+                .map(move |(key, _)| (path.clone(), key.line))
+        })
+        .collect()
+}
+
+/// Repeatedly selects the test covering the most lines not covered yet (the first by name on a
+/// tie) until no test adds any.
+pub fn minimal_set(tests: &BTreeMap<String, Report>) -> Selection {
+    let mut left: Vec<(&String, BTreeSet<(PathBuf, u32)>)> = tests
+        .iter()
+        .map(|(name, report)| (name, covered_lines(report)))
+        .collect();
+    let mut covered = BTreeSet::new();
+    let mut selection = Selection::default();
+    loop {
+        let best = left
+            .iter()
+            .enumerate()
+            .map(|(idx, (_, lines))| (idx, lines.difference(&covered).count()))
+            .filter(|&(_, new)| new > 0)
+            .fold(
+                None,
+                |best: Option<(usize, usize)>, (idx, new)| match best {
+                    Some((_, most)) if most >= new => best,
+                    _ => Some((idx, new)),
+                },
+            );
+        let (idx, new) = match best {
+            Some(best) => best,
+            None => break,
+        };
+        let (name, lines) = left.remove(idx);
+        covered.extend(lines);
+        selection.selected.push((name.clone(), new));
+    }
+    selection.redundant = left.into_iter().map(|(name, _)| name.clone()).collect();
+    selection.lines = covered.len();
+    selection
+}
+
+impl Selection {
+    /// `{"lines": .., "selected": [{"test": .., "new_lines": ..}], "redundant": [..]}`
+    pub fn to_json(&self) -> Value {
+        let selected = self
+            .selected
+            .iter()
+            .map(|(test, new)| {
+                Value::object(vec![
+                    ("test", Value::from(test.as_str())),
+                    ("new_lines", Value::from(*new)),
+                ])
+            })
+            .collect();
+        let redundant = self
+            .redundant
+            .iter()
+            .map(|test| Value::from(test.as_str()))
+            .collect();
+        Value::object(vec![
+            ("lines", Value::from(self.lines)),
+            ("selected", Value::Array(selected)),
+            ("redundant", Value::Array(redundant)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::minimal_set;
+    use crate::summary::by_test;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn greedy_cover() {
+        let input = "\
+TN:big\nSF:a.c\nDA:1,1\nDA:2,1\nDA:3,1\nDA:4,0\nend_of_record
+TN:part\nSF:a.c\nDA:2,1\nDA:3,1\nend_of_record
+TN:rest\nSF:a.c\nDA:4,1\nend_of_record\nTN:rest\nSF:b.c\nDA:1,1\nend_of_record
+TN:none\nSF:a.c\nDA:1,0\nend_of_record
+";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let selection = minimal_set(&by_test(&report));
+        assert_eq!(
+            selection.selected,
+            vec![("big".to_owned(), 3), ("rest".to_owned(), 2)]
+        );
+        assert_eq!(selection.redundant, vec!["none", "part"]);
+        assert_eq!(
+            selection.to_json().to_string(),
+            r#"{"lines":5,"selected":[{"test":"big","new_lines":3},{"test":"rest","new_lines":2}],"redundant":["none","part"]}"#
+        );
+    }
+}
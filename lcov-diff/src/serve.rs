@@ -0,0 +1,392 @@
+//! Minimal http service diffing uploaded reports against the configured baseline.
+//!
+//! `POST /diff` takes a report (any format of `import::parse_str`) and answers with the json
+//! summary of the diff, `GET /diff/<id>` and `GET /diff/<id>.json` serve the html and json
+//! results, `GET /` lists them. Only the latest `max_results` diffs are kept, in memory.
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use crate::import;
+use crate::json::Value;
+use crate::render::azure::summary_json;
+use crate::render::html::summary_page;
+use crate::render::number::Numbers;
+use crate::session::BaselineSession;
+use crate::summary::Comparison;
+
+/// Uploads bigger than this are rejected.
+const MAX_BODY: usize = 64 << 20;
+
+/// Request line and headers longer than this together are rejected.
+const MAX_HEADERS: u64 = 16 << 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    fn new(status: u16, content_type: &'static str, body: String) -> Self {
+        Response {
+            status,
+            content_type,
+            body,
+        }
+    }
+
+    fn text(status: u16, body: &str) -> Self {
+        Response::new(status, "text/plain; charset=utf-8", format!("{}\n", body))
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            201 => "Created",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            408 => "Request Timeout",
+            413 => "Payload Too Large",
+            431 => "Request Header Fields Too Large",
+            _ => "Internal Server Error",
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            self.reason(),
+            self.content_type,
+            self.body.len(),
+            self.body
+        )?;
+        writer.flush()
+    }
+}
+
+/// Reads the request line, the headers and the `Content-Length` body. The request line and
+/// the headers take at most `MAX_HEADERS` bytes.
+pub fn read_request<R: BufRead>(mut reader: R) -> Result<Request, Response> {
+    let bad_request = |e: io::Error| match e.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+            Response::text(408, "request timed out")
+        }
+        _ => Response::text(400, "malformed request"),
+    };
+    let mut left = MAX_HEADERS;
+    // A line without its end is cut by the limit
+    let mut read_line = |reader: &mut R, line: &mut String| {
+        line.clear();
+        let read = reader.take(left).read_line(line).map_err(bad_request)?;
+        left -= read as u64;
+        if left == 0 && !line.ends_with('\n') {
+            return Err(Response::text(431, "request headers are too large"));
+        }
+        Ok(())
+    };
+    let mut line = String::new();
+    read_line(&mut reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_owned(), path.to_owned()),
+        _ => return Err(Response::text(400, "malformed request")),
+    };
+    let mut length = 0;
+    loop {
+        read_line(&mut reader, &mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| Response::text(400, "invalid Content-Length"))?;
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Err(Response::text(413, "report is too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(bad_request)?;
+    Ok(Request { method, path, body })
+}
+
+#[derive(Debug, Clone)]
+pub struct Server {
+    session: BaselineSession,
+    max_results: usize,
+    /// Time limit of reading a request, and of every write of the response.
+    timeout: Duration,
+    next_id: u64,
+    results: VecDeque<(u64, Comparison)>,
+}
+
+impl Server {
+    pub fn new(session: BaselineSession, max_results: usize) -> Self {
+        Server {
+            session,
+            max_results,
+            timeout: Duration::from_secs(30),
+            next_id: 1,
+            results: VecDeque::new(),
+        }
+    }
+
+    /// Connections are served one by one, a client which doesn't send its whole request within
+    /// the timeout is dropped instead of blocking the others. 30 seconds by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Serves the connections one by one until the listener fails.
+    pub fn run(&mut self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            stream.set_write_timeout(Some(self.timeout))?;
+            let deadline = Deadline {
+                stream: &stream,
+                at: Instant::now() + self.timeout,
+            };
+            let response = match read_request(BufReader::new(deadline)) {
+                Ok(request) => self.handle(&request),
+                Err(response) => response,
+            };
+            // The client may be gone already, that doesn't stop the server
+            let _ = response.write_to(&stream);
+        }
+        Ok(())
+    }
+
+    pub fn handle(&mut self, request: &Request) -> Response {
+        let path = request.path.split('?').next().unwrap_or_default();
+        match (request.method.as_str(), path) {
+            ("GET", "/") => Response::new(200, "text/html; charset=utf-8", self.index()),
+            ("POST", "/diff") => self.upload(&request.body),
+            ("GET", path) => match path.strip_prefix("/diff/").and_then(|id| self.find(id)) {
+                Some((comparison, true)) => Response::new(
+                    200,
+                    "application/json",
+                    summary_json(comparison, &Numbers::default()).pretty(),
+                ),
+                Some((comparison, false)) => Response::new(
+                    200,
+                    "text/html; charset=utf-8",
+                    summary_page(comparison, &Numbers::default()),
+                ),
+                None => Response::text(404, "not found"),
+            },
+            (_, "/") | (_, "/diff") => Response::text(405, "method not allowed"),
+            _ => Response::text(404, "not found"),
+        }
+    }
+
+    fn upload(&mut self, body: &[u8]) -> Response {
+        let current = match std::str::from_utf8(body)
+            .map_err(|e| e.to_string())
+            .and_then(|input| import::parse_str(input).map_err(|e| e.to_string()))
+        {
+            Ok(report) => report,
+            Err(e) => return Response::text(400, &e),
+        };
+        let comparison = self.session.comparison(&current);
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut json = summary_json(&comparison, &Numbers::default());
+        if let Value::Object(fields) = &mut json {
+            fields.insert(0, ("id".to_owned(), Value::from(id)));
+            fields.insert(1, ("url".to_owned(), Value::from(format!("/diff/{}", id))));
+        }
+        self.results.push_back((id, comparison));
+        while self.results.len() > self.max_results {
+            self.results.pop_front();
+        }
+        Response::new(201, "application/json", json.pretty())
+    }
+
+    /// Comparison of the `<id>` or `<id>.json` path, and whether json is requested.
+    fn find(&self, name: &str) -> Option<(&Comparison, bool)> {
+        let (id, json) = match name.strip_suffix(".json") {
+            Some(id) => (id, true),
+            None => (name, false),
+        };
+        let id: u64 = id.parse().ok()?;
+        self.results
+            .iter()
+            .find(|(result_id, _)| *result_id == id)
+            .map(|(_, comparison)| (comparison, json))
+    }
+
+    fn index(&self) -> String {
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html><head><title>lcov-diff</title></head><body>\n<h1>Coverage diffs</h1>\n<ul>\n",
+        );
+        for (id, comparison) in self.results.iter().rev() {
+            let percent = comparison
+                .current
+                .lines
+                .percent()
+                .map_or_else(|| "-".to_owned(), |p| format!("{:.2}%", p));
+            let _ = writeln!(
+                out,
+                "<li><a href=\"/diff/{id}\">#{id}</a> {} ({:+.2}%) <a href=\"/diff/{id}.json\">json</a></li>",
+                percent,
+                comparison.lines_delta(),
+                id = id
+            );
+        }
+        out.push_str("</ul>\n</body></html>\n");
+        out
+    }
+}
+
+/// Reads of the stream which time out at the deadline rather than after every idle timeout.
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    at: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.at.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_request, Server};
+    use crate::session::BaselineSession;
+    use lcov::{Reader, Report};
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn upload_and_fetch() {
+        let baseline = "SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let mut server = Server::new(BaselineSession::new(baseline), 1);
+
+        let upload = "POST /diff HTTP/1.1\r\nHost: x\r\ncontent-length: 35\r\n\r\nSF:a.c\nDA:1,1\nDA:2,1\nend_of_record\n";
+        let request = read_request(upload.as_bytes()).unwrap();
+        let response = server.handle(&request);
+        assert_eq!(response.status, 201);
+        assert!(response.body.contains("\"url\": \"/diff/1\""));
+
+        let get = |path: &str| super::Request {
+            method: "GET".to_owned(),
+            path: path.to_owned(),
+            body: Vec::new(),
+        };
+        assert!(server
+            .handle(&get("/diff/1.json"))
+            .body
+            .contains("\"lines_delta\": 50"));
+        assert!(server
+            .handle(&get("/diff/1"))
+            .body
+            .starts_with("<!DOCTYPE html>"));
+        assert!(server
+            .handle(&get("/"))
+            .body
+            .contains("#1</a> 100.00% (+50.00%)"));
+
+        server.handle(&request);
+        assert_eq!(server.handle(&get("/diff/1")).status, 404);
+        assert_eq!(server.handle(&get("/diff/2")).status, 200);
+    }
+
+    #[test]
+    fn idle_client_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut server = Server::new(BaselineSession::new(Report::new()), 1)
+            .with_timeout(Duration::from_millis(100));
+        thread::spawn(move || server.run(listener));
+
+        // Never sends the request, the next client is served anyway
+        let _idle = TcpStream::connect(addr).unwrap();
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+    }
+
+    #[test]
+    fn trickling_client_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut server = Server::new(BaselineSession::new(Report::new()), 1)
+            .with_timeout(Duration::from_millis(300));
+        thread::spawn(move || server.run(listener));
+
+        // Every header byte comes before the timeout of a read, not of the request
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n").unwrap();
+        let started = Instant::now();
+        let mut response = Vec::new();
+        let mut buf = [0; 1024];
+        while started.elapsed() < Duration::from_secs(5) {
+            match client.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => response.extend_from_slice(&buf[..n]),
+                Err(_) if response.is_empty() => client.write_all(b"X").unwrap(),
+                Err(_) => break,
+            }
+        }
+        let response = String::from_utf8(response).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(response.starts_with("HTTP/1.1 408 Request Timeout"));
+    }
+
+    #[test]
+    fn headers_limit() {
+        let request = format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(20 << 10));
+        assert_eq!(read_request(request.as_bytes()).unwrap_err().status, 431);
+        let request = format!("GET {} HTTP/1.1\r\n\r\n", "a".repeat(20 << 10));
+        assert_eq!(read_request(request.as_bytes()).unwrap_err().status, 431);
+        let request = format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(8 << 10));
+        assert!(read_request(request.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn deeply_nested_upload() {
+        let mut server = Server::new(BaselineSession::new(Report::new()), 1);
+        let body = format!("{{\"a\":{}", "[".repeat(1_000_000));
+        let response = server.handle(&super::Request {
+            method: "POST".to_owned(),
+            path: "/diff".to_owned(),
+            body: body.into_bytes(),
+        });
+        assert_eq!(response.status, 400);
+    }
+}
@@ -0,0 +1,80 @@
+//! A baseline kept in memory to diff many reports against, e.g. the shards of a build against
+//! the baseline of the build. The baseline is merged by file once, not for every comparison.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use lcov::report::section::Value as SectionValue;
+use lcov::report::MergeError;
+use lcov::Report;
+
+use crate::import::{self, ImportError, LoadOptions};
+use crate::summary::{self, Comparison};
+use crate::{diff_reports_with, DiffOptions};
+
+#[derive(Debug, Clone)]
+pub struct BaselineSession {
+    baseline: Report,
+    files: BTreeMap<PathBuf, SectionValue>,
+    options: DiffOptions,
+}
+
+impl BaselineSession {
+    pub fn new(baseline: Report) -> Self {
+        BaselineSession {
+            files: summary::by_file(&baseline),
+            baseline,
+            options: DiffOptions::default(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P, options: &LoadOptions) -> Result<Self, ImportError> {
+        Ok(BaselineSession::new(
+            import::load_file_with(path, options)?.report,
+        ))
+    }
+
+    pub fn with_options(mut self, options: DiffOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn baseline(&self) -> &Report {
+        &self.baseline
+    }
+
+    /// `diff_reports` of the current report against the baseline.
+    pub fn diff(&self, current: &Report) -> Result<Report, MergeError> {
+        diff_reports_with(current, &self.baseline, &self.options)
+    }
+
+    /// `Comparison::new` of the baseline and the current report.
+    pub fn comparison(&self, current: &Report) -> Comparison {
+        Comparison::of_files(&self.files, &summary::by_file(current))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BaselineSession;
+    use crate::diff_reports;
+    use crate::summary::Comparison;
+    use crate::testing::{assert_reports_equal, Generator};
+
+    #[test]
+    fn session() {
+        let mut generator = Generator::new(7);
+        let baseline = generator.report();
+        let session = BaselineSession::new(baseline.clone());
+        for _ in 0..20 {
+            let current = generator.report();
+            assert_reports_equal(
+                &session.diff(&current).unwrap(),
+                &diff_reports(&current, &baseline).unwrap(),
+            );
+            assert_eq!(
+                session.comparison(&current),
+                Comparison::new(&baseline, &current)
+            );
+        }
+    }
+}
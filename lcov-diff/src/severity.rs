@@ -0,0 +1,324 @@
+//! Findings of the line and branch level comparison and the severities they are reported with.
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use lcov::Report;
+
+use crate::render::relative_path;
+use crate::snippet::Snippet;
+use crate::summary::{self, Comparison};
+
+/// Kind of a finding.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Kind {
+    /// Line covered in the baseline and not covered now.
+    LostLine,
+    /// Line instrumented only in the new report and never executed.
+    UncoveredNewLine,
+    /// Branch taken in the baseline and not taken now.
+    LostBranch,
+    /// File of the baseline missing in the new report.
+    DroppedFile,
+}
+
+impl Kind {
+    pub const VARIANTS: &'static [&'static str] = &[
+        "lost-line",
+        "uncovered-new-line",
+        "lost-branch",
+        "dropped-file",
+    ];
+
+    /// Rule id of the annotations.
+    pub fn id(self) -> &'static str {
+        match self {
+            Kind::LostLine => "lost-line",
+            Kind::UncoveredNewLine => "uncovered-new-line",
+            Kind::LostBranch => "lost-branch",
+            Kind::DroppedFile => "dropped-file",
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            Kind::LostLine => "Line no longer covered by tests",
+            Kind::UncoveredNewLine => "Line not covered by tests",
+            Kind::LostBranch => "Branch no longer taken by tests",
+            Kind::DroppedFile => "File is missing in the new report",
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lost-line" => Ok(Kind::LostLine),
+            "uncovered-new-line" => Ok(Kind::UncoveredNewLine),
+            "lost-branch" => Ok(Kind::LostBranch),
+            "dropped-file" => Ok(Kind::DroppedFile),
+            _ => Err(format!("unknown finding: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// Severity of a finding, `Off` ones aren't reported.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Severity {
+    Off,
+    Info,
+    Warning,
+    /// Fails the check.
+    Error,
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Severity::Off),
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(format!("unknown severity: {}", s)),
+        }
+    }
+}
+
+/// Severity of every kind of finding.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Severities {
+    pub lost_line: Severity,
+    pub uncovered_new_line: Severity,
+    pub lost_branch: Severity,
+    pub dropped_file: Severity,
+}
+
+impl Default for Severities {
+    fn default() -> Self {
+        Severities {
+            lost_line: Severity::Off,
+            uncovered_new_line: Severity::Warning,
+            lost_branch: Severity::Off,
+            dropped_file: Severity::Off,
+        }
+    }
+}
+
+impl Severities {
+    pub fn of(&self, kind: Kind) -> Severity {
+        match kind {
+            Kind::LostLine => self.lost_line,
+            Kind::UncoveredNewLine => self.uncovered_new_line,
+            Kind::LostBranch => self.lost_branch,
+            Kind::DroppedFile => self.dropped_file,
+        }
+    }
+
+    pub fn set(&mut self, kind: Kind, severity: Severity) {
+        match kind {
+            Kind::LostLine => self.lost_line = severity,
+            Kind::UncoveredNewLine => self.uncovered_new_line = severity,
+            Kind::LostBranch => self.lost_branch = severity,
+            Kind::DroppedFile => self.dropped_file = severity,
+        }
+    }
+}
+
+/// Parses `kind=severity` mapping entry.
+pub fn parse_entry(entry: &str) -> Result<(Kind, Severity), String> {
+    let (kind, severity) = entry
+        .split_once('=')
+        .ok_or_else(|| format!("`finding=severity` expected: {}", entry))?;
+    Ok((kind.parse()?, severity.parse()?))
+}
+
+/// Finding located in a file, at a line unless the whole file is concerned.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Finding {
+    pub kind: Kind,
+    pub severity: Severity,
+    pub path: PathBuf,
+    pub line: Option<u32>,
+    /// Last line of the range of the coalesced findings, `None` for a single line.
+    pub end_line: Option<u32>,
+    /// Source around the line, set by `Sources::annotate`.
+    pub snippet: Option<Snippet>,
+}
+
+/// Findings of the files of the comparison (the uncovered new lines are taken from it, so its
+/// patch settings apply) with a severity other than `Off`, ordered by path and line.
+pub fn findings(
+    baseline: &Report,
+    current: &Report,
+    comparison: &Comparison,
+    severities: &Severities,
+) -> Vec<Finding> {
+    let baseline_files = summary::by_file(baseline);
+    let current_files = summary::by_file(current);
+    let mut findings = Vec::new();
+    let mut push = |kind: Kind, path: &PathBuf, line: Option<u32>| {
+        let severity = severities.of(kind);
+        if severity != Severity::Off {
+            findings.push(Finding {
+                kind,
+                severity,
+                path: path.clone(),
+                line,
+                end_line: None,
+                snippet: None,
+            });
+        }
+    };
+    for (path, line) in &comparison.uncovered_new_lines {
+        push(Kind::UncoveredNewLine, path, Some(*line));
+    }
+    for file in &comparison.files {
+        let (old, new) = match (
+            baseline_files.get(&file.path),
+            current_files.get(&file.path),
+        ) {
+            (Some(_), None) => {
+                push(Kind::DroppedFile, &file.path, None);
+                continue;
+            }
+            (Some(old), Some(new)) => (old, new),
+            (None, _) => continue,
+        };
+        for (key, value) in &new.lines {
+            let was_hit = old.lines.get(key).is_some_and(|old| old.count > 0);
+            if was_hit && value.count == 0 {
+                push(Kind::LostLine, &file.path, Some(key.line));
+            }
+        }
+        for (key, value) in &new.branches {
+            let was_taken = old
+                .branches
+                .get(key)
+                .is_some_and(|old| old.taken.unwrap_or(0) > 0);
+            if was_taken && value.taken.unwrap_or(0) == 0 {
+                push(Kind::LostBranch, &file.path, Some(key.line));
+            }
+        }
+    }
+    findings.sort_by(|a, b| (&a.path, a.line, a.kind).cmp(&(&b.path, b.line, b.kind)));
+    findings
+}
+
+/// Merges the findings of consecutive lines of the same kind, severity and file into ranges,
+/// ordered by path and line.
+pub fn coalesce(mut findings: Vec<Finding>) -> Vec<Finding> {
+    findings.sort_by(|a, b| (&a.path, a.kind, a.line).cmp(&(&b.path, b.kind, b.line)));
+    let mut ranges: Vec<Finding> = Vec::with_capacity(findings.len());
+    for finding in findings {
+        if let (Some(last), Some(line)) = (ranges.last_mut(), finding.line) {
+            let last_line = last.end_line.or(last.line);
+            let same = last.kind == finding.kind
+                && last.severity == finding.severity
+                && last.path == finding.path;
+            if same && last_line.is_some_and(|last_line| last_line + 1 == line) {
+                last.end_line = Some(line);
+                continue;
+            }
+        }
+        ranges.push(finding);
+    }
+    ranges.sort_by(|a, b| (&a.path, a.line, a.kind).cmp(&(&b.path, b.line, b.kind)));
+    ranges
+}
+
+/// Makes the paths of the findings relative to the root of the repository, the annotations of
+/// GitHub and code scanning are only attached to the repository paths.
+pub fn relative_to(findings: &mut [Finding], root: &Path) {
+    for finding in findings {
+        finding.path = relative_path(root, &finding.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{coalesce, findings, parse_entry, relative_to, Kind, Severities, Severity};
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn findings_by_severity() {
+        let baseline = "\
+SF:a.c\nDA:1,1\nDA:2,1\nBRDA:2,0,0,3\nBRDA:2,0,1,1\nend_of_record
+SF:b.c\nDA:1,1\nend_of_record
+";
+        let current = "SF:a.c\nDA:1,1\nDA:2,0\nDA:3,0\nBRDA:2,0,0,0\nBRDA:2,0,1,1\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&baseline, &current);
+
+        let kinds = |severities: &Severities| -> Vec<(Kind, Option<u32>)> {
+            findings(&baseline, &current, &comparison, severities)
+                .into_iter()
+                .map(|finding| (finding.kind, finding.line))
+                .collect()
+        };
+        assert_eq!(
+            kinds(&Severities::default()),
+            vec![(Kind::UncoveredNewLine, Some(3))]
+        );
+        let mut severities = Severities {
+            lost_line: Severity::Error,
+            lost_branch: Severity::Info,
+            dropped_file: Severity::Warning,
+            ..Severities::default()
+        };
+        assert_eq!(
+            kinds(&severities),
+            vec![
+                (Kind::LostLine, Some(2)),
+                (Kind::LostBranch, Some(2)),
+                (Kind::UncoveredNewLine, Some(3)),
+                (Kind::DroppedFile, None),
+            ]
+        );
+        let ranges = coalesce(findings(&baseline, &current, &comparison, &severities));
+        assert_eq!(ranges.len(), 4);
+        let current = "SF:a.c\nDA:1,0\nDA:2,0\nDA:3,0\nDA:5,0\nend_of_record\n";
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&Report::new(), &current);
+        let ranges = coalesce(findings(&Report::new(), &current, &comparison, &severities));
+        let lines: Vec<(Option<u32>, Option<u32>)> =
+            ranges.iter().map(|f| (f.line, f.end_line)).collect();
+        assert_eq!(lines, vec![(Some(1), Some(3)), (Some(5), None)]);
+
+        let (kind, severity) = parse_entry("uncovered-new-line=off").unwrap();
+        severities.set(kind, severity);
+        assert_eq!(severities.uncovered_new_line, Severity::Off);
+        assert!(parse_entry("lost-line=fatal").is_err());
+    }
+
+    #[test]
+    fn repository_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/a.c"), "int a;\n").unwrap();
+        let current = "SF:/home/runner/work/r/r/src/a.c\nDA:1,0\nend_of_record\n";
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&Report::new(), &current);
+        let mut findings = findings(
+            &Report::new(),
+            &current,
+            &comparison,
+            &Severities::default(),
+        );
+        relative_to(&mut findings, dir.path());
+        assert_eq!(findings[0].path, PathBuf::from("src/a.c"));
+    }
+}
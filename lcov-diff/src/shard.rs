@@ -0,0 +1,103 @@
+//! Sharded diffs of the monorepo reports: both reports are partitioned by the source paths
+//! (by default by the top-level directory), the partitions are diffed independently, in this
+//! process or in a process per shard (`--shard`), and the diffs are merged. The diff of a file
+//! only depends on the sections of that file, so the merged diff is the diff of the reports.
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use lcov::report::MergeError;
+use lcov::Report;
+
+use crate::diff_reports;
+
+/// Shard name of the files directly in the root.
+pub const ROOT: &str = ".";
+
+/// Partitioner by the first directory of the paths relative to `root`, the paths outside of it
+/// are taken as relative.
+pub fn top_level(root: &Path) -> impl Fn(&Path) -> String {
+    let root: PathBuf = root.to_owned();
+    move |path: &Path| {
+        let relative = path.strip_prefix(&root).unwrap_or(path);
+        let mut components = relative
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)));
+        match (components.next(), components.next()) {
+            (Some(dir), Some(_)) => dir.as_os_str().to_string_lossy().into_owned(),
+            _ => ROOT.to_owned(),
+        }
+    }
+}
+
+/// Sections of the report by the shard of their source file.
+pub fn partition<F: Fn(&Path) -> String>(
+    report: &Report,
+    partitioner: F,
+) -> BTreeMap<String, Report> {
+    let mut shards: BTreeMap<String, Report> = BTreeMap::new();
+    for (key, section) in &report.sections {
+        shards
+            .entry(partitioner(&key.source_file))
+            .or_default()
+            .sections
+            .insert(key.clone(), section.clone());
+    }
+    shards
+}
+
+/// Merges the diffs of the shards, their sections don't overlap.
+pub fn merge<I: IntoIterator<Item = Report>>(shards: I) -> Result<Report, MergeError> {
+    let mut merged = Report::new();
+    for shard in shards {
+        merged.merge(shard)?;
+    }
+    Ok(merged)
+}
+
+/// `diff_reports` shard by shard.
+pub fn diff_sharded<F: Fn(&Path) -> String>(
+    first: &Report,
+    second: &Report,
+    partitioner: F,
+) -> Result<Report, MergeError> {
+    let mut baselines = partition(second, &partitioner);
+    let diffs = partition(first, &partitioner)
+        .into_iter()
+        .map(|(name, shard)| {
+            let baseline = baselines.remove(&name).unwrap_or_default();
+            diff_reports(&shard, &baseline)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    merge(diffs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_sharded, partition, top_level};
+    use crate::builder::ReportBuilder;
+    use crate::diff_reports;
+    use crate::testing::{assert_reports_equal, Generator};
+    use std::path::Path;
+
+    #[test]
+    fn shards() {
+        let report = ReportBuilder::file("/repo/app/src/a.c")
+            .line(1, 1)
+            .next_file("/repo/lib/b.c")
+            .line(1, 1)
+            .next_file("/repo/build.c")
+            .line(1, 1)
+            .build();
+        let shards = partition(&report, top_level(Path::new("/repo")));
+        assert_eq!(shards.keys().collect::<Vec<_>>(), vec![".", "app", "lib"]);
+
+        let by_file = |path: &Path| path.display().to_string();
+        for seed in 0..50 {
+            let (first, second) = Generator::new(seed).pair();
+            assert_reports_equal(
+                &diff_sharded(&first, &second, by_file).unwrap(),
+                &diff_reports(&first, &second).unwrap(),
+            );
+        }
+    }
+}
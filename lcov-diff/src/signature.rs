@@ -0,0 +1,157 @@
+//! Signature verification of the baselines before they're trusted, with minisign (ed25519)
+//! signatures. The signatures are checked by `minisign`, which has to be in `PATH`.
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Public key of the signer, the key file or the base64 key itself.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PublicKey {
+    File(PathBuf),
+    Key(String),
+}
+
+impl PublicKey {
+    /// The key file if there is one at `value`, the key otherwise.
+    pub fn parse(value: &str) -> Self {
+        if Path::new(value).is_file() {
+            PublicKey::File(PathBuf::from(value))
+        } else {
+            PublicKey::Key(value.trim().to_owned())
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SignatureError {
+    Io(io::Error),
+    /// The signature file doesn't exist.
+    Missing(PathBuf),
+    /// The signature doesn't match the file or the key, with the message of `minisign`.
+    Invalid(String),
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SignatureError::Io(e) => write!(f, "failed to run minisign: {}", e),
+            SignatureError::Missing(path) => write!(f, "signature {:?} doesn't exist", path),
+            SignatureError::Invalid(message) => write!(f, "invalid signature: {}", message),
+        }
+    }
+}
+
+impl Error for SignatureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SignatureError::Io(e) => Some(e),
+            SignatureError::Missing(_) | SignatureError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SignatureError {
+    fn from(e: io::Error) -> Self {
+        SignatureError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Verifier {
+    /// `minisign` by default.
+    pub program: PathBuf,
+    pub public_key: PublicKey,
+}
+
+impl Verifier {
+    pub fn new(public_key: PublicKey) -> Self {
+        Verifier {
+            program: PathBuf::from("minisign"),
+            public_key,
+        }
+    }
+
+    /// `<path>.minisig`, where `minisign -S` writes the signature of the file.
+    pub fn default_signature(path: &Path) -> PathBuf {
+        let mut signature = path.as_os_str().to_owned();
+        signature.push(".minisig");
+        PathBuf::from(signature)
+    }
+
+    /// Checks the signature of the file.
+    pub fn verify(&self, path: &Path, signature: &Path) -> Result<(), SignatureError> {
+        if !signature.is_file() {
+            return Err(SignatureError::Missing(signature.to_owned()));
+        }
+        let mut command = Command::new(&self.program);
+        command
+            .arg("-V")
+            .arg("-q")
+            .arg("-m")
+            .arg(path)
+            .arg("-x")
+            .arg(signature);
+        match &self.public_key {
+            PublicKey::File(file) => command.arg("-p").arg(file),
+            PublicKey::Key(key) => command.arg("-P").arg(key),
+        };
+        let output = command.output()?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            let message = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+            Err(SignatureError::Invalid(if message.is_empty() {
+                output.status.to_string()
+            } else {
+                message
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PublicKey, SignatureError, Verifier};
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn verify_with_minisign() {
+        let dir = tempfile::tempdir().unwrap();
+        // Accepts the signature made with the key `good`
+        let program = dir.path().join("minisign");
+        fs::write(
+            &program,
+            "#!/bin/sh\n[ \"$8\" = good ] && [ \"$(cat \"$6\")\" = signed ] && exit 0\n\
+             echo 'Signature verification failed' >&2\nexit 1\n",
+        )
+        .unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        let baseline = dir.path().join("base.info");
+        fs::write(&baseline, "TN:\n").unwrap();
+        let signature = Verifier::default_signature(&baseline);
+        assert_eq!(signature, dir.path().join("base.info.minisig"));
+
+        let verifier = |key: &str| Verifier {
+            program: program.clone(),
+            public_key: PublicKey::parse(key),
+        };
+        assert!(matches!(
+            verifier("good").verify(&baseline, &signature),
+            Err(SignatureError::Missing(_))
+        ));
+        fs::write(&signature, "signed").unwrap();
+        verifier("good").verify(&baseline, &signature).unwrap();
+        let error = verifier("other").verify(&baseline, &signature).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid signature: Signature verification failed"
+        );
+        assert_eq!(
+            PublicKey::parse(program.to_str().unwrap()),
+            PublicKey::File(program.clone())
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Similarity of the coverage of two reports, to find duplicated test suites and to cluster the
+//! tests by what they cover.
+use std::cmp::Ordering;
+use std::collections::btree_map;
+use std::iter::Peekable;
+
+use lcov::report::section::branch::Value as BranchValue;
+use lcov::report::section::Value as SectionValue;
+use lcov::Report;
+
+use crate::summary;
+
+/// Similarity of the sets of the covered lines and of the taken branches, 1.0 for two empty sets.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Similarity {
+    /// Common covered lines over the lines covered by either report.
+    pub jaccard_lines: f64,
+    /// Common taken branches over the branches taken by either report.
+    pub jaccard_branches: f64,
+    /// Common covered lines over the covered lines of the smaller report, 1.0 if one of the
+    /// reports covers a subset of the other.
+    pub overlap_coefficient: f64,
+}
+
+/// Sizes of the intersection and the sets.
+#[derive(Debug, Copy, Clone, Default)]
+struct Overlap {
+    both: usize,
+    only_a: usize,
+    only_b: usize,
+}
+
+impl Overlap {
+    fn jaccard(&self) -> f64 {
+        ratio(self.both, self.both + self.only_a + self.only_b)
+    }
+
+    fn coefficient(&self) -> f64 {
+        ratio(self.both, self.both + self.only_a.min(self.only_b))
+    }
+
+    /// Counts one element, present in `a` and/or `b`.
+    fn count(&mut self, a: bool, b: bool) {
+        match (a, b) {
+            (true, true) => self.both += 1,
+            (true, false) => self.only_a += 1,
+            (false, true) => self.only_b += 1,
+            (false, false) => {}
+        }
+    }
+}
+
+fn ratio(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        1.0
+    } else {
+        part as f64 / total as f64
+    }
+}
+
+pub fn similarity(a: &Report, b: &Report) -> Similarity {
+    let (a, b) = (summary::by_file(a), summary::by_file(b));
+    let mut lines = Overlap::default();
+    let mut branches = Overlap::default();
+    let empty = SectionValue::default();
+    for (a, b) in merge(a.iter(), b.iter()) {
+        let a = a.unwrap_or(&empty);
+        let b = b.unwrap_or(&empty);
+        for (a, b) in merge(a.lines.iter(), b.lines.iter()) {
+            lines.count(
+                a.is_some_and(|a| a.count > 0),
+                b.is_some_and(|b| b.count > 0),
+            );
+        }
+        let taken =
+            |value: Option<&BranchValue>| value.and_then(|value| value.taken).unwrap_or(0) > 0;
+        for (a, b) in merge(a.branches.iter(), b.branches.iter()) {
+            branches.count(taken(a), taken(b));
+        }
+    }
+    Similarity {
+        jaccard_lines: lines.jaccard(),
+        jaccard_branches: branches.jaccard(),
+        overlap_coefficient: lines.coefficient(),
+    }
+}
+
+/// Values of the keys of either sorted map, paired by the key.
+fn merge<'a, K: Ord + 'a, V: 'a>(
+    a: btree_map::Iter<'a, K, V>,
+    b: btree_map::Iter<'a, K, V>,
+) -> impl Iterator<Item = (Option<&'a V>, Option<&'a V>)> {
+    Merge {
+        a: a.peekable(),
+        b: b.peekable(),
+    }
+}
+
+struct Merge<I: Iterator> {
+    a: Peekable<I>,
+    b: Peekable<I>,
+}
+
+impl<'a, K: Ord + 'a, V: 'a, I: Iterator<Item = (&'a K, &'a V)>> Iterator for Merge<I> {
+    type Item = (Option<&'a V>, Option<&'a V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let order = match (self.a.peek(), self.b.peek()) {
+            (None, None) => return None,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((a, _)), Some((b, _))) => a.cmp(b),
+        };
+        Some(match order {
+            Ordering::Less => (self.a.next().map(|(_, v)| v), None),
+            Ordering::Greater => (None, self.b.next().map(|(_, v)| v)),
+            Ordering::Equal => (self.a.next().map(|(_, v)| v), self.b.next().map(|(_, v)| v)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::similarity;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn similarity_of_reports() {
+        let a = "SF:a.c\nDA:1,1\nDA:2,1\nDA:3,0\nBRDA:1,0,0,1\nBRDA:1,0,1,0\nend_of_record\n";
+        let b = "SF:a.c\nDA:1,4\nDA:2,0\nDA:3,0\nBRDA:1,0,0,2\nend_of_record\nSF:b.c\nDA:1,1\nend_of_record\n";
+        let a = Report::from_reader(Reader::new(a.as_bytes())).unwrap();
+        let b = Report::from_reader(Reader::new(b.as_bytes())).unwrap();
+
+        let ab = similarity(&a, &b);
+        // a covers {a:1, a:2}, b covers {a:1, b:1}
+        assert_eq!(ab.jaccard_lines, 1.0 / 3.0);
+        assert_eq!(ab.overlap_coefficient, 0.5);
+        assert_eq!(ab.jaccard_branches, 1.0);
+        assert_eq!(similarity(&a, &a).jaccard_lines, 1.0);
+        assert_eq!(
+            similarity(&Report::new(), &Report::new()).jaccard_lines,
+            1.0
+        );
+    }
+}
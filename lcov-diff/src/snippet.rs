@@ -0,0 +1,130 @@
+//! Source snippets around the uncovered lines, so the reviewers see the untested code in place.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::render::resolve_source;
+use crate::severity::Finding;
+
+/// Lines of the source with the line numbers.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Snippet {
+    pub start_line: u32,
+    pub end_line: u32,
+    pub text: String,
+}
+
+impl Snippet {
+    /// Text with the line numbers, the lines of `marked` (inclusive range) are marked with `!`.
+    pub fn numbered(&self, marked: (u32, u32)) -> String {
+        let width = self.end_line.to_string().len();
+        let mut out = String::new();
+        for (number, line) in (self.start_line..).zip(self.text.lines()) {
+            let mark = if (marked.0..=marked.1).contains(&number) {
+                '!'
+            } else {
+                ' '
+            };
+            out.push_str(&format!(
+                "{:>width$} {} {}\n",
+                number,
+                mark,
+                line,
+                width = width
+            ));
+        }
+        out
+    }
+}
+
+/// Reads the sources of the report paths in the root, every file once.
+#[derive(Debug, Clone)]
+pub struct Sources {
+    root: PathBuf,
+    /// Lines around the snippet line.
+    context: usize,
+    files: BTreeMap<PathBuf, Option<Vec<String>>>,
+}
+
+impl Sources {
+    pub fn new(root: &Path, context: usize) -> Self {
+        Sources {
+            root: root.to_owned(),
+            context,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the snippets of the findings with a line.
+    pub fn annotate(&mut self, findings: &mut [Finding]) {
+        for finding in findings {
+            if let Some(line) = finding.line {
+                let end_line = finding.end_line.unwrap_or(line);
+                finding.snippet = self.snippet(&finding.path, line, end_line);
+            }
+        }
+    }
+
+    /// Lines `line..=end_line` with the context lines around, `None` if the source can't be
+    /// read or is shorter.
+    pub fn snippet(&mut self, path: &Path, line: u32, end_line: u32) -> Option<Snippet> {
+        let root = &self.root;
+        let lines = self
+            .files
+            .entry(path.to_owned())
+            .or_insert_with(|| {
+                let source = fs::read_to_string(resolve_source(root, path)?).ok()?;
+                Some(source.lines().map(str::to_owned).collect())
+            })
+            .as_ref()?;
+        if line == 0 || line as usize > lines.len() {
+            return None;
+        }
+        let start = (line as usize - 1).saturating_sub(self.context);
+        let end = (end_line as usize + self.context).min(lines.len());
+        Some(Snippet {
+            start_line: start as u32 + 1,
+            end_line: end as u32,
+            text: lines[start..end].join("\n") + "\n",
+        })
+    }
+}
+
+/// Ranges of the consecutive lines of the sorted lines.
+pub fn ranges(lines: &[u32]) -> Vec<(u32, u32)> {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for &line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            _ => ranges.push((line, line)),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ranges, Sources};
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn snippet() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("a.c"),
+            "int f() {\n  if (x)\n    return 1;\n  return 0;\n}\n",
+        )
+        .unwrap();
+        let mut sources = Sources::new(dir.path(), 1);
+        let snippet = sources.snippet(Path::new("a.c"), 3, 3).unwrap();
+        assert_eq!((snippet.start_line, snippet.end_line), (2, 4));
+        assert_eq!(
+            snippet.numbered((3, 3)),
+            "2     if (x)\n3 !     return 1;\n4     return 0;\n"
+        );
+        assert!(sources.snippet(Path::new("a.c"), 9, 9).is_none());
+        assert!(sources.snippet(Path::new("missing.c"), 1, 1).is_none());
+        assert_eq!(ranges(&[1, 2, 3, 7]), vec![(1, 3), (7, 7)]);
+    }
+}
@@ -0,0 +1,297 @@
+//! Remapping of generated JavaScript coverage to the original sources with source maps (v3).
+//!
+//! Every generated line is mapped to the original line of its first mapping segment, the lines
+//! without mappings (bundler glue code) are dropped. Lines, functions and branches mapped to the
+//! same original line are combined by their biggest count.
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use lcov::report::section::{Key as SectionKey, Value as SectionValue};
+use lcov::Report;
+
+use crate::json::{self, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceMap {
+    /// Original sources, already joined to `sourceRoot`.
+    pub sources: Vec<PathBuf>,
+    /// Original source index and 1-based line by 0-based generated line.
+    lines: Vec<Option<(usize, u32)>>,
+}
+
+impl SourceMap {
+    /// Parses the source map, the relative sources are resolved against `dir`.
+    pub fn parse(input: &str, dir: &Path) -> Option<Self> {
+        let value = json::parse(input).ok()?;
+        if value.get("version").and_then(Value::as_u64) != Some(3) {
+            return None;
+        }
+        let root = value
+            .get("sourceRoot")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let sources = value
+            .get("sources")?
+            .as_array()?
+            .iter()
+            .map(|source| {
+                let source = strip_scheme(source.as_str()?);
+                Some(normalize(&dir.join(root).join(source)))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let lines = decode_mappings(value.get("mappings")?.as_str()?)?;
+        Some(SourceMap { sources, lines })
+    }
+
+    /// Loads the map of the generated file from its `sourceMappingURL` comment (a file or
+    /// base64 data url), or from the `.map` file next to it.
+    pub fn load_for(generated: &Path) -> Option<Self> {
+        let dir = generated.parent().unwrap_or_else(|| Path::new(""));
+        let url = fs::read_to_string(generated).ok().and_then(|code| {
+            code.lines().rev().find_map(|line| {
+                let url = line
+                    .trim()
+                    .strip_prefix("//# sourceMappingURL=")
+                    .or_else(|| line.trim().strip_prefix("//@ sourceMappingURL="))?;
+                Some(url.trim().to_owned())
+            })
+        });
+        match url {
+            Some(url) if url.starts_with("data:") => {
+                let (_, data) = url.split_once(";base64,")?;
+                let input = String::from_utf8(base64(data)?).ok()?;
+                SourceMap::parse(&input, dir)
+            }
+            Some(url) => {
+                let path = dir.join(url);
+                SourceMap::parse(&fs::read_to_string(&path).ok()?, path.parent()?)
+            }
+            None => {
+                let mut path = generated.as_os_str().to_owned();
+                path.push(".map");
+                SourceMap::parse(&fs::read_to_string(&path).ok()?, dir)
+            }
+        }
+    }
+
+    /// Original source and line of the 1-based generated line.
+    pub fn original(&self, line: u32) -> Option<(&Path, u32)> {
+        let (source, line) = (*self.lines.get(line.checked_sub(1)? as usize)?)?;
+        Some((self.sources.get(source)?, line))
+    }
+}
+
+/// Remaps sections of the report which have source maps, the rest is kept as is.
+pub fn remap<F>(report: &Report, mut load: F) -> Report
+where
+    F: FnMut(&Path) -> Option<SourceMap>,
+{
+    let mut remapped = Report::new();
+    for (section_key, section) in &report.sections {
+        let map = match load(&section_key.source_file) {
+            Some(map) => map,
+            None => {
+                let mut single = Report::new();
+                single.sections.insert(section_key.clone(), section.clone());
+                remapped.merge_lossy(single);
+                continue;
+            }
+        };
+        for (line_key, value) in &section.lines {
+            if let Some((source, line)) = map.original(line_key.line) {
+                let mut key = *line_key;
+                key.line = line;
+                let target = section_of(&mut remapped, &section_key.test_name, source)
+                    .lines
+                    .entry(key)
+                    .or_default();
+                target.count = target.count.max(value.count);
+            }
+        }
+        for (function_key, value) in &section.functions {
+            let original = value.start_line.and_then(|line| map.original(line));
+            let (source, start_line) = match original {
+                Some((source, line)) => (source, Some(line)),
+                None => continue,
+            };
+            let target = section_of(&mut remapped, &section_key.test_name, source)
+                .functions
+                .entry(function_key.clone())
+                .or_default();
+            target.start_line = start_line;
+            target.count = target.count.max(value.count);
+        }
+        for (branch_key, value) in &section.branches {
+            if let Some((source, line)) = map.original(branch_key.line) {
+                let mut key = *branch_key;
+                key.line = line;
+                let target = section_of(&mut remapped, &section_key.test_name, source)
+                    .branches
+                    .entry(key)
+                    .or_default();
+                target.taken = target.taken.max(value.taken);
+            }
+        }
+    }
+    remapped
+}
+
+fn section_of<'a>(report: &'a mut Report, test_name: &str, source: &Path) -> &'a mut SectionValue {
+    report
+        .sections
+        .entry(SectionKey {
+            test_name: test_name.to_owned(),
+            source_file: source.to_owned(),
+        })
+        .or_default()
+}
+
+/// Decodes the vlq `mappings` into the first original position of every generated line.
+fn decode_mappings(mappings: &str) -> Option<Vec<Option<(usize, u32)>>> {
+    let mut lines = Vec::new();
+    let (mut source, mut original_line) = (0i64, 0i64);
+    let mut fields = [0i64; 5];
+    for generated in mappings.split(';') {
+        let mut first = None;
+        for segment in generated.split(',').filter(|s| !s.is_empty()) {
+            let len = vlq(segment, &mut fields)?;
+            if len < 4 {
+                continue;
+            }
+            source += fields[1];
+            original_line += fields[2];
+            if first.is_none() && source >= 0 && original_line >= 0 {
+                first = Some((source as usize, original_line as u32 + 1));
+            }
+        }
+        lines.push(first);
+    }
+    Some(lines)
+}
+
+/// Decodes base64 vlq numbers of the segment into `fields`, returns their number.
+fn vlq(segment: &str, fields: &mut [i64; 5]) -> Option<usize> {
+    let mut len = 0;
+    let (mut value, mut shift) = (0i64, 0);
+    for byte in segment.bytes() {
+        let digit = base64_digit(byte)? as i64;
+        value += (digit & 31) << shift;
+        if digit & 32 != 0 {
+            shift += 5;
+            continue;
+        }
+        *fields.get_mut(len)? = if value & 1 == 1 {
+            -(value >> 1)
+        } else {
+            value >> 1
+        };
+        len += 1;
+        value = 0;
+        shift = 0;
+    }
+    Some(len)
+}
+
+fn base64_digit(byte: u8) -> Option<u8> {
+    match byte {
+        b'A'..=b'Z' => Some(byte - b'A'),
+        b'a'..=b'z' => Some(byte - b'a' + 26),
+        b'0'..=b'9' => Some(byte - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+fn base64(data: &str) -> Option<Vec<u8>> {
+    let digits = data
+        .bytes()
+        .filter(|b| *b != b'=')
+        .map(base64_digit)
+        .collect::<Option<Vec<_>>>()?;
+    let mut out = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (idx, d)| {
+            bits | u32::from(*d) << (18 - 6 * idx)
+        });
+        out.extend_from_slice(&bits.to_be_bytes()[1..chunk.len()]);
+    }
+    Some(out)
+}
+
+/// `webpack://app/./src/a.ts` is `./src/a.ts`.
+fn strip_scheme(source: &str) -> &str {
+    match source.split_once("://") {
+        Some((_, rest)) => rest.split_once('/').map_or(rest, |(_, path)| path),
+        None => source,
+    }
+}
+
+/// Resolves `.` and `..` components without touching the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if out.file_name().is_some() => {
+                out.pop();
+            }
+            component => out.push(component),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{remap, SourceMap};
+    use lcov::{Reader, Report};
+    use std::path::Path;
+
+    #[test]
+    fn remap_report() {
+        // Generated lines 1 and 2 are a.ts:1 and a.ts:3, line 3 has no mapping, line 4 is b.ts:1
+        let map = r#"{"version": 3, "sourceRoot": "", "sources": ["webpack://app/./src/a.ts", "../src/b.ts"],
+            "mappings": "AAAA;AAEA;;ACFA"}"#;
+        let map = SourceMap::parse(map, Path::new("/repo/dist")).unwrap();
+        assert_eq!(map.original(2), Some((Path::new("/repo/dist/src/a.ts"), 3)));
+
+        let input = "\
+SF:/repo/dist/bundle.js
+FN:2,main
+FNDA:1,main
+DA:1,1
+DA:2,1
+DA:3,5
+DA:4,0
+end_of_record
+";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let records: Vec<String> = remap(&report, |_| Some(map.clone()))
+            .into_records()
+            .map(|rec| rec.to_string())
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                "TN:",
+                "SF:/repo/dist/src/a.ts",
+                "FN:3,main",
+                "FNDA:1,main",
+                "FNF:1",
+                "FNH:1",
+                "DA:1,1",
+                "DA:3,1",
+                "LF:2",
+                "LH:2",
+                "end_of_record",
+                "TN:",
+                "SF:/repo/src/b.ts",
+                "DA:1,0",
+                "LF:1",
+                "LH:0",
+                "end_of_record",
+            ]
+        );
+    }
+}
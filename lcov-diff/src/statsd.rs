@@ -0,0 +1,119 @@
+//! StatsD/DogStatsD client pushing the summary gauges over udp.
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+
+use crate::summary::Comparison;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsD {
+    /// Prefix of the metric names, joined with `.`.
+    pub prefix: String,
+    /// DogStatsD tags, plain StatsD has no tags so they are skipped without `dogstatsd`.
+    pub tags: Vec<(String, String)>,
+    pub dogstatsd: bool,
+}
+
+impl Default for StatsD {
+    fn default() -> Self {
+        StatsD {
+            prefix: "lcov_diff".to_owned(),
+            tags: Vec::new(),
+            dogstatsd: false,
+        }
+    }
+}
+
+impl StatsD {
+    /// Gauge lines, the metrics of missing coverage (no lines) are skipped.
+    pub fn lines(&self, comparison: &Comparison) -> Vec<String> {
+        let mut gauges = Vec::new();
+        if let Some(percent) = comparison.current.lines.percent() {
+            gauges.push(("lines.percent", percent));
+        }
+        gauges.push(("lines.delta", comparison.lines_delta()));
+        if let Some(percent) = comparison.patch.percent() {
+            gauges.push(("patch.percent", percent));
+        }
+        gauges.push((
+            "uncovered_new_lines",
+            comparison.uncovered_new_lines.len() as f64,
+        ));
+
+        let tags = if self.dogstatsd && !self.tags.is_empty() {
+            let tags: Vec<String> = self
+                .tags
+                .iter()
+                .map(|(name, value)| format!("{}:{}", name, value))
+                .collect();
+            format!("|#{}", tags.join(","))
+        } else {
+            String::new()
+        };
+        gauges
+            .into_iter()
+            .map(|(name, value)| {
+                let name = if self.prefix.is_empty() {
+                    name.to_owned()
+                } else {
+                    format!("{}.{}", self.prefix, name)
+                };
+                // Gauges can't be set to negative values, `-` means the decrement
+                let value = (value * 100.0).round() / 100.0;
+                if value < 0.0 {
+                    format!("{}:0|g{}\n{}:{}|g{}", name, tags, name, value, tags)
+                } else {
+                    format!("{}:{}|g{}", name, value, tags)
+                }
+            })
+            .collect()
+    }
+
+    /// Sends every gauge as a datagram.
+    pub fn send<A: ToSocketAddrs>(&self, addr: A, comparison: &Comparison) -> io::Result<()> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(addr)?;
+        for line in self.lines(comparison) {
+            socket.send(line.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StatsD;
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+    use std::net::UdpSocket;
+
+    #[test]
+    fn send() {
+        let baseline = "SF:a.c\nDA:1,1\nend_of_record\n";
+        let current = "SF:a.c\nDA:1,1\nDA:2,0\nDA:3,1\nend_of_record\n";
+        let baseline = Report::from_reader(Reader::new(baseline.as_bytes())).unwrap();
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let comparison = Comparison::new(&baseline, &current);
+        let statsd = StatsD {
+            tags: vec![("team".to_owned(), "core".to_owned())],
+            dogstatsd: true,
+            ..StatsD::default()
+        };
+        assert_eq!(
+            statsd.lines(&comparison),
+            vec![
+                "lcov_diff.lines.percent:66.67|g|#team:core",
+                "lcov_diff.lines.delta:0|g|#team:core\nlcov_diff.lines.delta:-33.33|g|#team:core",
+                "lcov_diff.patch.percent:50|g|#team:core",
+                "lcov_diff.uncovered_new_lines:1|g|#team:core",
+            ]
+        );
+
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        statsd
+            .send(server.local_addr().unwrap(), &comparison)
+            .unwrap();
+        let mut buf = [0; 512];
+        let len = server.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"lcov_diff.lines.percent:66.67|g|#team:core");
+    }
+}
@@ -0,0 +1,588 @@
+//! Aggregated coverage statistics of reports and their deltas.
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::AddAssign;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use lcov::report::section::{Key as SectionKey, Value as SectionValue};
+use lcov::Report;
+
+use crate::changes::Changes;
+use crate::functions;
+use crate::render::number::Numbers;
+
+/// Number of found (instrumented) and hit entries.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Counter {
+    pub found: u64,
+    pub hit: u64,
+}
+
+impl Counter {
+    /// Coverage percentage, `None` if nothing was instrumented.
+    pub fn percent(&self) -> Option<f64> {
+        if self.found == 0 {
+            None
+        } else {
+            Some(self.hit as f64 * 100.0 / self.found as f64)
+        }
+    }
+}
+
+impl AddAssign for Counter {
+    fn add_assign(&mut self, other: Self) {
+        self.found += other.found;
+        self.hit += other.hit;
+    }
+}
+
+/// Line, function and branch coverage of a file or a whole report.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Coverage {
+    pub lines: Counter,
+    pub functions: Counter,
+    pub branches: Counter,
+}
+
+impl Coverage {
+    pub fn of_section(section: &SectionValue) -> Self {
+        let count = |found: usize, hit: usize| Counter {
+            found: found as u64,
+            hit: hit as u64,
+        };
+        Coverage {
+            lines: count(
+                section.lines.len(),
+                section.lines.values().filter(|v| v.count > 0).count(),
+            ),
+            functions: {
+                let functions = functions::functions_of(section);
+                let hit = functions.iter().filter(|f| f.count > 0).count();
+                count(functions.len(), hit)
+            },
+            branches: count(
+                section.branches.len(),
+                section
+                    .branches
+                    .values()
+                    .filter(|v| v.taken.unwrap_or(0) > 0)
+                    .count(),
+            ),
+        }
+    }
+
+    pub fn of_report(report: &Report) -> Self {
+        let mut coverage = Coverage::default();
+        let mut paths = BTreeSet::new();
+        if report
+            .sections
+            .keys()
+            .all(|key| paths.insert(&key.source_file))
+        {
+            // A section per file, there is nothing to merge
+            for section in report.sections.values() {
+                coverage += Coverage::of_section(section);
+            }
+        } else {
+            for section in by_file(report).values() {
+                coverage += Coverage::of_section(section);
+            }
+        }
+        coverage
+    }
+}
+
+impl AddAssign for Coverage {
+    fn add_assign(&mut self, other: Self) {
+        self.lines += other.lines;
+        self.functions += other.functions;
+        self.branches += other.branches;
+    }
+}
+
+/// Sections of the report by source file, sections of different tests are merged together.
+pub fn by_file(report: &Report) -> BTreeMap<PathBuf, SectionValue> {
+    let mut merged = Report::new();
+    for (key, section) in &report.sections {
+        let mut single = Report::new();
+        single.sections.insert(
+            SectionKey {
+                test_name: String::new(),
+                source_file: key.source_file.clone(),
+            },
+            section.clone(),
+        );
+        merged.merge_lossy(single);
+    }
+    merged
+        .sections
+        .into_iter()
+        .map(|(key, section)| (key.source_file, section))
+        .collect()
+}
+
+/// Report of every test name (`TN:`), the sections without name are under the empty one.
+pub fn by_test(report: &Report) -> BTreeMap<String, Report> {
+    let mut tests: BTreeMap<String, Report> = BTreeMap::new();
+    for (key, section) in &report.sections {
+        tests
+            .entry(key.test_name.clone())
+            .or_default()
+            .sections
+            .insert(key.clone(), section.clone());
+    }
+    tests
+}
+
+/// Comparisons of the groups of files (packages, owners), the groups of a file are given
+/// by `groups`, a file may be in several groups or in none. Ordered by the group name.
+pub fn by_group<F>(baseline: &Report, current: &Report, mut groups: F) -> Vec<(String, Comparison)>
+where
+    F: FnMut(&Path) -> Vec<String>,
+{
+    let mut reports: BTreeMap<String, (Report, Report)> = BTreeMap::new();
+    for (idx, report) in [baseline, current].iter().enumerate() {
+        for (key, section) in &report.sections {
+            for group in groups(&key.source_file) {
+                let entry = reports.entry(group).or_default();
+                let target = if idx == 0 { &mut entry.0 } else { &mut entry.1 };
+                target.sections.insert(key.clone(), section.clone());
+            }
+        }
+    }
+    reports
+        .into_iter()
+        .map(|(group, (old, new))| (group, Comparison::new(&old, &new)))
+        .collect()
+}
+
+/// Coverage of a single file in both reports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDelta {
+    pub path: PathBuf,
+    /// `None` if the file isn't presented in the baseline.
+    pub baseline: Option<Coverage>,
+    /// `None` if the file isn't presented in the new report.
+    pub current: Option<Coverage>,
+}
+
+impl FileDelta {
+    /// Change of line coverage in percentage points, missing files are counted as 0%.
+    pub fn lines_delta(&self) -> f64 {
+        let percent = |coverage: Option<Coverage>| {
+            coverage.and_then(|c| c.lines.percent()).unwrap_or_default()
+        };
+        percent(self.current) - percent(self.baseline)
+    }
+
+    /// Change of the number of covered lines, missing files have none.
+    pub fn hit_delta(&self) -> i64 {
+        let hit = |coverage: Option<Coverage>| coverage.map_or(0, |c| c.lines.hit as i64);
+        hit(self.current) - hit(self.baseline)
+    }
+
+    /// Change of the file by the measure.
+    pub fn change(&self, rank: Rank) -> f64 {
+        match rank {
+            Rank::Lines => self.hit_delta() as f64,
+            Rank::Percent => self.lines_delta(),
+        }
+    }
+}
+
+/// Measure of the change the files are ranked by.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Rank {
+    /// Number of covered lines gained or lost.
+    Lines,
+    /// Line coverage in percentage points.
+    Percent,
+}
+
+impl Rank {
+    pub const VARIANTS: &'static [&'static str] = &["lines", "percent"];
+}
+
+impl FromStr for Rank {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lines" => Ok(Rank::Lines),
+            "percent" => Ok(Rank::Percent),
+            _ => Err(format!("unknown rank: {}", s)),
+        }
+    }
+}
+
+/// Files with the largest coverage changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopChanges<'a> {
+    /// Largest losses first.
+    pub regressions: Vec<&'a FileDelta>,
+    /// Largest gains first.
+    pub gains: Vec<&'a FileDelta>,
+}
+
+/// Total coverage of the baseline and the new report, without the comparison of the files
+/// and the diff.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Totals {
+    pub baseline: Coverage,
+    pub current: Coverage,
+}
+
+impl Totals {
+    pub fn new(baseline: &Report, current: &Report) -> Self {
+        Totals {
+            baseline: Coverage::of_report(baseline),
+            current: Coverage::of_report(current),
+        }
+    }
+
+    /// Change of the total line coverage in percentage points.
+    pub fn lines_delta(&self) -> f64 {
+        self.current.lines.percent().unwrap_or_default()
+            - self.baseline.lines.percent().unwrap_or_default()
+    }
+
+    /// `lines: 81.20% -> 81.60% (+0.40%)`, a line per kind with `n/a` where nothing is
+    /// instrumented.
+    pub fn text(&self, numbers: &Numbers) -> String {
+        let kinds = [
+            ("lines", self.baseline.lines, self.current.lines),
+            ("functions", self.baseline.functions, self.current.functions),
+            ("branches", self.baseline.branches, self.current.branches),
+        ];
+        let mut out = String::new();
+        for (kind, old, new) in kinds {
+            let percent = |counter: Counter| {
+                counter
+                    .percent()
+                    .map_or("n/a".to_owned(), |percent| numbers.percent(percent))
+            };
+            out.push_str(&format!("{}: {} -> {}", kind, percent(old), percent(new)));
+            if let (Some(old), Some(new)) = (old.percent(), new.percent()) {
+                out.push_str(&format!(" ({})", numbers.delta(new - old)));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Comparison of the new coverage against the baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub baseline: Coverage,
+    pub current: Coverage,
+    pub files: Vec<FileDelta>,
+    /// Coverage of the lines instrumented only in the new report.
+    pub patch: Counter,
+    /// Lines which are instrumented only in the new report and have never been executed.
+    pub uncovered_new_lines: Vec<(PathBuf, u32)>,
+}
+
+impl Comparison {
+    pub fn new(baseline: &Report, current: &Report) -> Self {
+        Comparison::of_files(&by_file(baseline), &by_file(current))
+    }
+
+    /// Comparison of the reports merged by `by_file`.
+    pub fn of_files(
+        baseline_files: &BTreeMap<PathBuf, SectionValue>,
+        current_files: &BTreeMap<PathBuf, SectionValue>,
+    ) -> Self {
+        let paths: BTreeSet<&PathBuf> = baseline_files.keys().chain(current_files.keys()).collect();
+
+        let mut comparison = Comparison {
+            baseline: Coverage::default(),
+            current: Coverage::default(),
+            files: Vec::new(),
+            patch: Counter::default(),
+            uncovered_new_lines: Vec::new(),
+        };
+        for path in paths {
+            let old = baseline_files.get(path);
+            let new = current_files.get(path);
+            let delta = FileDelta {
+                path: path.clone(),
+                baseline: old.map(Coverage::of_section),
+                current: new.map(Coverage::of_section),
+            };
+            comparison.baseline += delta.baseline.unwrap_or_default();
+            comparison.current += delta.current.unwrap_or_default();
+            comparison.files.push(delta);
+
+            if let Some(new) = new {
+                for (key, value) in &new.lines {
+                    let is_new = !old.is_some_and(|old| old.lines.contains_key(key));
+                    if !is_new {
+                        continue;
+                    }
+                    comparison.patch.found += 1;
+                    if value.count > 0 {
+                        comparison.patch.hit += 1;
+                    } else {
+                        comparison
+                            .uncovered_new_lines
+                            .push((path.clone(), key.line));
+                    }
+                }
+            }
+        }
+        comparison
+    }
+
+    /// Change of the total line coverage in percentage points.
+    pub fn lines_delta(&self) -> f64 {
+        self.current.lines.percent().unwrap_or_default()
+            - self.baseline.lines.percent().unwrap_or_default()
+    }
+
+    /// Files whose line coverage has changed, worst regressions first.
+    pub fn changed_files(&self) -> Vec<&FileDelta> {
+        let mut files: Vec<&FileDelta> = self
+            .files
+            .iter()
+            .filter(|file| file.baseline != file.current)
+            .collect();
+        files.sort_by(|a, b| {
+            a.lines_delta()
+                .partial_cmp(&b.lines_delta())
+                .unwrap_or(Ordering::Equal)
+        });
+        files
+    }
+
+    /// Up to `n` files with the largest loss and `n` with the largest gain by the measure,
+    /// the files without change are in neither.
+    pub fn top_changes(&self, n: usize, rank: Rank) -> TopChanges<'_> {
+        let mut files: Vec<&FileDelta> = self
+            .files
+            .iter()
+            .filter(|file| file.change(rank) != 0.0)
+            .collect();
+        files.sort_by(|a, b| {
+            a.change(rank)
+                .partial_cmp(&b.change(rank))
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        let regressions = files
+            .iter()
+            .copied()
+            .take_while(|file| file.change(rank) < 0.0)
+            .take(n)
+            .collect();
+        let gains = files
+            .iter()
+            .rev()
+            .copied()
+            .take_while(|file| file.change(rank) > 0.0)
+            .take(n)
+            .collect();
+        TopChanges { regressions, gains }
+    }
+
+    /// Uncovered new lines grouped by file.
+    pub fn uncovered_new_lines_by_file(&self) -> BTreeMap<&Path, Vec<u32>> {
+        let mut files: BTreeMap<&Path, Vec<u32>> = BTreeMap::new();
+        for (path, line) in &self.uncovered_new_lines {
+            files.entry(path.as_path()).or_default().push(*line);
+        }
+        files
+    }
+
+    /// Recomputes the patch coverage from the git changes: the instrumented lines of the
+    /// current report which were added or modified.
+    pub fn set_patch(&mut self, current: &Report, changes: &Changes) {
+        self.patch = Counter::default();
+        self.uncovered_new_lines.clear();
+        for (path, section) in by_file(current) {
+            let changed = match changes.lines_of(&path) {
+                Some(changed) => changed,
+                None => continue,
+            };
+            for (key, value) in &section.lines {
+                if !changed.contains(&key.line) {
+                    continue;
+                }
+                self.patch.found += 1;
+                if value.count > 0 {
+                    self.patch.hit += 1;
+                } else {
+                    self.uncovered_new_lines.push((path.clone(), key.line));
+                }
+            }
+        }
+    }
+
+    /// Keeps only the files (and their uncovered new lines) whose path ends with one of the
+    /// repository relative `paths`, the totals are kept as is.
+    pub fn retain_paths(&mut self, paths: &[PathBuf]) {
+        let keep = |path: &Path| paths.iter().any(|changed| path.ends_with(changed));
+        self.files.retain(|file| keep(&file.path));
+        self.uncovered_new_lines.retain(|(path, _)| keep(path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Comparison, Counter, Rank, Totals};
+    use crate::changes::Changes;
+    use crate::render::number::Numbers;
+    use lcov::{Reader, Report};
+    use std::path::PathBuf;
+
+    const BASELINE: &str = "\
+TN:a
+SF:a.c
+DA:1,1
+DA:2,0
+end_of_record
+TN:b
+SF:a.c
+DA:2,0
+DA:3,1
+end_of_record
+";
+    const CURRENT: &str = "\
+SF:a.c
+DA:1,1
+DA:2,1
+DA:3,1
+DA:4,0
+end_of_record
+SF:b.c
+DA:1,0
+end_of_record
+";
+
+    fn report(input: &str) -> Report {
+        Report::from_reader(Reader::new(input.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn totals() {
+        let (baseline, current) = (report(BASELINE), report(CURRENT));
+        let comparison = Comparison::new(&baseline, &current);
+
+        assert_eq!(comparison.baseline.lines, Counter { found: 3, hit: 2 });
+        assert_eq!(comparison.current.lines, Counter { found: 5, hit: 3 });
+        let totals = Totals::new(&baseline, &current);
+        assert_eq!(
+            (totals.baseline, totals.current),
+            (comparison.baseline, comparison.current)
+        );
+        assert_eq!(
+            totals.text(&Numbers::default()),
+            "lines: 66.67% -> 60.00% (-6.67%)\nfunctions: n/a -> n/a\nbranches: n/a -> n/a\n"
+        );
+    }
+
+    #[test]
+    fn uncovered_new_lines() {
+        let comparison = Comparison::new(&report(BASELINE), &report(CURRENT));
+
+        assert_eq!(comparison.patch, Counter { found: 2, hit: 0 });
+        assert_eq!(
+            comparison.uncovered_new_lines,
+            vec![(PathBuf::from("a.c"), 4), (PathBuf::from("b.c"), 1)]
+        );
+    }
+
+    #[test]
+    fn regression() {
+        let baseline =
+            report("SF:a.c\nDA:1,1\nDA:2,1\nend_of_record\nSF:b.c\nDA:1,1\nend_of_record\n");
+        let current =
+            report("SF:a.c\nDA:1,1\nDA:2,0\nend_of_record\nSF:b.c\nDA:1,1\nend_of_record\n");
+        let comparison = Comparison::new(&baseline, &current);
+
+        let changed = comparison.changed_files();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].path, PathBuf::from("a.c"));
+        assert_eq!(changed[0].lines_delta(), -50.0);
+        assert_eq!(changed[0].hit_delta(), -1);
+        for rank in [Rank::Lines, Rank::Percent] {
+            let top = comparison.top_changes(5, rank);
+            assert_eq!(top.regressions, changed);
+            assert!(top.gains.is_empty());
+        }
+        assert_eq!(comparison.patch, Counter::default());
+    }
+
+    #[test]
+    fn new_file() {
+        let baseline = report("SF:a.c\nDA:1,1\nend_of_record\n");
+        let current =
+            report("SF:a.c\nDA:1,1\nend_of_record\nSF:b.c\nDA:1,1\nDA:2,0\nend_of_record\n");
+        let comparison = Comparison::new(&baseline, &current);
+
+        let changed = comparison.changed_files();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].path, PathBuf::from("b.c"));
+        assert_eq!(changed[0].baseline, None);
+        assert_eq!(
+            changed[0].current.map(|coverage| coverage.lines),
+            Some(Counter { found: 2, hit: 1 })
+        );
+        let top = comparison.top_changes(5, Rank::Lines);
+        assert!(top.regressions.is_empty());
+        assert_eq!(top.gains, changed);
+        assert_eq!(comparison.patch, Counter { found: 2, hit: 1 });
+        assert_eq!(
+            comparison.uncovered_new_lines,
+            vec![(PathBuf::from("b.c"), 2)]
+        );
+    }
+
+    #[test]
+    fn removed_file() {
+        let baseline = report("SF:a.c\nDA:1,1\nend_of_record\nSF:b.c\nDA:1,1\nend_of_record\n");
+        let current = report("SF:a.c\nDA:1,1\nend_of_record\n");
+        let comparison = Comparison::new(&baseline, &current);
+
+        let changed = comparison.changed_files();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].path, PathBuf::from("b.c"));
+        assert_eq!(changed[0].current, None);
+        assert_eq!(changed[0].hit_delta(), -1);
+        assert_eq!(comparison.baseline.lines, Counter { found: 2, hit: 2 });
+        assert_eq!(comparison.current.lines, Counter { found: 1, hit: 1 });
+        let top = comparison.top_changes(5, Rank::Lines);
+        assert_eq!(top.regressions, changed);
+        assert!(top.gains.is_empty());
+        assert_eq!(comparison.patch, Counter::default());
+    }
+
+    #[test]
+    fn patch_of_changes() {
+        let current = report(CURRENT);
+        let mut comparison = Comparison::new(&report(BASELINE), &current);
+        let changes = Changes::parse("+++ b/a.c\n@@ -2,0 +3,2 @@\n+x\n+y\n");
+        comparison.set_patch(&current, &changes);
+
+        assert_eq!(comparison.patch, Counter { found: 2, hit: 1 });
+        assert_eq!(
+            comparison.uncovered_new_lines,
+            vec![(PathBuf::from("a.c"), 4)]
+        );
+    }
+
+    #[test]
+    fn retain_paths() {
+        let mut comparison = Comparison::new(&report(BASELINE), &report(CURRENT));
+        let totals = (comparison.baseline, comparison.current);
+        comparison.retain_paths(&[PathBuf::from("a.c")]);
+
+        assert_eq!(comparison.files.len(), 1);
+        assert_eq!(
+            comparison.uncovered_new_lines,
+            vec![(PathBuf::from("a.c"), 4)]
+        );
+        assert_eq!((comparison.baseline, comparison.current), totals);
+    }
+}
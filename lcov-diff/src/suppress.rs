@@ -0,0 +1,101 @@
+//! Inline suppression comments: `// lcov-diff: allow-uncovered` acknowledges the uncovered line
+//! it ends, or the next line when it's alone on its line.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::render::resolve_source;
+use crate::summary::Comparison;
+
+pub const MARKER: &str = "lcov-diff: allow-uncovered";
+
+/// Suppressed lines by the report path.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Suppressions {
+    pub lines: BTreeMap<PathBuf, BTreeSet<u32>>,
+}
+
+impl Suppressions {
+    /// Reads the sources of the paths in `root`, the sources which can't be found or read
+    /// suppress nothing.
+    pub fn load<'a, I: IntoIterator<Item = &'a Path>>(root: &Path, paths: I) -> Self {
+        let mut suppressions = Suppressions::default();
+        for path in paths {
+            let source = resolve_source(root, path).and_then(|file| fs::read_to_string(file).ok());
+            let lines = source.as_deref().map(suppressed_lines).unwrap_or_default();
+            if !lines.is_empty() {
+                suppressions.lines.insert(path.to_owned(), lines);
+            }
+        }
+        suppressions
+    }
+
+    pub fn contains(&self, path: &Path, line: u32) -> bool {
+        self.lines
+            .get(path)
+            .is_some_and(|lines| lines.contains(&line))
+    }
+
+    /// Drops the suppressed lines from the uncovered new lines and the patch coverage, returns
+    /// their number.
+    pub fn apply(&self, comparison: &mut Comparison) -> usize {
+        let before = comparison.uncovered_new_lines.len();
+        comparison
+            .uncovered_new_lines
+            .retain(|(path, line)| !self.contains(path, *line));
+        let suppressed = before - comparison.uncovered_new_lines.len();
+        comparison.patch.found -= suppressed as u64;
+        suppressed
+    }
+}
+
+/// Numbers of the lines the markers of the source apply to.
+pub fn suppressed_lines(source: &str) -> BTreeSet<u32> {
+    let mut lines = BTreeSet::new();
+    for (idx, text) in source.lines().enumerate() {
+        let code = match text.find(MARKER) {
+            Some(start) => &text[..start],
+            None => continue,
+        };
+        let alone = code
+            .trim()
+            .trim_matches(|c| matches!(c, '/' | '*' | '#' | '-' | ';' | '%' | '!' | '<'))
+            .trim()
+            .is_empty();
+        lines.insert(idx as u32 + 1 + alone as u32);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{suppressed_lines, Suppressions};
+    use crate::summary::Comparison;
+    use lcov::{Reader, Report};
+    use std::path::PathBuf;
+
+    #[test]
+    fn suppress() {
+        let source = "\
+fn f() {
+    unreachable!() // lcov-diff: allow-uncovered
+    # lcov-diff: allow-uncovered
+    x
+}
+";
+        let lines = suppressed_lines(source);
+        assert_eq!(lines, vec![2, 4].into_iter().collect());
+
+        let current = "SF:a.rs\nDA:2,0\nDA:3,0\nend_of_record\n";
+        let current = Report::from_reader(Reader::new(current.as_bytes())).unwrap();
+        let mut comparison = Comparison::new(&Report::new(), &current);
+        let mut suppressions = Suppressions::default();
+        suppressions.lines.insert(PathBuf::from("a.rs"), lines);
+        assert_eq!(suppressions.apply(&mut comparison), 1);
+        assert_eq!(
+            comparison.uncovered_new_lines,
+            vec![(PathBuf::from("a.rs"), 3)]
+        );
+        assert_eq!(comparison.patch.found, 1);
+    }
+}
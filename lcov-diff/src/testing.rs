@@ -0,0 +1,302 @@
+//! Arbitrary but valid reports for the property tests, here and in the downstream crates
+//! (the `testing` feature). The reports come from a seeded generator, so a failing case is
+//! replayed by its seed; there is no shrinking. `assert_reports_equal` compares the reports
+//! record by record.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use lcov::report::section::line::Key as LineKey;
+use lcov::report::section::Key as SectionKey;
+use lcov::Report;
+
+use crate::builder::ReportBuilder;
+
+const TEST_NAMES: &[&str] = &["", "unit", "integration"];
+
+/// Splitmix64, the same seed gives the same reports on every platform.
+#[derive(Debug, Clone)]
+pub struct Generator {
+    state: u64,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        Generator { state: seed }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+
+    /// Zero in 40% of the cases, otherwise up to 5.
+    fn count(&mut self) -> u64 {
+        if self.chance(40) {
+            0
+        } else {
+            1 + self.below(5)
+        }
+    }
+
+    fn taken(&mut self) -> Option<u64> {
+        if self.chance(20) {
+            None
+        } else {
+            Some(self.count())
+        }
+    }
+
+    /// Report of 1 to 4 files with lines, functions and branches. A function `fN` always
+    /// starts on the line `N * 10 + 1`, so the reports of a generator never conflict.
+    pub fn report(&mut self) -> Report {
+        let files = 1 + self.below(4);
+        let mut builder: Option<ReportBuilder> = None;
+        for file in 0..files {
+            let path = format!("src/file{}.c", file);
+            let test_name = TEST_NAMES[self.below(TEST_NAMES.len() as u64) as usize];
+            let mut section = match builder {
+                Some(builder) => builder.next_file(path),
+                None => ReportBuilder::file(path),
+            }
+            .test(test_name);
+            for function in 0..self.below(4) as u32 {
+                let count = self.count();
+                section = section.function(format!("f{}", function), function * 10 + 1, count);
+            }
+            for line in 1..=20 {
+                if self.chance(70) {
+                    let count = self.count();
+                    section = section.line(line, count);
+                }
+            }
+            for branch in 0..self.below(5) as u32 {
+                let (line, taken) = (1 + self.below(20) as u32, self.taken());
+                section = section.branch(line, 0, branch, taken);
+            }
+            builder = Some(section);
+        }
+        builder.map(ReportBuilder::build).unwrap_or_default()
+    }
+
+    /// A report and a baseline of the same sources: the baseline has some of the counts
+    /// changed, some of the lines and files removed and some lines added.
+    pub fn pair(&mut self) -> (Report, Report) {
+        let first = self.report();
+        let mut second = first.clone();
+        let keys: Vec<_> = second.sections.keys().cloned().collect();
+        for key in keys {
+            if self.chance(10) {
+                second.sections.remove(&key);
+                continue;
+            }
+            let section = second.sections.get_mut(&key).unwrap();
+            let lines: Vec<_> = section.lines.keys().cloned().collect();
+            for line in lines {
+                if self.chance(10) {
+                    section.lines.remove(&line);
+                } else if self.chance(30) {
+                    section.lines.get_mut(&line).unwrap().count = self.count();
+                }
+            }
+            for function in section.functions.values_mut() {
+                if self.chance(30) {
+                    function.count = self.count();
+                }
+            }
+            for branch in section.branches.values_mut() {
+                if self.chance(30) {
+                    branch.taken = self.taken();
+                }
+            }
+            if self.chance(30) {
+                let line = 21 + self.below(10) as u32;
+                let count = self.count();
+                section.lines.entry(LineKey { line }).or_default().count = count;
+            }
+        }
+        (first, second)
+    }
+}
+
+/// Differences of the actual report from the expected one, one line per record:
+/// `a.c (unit): DA:3: expected 1, actual 0`.
+pub fn mismatches(actual: &Report, expected: &Report) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    let keys: BTreeSet<&SectionKey> = expected
+        .sections
+        .keys()
+        .chain(actual.sections.keys())
+        .collect();
+    for key in keys {
+        let section = if key.test_name.is_empty() {
+            key.source_file.display().to_string()
+        } else {
+            format!("{} ({})", key.source_file.display(), key.test_name)
+        };
+        let (actual, expected) = match (actual.sections.get(key), expected.sections.get(key)) {
+            (Some(actual), Some(expected)) => (actual, expected),
+            (actual, _) => {
+                let (expected, actual) = if actual.is_some() {
+                    ("none", "section")
+                } else {
+                    ("section", "none")
+                };
+                mismatches.push(format!(
+                    "{}: expected {}, actual {}",
+                    section, expected, actual
+                ));
+                continue;
+            }
+        };
+        compare(
+            &mut mismatches,
+            &section,
+            &actual.lines,
+            &expected.lines,
+            |key| format!("DA:{}", key.line),
+            |value| match &value.checksum {
+                Some(checksum) => format!("{},{}", value.count, checksum),
+                None => value.count.to_string(),
+            },
+        );
+        compare(
+            &mut mismatches,
+            &section,
+            &actual.functions,
+            &expected.functions,
+            |key| format!("FN:{}", key.name),
+            |value| match value.start_line {
+                Some(line) => format!("line {}, count {}", line, value.count),
+                None => format!("count {}", value.count),
+            },
+        );
+        compare(
+            &mut mismatches,
+            &section,
+            &actual.branches,
+            &expected.branches,
+            |key| format!("BRDA:{},{},{}", key.line, key.block, key.branch),
+            |value| match value.taken {
+                Some(taken) => taken.to_string(),
+                None => "-".to_owned(),
+            },
+        );
+    }
+    mismatches
+}
+
+fn compare<K: Ord, V: PartialEq, L: Display, F: Display>(
+    mismatches: &mut Vec<String>,
+    section: &str,
+    actual: &BTreeMap<K, V>,
+    expected: &BTreeMap<K, V>,
+    label: impl Fn(&K) -> L,
+    format: impl Fn(&V) -> F,
+) {
+    let keys: BTreeSet<&K> = expected.keys().chain(actual.keys()).collect();
+    for key in keys {
+        let (actual, expected) = (actual.get(key), expected.get(key));
+        if actual != expected {
+            let value =
+                |value: Option<&V>| value.map_or("none".to_owned(), |v| format(v).to_string());
+            mismatches.push(format!(
+                "{}: {}: expected {}, actual {}",
+                section,
+                label(key),
+                value(expected),
+                value(actual)
+            ));
+        }
+    }
+}
+
+/// Panics with the `mismatches` if the reports differ.
+#[track_caller]
+pub fn assert_reports_equal(actual: &Report, expected: &Report) {
+    let mismatches = mismatches(actual, expected);
+    if !mismatches.is_empty() {
+        panic!("reports differ:\n  {}", mismatches.join("\n  "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assert_reports_equal, mismatches, Generator};
+    use crate::builder::ReportBuilder;
+    use crate::{apply_diff, diff_reports, revert_diff};
+
+    #[test]
+    fn properties() {
+        for seed in 0..200 {
+            let (first, second) = Generator::new(seed).pair();
+            assert_eq!(Generator::new(seed).pair(), (first.clone(), second.clone()));
+
+            let same = diff_reports(&first, &first).unwrap();
+            let covered = same.sections.values().any(|section| {
+                section.lines.values().any(|line| line.count > 0)
+                    || section
+                        .functions
+                        .values()
+                        .any(|function| function.count > 0)
+                    || section
+                        .branches
+                        .values()
+                        .any(|branch| branch.taken > Some(0))
+            });
+            assert!(!covered, "seed {}", seed);
+
+            let diff = diff_reports(&first, &second).unwrap();
+            let mut applied = first.clone();
+            let inserted = apply_diff(&mut applied, &diff).unwrap();
+            revert_diff(&mut applied, &diff, &inserted).unwrap();
+            assert_eq!(applied, first, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn report_mismatches() {
+        let expected = ReportBuilder::file("a.c")
+            .line(1, 1)
+            .line(3, 1)
+            .branch(3, 0, 0, None)
+            .build();
+        let actual = ReportBuilder::file("a.c")
+            .line(1, 1)
+            .line(3, 0)
+            .function("main", 1, 1)
+            .test("unit")
+            .build();
+        assert_reports_equal(&expected, &expected.clone());
+        assert_eq!(
+            mismatches(&actual, &expected),
+            vec![
+                "a.c: expected section, actual none",
+                "a.c (unit): expected none, actual section",
+            ]
+        );
+        let actual = ReportBuilder::file("a.c")
+            .line(1, 1)
+            .line(3, 0)
+            .function("main", 1, 1)
+            .build();
+        assert_eq!(
+            mismatches(&actual, &expected),
+            vec![
+                "a.c: DA:3: expected 1, actual 0",
+                "a.c: FN:main: expected none, actual line 1, count 1",
+                "a.c: BRDA:3,0,0: expected -, actual none",
+            ]
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Line based diff of two sequences (Myers' algorithm).
+//!
+//! It's used to align two versions of a source file, so the coverage of the same line could be
+//! shown next to each other even if some lines were added or removed in between.
+
+/// Single edit operation, indexes are referring to the positions in the input slices.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Op {
+    Equal { old: usize, new: usize },
+    Delete { old: usize },
+    Insert { new: usize },
+}
+
+/// Returns the shortest edit script transforming `old` into `new`.
+pub fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Op> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = n + m;
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    // Only the window of `v` visited on every step is stored: -(d + 1)..=(d + 1)
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        let mut k = -d;
+        while k <= d {
+            let idx = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+            k += 2;
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, window) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let get = |k: isize| window[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = get(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            ops.push(Op::Equal {
+                old: x as usize,
+                new: y as usize,
+            });
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push(Op::Insert {
+                    new: (y - 1) as usize,
+                });
+            } else {
+                ops.push(Op::Delete {
+                    old: (x - 1) as usize,
+                });
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.reverse();
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, Op};
+
+    #[test]
+    fn diff_lines() {
+        let old = ["a", "b", "c", "d"];
+        let new = ["a", "c", "x", "d"];
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Op::Equal { old: 0, new: 0 },
+                Op::Delete { old: 1 },
+                Op::Equal { old: 2, new: 1 },
+                Op::Insert { new: 2 },
+                Op::Equal { old: 3, new: 3 },
+            ]
+        );
+        assert!(diff::<&str>(&[], &[]).is_empty());
+        assert_eq!(diff(&[], &["a"]), vec![Op::Insert { new: 0 }]);
+    }
+}
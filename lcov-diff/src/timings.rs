@@ -0,0 +1,154 @@
+//! Durations and record counts of the phases of a run, to see where the time goes on an input
+//! without a profiler.
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use lcov::Report;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Phase {
+    pub name: &'static str,
+    pub duration: Duration,
+    /// Records the phase produced.
+    pub records: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timings {
+    pub phases: Vec<Phase>,
+    last: Instant,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Timings {
+            phases: Vec::new(),
+            last: Instant::now(),
+        }
+    }
+}
+
+impl Timings {
+    /// Starts the first phase.
+    pub fn new() -> Self {
+        Timings::default()
+    }
+
+    /// Ends the phase started at the previous lap, the next one starts.
+    pub fn lap(&mut self, name: &'static str, records: usize) {
+        let now = Instant::now();
+        self.phases.push(Phase {
+            name,
+            duration: now - self.last,
+            records,
+        });
+        self.last = now;
+    }
+
+    /// Moves the `duration` of the last phase which was spent on a step of its own, such as
+    /// the merge of the inputs during their load, into the phase `name` after it.
+    pub fn split_last(&mut self, name: &'static str, duration: Duration, records: usize) {
+        if let Some(last) = self.phases.last_mut() {
+            let duration = duration.min(last.duration);
+            last.duration -= duration;
+            self.phases.push(Phase {
+                name,
+                duration,
+                records,
+            });
+        }
+    }
+
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|phase| phase.duration).sum()
+    }
+}
+
+/// Line, function and branch records of the report.
+pub fn records(report: &Report) -> usize {
+    report
+        .sections
+        .values()
+        .map(|section| section.lines.len() + section.functions.len() + section.branches.len())
+        .sum()
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for phase in &self.phases {
+            writeln!(
+                f,
+                "{:<12} {:>10.3} ms {:>10} records",
+                phase.name,
+                phase.duration.as_secs_f64() * 1000.0,
+                phase.records
+            )?;
+        }
+        writeln!(
+            f,
+            "{:<12} {:>10.3} ms",
+            "total",
+            self.total().as_secs_f64() * 1000.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{records, Phase, Timings};
+    use crate::builder::ReportBuilder;
+    use std::time::Duration;
+
+    #[test]
+    fn phases() {
+        let mut timings = Timings::new();
+        timings.lap("load", 3);
+        assert_eq!(timings.phases.len(), 1);
+
+        timings.phases = vec![
+            Phase {
+                name: "load",
+                duration: Duration::from_micros(1500),
+                records: 3,
+            },
+            Phase {
+                name: "diff",
+                duration: Duration::from_millis(2),
+                records: 2,
+            },
+        ];
+        assert_eq!(timings.total(), Duration::from_micros(3500));
+        assert_eq!(
+            timings.to_string(),
+            "\
+load              1.500 ms          3 records
+diff              2.000 ms          2 records
+total             3.500 ms
+"
+        );
+
+        timings.phases.truncate(1);
+        timings.split_last("merge", Duration::from_micros(500), 2);
+        timings.split_last("remap", Duration::from_secs(1), 0);
+        let phases: Vec<_> = timings
+            .phases
+            .iter()
+            .map(|phase| (phase.name, phase.duration, phase.records))
+            .collect();
+        assert_eq!(
+            phases,
+            vec![
+                ("load", Duration::from_millis(1), 3),
+                ("merge", Duration::ZERO, 2),
+                ("remap", Duration::from_micros(500), 0),
+            ]
+        );
+        assert_eq!(timings.total(), Duration::from_micros(1500));
+        let report = ReportBuilder::file("a.c")
+            .line(1, 1)
+            .function("main", 1, 1)
+            .branch(1, 0, 0, None)
+            .build();
+        assert_eq!(records(&report), 3);
+    }
+}
@@ -0,0 +1,358 @@
+//! Coverage trend of the snapshot history (`notes::Notes::history`): the moving average of the
+//! line coverage, the largest week-over-week drop and the first-to-last change per top-level
+//! directory, and the commit which caused the largest drop of a period, with its author and
+//! pull request.
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::json::Value;
+use crate::notes::Snapshot;
+use crate::render::number::Numbers;
+use crate::shard;
+use crate::summary::{Counter, Coverage};
+
+const WEEK: u64 = 7 * 24 * 60 * 60;
+
+/// Line coverage of a snapshot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Point {
+    pub commit: String,
+    pub time: u64,
+    pub author: String,
+    pub pull_request: Option<u64>,
+    pub lines: Counter,
+    /// Line coverage by the top-level directory, as the shards of `shard::top_level`.
+    pub directories: BTreeMap<String, Counter>,
+}
+
+impl Point {
+    pub fn of_snapshot(snapshot: &Snapshot, root: &Path) -> Self {
+        let directories = shard::partition(&snapshot.report, shard::top_level(root))
+            .into_iter()
+            .map(|(name, report)| (name, Coverage::of_report(&report).lines))
+            .collect();
+        Point {
+            commit: snapshot.commit.clone(),
+            time: snapshot.time,
+            author: snapshot.author.clone(),
+            pull_request: snapshot.pull_request,
+            lines: Coverage::of_report(&snapshot.report).lines,
+            directories,
+        }
+    }
+}
+
+/// Change between the last points of two consecutive weeks which have snapshots.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeeklyDrop {
+    pub from: String,
+    pub to: String,
+    /// Percentage points, negative.
+    pub delta: f64,
+}
+
+/// Drop of the line coverage from the previous snapshot to the one of the commit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+    pub commit: String,
+    pub time: u64,
+    pub author: String,
+    pub pull_request: Option<u64>,
+    pub before: f64,
+    pub after: f64,
+}
+
+impl Regression {
+    pub fn delta(&self) -> f64 {
+        self.after - self.before
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("commit", self.commit.as_str().into()),
+            ("time", self.time.into()),
+            ("author", self.author.as_str().into()),
+            ("pull_request", self.pull_request.into()),
+            ("before", self.before.into()),
+            ("after", self.after.into()),
+            ("delta", self.delta().into()),
+        ])
+    }
+
+    /// `-1.20% in 0123456789 by Jane Doe (#12)`
+    pub fn text(&self, numbers: &Numbers) -> String {
+        let mut out = format!(
+            "{} in {} by {}",
+            numbers.delta(self.delta()),
+            short(&self.commit),
+            self.author
+        );
+        if let Some(pull_request) = self.pull_request {
+            out.push_str(&format!(" (#{})", pull_request));
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectoryTrend {
+    pub name: String,
+    pub first: Option<f64>,
+    pub last: Option<f64>,
+}
+
+impl DirectoryTrend {
+    pub fn delta(&self) -> Option<f64> {
+        Some(self.last? - self.first?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trend {
+    /// The oldest first.
+    pub points: Vec<Point>,
+    /// Mean line coverage of the point and the `window - 1` points before it, by point.
+    pub moving_average: Vec<Option<f64>>,
+    pub largest_drop: Option<WeeklyDrop>,
+    pub directories: Vec<DirectoryTrend>,
+}
+
+fn moving_average(points: &[Point], window: usize) -> Vec<Option<f64>> {
+    let window = window.max(1);
+    (0..points.len())
+        .map(|i| {
+            let percents: Vec<f64> = points[(i + 1).saturating_sub(window)..=i]
+                .iter()
+                .filter_map(|point| point.lines.percent())
+                .collect();
+            if percents.is_empty() {
+                None
+            } else {
+                Some(percents.iter().sum::<f64>() / percents.len() as f64)
+            }
+        })
+        .collect()
+}
+
+fn largest_drop(points: &[Point]) -> Option<WeeklyDrop> {
+    let mut weeks: BTreeMap<u64, &Point> = BTreeMap::new();
+    for point in points {
+        weeks.insert(point.time / WEEK, point);
+    }
+    let weeks: Vec<&Point> = weeks.into_values().collect();
+    weeks
+        .windows(2)
+        .filter_map(|pair| {
+            let delta = pair[1].lines.percent()? - pair[0].lines.percent()?;
+            Some(WeeklyDrop {
+                from: pair[0].commit.clone(),
+                to: pair[1].commit.clone(),
+                delta,
+            })
+        })
+        .filter(|drop| drop.delta < 0.0)
+        .min_by(|a, b| a.delta.total_cmp(&b.delta))
+}
+
+fn directories(points: &[Point]) -> Vec<DirectoryTrend> {
+    let names: BTreeSet<&String> = points
+        .iter()
+        .flat_map(|point| point.directories.keys())
+        .collect();
+    let percent =
+        |point: Option<&Point>, name: &str| point?.directories.get(name).and_then(Counter::percent);
+    names
+        .into_iter()
+        .map(|name| DirectoryTrend {
+            name: name.clone(),
+            first: percent(points.first(), name),
+            last: percent(points.last(), name),
+        })
+        .collect()
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(10)]
+}
+
+impl Trend {
+    pub fn new(points: Vec<Point>, window: usize) -> Self {
+        Trend {
+            moving_average: moving_average(&points, window),
+            largest_drop: largest_drop(&points),
+            directories: directories(&points),
+            points,
+        }
+    }
+
+    /// Largest drop from a snapshot to the next one made at `since` or later.
+    pub fn largest_commit_drop(&self, since: u64) -> Option<Regression> {
+        self.points
+            .windows(2)
+            .filter(|pair| pair[1].time >= since)
+            .filter_map(|pair| {
+                let (before, after) = (pair[0].lines.percent()?, pair[1].lines.percent()?);
+                Some(Regression {
+                    commit: pair[1].commit.clone(),
+                    time: pair[1].time,
+                    author: pair[1].author.clone(),
+                    pull_request: pair[1].pull_request,
+                    before,
+                    after,
+                })
+            })
+            .filter(|regression| regression.delta() < 0.0)
+            .min_by(|a, b| a.delta().total_cmp(&b.delta()))
+    }
+
+    pub fn to_json(&self) -> Value {
+        let points = self
+            .points
+            .iter()
+            .zip(&self.moving_average)
+            .map(|(point, average)| {
+                Value::object(vec![
+                    ("commit", point.commit.as_str().into()),
+                    ("time", point.time.into()),
+                    ("author", point.author.as_str().into()),
+                    ("pull_request", point.pull_request.into()),
+                    ("lines", point.lines.percent().into()),
+                    ("moving_average", (*average).into()),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let drop = self.largest_drop.as_ref().map(|drop| {
+            Value::object(vec![
+                ("from", drop.from.as_str().into()),
+                ("to", drop.to.as_str().into()),
+                ("delta", drop.delta.into()),
+            ])
+        });
+        let directories = self
+            .directories
+            .iter()
+            .map(|dir| {
+                Value::object(vec![
+                    ("name", dir.name.as_str().into()),
+                    ("first", dir.first.into()),
+                    ("last", dir.last.into()),
+                    ("delta", dir.delta().into()),
+                ])
+            })
+            .collect::<Vec<_>>();
+        Value::object(vec![
+            ("points", points.into()),
+            ("largest_weekly_drop", drop.unwrap_or(Value::Null)),
+            ("directories", directories.into()),
+        ])
+    }
+
+    pub fn markdown(&self, numbers: &Numbers) -> String {
+        let percent = |value: Option<f64>| value.map_or("n/a".to_owned(), |v| numbers.percent(v));
+        let mut out = String::from("### Coverage trend\n\n");
+        out.push_str("| Commit | Lines | Moving average |\n|---|---|---|\n");
+        for (point, average) in self.points.iter().zip(&self.moving_average) {
+            out.push_str(&format!(
+                "| {} | {} | {} |\n",
+                short(&point.commit),
+                percent(point.lines.percent()),
+                percent(*average)
+            ));
+        }
+        if let Some(drop) = &self.largest_drop {
+            out.push_str(&format!(
+                "\nLargest week-over-week drop: {} from {} to {}\n",
+                numbers.delta(drop.delta),
+                short(&drop.from),
+                short(&drop.to)
+            ));
+        }
+        if !self.directories.is_empty() {
+            out.push_str("\n| Directory | First | Last | Delta |\n|---|---|---|---|\n");
+            for dir in &self.directories {
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    dir.name,
+                    percent(dir.first),
+                    percent(dir.last),
+                    dir.delta()
+                        .map_or("n/a".to_owned(), |delta| numbers.delta(delta))
+                ));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Point, Trend, WEEK};
+    use crate::builder::ReportBuilder;
+    use crate::notes::Snapshot;
+    use crate::render::number::Numbers;
+    use std::path::Path;
+
+    #[test]
+    fn trend() {
+        let snapshot = |commit: &str, time: u64, covered: u64| Snapshot {
+            commit: commit.to_owned(),
+            time,
+            author: format!("author of {}", commit),
+            pull_request: Some(time),
+            report: ReportBuilder::file("src/a.c")
+                .line(1, 1)
+                .line(2, covered)
+                .next_file("b.c")
+                .line(1, 1)
+                .build(),
+        };
+        let points = [
+            snapshot("c1", 0, 1),
+            snapshot("c2", 1, 0),
+            snapshot("c3", WEEK, 1),
+            snapshot("c4", 2 * WEEK, 0),
+        ]
+        .iter()
+        .map(|snapshot| Point::of_snapshot(snapshot, Path::new("")))
+        .collect();
+        let trend = Trend::new(points, 2);
+
+        let average: Vec<_> = trend
+            .moving_average
+            .iter()
+            .map(|v| format!("{:.2}", v.unwrap()))
+            .collect();
+        assert_eq!(average, vec!["100.00", "83.33", "83.33", "83.33"]);
+        let drop = trend.largest_drop.as_ref().unwrap();
+        assert_eq!((drop.from.as_str(), drop.to.as_str()), ("c3", "c4"));
+        assert!((drop.delta + 100.0 / 3.0).abs() < 1e-9);
+        assert_eq!(trend.directories.len(), 2);
+        assert_eq!(trend.directories[0].name, ".");
+        assert_eq!(trend.directories[0].delta(), Some(0.0));
+        assert_eq!(trend.directories[1].name, "src");
+        assert_eq!(trend.directories[1].delta(), Some(-50.0));
+
+        let markdown = trend.markdown(&Numbers::default());
+        assert!(markdown.contains("| c4 | 66.67% | 83.33% |"));
+        assert!(markdown.contains("Largest week-over-week drop: -33.33% from c3 to c4"));
+        assert!(markdown.contains("| src | 100.00% | 50.00% | -50.00% |"));
+
+        assert_eq!(trend.largest_commit_drop(0).unwrap().commit, "c2");
+        let regression = trend.largest_commit_drop(WEEK).unwrap();
+        assert_eq!(
+            regression.text(&Numbers::default()),
+            format!("-33.33% in c4 by author of c4 (#{})", 2 * WEEK)
+        );
+        assert!(trend.largest_commit_drop(3 * WEEK).is_none());
+        assert_eq!(
+            trend
+                .to_json()
+                .get("directories")
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+}
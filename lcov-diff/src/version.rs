@@ -0,0 +1,291 @@
+//! Differences of the lcov 1.x and 2.x formats.
+//!
+//! lcov 2.x adds the `VER:` header, the end lines of `FN:<start>,<end>,<name>`, the function
+//! aliases of `FNL:<index>,<start>[,<end>]` and `FNA:<index>,<count>,<name>`, the exception
+//! branches `BRDA:<line>,e<block>,...` and the branch expressions in place of the numbers. The
+//! inputs are shimmed to the 1.x records the `lcov` crate reads.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use lcov::report::section::function::{Key as FunctionKey, Value as FunctionValue};
+use lcov::Report;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Version {
+    V1,
+    V2,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Version::V1 => "1.x",
+            Version::V2 => "2.x",
+        })
+    }
+}
+
+/// Version of the format of the lcov input by the records it uses.
+pub fn detect(input: &str) -> Version {
+    let v2 = input.lines().any(|line| {
+        let (kind, fields) = line.trim().split_once(':').unwrap_or(("", ""));
+        match kind {
+            "VER" | "FNL" | "FNA" => true,
+            "FN" => fields.splitn(3, ',').count() == 3 && end_line(fields).is_some(),
+            "BRDA" => branch_fields(fields).is_some_and(|(_, block, branch, _)| {
+                block.starts_with('e') || branch.parse::<u32>().is_err()
+            }),
+            _ => false,
+        }
+    });
+    if v2 {
+        Version::V2
+    } else {
+        Version::V1
+    }
+}
+
+/// Line, block, branch and taken fields of `BRDA`, the branch expressions may have commas.
+fn branch_fields(fields: &str) -> Option<(&str, &str, &str, &str)> {
+    let mut fields = fields.splitn(3, ',');
+    let (line, block) = (fields.next()?, fields.next()?);
+    let (branch, taken) = fields.next()?.rsplit_once(',')?;
+    Some((line, block, branch, taken))
+}
+
+/// End line of the `FN:<start>,<end>,<name>` fields.
+fn end_line(fields: &str) -> Option<u32> {
+    let mut fields = fields.splitn(3, ',');
+    fields.next()?.parse::<u32>().ok()?;
+    fields.next()?.parse().ok()
+}
+
+/// End lines of the lcov 2.x functions by the source file and the function name, the 1.x
+/// `FN` records have no place for them.
+pub type FunctionEnds = BTreeMap<PathBuf, BTreeMap<String, u32>>;
+
+/// Start line and name of the lcov 2.x `FN:<start>,<end>,<name>` fields, with the end line.
+fn function_fields(fields: &str) -> Option<(&str, u32, &str)> {
+    let mut fields = fields.splitn(3, ',');
+    let start = fields.next()?;
+    start.parse::<u32>().ok()?;
+    let end = fields.next()?.parse().ok()?;
+    Some((start, end, fields.next()?))
+}
+
+/// Rewrites the lcov 2.x records to the 1.x ones, see `shim_with_ends`.
+pub fn shim(input: &str, keep_version: bool) -> String {
+    shim_with_ends(input, keep_version).0
+}
+
+/// Rewrites the lcov 2.x records to the 1.x ones: `FN:<start>,<end>,<name>` is written as
+/// `FN:<start>,<name>` and the end lines are returned aside. The `VER:` header has no 1.x
+/// equivalent, it's dropped unless `keep_version`. The 1.x inputs are returned as is.
+pub fn shim_with_ends(input: &str, keep_version: bool) -> (String, FunctionEnds) {
+    let (out, ends, _) = shim_numbered(input, &[], detect(input), keep_version);
+    (out, ends)
+}
+
+/// Shimmed text and the line numbers of its lines.
+struct Numbered {
+    text: String,
+    numbers: Vec<usize>,
+}
+
+impl Numbered {
+    fn push(&mut self, line: &str, number: usize) {
+        self.text.push_str(line);
+        self.text.push('\n');
+        self.numbers.push(number);
+    }
+}
+
+/// `shim_with_ends` of the input of the `version`, with the number of every output line:
+/// the one of the input line it comes from in `numbers`, or in the input if `numbers` is empty.
+pub(crate) fn shim_numbered(
+    input: &str,
+    numbers: &[usize],
+    version: Version,
+    keep_version: bool,
+) -> (String, FunctionEnds, Vec<usize>) {
+    let mut ends = FunctionEnds::new();
+    let number_of = |idx: usize| numbers.get(idx).copied().unwrap_or(idx + 1);
+    if version == Version::V1 {
+        let numbers = (0..input.lines().count()).map(number_of).collect();
+        return (input.to_owned(), ends, numbers);
+    }
+    let mut out = Numbered {
+        text: String::with_capacity(input.len()),
+        numbers: Vec::new(),
+    };
+    let mut source_file = PathBuf::new();
+    // FNL lines of the section by the index
+    let mut locations: BTreeMap<String, String> = BTreeMap::new();
+    // Numbers of the branch expressions by the line and block
+    let mut branches: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+    for (idx, line) in input.lines().enumerate() {
+        let number = number_of(idx);
+        let (kind, fields) = line.trim().split_once(':').unwrap_or((line.trim(), ""));
+        match kind {
+            "VER" if !keep_version => continue,
+            "SF" => source_file = PathBuf::from(fields),
+            "FN" => {
+                if let Some((start, end, name)) = function_fields(fields) {
+                    out.push(&format!("FN:{},{}", start, name), number);
+                    ends.entry(source_file.clone())
+                        .or_default()
+                        .insert(name.to_owned(), end);
+                    continue;
+                }
+            }
+            "FNL" => {
+                if let Some((index, lines)) = fields.split_once(',') {
+                    locations.insert(index.to_owned(), lines.to_owned());
+                }
+                continue;
+            }
+            "FNA" => {
+                let mut fields = fields.splitn(3, ',');
+                let (index, count, name) = match (fields.next(), fields.next(), fields.next()) {
+                    (Some(index), Some(count), Some(name)) => (index, count, name),
+                    _ => continue,
+                };
+                if let Some(lines) = locations.get(index) {
+                    let (start, end) = match lines.split_once(',') {
+                        Some((start, end)) => (start, end.parse().ok()),
+                        None => (lines.as_str(), None),
+                    };
+                    out.push(&format!("FN:{},{}", start, name), number);
+                    if let Some(end) = end {
+                        ends.entry(source_file.clone())
+                            .or_default()
+                            .insert(name.to_owned(), end);
+                    }
+                }
+                out.push(&format!("FNDA:{},{}", count, name), number);
+                continue;
+            }
+            "BRDA" => {
+                if let Some((branch_line, block, branch, taken)) = branch_fields(fields) {
+                    let block = block.trim_start_matches('e');
+                    let branch = if branch.parse::<u32>().is_ok() {
+                        branch.to_string()
+                    } else {
+                        let names = branches
+                            .entry((branch_line.to_string(), block.to_owned()))
+                            .or_default();
+                        let index = match names.iter().position(|name| name == branch) {
+                            Some(index) => index,
+                            None => {
+                                names.push(branch.to_string());
+                                names.len() - 1
+                            }
+                        };
+                        index.to_string()
+                    };
+                    out.push(
+                        &format!("BRDA:{},{},{},{}", branch_line, block, branch, taken),
+                        number,
+                    );
+                    continue;
+                }
+            }
+            "end_of_record" => {
+                locations.clear();
+                branches.clear();
+            }
+            _ => {}
+        }
+        out.push(line, number);
+    }
+    (out.text, ends, out.numbers)
+}
+
+/// Merges the functions the `lcov` crate read from the unshimmed lcov 2.x `FN` records with
+/// the end line (`<end>,<name>`) into the `<name>` ones, so the reports of both versions have
+/// the same functions. The end lines are lost. The importers shim every 2.x input, this is for
+/// the reports read with the `lcov` crate directly.
+pub fn normalize(report: &mut Report) {
+    for section in report.sections.values_mut() {
+        let functions = std::mem::take(&mut section.functions);
+        for (key, value) in functions {
+            let name = match key.name.split_once(',') {
+                Some((end, name)) if end.bytes().all(|b| b.is_ascii_digit()) => name.to_owned(),
+                _ => key.name,
+            };
+            let function = section
+                .functions
+                .entry(FunctionKey { name })
+                .or_insert_with(FunctionValue::default);
+            function.start_line = function.start_line.or(value.start_line);
+            function.count = function.count.saturating_add(value.count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect, normalize, shim, shim_with_ends, Version};
+    use lcov::report::section::function::Key as FunctionKey;
+    use lcov::{Reader, Report};
+    use std::path::PathBuf;
+
+    #[test]
+    fn lcov2() {
+        let v1 = "TN:\nSF:a.c\nFN:1,main\nFNDA:2,main\nBRDA:1,0,0,1\nDA:1,2\nend_of_record\n";
+        let v2 = "\
+VER:2.0
+TN:
+SF:a.c
+FNL:0,1,3
+FNA:0,2,main
+BRDA:1,e0,f(x, y) == True,1
+BRDA:1,e0,f(x, y) == False,0
+DA:1,2
+end_of_record
+";
+        assert_eq!(detect(v1), Version::V1);
+        assert_eq!(detect(v2), Version::V2);
+        assert_eq!(detect("SF:a.c\nFN:1,3,main\nend_of_record\n"), Version::V2);
+        assert_eq!(shim(v1, false), v1);
+        let (shimmed, ends) = shim_with_ends(v2, false);
+        assert_eq!(
+            shimmed,
+            "TN:\nSF:a.c\nFN:1,main\nFNDA:2,main\nBRDA:1,0,0,1\nBRDA:1,0,1,0\nDA:1,2\nend_of_record\n"
+        );
+        assert_eq!(ends[&PathBuf::from("a.c")]["main"], 3);
+        assert!(shim(v2, true).starts_with("VER:2.0\n"));
+
+        let shimmed = Report::from_reader(Reader::new(shimmed.as_bytes())).unwrap();
+        let v1 = Report::from_reader(Reader::new(v1.as_bytes())).unwrap();
+        let functions =
+            |report: &Report| report.sections.values().next().unwrap().functions.clone();
+        assert_eq!(functions(&shimmed), functions(&v1));
+    }
+
+    #[test]
+    fn function_end_lines() {
+        let input = "SF:a.c\nFN:1,3,main\nFNDA:2,main\nDA:1,2\nend_of_record\n";
+        let (shimmed, ends) = shim_with_ends(input, false);
+        assert_eq!(
+            shimmed,
+            "SF:a.c\nFN:1,main\nFNDA:2,main\nDA:1,2\nend_of_record\n"
+        );
+        assert_eq!(ends[&PathBuf::from("a.c")]["main"], 3);
+
+        // Without the shim the lcov crate reads the function `3,main`
+        let mut report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        assert_eq!(report.sections.values().next().unwrap().functions.len(), 2);
+        normalize(&mut report);
+        let functions = &report.sections.values().next().unwrap().functions;
+        assert_eq!(functions.len(), 1);
+        assert_eq!(
+            functions[&FunctionKey {
+                name: "main".to_owned()
+            }]
+                .count,
+            2
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Traversal of the changes between a baseline and a current report, for the renderers and
+//! the analyses which don't need the whole `Report` structure. The sections of the test
+//! names are merged by file, as in `summary::by_file`.
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use lcov::report::section::branch::{Key as BranchKey, Value as BranchValue};
+use lcov::report::section::function::Value as FunctionValue;
+use lcov::report::section::line::Value as LineValue;
+use lcov::report::section::Value as SectionValue;
+use lcov::Report;
+
+use crate::summary;
+
+/// Callbacks of `visit`, the records are `None` where a report doesn't have them. All of them
+/// do nothing by default.
+pub trait DiffVisitor {
+    /// Every file of either report, in the path order.
+    fn enter_section(&mut self, _path: &Path) {}
+    fn line_changed(
+        &mut self,
+        _line: u32,
+        _baseline: Option<&LineValue>,
+        _current: Option<&LineValue>,
+    ) {
+    }
+    fn function_changed(
+        &mut self,
+        _name: &str,
+        _baseline: Option<&FunctionValue>,
+        _current: Option<&FunctionValue>,
+    ) {
+    }
+    fn branch_changed(
+        &mut self,
+        _key: &BranchKey,
+        _baseline: Option<&BranchValue>,
+        _current: Option<&BranchValue>,
+    ) {
+    }
+    fn leave_section(&mut self, _path: &Path) {}
+}
+
+/// Keys of either map whose values differ.
+fn changed<'a, K: Ord, V: PartialEq>(
+    baseline: &'a BTreeMap<K, V>,
+    current: &'a BTreeMap<K, V>,
+) -> impl Iterator<Item = (&'a K, Option<&'a V>, Option<&'a V>)> {
+    let keys: BTreeSet<&K> = baseline.keys().chain(current.keys()).collect();
+    keys.into_iter().filter_map(move |key| {
+        let (old, new) = (baseline.get(key), current.get(key));
+        if old == new {
+            None
+        } else {
+            Some((key, old, new))
+        }
+    })
+}
+
+/// Calls the visitor for the files and the records of them which differ.
+pub fn visit<V: DiffVisitor + ?Sized>(baseline: &Report, current: &Report, visitor: &mut V) {
+    let (baseline, current) = (summary::by_file(baseline), summary::by_file(current));
+    let paths: BTreeSet<&Path> = baseline
+        .keys()
+        .chain(current.keys())
+        .map(|path| path.as_path())
+        .collect();
+    let empty = SectionValue::default();
+    for path in paths {
+        let old = baseline.get(path).unwrap_or(&empty);
+        let new = current.get(path).unwrap_or(&empty);
+        visitor.enter_section(path);
+        for (key, old, new) in changed(&old.functions, &new.functions) {
+            visitor.function_changed(&key.name, old, new);
+        }
+        for (key, old, new) in changed(&old.branches, &new.branches) {
+            visitor.branch_changed(key, old, new);
+        }
+        for (key, old, new) in changed(&old.lines, &new.lines) {
+            visitor.line_changed(key.line, old, new);
+        }
+        visitor.leave_section(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{visit, DiffVisitor};
+    use crate::builder::ReportBuilder;
+    use lcov::report::section::line::Value as LineValue;
+    use std::path::Path;
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    impl DiffVisitor for Log {
+        fn enter_section(&mut self, path: &Path) {
+            self.0.push(format!("SF:{}", path.display()));
+        }
+
+        fn line_changed(
+            &mut self,
+            line: u32,
+            baseline: Option<&LineValue>,
+            current: Option<&LineValue>,
+        ) {
+            let count = |value: Option<&LineValue>| value.map(|value| value.count);
+            self.0.push(format!(
+                "DA:{} {:?} -> {:?}",
+                line,
+                count(baseline),
+                count(current)
+            ));
+        }
+    }
+
+    #[test]
+    fn visit_changes() {
+        let baseline = ReportBuilder::file("a.c")
+            .line(1, 1)
+            .line(2, 0)
+            .test("unit")
+            .next_file("b.c")
+            .line(1, 1)
+            .build();
+        let current = ReportBuilder::file("a.c")
+            .line(1, 1)
+            .line(2, 3)
+            .line(3, 0)
+            .build();
+        let mut log = Log::default();
+        visit(&baseline, &current, &mut log);
+        assert_eq!(
+            log.0,
+            vec![
+                "SF:a.c",
+                "DA:2 Some(0) -> Some(3)",
+                "DA:3 None -> Some(0)",
+                "SF:b.c",
+                "DA:1 Some(1) -> None",
+            ]
+        );
+    }
+}
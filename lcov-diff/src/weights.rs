@@ -0,0 +1,118 @@
+//! Weighted line coverage: lines of the important code (by path pattern or by externally
+//! supplied per-line weights, e.g. the cyclomatic complexity) count more.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use lcov::Report;
+
+use crate::owners;
+use crate::summary;
+
+/// Weights of the lines, 1.0 unless a rule matches.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Weights {
+    /// CODEOWNERS-like patterns, the last matching one wins.
+    patterns: Vec<(String, f64)>,
+    /// Weights of the single lines by the repository relative path, override the patterns.
+    lines: BTreeMap<PathBuf, BTreeMap<u32, f64>>,
+}
+
+/// Weighted number of the instrumented and covered lines.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Score {
+    pub found: f64,
+    pub hit: f64,
+}
+
+impl Score {
+    pub fn percent(&self) -> Option<f64> {
+        if self.found > 0.0 {
+            Some(self.hit / self.found * 100.0)
+        } else {
+            None
+        }
+    }
+}
+
+impl Weights {
+    /// Parses `<pattern> <weight>` rules and `<path>:<line>[-<line>] <weight>` line weights,
+    /// `#` starts a comment.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut weights = Weights::default();
+        for (number, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || format!("invalid weight rule {}: {}", number + 1, line);
+            let (target, weight) = line.rsplit_once(char::is_whitespace).ok_or_else(invalid)?;
+            let weight: f64 = weight.parse().map_err(|_| invalid())?;
+            let target = target.trim();
+            let range = target.rsplit_once(':').and_then(|(path, range)| {
+                let (start, end) = range.split_once('-').unwrap_or((range, range));
+                Some((path, start.parse::<u32>().ok()?, end.parse::<u32>().ok()?))
+            });
+            match range {
+                Some((path, start, end)) => {
+                    let lines = weights.lines.entry(PathBuf::from(path)).or_default();
+                    lines.extend((start..=end).map(|line| (line, weight)));
+                }
+                None => weights.patterns.push((target.to_owned(), weight)),
+            }
+        }
+        Ok(weights)
+    }
+
+    /// Weight of the line of the repository relative path.
+    pub fn weight(&self, path: &Path, line: u32) -> f64 {
+        if let Some(weight) = self.lines.get(path).and_then(|lines| lines.get(&line)) {
+            return *weight;
+        }
+        let path = path.to_string_lossy().replace('\\', "/");
+        self.patterns
+            .iter()
+            .rev()
+            .find(|(pattern, _)| owners::matches(pattern, &path))
+            .map_or(1.0, |(_, weight)| *weight)
+    }
+
+    /// Weighted line coverage of the report, absolute source paths are made relative to `root`.
+    pub fn score(&self, report: &Report, root: &Path) -> Score {
+        let mut score = Score::default();
+        for (path, section) in summary::by_file(report) {
+            let path = path.strip_prefix(root).unwrap_or(&path);
+            for (key, value) in &section.lines {
+                let weight = self.weight(path, key.line);
+                score.found += weight;
+                if value.count > 0 {
+                    score.hit += weight;
+                }
+            }
+        }
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Weights;
+    use lcov::{Reader, Report};
+    use std::path::Path;
+
+    #[test]
+    fn weighted() {
+        let weights = Weights::parse(
+            "# core code matters\nsrc/parser/ 3\ntests/ 0\nsrc/parser/lex.c:5-6 10\n",
+        )
+        .unwrap();
+        assert_eq!(weights.weight(Path::new("src/parser/ast.c"), 1), 3.0);
+        assert_eq!(weights.weight(Path::new("src/parser/lex.c"), 6), 10.0);
+        assert_eq!(weights.weight(Path::new("src/main.c"), 1), 1.0);
+        assert!(Weights::parse("src/ heavy").is_err());
+
+        let input = "SF:/repo/src/parser/ast.c\nDA:1,1\nDA:2,0\nend_of_record\nSF:/repo/tests/t.c\nDA:1,0\nend_of_record\nSF:/repo/src/main.c\nDA:1,0\nend_of_record\n";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let score = weights.score(&report, Path::new("/repo"));
+        assert_eq!((score.found, score.hit), (7.0, 3.0));
+    }
+}
@@ -0,0 +1,139 @@
+//! What goes into the written lcov, since genhtml, Codecov and the other consumers are picky
+//! in different ways.
+use lcov::report::section::Key as SectionKey;
+use lcov::Record;
+
+use crate::extras::Extras;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct WriteOptions {
+    /// `TN:` records.
+    pub test_names: bool,
+    /// Checksums of the `DA:` records.
+    pub checksums: bool,
+    /// `FN:`, `FNDA:`, `FNF:` and `FNH:` records.
+    pub functions: bool,
+    /// `BRDA:`, `BRF:` and `BRH:` records.
+    pub branches: bool,
+    /// Derived `LF:`, `LH:`, `FNF:`, `FNH:`, `BRF:` and `BRH:` records.
+    pub summaries: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            test_names: true,
+            checksums: true,
+            functions: true,
+            branches: true,
+            summaries: true,
+        }
+    }
+}
+
+impl WriteOptions {
+    pub const OMITTABLE: &'static [&'static str] = &[
+        "test-names",
+        "checksums",
+        "functions",
+        "branches",
+        "summaries",
+    ];
+
+    /// Turns off one of `OMITTABLE`.
+    pub fn omit(&mut self, what: &str) -> Result<(), String> {
+        match what {
+            "test-names" => self.test_names = false,
+            "checksums" => self.checksums = false,
+            "functions" => self.functions = false,
+            "branches" => self.branches = false,
+            "summaries" => self.summaries = false,
+            _ => return Err(format!("unknown lcov records: {}", what)),
+        }
+        Ok(())
+    }
+
+    /// Record as written, `None` if it's omitted.
+    pub fn record(&self, record: Record) -> Option<Record> {
+        let keep = match &record {
+            Record::TestName { .. } => self.test_names,
+            Record::FunctionName { .. } | Record::FunctionData { .. } => self.functions,
+            Record::FunctionsFound { .. } | Record::FunctionsHit { .. } => {
+                self.functions && self.summaries
+            }
+            Record::BranchData { .. } => self.branches,
+            Record::BranchesFound { .. } | Record::BranchesHit { .. } => {
+                self.branches && self.summaries
+            }
+            Record::LinesFound { .. } | Record::LinesHit { .. } => self.summaries,
+            _ => true,
+        };
+        match record {
+            Record::LineData { line, count, .. } if !self.checksums => Some(Record::LineData {
+                line,
+                count,
+                checksum: None,
+            }),
+            record => Some(record).filter(|_| keep),
+        }
+    }
+
+    /// Lines of the records of a report (see `order`) with the unknown records of `extras`: the
+    /// ones outside of the sections first, the ones of a section at its end. The records of the
+    /// sections missing in the report are dropped.
+    pub fn lines<I: IntoIterator<Item = Record>>(
+        &self,
+        records: I,
+        extras: &Extras,
+    ) -> Vec<String> {
+        let mut lines = extras.header.clone();
+        let mut key = SectionKey {
+            test_name: String::new(),
+            source_file: Default::default(),
+        };
+        for record in records {
+            match &record {
+                Record::TestName { name } => key.test_name = name.clone(),
+                Record::SourceFile { path } => key.source_file = path.clone(),
+                Record::EndOfRecord => {
+                    lines.extend(extras.sections.get(&key).into_iter().flatten().cloned());
+                    key.test_name.clear();
+                }
+                _ => {}
+            }
+            if let Some(record) = self.record(record) {
+                lines.push(record.to_string());
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WriteOptions;
+    use crate::extras::Extras;
+    use lcov::{Reader, Report};
+
+    #[test]
+    fn omit_records() {
+        let input =
+            "TN:unit\nSF:a.c\nFN:1,main\nFNDA:1,main\nBRDA:1,0,0,1\nDA:1,1,abc\nend_of_record\n";
+        let report = Report::from_reader(Reader::new(input.as_bytes())).unwrap();
+        let mut options = WriteOptions::default();
+        for what in WriteOptions::OMITTABLE {
+            options.omit(what).unwrap();
+        }
+        assert_eq!(
+            options.lines(report.clone().into_records(), &Extras::default()),
+            vec!["SF:a.c", "DA:1,1", "end_of_record"]
+        );
+        assert_eq!(
+            WriteOptions::default()
+                .lines(report.into_records(), &Extras::default())
+                .len(),
+            13
+        );
+        assert!(options.omit("TN").is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//! Minimal xml reader for the xml based coverage formats.
+//!
+//! It only reports elements, their attributes and text. Comments, processing instructions,
+//! CDATA and doctype are skipped since none of the supported formats keeps data there.
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Event {
+    /// Opening tag, empty elements are reported as `Start` followed by `End`.
+    Start {
+        name: String,
+        attrs: HashMap<String, String>,
+    },
+    End {
+        name: String,
+    },
+    /// Non-blank text between tags, trimmed.
+    Text(String),
+}
+
+impl Event {
+    /// Attribute of the start tag.
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        match self {
+            Event::Start { attrs, .. } => attrs.get(name).map(String::as_str),
+            Event::End { .. } | Event::Text(_) => None,
+        }
+    }
+}
+
+/// Syntax error with the byte offset of the document.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Error {
+    pub offset: usize,
+    pub message: &'static str,
+}
+
+/// Iterator over the element events of the document.
+#[derive(Debug)]
+pub struct Reader<'a> {
+    input: &'a str,
+    pos: usize,
+    pending_end: Option<String>,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Reader {
+            input,
+            pos: 0,
+            pending_end: None,
+        }
+    }
+
+    fn error(&self, message: &'static str) -> Error {
+        Error {
+            offset: self.pos,
+            message,
+        }
+    }
+
+    fn skip_past(&mut self, end: &str) -> Result<(), Error> {
+        match self.input[self.pos..].find(end) {
+            Some(idx) => {
+                self.pos += idx + end.len();
+                Ok(())
+            }
+            None => Err(self.error("unterminated markup")),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, Error> {
+        let rest = &self.input[self.pos..];
+        let len = rest
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/' || c == '=')
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("name expected"));
+        }
+        self.pos += len;
+        Ok(rest[..len].to_owned())
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn tag(&mut self) -> Result<Event, Error> {
+        // `<` is already consumed
+        if self.input[self.pos..].starts_with('/') {
+            self.pos += 1;
+            let name = self.name()?;
+            self.skip_whitespace();
+            if !self.input[self.pos..].starts_with('>') {
+                return Err(self.error("`>` expected"));
+            }
+            self.pos += 1;
+            return Ok(Event::End { name });
+        }
+
+        let name = self.name()?;
+        let mut attrs = HashMap::new();
+        loop {
+            self.skip_whitespace();
+            let rest = &self.input[self.pos..];
+            if rest.starts_with("/>") {
+                self.pos += 2;
+                self.pending_end = Some(name.clone());
+                break;
+            }
+            if rest.starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let attr = self.name()?;
+            self.skip_whitespace();
+            if !self.input[self.pos..].starts_with('=') {
+                return Err(self.error("`=` expected"));
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+            let quote = match self.input[self.pos..].chars().next() {
+                Some(quote @ '"') | Some(quote @ '\'') => quote,
+                _ => return Err(self.error("quoted attribute value expected")),
+            };
+            self.pos += 1;
+            let len = self.input[self.pos..]
+                .find(quote)
+                .ok_or_else(|| self.error("unterminated attribute value"))?;
+            let value = unescape(&self.input[self.pos..self.pos + len]);
+            self.pos += len + 1;
+            attrs.insert(attr, value);
+        }
+        Ok(Event::Start { name, attrs })
+    }
+}
+
+impl<'a> Iterator for Reader<'a> {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(name) = self.pending_end.take() {
+            return Some(Ok(Event::End { name }));
+        }
+        loop {
+            let rest = &self.input[self.pos..];
+            let idx = rest.find('<').unwrap_or(rest.len());
+            let text = rest[..idx].trim();
+            if !text.is_empty() {
+                self.pos += idx;
+                return Some(Ok(Event::Text(unescape(text))));
+            }
+            if idx == rest.len() {
+                return None;
+            }
+            self.pos += idx + 1;
+            let rest = &self.input[self.pos..];
+            let skipped = if rest.starts_with("!--") {
+                self.skip_past("-->")
+            } else if rest.starts_with("![CDATA[") {
+                self.skip_past("]]>")
+            } else if rest.starts_with('?') {
+                self.skip_past("?>")
+            } else if rest.starts_with('!') {
+                // doctype, internal subsets aren't supported
+                self.skip_past(">")
+            } else {
+                return Some(self.tag());
+            };
+            if let Err(e) = skipped {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Replaces predefined entities and character references.
+pub fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_owned();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find('&') {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let entity = &rest[1..end];
+        let ch = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16)
+                .ok()
+                .and_then(char::from_u32),
+            _ if entity.starts_with('#') => entity[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match ch {
+            Some(ch) => {
+                out.push(ch);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, Reader};
+
+    #[test]
+    fn events() {
+        let input = "<?xml version=\"1.0\"?>\n<!DOCTYPE coverage>\n<!-- comment -->\n\
+                     <a x=\"1 &amp; 2\"><b y='&#65;'/>text</a>";
+        let events: Vec<Event> = Reader::new(input).collect::<Result<_, _>>().unwrap();
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[0].attr("x"), Some("1 & 2"));
+        assert_eq!(events[1].attr("y"), Some("A"));
+        assert_eq!(
+            events[2],
+            Event::End {
+                name: "b".to_owned()
+            }
+        );
+        assert_eq!(events[3], Event::Text("text".to_owned()));
+        assert!(Reader::new("<a x=1>").next().unwrap().is_err());
+    }
+}
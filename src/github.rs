@@ -0,0 +1,213 @@
+//! GitHub integration: sticky pull request comment with the coverage summary and the baseline
+//! fetched from the Actions artifacts.
+//!
+//! All requests are made with `gh` CLI, so the authentication is the same as for `gh`
+//! itself (`GH_TOKEN`/`GITHUB_TOKEN` in CI).
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use lcov_diff::json::{self, Value};
+use log::{debug, info};
+
+use crate::remote::RemoteOptions;
+
+/// Hidden marker used to find the comment posted before.
+const MARKER: &str = "<!-- lcov-diff -->";
+
+/// Pages of the workflow runs looked through for the baseline artifact, of `RUNS_PER_PAGE`.
+const MAX_RUN_PAGES: u32 = 10;
+const RUNS_PER_PAGE: u32 = 50;
+
+/// Pull request to comment, `repo` is `owner/name`.
+#[derive(Debug, Clone)]
+pub struct PullRequest {
+    pub repo: String,
+    pub number: u64,
+}
+
+/// Updates the comment posted by previous runs or creates a new one.
+pub fn post_sticky_comment(
+    pr: &PullRequest,
+    body: &str,
+    remote: &RemoteOptions,
+) -> Result<(), Box<dyn Error>> {
+    let body = format!("{}\n{}", MARKER, body);
+    match find_comment(pr, remote)? {
+        Some(id) => {
+            info!("Updating comment {} of {}#{}", id, pr.repo, pr.number);
+            gh(
+                &[
+                    "api",
+                    "-X",
+                    "PATCH",
+                    &format!("repos/{}/issues/comments/{}", pr.repo, id),
+                    "-F",
+                    "body=@-",
+                ],
+                &body,
+                remote,
+            )?;
+        }
+        None => {
+            info!("Posting comment to {}#{}", pr.repo, pr.number);
+            gh(
+                &[
+                    "api",
+                    "-X",
+                    "POST",
+                    &format!("repos/{}/issues/{}/comments", pr.repo, pr.number),
+                    "-F",
+                    "body=@-",
+                ],
+                &body,
+                remote,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Coverage artifact of the workflow runs, `file` is the report inside of it.
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub repo: String,
+    pub name: String,
+    pub branch: String,
+    pub file: Option<PathBuf>,
+}
+
+/// Downloads (and unzips) the artifact of the latest successful run of the branch into `dir`,
+/// returns the path of the report.
+pub fn download_baseline(
+    artifact: &Artifact,
+    dir: &Path,
+    remote: &RemoteOptions,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let run = find_run(artifact, remote)?.ok_or_else(|| {
+        format!(
+            "no baseline artifact found: no successful run of {} has `{}` artifact (looked through the latest {} runs at most)",
+            artifact.branch,
+            artifact.name,
+            MAX_RUN_PAGES * RUNS_PER_PAGE
+        )
+    })?;
+    info!("Downloading `{}` artifact of run {}", artifact.name, run);
+    gh(
+        &[
+            "run",
+            "download",
+            &run.to_string(),
+            "-R",
+            &artifact.repo,
+            "-n",
+            &artifact.name,
+            "-D",
+            &dir.to_string_lossy(),
+        ],
+        "",
+        remote,
+    )?;
+    if let Some(file) = &artifact.file {
+        return Ok(dir.join(file));
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        files.push(entry?.path());
+    }
+    match files.as_slice() {
+        [file] => Ok(file.clone()),
+        _ => Err(format!(
+            "`{}` artifact has {} files, choose the report with --baseline-artifact-file",
+            artifact.name,
+            files.len()
+        )
+        .into()),
+    }
+}
+
+/// Latest successful run of the branch with the unexpired artifact, the runs are paged
+/// newest first until one is found or `MAX_RUN_PAGES` are looked through.
+fn find_run(artifact: &Artifact, remote: &RemoteOptions) -> Result<Option<u64>, Box<dyn Error>> {
+    for page in 1..=MAX_RUN_PAGES {
+        let runs = gh(
+            &[
+                "api",
+                "-X",
+                "GET",
+                &format!("repos/{}/actions/runs", artifact.repo),
+                "-f",
+                &format!("branch={}", artifact.branch),
+                "-f",
+                "status=success",
+                "-f",
+                &format!("per_page={}", RUNS_PER_PAGE),
+                "-f",
+                &format!("page={}", page),
+                "--jq",
+                ".workflow_runs[].id",
+            ],
+            "",
+            remote,
+        )?;
+        if runs.trim().is_empty() {
+            return Ok(None);
+        }
+        for run in runs.lines() {
+            // The name is compared here, it can't be quoted into the `--jq` filter safely
+            let artifacts = gh(
+                &[
+                    "api",
+                    "-X",
+                    "GET",
+                    &format!("repos/{}/actions/runs/{}/artifacts", artifact.repo, run),
+                    "-f",
+                    &format!("name={}", artifact.name),
+                ],
+                "",
+                remote,
+            )?;
+            if has_artifact(&json::parse(&artifacts)?, &artifact.name) {
+                return Ok(Some(run.trim().parse()?));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Whether the artifacts of the run have the unexpired one of the name.
+fn has_artifact(listing: &Value, name: &str) -> bool {
+    listing
+        .get("artifacts")
+        .and_then(Value::as_array)
+        .unwrap_or_default()
+        .iter()
+        .any(|artifact| {
+            artifact.get("name").and_then(Value::as_str) == Some(name)
+                && artifact.get("expired") != Some(&Value::Bool(true))
+        })
+}
+
+fn find_comment(pr: &PullRequest, remote: &RemoteOptions) -> Result<Option<u64>, Box<dyn Error>> {
+    let ids = gh(
+        &[
+            "api",
+            "--paginate",
+            &format!("repos/{}/issues/{}/comments", pr.repo, pr.number),
+            "--jq",
+            &format!(".[] | select(.body | startswith(\"{}\")) | .id", MARKER),
+        ],
+        "",
+        remote,
+    )?;
+    Ok(match ids.lines().next() {
+        Some(id) => Some(id.trim().parse()?),
+        None => None,
+    })
+}
+
+fn gh(args: &[&str], input: &str, remote: &RemoteOptions) -> Result<String, Box<dyn Error>> {
+    debug!("gh {:?}", args);
+    let output = remote.run(&format!("gh {}", args[0]), "gh", args, input.as_bytes())?;
+    Ok(String::from_utf8(output)?)
+}
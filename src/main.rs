@@ -1,57 +1,1052 @@
-use lcov_diff::diff_reports;
+#[cfg(feature = "github")]
+mod github;
+#[cfg(any(feature = "http", feature = "github"))]
+mod remote;
+#[cfg(feature = "http")]
+mod webhook;
 
+use lcov_diff::blame;
+use lcov_diff::cargo;
+use lcov_diff::changes::{self, Changes};
+use lcov_diff::check::{Thresholds, Violation};
+use lcov_diff::checksum;
+use lcov_diff::ci::{self, CiEnv};
+use lcov_diff::clusters;
+use lcov_diff::debt::Debt;
+use lcov_diff::explain;
+use lcov_diff::extras::Extras;
+use lcov_diff::filter::{self, PathFilter};
+use lcov_diff::fingerprint::fingerprint;
+use lcov_diff::functions;
+use lcov_diff::import::{self, LoadOptions, Loaded};
+use lcov_diff::json::Value;
+use lcov_diff::memory;
+use lcov_diff::notes::{parse_snapshot, Notes};
+use lcov_diff::order::SectionOrder;
+#[cfg(feature = "otel")]
+use lcov_diff::otel;
+use lcov_diff::owners::{self, CodeOwners};
+use lcov_diff::permalink::{self, Permalink};
+use lcov_diff::process::{diff_and_process, DropZeros, EmptySections, PostProcessor};
+use lcov_diff::proto::DiffResult;
+use lcov_diff::render;
+#[cfg(feature = "http")]
+use lcov_diff::render::alert::Alert;
+use lcov_diff::render::badge::{Badge, Metric};
+use lcov_diff::render::cobertura::Cobertura;
+use lcov_diff::render::codecov::{self, Codecov};
+use lcov_diff::render::coveralls::{self, Coveralls};
+use lcov_diff::render::csv::{Column, Table};
+use lcov_diff::render::email::Email;
+use lcov_diff::render::html::SideBySide;
+use lcov_diff::render::markdown::Markdown;
+use lcov_diff::render::number::{self, Numbers, Rounding};
+use lcov_diff::render::pdf::Pdf;
+use lcov_diff::render::prometheus::{self, Prometheus};
+use lcov_diff::render::slack::Slack;
+use lcov_diff::render::teams::Teams;
+use lcov_diff::selection;
+use lcov_diff::serve::Server;
+use lcov_diff::session::BaselineSession;
+use lcov_diff::severity::{self, Finding, Kind, Severities, Severity};
+use lcov_diff::shard;
+use lcov_diff::signature::{PublicKey, Verifier};
+use lcov_diff::snippet::Sources;
+use lcov_diff::sourcemap::{self, SourceMap};
+use lcov_diff::statsd::StatsD;
+use lcov_diff::summary::{self, Comparison, Rank, Totals};
+use lcov_diff::suppress::{self, Suppressions};
+use lcov_diff::timings::{self, Timings};
+use lcov_diff::trend::{Point, Regression, Trend};
+use lcov_diff::version::{self, FunctionEnds};
+use lcov_diff::weights::{Score, Weights};
+use lcov_diff::write::WriteOptions;
+use lcov_diff::DiffOptions;
+
+use std::collections::BTreeSet;
+use std::env;
 use std::error::Error;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use lcov::Report;
+use log::{debug, error, info, warn};
+#[cfg(any(feature = "http", feature = "github"))]
+use remote::RemoteOptions;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::net::TcpListener;
+use std::process::{Command, ExitCode};
+#[cfg(feature = "otel")]
+use std::time::Instant;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use structopt::StructOpt;
+use tempfile::NamedTempFile;
+#[cfg(feature = "github")]
+use tempfile::TempDir;
+
+/// Output formats of the diff
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Format {
+    Lcov,
+    Markdown,
+    Github,
+    Gitlab,
+    Teamcity,
+    Azure,
+    Sarif,
+    Junit,
+    Cobertura,
+    Coveralls,
+    Codecov,
+    Slack,
+    Teams,
+    Email,
+    Pdf,
+    Csv,
+    Tsv,
+    Prometheus,
+    Patch,
+}
+
+impl Format {
+    const VARIANTS: &'static [&'static str] = &[
+        "lcov",
+        "markdown",
+        "github",
+        "gitlab",
+        "teamcity",
+        "azure",
+        "sarif",
+        "junit",
+        "cobertura",
+        "coveralls",
+        "codecov",
+        "slack",
+        "teams",
+        "email",
+        "pdf",
+        "csv",
+        "tsv",
+        "prometheus",
+        "patch",
+    ];
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lcov" => Ok(Format::Lcov),
+            "markdown" => Ok(Format::Markdown),
+            "github" => Ok(Format::Github),
+            "gitlab" => Ok(Format::Gitlab),
+            "teamcity" => Ok(Format::Teamcity),
+            "azure" => Ok(Format::Azure),
+            "sarif" => Ok(Format::Sarif),
+            "junit" => Ok(Format::Junit),
+            "cobertura" => Ok(Format::Cobertura),
+            "coveralls" => Ok(Format::Coveralls),
+            "codecov" => Ok(Format::Codecov),
+            "slack" => Ok(Format::Slack),
+            "teams" => Ok(Format::Teams),
+            "email" => Ok(Format::Email),
+            "pdf" => Ok(Format::Pdf),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "prometheus" => Ok(Format::Prometheus),
+            "patch" => Ok(Format::Patch),
+            _ => Err(format!("unknown format: {}", s)),
+        }
+    }
+}
+
+/// Defines CLI structure
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(
+    name = "lcov-diff",
+    about = "lcov-diff utility to get diff of two lcov files"
+)]
+struct Cli {
+    #[structopt(short, long, help = "Show Debug logging")]
+    verbose: bool,
+
+    #[structopt(
+        name = "PATH",
+        short = "o",
+        long = "output",
+        help = "output file to write (stdout if not presented)",
+        parse(from_os_str)
+    )]
+    output: Option<PathBuf>,
+
+    #[structopt(
+        long = "summary-json",
+        help = "Summary json written by azure format (default coverage-summary.json)",
+        parse(from_os_str)
+    )]
+    summary_json: Option<PathBuf>,
+
+    #[structopt(
+        long = "protobuf",
+        help = "Write the diff results as protobuf DiffResult message (lcov-diff/proto/lcov_diff.proto)",
+        parse(from_os_str)
+    )]
+    protobuf: Option<PathBuf>,
+
+    #[structopt(
+        short = "f",
+        long = "format",
+        help = "Output format, summary formats compare the first file against the second one",
+        default_value = "lcov",
+        possible_values = Format::VARIANTS
+    )]
+    format: Format,
+
+    #[structopt(
+        long = "max-files",
+        help = "Maximum number of files listed by summary formats",
+        default_value = "10"
+    )]
+    max_files: usize,
+
+    #[structopt(
+        long = "max-lines",
+        help = "Maximum number of uncovered new lines listed by summary formats",
+        default_value = "20"
+    )]
+    max_lines: usize,
+
+    #[structopt(
+        long = "max-bytes",
+        help = "Maximum length in bytes of the markdown summary, the rows past it are counted in a footer",
+        default_value = "60000"
+    )]
+    max_bytes: usize,
+
+    #[structopt(
+        long = "file-threshold",
+        help = "Skip files whose line coverage changed less than this (in percentage points)",
+        default_value = "0"
+    )]
+    file_threshold: f64,
+
+    #[structopt(
+        long = "min-lines",
+        help = "Fail if the line coverage of the first file is below this (in percents)"
+    )]
+    min_lines: Option<f64>,
+
+    #[structopt(
+        long = "max-drop",
+        help = "Fail if the line coverage dropped more than this (in percentage points)"
+    )]
+    max_drop: Option<f64>,
+
+    #[structopt(long = "badge", help = "Write coverage badge svg", parse(from_os_str))]
+    badge: Option<PathBuf>,
+
+    #[structopt(
+        long = "badge-json",
+        help = "Write shields.io endpoint json of the coverage badge",
+        parse(from_os_str)
+    )]
+    badge_json: Option<PathBuf>,
+
+    #[structopt(
+        long = "badge-metric",
+        help = "Number shown by the badges",
+        default_value = "total",
+        possible_values = Metric::VARIANTS
+    )]
+    badge_metric: Metric,
+
+    #[structopt(
+        long = "badge-red",
+        help = "Badge is red if the coverage is below this (in percents)",
+        default_value = "50"
+    )]
+    badge_red: f64,
+
+    #[structopt(
+        long = "badge-yellow",
+        help = "Badge is yellow if the coverage is below this (in percents)",
+        default_value = "80"
+    )]
+    badge_yellow: f64,
+
+    #[structopt(
+        long = "coveralls-token",
+        help = "Repository token of the coveralls format",
+        env = "COVERALLS_REPO_TOKEN",
+        hide_env_values = true
+    )]
+    coveralls_token: Option<String>,
+
+    #[structopt(
+        long = "coveralls-service",
+        help = "Service name of the coveralls format",
+        env = "COVERALLS_SERVICE_NAME",
+        default_value = "lcov-diff"
+    )]
+    coveralls_service: String,
+
+    #[structopt(
+        long = "coveralls-job",
+        help = "Service job id of the coveralls format",
+        env = "COVERALLS_SERVICE_JOB_ID"
+    )]
+    coveralls_job: Option<String>,
+
+    #[structopt(
+        long = "codecov-fix",
+        help = "Path fix rule of the codecov format, `before::after` prefix replacement",
+        parse(try_from_str = codecov::parse_fix),
+        number_of_values = 1
+    )]
+    codecov_fixes: Vec<(String, String)>,
+
+    #[structopt(
+        long = "report-url",
+        help = "Link to the full report in the slack, teams and email messages"
+    )]
+    report_url: Option<String>,
+
+    #[structopt(
+        long = "columns",
+        help = "Columns of the csv and tsv formats (all by default)",
+        use_delimiter = true,
+        possible_values = Column::VARIANTS
+    )]
+    columns: Vec<Column>,
+
+    #[structopt(
+        long = "cargo-workspace",
+        help = "Add coverage by crate of the Cargo workspace in --src to the markdown summary and check the thresholds per crate too"
+    )]
+    cargo_workspace: bool,
+
+    #[structopt(
+        long = "test-clusters",
+        help = "Write json clusters of the tests (`TN:`) of the first file with nearly identical coverage, the candidates for removal",
+        parse(from_os_str)
+    )]
+    test_clusters: Option<PathBuf>,
+
+    #[structopt(
+        long = "cluster-threshold",
+        help = "Minimal Jaccard similarity of the covered lines of the clustered tests",
+        default_value = "0.95"
+    )]
+    cluster_threshold: f64,
+
+    #[structopt(
+        long = "dead-code",
+        help = "Write markdown list of the functions never executed in the first file and all --dead-code-suite files",
+        parse(from_os_str)
+    )]
+    dead_code: Option<PathBuf>,
+
+    #[structopt(
+        long = "dead-code-suite",
+        help = "Report of another test suite for --dead-code",
+        parse(from_os_str),
+        number_of_values = 1
+    )]
+    dead_code_suite: Vec<PathBuf>,
+
+    #[structopt(
+        long = "dead-code-exclude",
+        help = "Skip the files whose path contains the text (e.g. `tests/`) in --dead-code",
+        number_of_values = 1
+    )]
+    dead_code_exclude: Vec<String>,
+
+    #[structopt(
+        long = "minimal-tests",
+        help = "Write json of the greedy minimal set of the tests (`TN:`) of the first file keeping its line coverage and of the redundant tests",
+        parse(from_os_str)
+    )]
+    minimal_tests: Option<PathBuf>,
+
+    #[structopt(
+        long = "precision",
+        help = "Digits after the decimal separator of the percentages",
+        default_value = "2"
+    )]
+    precision: usize,
+
+    #[structopt(
+        long = "rounding",
+        help = "Rounding of the percentages to the precision",
+        default_value = "half-up",
+        possible_values = Rounding::VARIANTS
+    )]
+    rounding: Rounding,
+
+    #[structopt(
+        long = "locale",
+        help = "Locale (`de`, `fr_FR.UTF-8`, ...) of the decimal separator of the human readable outputs, json keeps the dot"
+    )]
+    locale: Option<String>,
+
+    #[structopt(
+        long = "summary-only",
+        help = "Only print the total line, function and branch coverage of both files and the change, without the \
+                diff and the other outputs"
+    )]
+    summary_only: bool,
+
+    #[structopt(
+        long = "drop-zeros",
+        help = "Leave the records with a zero count out of the diff, so it only has the newly covered ones"
+    )]
+    drop_zeros: bool,
+
+    #[structopt(
+        long = "empty-sections",
+        help = "Handling of the sections without any record in the inputs and the diff: keep, drop or warn (keep them and warn)",
+        default_value = "keep",
+        possible_values = EmptySections::VARIANTS
+    )]
+    empty_sections: EmptySections,
+
+    #[structopt(
+        long = "large-input",
+        help = "Diff the sections on a thread per core, for the reports with thousands of files"
+    )]
+    large_input: bool,
+
+    #[structopt(
+        long = "keep-order",
+        help = "Write the sections of the lcov output in the order of the first file instead of sorted by the test name \
+                and the path"
+    )]
+    keep_order: bool,
+
+    #[structopt(
+        long = "lcov-omit",
+        help = "Leave the records out of the lcov output: `TN:` (test-names), the `DA:` checksums, `FN:`/`FNDA:` \
+                (functions), `BRDA:` (branches) or the derived `LF:`/`LH:`/... (summaries)",
+        number_of_values = 1,
+        possible_values = WriteOptions::OMITTABLE
+    )]
+    lcov_omit: Vec<String>,
+
+    #[structopt(
+        long = "lenient",
+        help = "Skip the malformed lines of the lcov files with a warning instead of failing, \
+                the sections missing `end_of_record` are closed"
+    )]
+    lenient: bool,
+
+    #[structopt(
+        long = "include",
+        help = "Only diff the files whose path contains the text (e.g. `services/api/`), the other sections of the \
+                lcov files are skipped without parsing them",
+        number_of_values = 1
+    )]
+    include: Vec<String>,
+
+    #[structopt(
+        long = "exclude",
+        help = "Leave out the files whose path contains the text (e.g. `vendor/`), even if included",
+        number_of_values = 1
+    )]
+    exclude: Vec<String>,
+
+    #[structopt(
+        long = "manifest",
+        help = "File with the paths of the files to keep, one per line, e.g. the file set of a release; the \
+                relative paths match the report paths ending with them",
+        parse(from_os_str)
+    )]
+    manifest: Option<PathBuf>,
+
+    #[structopt(
+        long = "shard",
+        help = "Diff only the files of the top-level directory of --src (`.` for the files in the root), to split \
+                the diff of a monorepo between processes; the lcov outputs of the shards can be concatenated"
+    )]
+    shard: Option<String>,
+
+    #[structopt(
+        long = "max-input-size",
+        help = "Fail before reading an input file larger than this (`512K`, `64M`, `2G`), or once a gzipped input or \
+                archive unpacks to more, instead of running out of memory halfway",
+        parse(try_from_str = memory::parse_size)
+    )]
+    max_input_size: Option<u64>,
+
+    #[structopt(
+        long = "keep-unknown-records",
+        help = "Read the lcov records the parser doesn't know (`VER:`, vendor extensions, comments) instead of failing, \
+                and write the ones of the first file back into the lcov output"
+    )]
+    keep_unknown_records: bool,
+
+    #[structopt(
+        long = "explain",
+        help = "Print on stderr why the line `path:line` has its status in the diff: the counts of its records, \
+                their checksums and the settings which affected it",
+        number_of_values = 1,
+        parse(try_from_str = explain::parse_location)
+    )]
+    explain: Vec<(PathBuf, u32)>,
+
+    #[structopt(
+        long = "context",
+        help = "Include this many source lines (read from --src) around the uncovered lines in the markdown, \
+                alert and SARIF outputs"
+    )]
+    context: Option<usize>,
+
+    #[structopt(
+        long = "line-ranges",
+        help = "Report the findings of consecutive lines as a single range (`foo.c:120-138`) in the annotations"
+    )]
+    line_ranges: bool,
+
+    #[structopt(
+        long = "verdict",
+        help = "Write json verdict of the checks (status, violations and totals) to the file",
+        parse(from_os_str)
+    )]
+    verdict: Option<PathBuf>,
+
+    #[structopt(
+        long = "checksums",
+        help = "Write sha256 manifest of the written outputs (the diff, html reports and json files) to the file",
+        parse(from_os_str)
+    )]
+    checksums: Option<PathBuf>,
+
+    #[structopt(
+        long = "verify-checksums",
+        help = "Fail unless the files of the sha256 manifest match it, before reading the inputs",
+        parse(from_os_str)
+    )]
+    verify_checksums: Option<PathBuf>,
+
+    #[structopt(
+        long = "baseline-public-key",
+        help = "Minisign public key (or key file) the second file or the --baseline-note note has to be signed with, \
+                checked by `minisign`"
+    )]
+    baseline_public_key: Option<String>,
+
+    #[structopt(
+        long = "baseline-signature",
+        help = "Minisign signature of the second file [default: <second file>.minisig], required for a \
+                --baseline-note note",
+        parse(from_os_str)
+    )]
+    baseline_signature: Option<PathBuf>,
+
+    #[structopt(
+        long = "severity",
+        help = "Severity of a kind of findings, `kind=error|warning|info|off`; errors fail the check. \
+                Kinds: lost-line, uncovered-new-line (warning by default), lost-branch, dropped-file",
+        parse(try_from_str = severity::parse_entry),
+        number_of_values = 1
+    )]
+    severity: Vec<(Kind, Severity)>,
+
+    #[structopt(
+        long = "ignore-suppressions",
+        help = "Don't read the `lcov-diff: allow-uncovered` comments of the sources in --src"
+    )]
+    ignore_suppressions: bool,
+
+    #[structopt(
+        long = "debt",
+        help = "File of the known uncovered lines (coverage debt), fail only on the uncovered lines which aren't in it",
+        parse(from_os_str)
+    )]
+    debt: Option<PathBuf>,
+
+    #[structopt(
+        long = "update-debt",
+        help = "Remove the lines covered now from the --debt file, or create it from all uncovered lines"
+    )]
+    update_debt: bool,
+
+    #[structopt(
+        long = "owners",
+        help = "CODEOWNERS (or mapping file of the same syntax) to add coverage by owner to the markdown summary and check the thresholds per owner",
+        parse(from_os_str)
+    )]
+    owners: Option<PathBuf>,
+
+    #[structopt(long = "codeowners", help = "Use CODEOWNERS file of --src as --owners")]
+    codeowners: bool,
+
+    #[structopt(
+        long = "owner-min-lines",
+        help = "Minimal line coverage of the owner's files, `owner=percent` (--min-lines by default)",
+        parse(try_from_str = owners::parse_limit),
+        number_of_values = 1
+    )]
+    owner_min_lines: Vec<(String, f64)>,
+
+    #[structopt(
+        long = "directory-depth",
+        help = "Add table of the directories with changed coverage, this deep below the common root, to the markdown summary, and fold the deeper directories of the side-by-side html index"
+    )]
+    directory_depth: Option<usize>,
+
+    #[structopt(
+        long = "function-table",
+        help = "Add table of the functions with changed line coverage to the markdown summary"
+    )]
+    function_table: bool,
+
+    #[structopt(
+        long = "weights",
+        help = "Line weights, `<pattern> <weight>` or `<path>:<line>[-<line>] <weight>` per line, to add the weighted line coverage to the markdown summary",
+        parse(from_os_str)
+    )]
+    weights: Option<PathBuf>,
+
+    #[structopt(
+        long = "top",
+        help = "Add lists of this many files with the largest coverage losses and gains to the markdown summary"
+    )]
+    top: Option<usize>,
+
+    #[structopt(
+        long = "rank",
+        help = "Measure of the --top lists: lines (covered lines gained or lost) or percent (percentage points)",
+        default_value = "percent",
+        possible_values = Rank::VARIANTS
+    )]
+    rank: Rank,
+
+    #[structopt(
+        long = "metrics-depth",
+        help = "Number of the leading directories the prometheus metrics are grouped by (0 disables it)",
+        default_value = "1"
+    )]
+    metrics_depth: usize,
+
+    #[structopt(
+        long = "metric-label",
+        help = "Label added to all metrics, `name=value`",
+        parse(try_from_str = prometheus::parse_label),
+        number_of_values = 1
+    )]
+    metric_labels: Vec<(String, String)>,
+
+    #[structopt(
+        long = "statsd",
+        help = "Push the summary gauges to the StatsD endpoint, `host:port`"
+    )]
+    statsd: Option<String>,
+
+    #[structopt(
+        long = "statsd-prefix",
+        help = "Prefix of the StatsD metric names",
+        default_value = "lcov_diff"
+    )]
+    statsd_prefix: String,
+
+    #[structopt(long = "dogstatsd", help = "Send the metric labels as DogStatsD tags")]
+    dogstatsd: bool,
+
+    #[structopt(
+        long = "email-to",
+        help = "Recipients of the email format, the message could be piped to `sendmail -t`",
+        number_of_values = 1
+    )]
+    email_to: Vec<String>,
+
+    #[structopt(
+        long = "email-subject",
+        help = "Subject of the email format and the title of the pdf one",
+        default_value = "Coverage report"
+    )]
+    email_subject: String,
+
+    #[cfg(feature = "http")]
+    #[structopt(
+        long = "chat-webhook",
+        help = "Post the slack or teams message to the incoming webhook",
+        hide_env_values = true,
+        env = "LCOV_DIFF_CHAT_WEBHOOK"
+    )]
+    chat_webhook: Option<String>,
+
+    #[cfg(feature = "http")]
+    #[structopt(
+        long = "alert-webhook",
+        help = "Post the json alert to the webhook when the thresholds checks fail",
+        hide_env_values = true,
+        env = "LCOV_DIFF_ALERT_WEBHOOK"
+    )]
+    alert_webhook: Option<String>,
+
+    #[cfg(any(feature = "http", feature = "github"))]
+    #[structopt(
+        long = "retries",
+        help = "Retries of the failed network requests (webhooks, OTLP export and GitHub API)",
+        default_value = "0"
+    )]
+    retries: u32,
+
+    #[cfg(any(feature = "http", feature = "github"))]
+    #[structopt(
+        long = "retry-backoff",
+        help = "Seconds before the first retry of a network request, doubled for every next one",
+        default_value = "1"
+    )]
+    retry_backoff: f64,
+
+    #[cfg(any(feature = "http", feature = "github"))]
+    #[structopt(long = "timeout", help = "Seconds every network request may take")]
+    timeout: Option<f64>,
+
+    #[cfg(any(feature = "http", feature = "github"))]
+    #[structopt(long = "proxy", help = "Proxy url of the network requests")]
+    proxy: Option<String>,
+
+    #[cfg(feature = "otel")]
+    #[structopt(
+        long = "otel-endpoint",
+        help = "OTLP/HTTP endpoint the span and metrics of the run are exported to",
+        env = "OTEL_EXPORTER_OTLP_ENDPOINT"
+    )]
+    otel_endpoint: Option<String>,
+
+    #[cfg(feature = "github")]
+    #[structopt(
+        long = "github-pr",
+        help = "Post (or update) the markdown summary as a comment of the pull request"
+    )]
+    github_pr: Option<u64>,
+
+    #[cfg(feature = "github")]
+    #[structopt(
+        long = "github-repo",
+        help = "Repository of the pull request (owner/name)",
+        env = "GITHUB_REPOSITORY"
+    )]
+    github_repo: Option<String>,
+
+    #[cfg(feature = "github")]
+    #[structopt(
+        long = "baseline-artifact",
+        help = "Use the Actions artifact of the latest successful run of --baseline-branch as the second file"
+    )]
+    baseline_artifact: Option<String>,
+
+    #[cfg(feature = "github")]
+    #[structopt(
+        long = "baseline-branch",
+        help = "Branch of the baseline artifact (the pull request base by default)"
+    )]
+    baseline_branch: Option<String>,
+
+    #[cfg(feature = "github")]
+    #[structopt(
+        long = "baseline-artifact-file",
+        help = "Report inside the baseline artifact, if it has several files",
+        parse(from_os_str)
+    )]
+    baseline_artifact_file: Option<PathBuf>,
+
+    #[structopt(
+        short = "w",
+        long = "web",
+        help = "Generate html report from output file (default name web)"
+    )]
+    web: Option<Option<PathBuf>>,
+
+    #[structopt(
+        long = "side-by-side",
+        help = "Generate side-by-side html view of the baseline (second file) and the new (first file) coverage",
+        parse(from_os_str)
+    )]
+    side_by_side: Option<PathBuf>,
+
+    #[structopt(
+        long = "baseline-src",
+        help = "Source tree of the baseline, used by --side-by-side",
+        default_value = ".",
+        parse(from_os_str)
+    )]
+    baseline_src: PathBuf,
+
+    #[structopt(
+        long = "src",
+        help = "Source tree of the new coverage, used by --side-by-side and Cobertura based formats",
+        default_value = ".",
+        parse(from_os_str)
+    )]
+    src: PathBuf,
+
+    #[structopt(
+        long = "source-maps",
+        help = "Remap coverage of generated JavaScript files to the original sources with their source maps"
+    )]
+    source_maps: bool,
+
+    #[structopt(
+        long = "permalink",
+        help = "Url template to link source lines, supports {commit}, {path} and {line} placeholders"
+    )]
+    permalink: Option<String>,
+
+    #[structopt(
+        long = "commit",
+        help = "Commit to use in the --permalink links, revisions such as HEAD are resolved in --src",
+        default_value = "HEAD"
+    )]
+    commit: String,
+
+    #[structopt(
+        long = "blame",
+        help = "Write markdown report of the uncovered new lines by author (git blame in --src)",
+        parse(from_os_str)
+    )]
+    blame: Option<PathBuf>,
+
+    #[structopt(
+        long = "save-note",
+        help = "Store the first file as the coverage note of --commit"
+    )]
+    save_note: bool,
+
+    #[structopt(
+        long = "pull-request",
+        help = "Pull request stored with the --save-note note (detected on the CI)"
+    )]
+    pull_request: Option<u64>,
+
+    #[structopt(
+        long = "baseline-note",
+        help = "Use the coverage note of the revision (or of its nearest ancestor) as the second file"
+    )]
+    baseline_note: Option<String>,
+
+    #[structopt(
+        long = "notes-ref",
+        help = "Git notes ref of the coverage notes",
+        default_value = "refs/notes/coverage"
+    )]
+    notes_ref: String,
+
+    #[structopt(
+        long = "base",
+        help = "Git revision the changed files are taken against (detected on GitHub Actions, GitLab, Buildkite and Jenkins)"
+    )]
+    base: Option<String>,
+
+    #[structopt(
+        long = "git-patch",
+        help = "Patch coverage of the lines changed since the merge base of --base, instead of the newly instrumented ones"
+    )]
+    git_patch: bool,
 
-use lcov::Report;
-use log::{debug, info};
-use std::fs::File;
-use std::io::{BufWriter, Write};
-use std::process::Command;
-use structopt::StructOpt;
-use tempfile::NamedTempFile;
+    #[structopt(
+        long = "follow-renames",
+        help = "Compare the files renamed since the merge base of --base with their old paths in the second file"
+    )]
+    follow_renames: bool,
+
+    #[structopt(
+        long = "changed-only",
+        help = "List only the files changed since the merge base of --base in the summaries"
+    )]
+    changed_only: bool,
+
+    /// Files to process, right now just two of them
+    #[structopt(
+        name = "FILE",
+        parse(from_os_str),
+        required = true,
+        min_values = 1,
+        max_values = 2
+    )]
+    files: Vec<PathBuf>,
+}
 
-/// Defines CLI structure
 #[derive(Debug, StructOpt)]
 #[structopt(
-    name = "lcov-diff",
-    about = "lcov-diff utility to get diff of two lcov files"
+    name = "lcov-diff serve",
+    about = "Http service diffing the uploaded reports against the baseline"
 )]
-struct Cli {
+struct Serve {
     #[structopt(short, long, help = "Show Debug logging")]
     verbose: bool,
 
+    #[structopt(long = "baseline", help = "Baseline report", parse(from_os_str))]
+    baseline: PathBuf,
+
     #[structopt(
-        name = "PATH",
-        short = "o",
-        long = "output",
-        help = "output file to write (stdout if not presented)",
+        long = "bind",
+        help = "Address to listen on",
+        default_value = "127.0.0.1"
+    )]
+    bind: String,
+
+    #[structopt(long = "port", help = "Port to listen on", default_value = "8080")]
+    port: u16,
+
+    #[structopt(
+        long = "max-results",
+        help = "Number of the latest diffs kept in memory",
+        default_value = "100"
+    )]
+    max_results: usize,
+
+    #[structopt(
+        long = "timeout",
+        help = "Seconds a client may take to send its request before it's dropped",
+        default_value = "30"
+    )]
+    timeout: u64,
+}
+
+fn serve(options: Serve) -> Result<(), Box<dyn Error>> {
+    let session = BaselineSession::new(import::load_file(&options.baseline)?);
+    let listener = TcpListener::bind((options.bind.as_str(), options.port))?;
+    info!(
+        "Serving diffs against {:?} on http://{}",
+        options.baseline,
+        listener.local_addr()?
+    );
+    Server::new(session, options.max_results)
+        .with_timeout(Duration::from_secs(options.timeout))
+        .run(listener)?;
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "lcov-diff trend",
+    about = "Coverage trend of the coverage notes of a revision and its ancestors"
+)]
+struct TrendOptions {
+    #[structopt(short, long, help = "Show Debug logging")]
+    verbose: bool,
+
+    #[structopt(
+        long = "src",
+        help = "Git repository of the notes, the directories are taken relative to it",
+        default_value = ".",
         parse(from_os_str)
     )]
-    output: Option<PathBuf>,
+    src: PathBuf,
 
     #[structopt(
-        short = "w",
-        long = "web",
-        help = "Generate html report from output file (default name web)"
+        long = "notes-ref",
+        help = "Git notes ref of the coverage notes",
+        default_value = "refs/notes/coverage"
     )]
-    web: Option<Option<PathBuf>>,
+    notes_ref: String,
+
+    #[structopt(long = "rev", help = "Latest revision", default_value = "HEAD")]
+    rev: String,
 
-    /// Files to process, right now just two of them
     #[structopt(
-        name = "FILE",
-        parse(from_os_str),
-        required = true,
-        min_values = 2,
-        max_values = 2
+        long = "max-snapshots",
+        help = "Number of the latest notes taken",
+        default_value = "100"
     )]
-    files: Vec<PathBuf>,
+    max_snapshots: usize,
+
+    #[structopt(
+        long = "window",
+        help = "Number of the snapshots of the moving average",
+        default_value = "5"
+    )]
+    window: usize,
+
+    #[structopt(
+        long = "days",
+        help = "Attribute the largest commit-to-commit drop of the last days to its commit, author and pull request"
+    )]
+    days: Option<u64>,
+
+    #[structopt(
+        short = "f",
+        long = "format",
+        help = "Output format",
+        default_value = "markdown",
+        possible_values = &["markdown", "json"]
+    )]
+    format: String,
+}
+
+fn trend(options: TrendOptions) -> Result<(), Box<dyn Error>> {
+    let notes = Notes {
+        repo: options.src.clone(),
+        notes_ref: options.notes_ref.clone(),
+    };
+    let snapshots = notes.history(&options.rev, options.max_snapshots)?;
+    if snapshots.is_empty() {
+        return Err(format!("no coverage notes on {} or its ancestors", options.rev).into());
+    }
+    debug!("Loaded {} coverage notes", snapshots.len());
+    let root = fs::canonicalize(&options.src)?;
+    let points = snapshots
+        .iter()
+        .map(|snapshot| Point::of_snapshot(snapshot, &root))
+        .collect();
+    let trend = Trend::new(points, options.window);
+    let regression = options.days.map(|days| {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs());
+        trend.largest_commit_drop(now.saturating_sub(days * 24 * 60 * 60))
+    });
+    let numbers = Numbers::default();
+    if options.format == "json" {
+        let mut json = trend.to_json();
+        if let (Value::Object(fields), Some(regression)) = (&mut json, &regression) {
+            let regression = regression.as_ref().map_or(Value::Null, Regression::to_json);
+            fields.push(("largest_commit_drop".to_owned(), regression));
+        }
+        println!("{}", json.pretty());
+    } else {
+        print!("{}", trend.markdown(&numbers));
+        if let (Some(days), Some(regression)) = (options.days, &regression) {
+            match regression {
+                Some(regression) => println!(
+                    "\nLargest drop of the last {} days: {}",
+                    days,
+                    regression.text(&numbers)
+                ),
+                None => println!("\nNo coverage drop in the last {} days", days),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_lcov<W: Write>(
+    writer: W,
+    report: Report,
+    extras: &Extras,
+    options: &WriteOptions,
+    order: Option<&SectionOrder>,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(writer);
+    let records = match order {
+        Some(order) => order.records(report),
+        None => report.into_records().collect(),
+    };
+    for line in options.lines(records, extras) {
+        writeln!(writer, "{}", line)?;
+    }
+    writer.flush()
 }
 
 fn genhtml<P: AsRef<Path>>(lcov_path: P, report_dir: &str) -> bool {
     info!("Generate html report for the {:?}", lcov_path.as_ref());
     let mut out = Command::new("genhtml")
-        .args(&[
+        .args([
             "--ignore-errors",
             "source",
             "-o",
@@ -67,66 +1062,1269 @@ fn genhtml<P: AsRef<Path>>(lcov_path: P, report_dir: &str) -> bool {
     status.success()
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let options = Cli::from_args();
-
+fn init_logger(verbose: bool) {
     env_logger::builder()
-        .filter_level(match options.verbose {
+        .filter_level(match verbose {
             true => log::LevelFilter::Debug,
             false => log::LevelFilter::Info,
         })
         .init();
+}
 
-    debug!("{:#?}", options);
+fn main() -> Result<ExitCode, Box<dyn Error>> {
+    let mut args: Vec<_> = env::args_os().collect();
+    if args.get(1).is_some_and(|arg| arg == "serve") {
+        args.remove(1);
+        let options = Serve::from_iter(args);
+        init_logger(options.verbose);
+        serve(options)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    if args.get(1).is_some_and(|arg| arg == "trend") {
+        args.remove(1);
+        let options = TrendOptions::from_iter(args);
+        init_logger(options.verbose);
+        trend(options)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    let options = Cli::from_iter(args);
+    init_logger(options.verbose);
 
-    info!("Processing diff for two lcov files: {:?}", options.files);
+    debug!("{:#?}", options.redacted());
+    diff(options)
+}
+
+impl Cli {
+    /// Options for the debug log, without the webhook urls and the token which are credentials.
+    fn redacted(&self) -> Cli {
+        let hidden = |value: &Option<String>| value.as_ref().map(|_| "<redacted>".to_owned());
+        let mut options = self.clone();
+        options.coveralls_token = hidden(&self.coveralls_token);
+        #[cfg(feature = "http")]
+        {
+            options.chat_webhook = hidden(&self.chat_webhook);
+            options.alert_webhook = hidden(&self.alert_webhook);
+        }
+        options
+    }
+}
+
+/// Fails unless the files of the `--verify-checksums` manifest match it.
+fn verify_checksums(path: &Path) -> Result<(), Box<dyn Error>> {
+    let mismatches = checksum::verify_manifest(path)?;
+    if !mismatches.is_empty() {
+        for mismatch in &mismatches {
+            error!("{}", mismatch);
+        }
+        return Err(format!("{} files don't match {:?}", mismatches.len(), path).into());
+    }
+    info!("Verified checksums of {:?}", path);
+    Ok(())
+}
+
+/// Downloads the `--baseline-artifact` as the second file into the returned directory, which
+/// removes it when it's dropped.
+#[cfg(feature = "github")]
+fn download_baseline_artifact(
+    options: &mut Cli,
+    ci: Option<&CiEnv>,
+    remote: &RemoteOptions,
+) -> Result<Option<TempDir>, Box<dyn Error>> {
+    let name = match &options.baseline_artifact {
+        Some(name) if options.files.len() == 1 => name.clone(),
+        Some(_) => return Err("--baseline-artifact replaces the second file".into()),
+        None => return Ok(None),
+    };
+    let artifact = github::Artifact {
+        repo: options
+            .github_repo
+            .clone()
+            .ok_or("--github-repo is required to fetch the baseline artifact")?,
+        name,
+        branch: options
+            .baseline_branch
+            .clone()
+            .or_else(|| ci?.base_ref.clone())
+            .ok_or("--baseline-branch is required outside of pull request builds")?,
+        file: options.baseline_artifact_file.clone(),
+    };
+    let dir = tempfile::tempdir()?;
+    let path = github::download_baseline(&artifact, dir.path(), remote)?;
+    options.files.push(path);
+    Ok(Some(dir))
+}
+
+/// Checks the minisign signature of the second file against the key.
+fn verify_baseline(options: &Cli, key: &str) -> Result<(), Box<dyn Error>> {
+    let baseline = options
+        .files
+        .get(1)
+        .ok_or("--baseline-public-key needs the second file or --baseline-note")?;
+    let signature = options
+        .baseline_signature
+        .clone()
+        .unwrap_or_else(|| Verifier::default_signature(baseline));
+    Verifier::new(PublicKey::parse(key)).verify(baseline, &signature)?;
+    info!("Verified signature {:?} of the baseline", signature);
+    Ok(())
+}
 
-    let report = diff_reports(
-        &Report::from_file(options.files[0].as_path())?,
-        &Report::from_file(options.files[1].as_path())?,
+/// Checks the `--baseline-signature` of the baseline note against the key.
+fn verify_note(options: &Cli, key: &str, note: &str) -> Result<(), Box<dyn Error>> {
+    let signature = options.baseline_signature.as_ref().ok_or(
+        "--baseline-public-key with --baseline-note requires --baseline-signature of the note",
     )?;
+    // minisign verifies files, the note is checked as `git notes show` prints it
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("baseline.info");
+    fs::write(&path, note)?;
+    Verifier::new(PublicKey::parse(key)).verify(&path, signature)?;
+    info!("Verified signature {:?} of the baseline note", signature);
+    Ok(())
+}
+
+fn load_options_of(options: &Cli) -> LoadOptions {
+    LoadOptions {
+        keep_unknown: options.keep_unknown_records,
+        lenient: options.lenient,
+        max_input_size: options.max_input_size,
+        filter: PathFilter {
+            include: options.include.clone(),
+            exclude: options.exclude.clone(),
+        },
+        keep_text: false,
+    }
+}
+
+/// Loads the file, logging the warnings of the lenient parse.
+fn load(path: &Path, load_options: &LoadOptions) -> Result<Loaded, import::ImportError> {
+    let loaded = import::load_file_with(path, load_options)?;
+    for warning in &loaded.warnings {
+        warn!("{}: {}", path.display(), warning);
+    }
+    if let Some(version) = loaded.version {
+        debug!("{} is lcov {}", path.display(), version);
+    }
+    debug!(
+        "{} takes about {} in memory",
+        path.display(),
+        memory::format_size(memory::approximate_size(&loaded.report) as u64)
+    );
+    Ok(loaded)
+}
+
+/// The reports to diff, with what the outputs take from the files.
+struct Inputs {
+    first: Report,
+    second: Report,
+    /// Unknown records of the first file.
+    extras: Extras,
+    first_ends: FunctionEnds,
+    second_ends: FunctionEnds,
+    /// Order of the sections of the first file, with `--keep-order`.
+    order: Option<SectionOrder>,
+    /// Text of the first file if it's an lcov file, for the patch.
+    first_text: Option<String>,
+    /// Time the fragments of the archive inputs took to merge while they were loaded, with
+    /// the records of the merged reports.
+    merge: (Duration, usize),
+}
+
+/// Loads the first file and the baseline, the second file or the `--baseline-note` note.
+fn load_inputs(
+    options: &Cli,
+    load_options: &LoadOptions,
+    notes: &Notes,
+) -> Result<Inputs, Box<dyn Error>> {
+    // The order and the patch are taken from the text of the first file, read only once
+    let first_options = LoadOptions {
+        keep_text: options.keep_order || options.format == Format::Patch,
+        ..load_options.clone()
+    };
+    let loaded = load(&options.files[0], &first_options)?;
+    let order = if options.keep_order {
+        Some(SectionOrder::of_input(
+            loaded.text.as_deref().unwrap_or_default(),
+        ))
+    } else {
+        None
+    };
+    let mut merge = (Duration::ZERO, 0);
+    let mut add_merge = |loaded: &Loaded| {
+        if let Some(merge_time) = loaded.merge_time {
+            merge.0 += merge_time;
+            merge.1 += timings::records(&loaded.report);
+        }
+    };
+    add_merge(&loaded);
+    // A patch applies to an lcov file, not to the fragments of an archive
+    let first_text = if loaded.version.is_some() {
+        loaded.text
+    } else {
+        None
+    };
+    let (second, second_ends) = match (options.files.get(1), &options.baseline_note) {
+        (Some(path), None) => {
+            let loaded = load(path, load_options)?;
+            add_merge(&loaded);
+            (loaded.report, loaded.function_ends)
+        }
+        (None, Some(rev)) => (
+            load_note(options, load_options, notes, rev)?,
+            FunctionEnds::new(),
+        ),
+        (Some(_), Some(_)) => return Err("--baseline-note replaces the second file".into()),
+        (None, None) => return Err("two files are required".into()),
+    };
+    Ok(Inputs {
+        first: loaded.report,
+        second,
+        extras: loaded.extras,
+        first_ends: loaded.function_ends,
+        second_ends,
+        order,
+        first_text,
+        merge,
+    })
+}
+
+/// Report of the coverage note of the revision or of its nearest ancestor.
+fn load_note(
+    options: &Cli,
+    load_options: &LoadOptions,
+    notes: &Notes,
+    rev: &str,
+) -> Result<Report, Box<dyn Error>> {
+    let (commit, note) = notes
+        .find_note(rev)?
+        .ok_or_else(|| format!("no coverage note on {} or its ancestors", rev))?;
+    info!("Using coverage note of {} as the baseline", commit);
+    if let Some(key) = &options.baseline_public_key {
+        verify_note(options, key, &note)?;
+    }
+    let mut report = parse_snapshot(&note)?;
+    // The notes are read with the lcov crate, without the lcov 2.x shim
+    version::normalize(&mut report);
+    load_options.filter.retain(&mut report);
+    Ok(report)
+}
+
+/// Keeps the files of the `--manifest` and remaps the `--source-maps`.
+fn remap(options: &Cli, inputs: &mut Inputs) -> Result<(), Box<dyn Error>> {
+    if let Some(path) = &options.manifest {
+        let manifest = filter::parse_manifest(&fs::read_to_string(path)?);
+        info!(
+            "Keeping the {} files of the manifest {:?}",
+            manifest.len(),
+            path
+        );
+        filter::retain_files(&mut inputs.first, &manifest);
+        filter::retain_files(&mut inputs.second, &manifest);
+    }
+    if options.source_maps {
+        inputs.first = sourcemap::remap(&inputs.first, SourceMap::load_for);
+        inputs.second = sourcemap::remap(&inputs.second, SourceMap::load_for);
+    }
+    Ok(())
+}
+
+/// Changes since the merge base for `--git-patch` and `--follow-renames`.
+fn git_changes(options: &Cli, base: Option<&str>) -> Result<Option<Changes>, Box<dyn Error>> {
+    if !options.git_patch && !options.follow_renames {
+        return Ok(None);
+    }
+    let base = base
+        .ok_or("--git-patch and --follow-renames require --base outside of pull request builds")?;
+    let changes = changes::since_merge_base(&options.src, base)?;
+    info!(
+        "{} files changed and {} renamed since the merge base of {}",
+        changes.lines.len(),
+        changes.renames.len(),
+        base
+    );
+    Ok(Some(changes))
+}
+
+/// Keeps the files of the shard in both reports.
+fn keep_shard(options: &Cli, name: &str, inputs: &mut Inputs) -> Result<(), Box<dyn Error>> {
+    let root = fs::canonicalize(&options.src)?;
+    let partitioner = shard::top_level(&root);
+    inputs.first = shard::partition(&inputs.first, &partitioner)
+        .remove(name)
+        .unwrap_or_default();
+    inputs.second = shard::partition(&inputs.second, &partitioner)
+        .remove(name)
+        .unwrap_or_default();
+    info!(
+        "Diffing {} files of the shard {}",
+        inputs.first.sections.len(),
+        name
+    );
+    Ok(())
+}
+
+/// Applies `--empty-sections` to the report, `what` names it in the warnings.
+fn empty_sections(options: &Cli, report: &mut Report, what: &str) {
+    for path in options.empty_sections.apply(report) {
+        if options.empty_sections == EmptySections::Warn {
+            warn!(
+                "{}: section without records in the {}",
+                path.display(),
+                what
+            );
+        }
+    }
+}
 
+/// Numbers of the summaries and of the json outputs, which always have the dot.
+fn number_formats(options: &Cli) -> Result<(Numbers, Numbers), Box<dyn Error>> {
+    let numbers = Numbers {
+        precision: options.precision,
+        rounding: options.rounding,
+        decimal_separator: match &options.locale {
+            Some(locale) => number::decimal_separator(locale)?,
+            None => '.',
+        },
+    };
+    let json_numbers = Numbers {
+        decimal_separator: '.',
+        ..numbers
+    };
+    Ok((numbers, json_numbers))
+}
+
+/// The `--permalink` of the commit.
+fn resolve_permalink(
+    options: &Cli,
+    ci: Option<&CiEnv>,
+) -> Result<Option<Permalink>, Box<dyn Error>> {
+    let template = match &options.permalink {
+        Some(template) => template,
+        None => return Ok(None),
+    };
+    let commit = match (ci, options.commit.as_str()) {
+        (
+            Some(CiEnv {
+                commit: Some(commit),
+                ..
+            }),
+            "HEAD",
+        ) => commit.clone(),
+        _ => options.commit.clone(),
+    };
+    // A moving revision such as `HEAD` would make the links point at other lines later
+    let commit = permalink::resolve_commit(&options.src, &commit)
+        .map_err(|e| format!("--permalink requires --commit of a commit in --src ({})", e))?;
+    Ok(Some(Permalink::new(template, commit).root(&options.src)))
+}
+
+/// Comparison of the first file against the baseline, narrowed to the `--git-patch` lines and
+/// the `--changed-only` files, without the suppressed uncovered lines.
+fn compare(
+    options: &Cli,
+    inputs: &Inputs,
+    changes: Option<&Changes>,
+    base: Option<&str>,
+) -> Result<Comparison, Box<dyn Error>> {
+    let mut comparison = Comparison::new(&inputs.second, &inputs.first);
+    if let (true, Some(changes)) = (options.git_patch, changes) {
+        comparison.set_patch(&inputs.first, changes);
+    }
+    if options.changed_only {
+        let base = base.ok_or("--changed-only requires --base outside of pull request builds")?;
+        let changed = ci::changed_files(&options.src, base)
+            .ok_or_else(|| format!("git diff against {} failed", base))?;
+        info!("{} files changed since {}", changed.len(), base);
+        comparison.retain_paths(&changed);
+    }
+    if !options.ignore_suppressions {
+        let paths: BTreeSet<&Path> = comparison
+            .uncovered_new_lines
+            .iter()
+            .map(|(path, _)| path.as_path())
+            .collect();
+        let suppressions = Suppressions::load(&options.src, paths);
+        let suppressed = suppressions.apply(&mut comparison);
+        if suppressed > 0 {
+            info!(
+                "{} uncovered new lines marked `{}`",
+                suppressed,
+                suppress::MARKER
+            );
+        }
+    }
+    Ok(comparison)
+}
+
+/// Source root and context lines of the `--context` snippets.
+fn snippets(options: &Cli) -> Option<(PathBuf, usize)> {
+    options
+        .context
+        .map(|context| (options.src.clone(), context))
+}
+
+/// Findings of the comparison with the `--severity` levels, relative to `--src`.
+fn collect_findings(options: &Cli, inputs: &Inputs, comparison: &Comparison) -> Vec<Finding> {
+    let mut severities = Severities::default();
+    for (kind, severity) in &options.severity {
+        severities.set(*kind, *severity);
+    }
+    let mut findings = severity::findings(&inputs.second, &inputs.first, comparison, &severities);
+    if options.line_ranges {
+        findings = severity::coalesce(findings);
+    }
+    severity::relative_to(&mut findings, &options.src);
+    if let Some((root, context)) = &snippets(options) {
+        Sources::new(root, *context).annotate(&mut findings);
+    }
+    findings
+}
+
+/// Comparisons of the `--cargo-workspace` crates.
+fn crate_comparisons(
+    options: &Cli,
+    inputs: &Inputs,
+) -> Result<Vec<(String, Comparison)>, Box<dyn Error>> {
+    if !options.cargo_workspace {
+        return Ok(Vec::new());
+    }
+    let root = fs::canonicalize(&options.src)?;
+    let packages = cargo::workspace_packages(&root)?;
+    Ok(cargo::by_package(
+        &packages,
+        &root,
+        &inputs.second,
+        &inputs.first,
+    ))
+}
+
+/// Comparisons of the code owners of `--owners` or `--codeowners`.
+fn owner_comparisons(
+    options: &Cli,
+    inputs: &Inputs,
+) -> Result<Vec<(String, Comparison)>, Box<dyn Error>> {
+    let owners = match (&options.owners, options.codeowners) {
+        (Some(path), _) => CodeOwners::parse(&fs::read_to_string(path)?),
+        (None, true) => {
+            CodeOwners::find(&options.src)?.ok_or("no CODEOWNERS file found in --src")?
+        }
+        (None, false) => return Ok(Vec::new()),
+    };
+    Ok(owners.by_owner(
+        &fs::canonicalize(&options.src)?,
+        &inputs.second,
+        &inputs.first,
+    ))
+}
+
+/// `(kind, message)` of the failed checks besides the total thresholds: the error findings and
+/// the thresholds of the crates and the owners.
+fn check_failures(options: &Cli, outcome: &Outcome) -> Vec<(String, String)> {
+    let mut failures: Vec<(String, String)> = outcome
+        .findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Error)
+        .map(|finding| {
+            let message = match (finding.line, finding.end_line) {
+                (Some(line), Some(end_line)) => format!(
+                    "{}:{}-{}: {}",
+                    finding.path.display(),
+                    line,
+                    end_line,
+                    finding.kind.message()
+                ),
+                (Some(line), None) => format!(
+                    "{}:{}: {}",
+                    finding.path.display(),
+                    line,
+                    finding.kind.message()
+                ),
+                (None, _) => format!("{}: {}", finding.path.display(), finding.kind.message()),
+            };
+            (finding.kind.id().replace('-', "_"), message)
+        })
+        .collect();
+    for (name, comparison) in &outcome.packages {
+        for violation in outcome.thresholds.check(comparison) {
+            failures.push((
+                "crate".to_owned(),
+                format!("{}: {}", name, violation.message(&outcome.numbers)),
+            ));
+        }
+    }
+    for (name, comparison) in &outcome.teams {
+        let min_lines = options
+            .owner_min_lines
+            .iter()
+            .find(|(owner, _)| owner == name)
+            .map(|(_, min)| *min)
+            .or(outcome.thresholds.min_lines);
+        let thresholds = Thresholds {
+            min_lines,
+            ..outcome.thresholds
+        };
+        for violation in thresholds.check(comparison) {
+            failures.push((
+                "owner".to_owned(),
+                format!("{}: {}", name, violation.message(&outcome.numbers)),
+            ));
+        }
+    }
+    failures
+}
+
+/// Writes the protobuf results, the blame report and the badges, and pushes the statsd metrics.
+fn write_artifacts(
+    options: &Cli,
+    comparison: &Comparison,
+    violations: &[Violation],
+) -> Result<(), Box<dyn Error>> {
+    if let Some(path) = &options.protobuf {
+        info!("Writing protobuf diff results to {:?}", path);
+        let result = DiffResult {
+            comparison: comparison.clone(),
+            warnings: violations.iter().map(ToString::to_string).collect(),
+        };
+        fs::write(path, result.encode())?;
+    }
+
+    if let Some(path) = &options.blame {
+        info!("Writing blame report to {:?}", path);
+        fs::write(
+            path,
+            blame::markdown(&blame::by_author(comparison, &options.src)?),
+        )?;
+    }
+
+    if let Some(addr) = &options.statsd {
+        info!("Pushing summary metrics to {}", addr);
+        let statsd = StatsD {
+            prefix: options.statsd_prefix.clone(),
+            tags: options.metric_labels.clone(),
+            dogstatsd: options.dogstatsd,
+        };
+        statsd.send(addr.as_str(), comparison)?;
+    }
+
+    let badge = Badge {
+        metric: options.badge_metric,
+        label: None,
+        red: options.badge_red,
+        yellow: options.badge_yellow,
+    };
+    if let Some(path) = &options.badge {
+        info!("Writing badge to {:?}", path);
+        fs::write(path, badge.svg(comparison))?;
+    }
+    if let Some(path) = &options.badge_json {
+        info!("Writing badge json to {:?}", path);
+        fs::write(path, badge.endpoint_json(comparison).pretty())?;
+    }
+    Ok(())
+}
+
+/// Weighted line coverage of the baseline and the first file with `--weights`.
+fn weighted_coverage(
+    options: &Cli,
+    inputs: &Inputs,
+) -> Result<Option<(Score, Score)>, Box<dyn Error>> {
+    let path = match &options.weights {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let weights = Weights::parse(&fs::read_to_string(path)?)?;
+    let root = fs::canonicalize(&options.src)?;
+    let (old, new) = (
+        weights.score(&inputs.second, &root),
+        weights.score(&inputs.first, &root),
+    );
+    info!(
+        "Weighted line coverage {:.2}% -> {:.2}%",
+        old.percent().unwrap_or_default(),
+        new.percent().unwrap_or_default()
+    );
+    Ok(Some((old, new)))
+}
+
+/// The diff with all the outputs are made of.
+struct Outcome {
+    inputs: Inputs,
+    permalink: Option<Permalink>,
+    report: Report,
+    comparison: Comparison,
+    findings: Vec<Finding>,
+    violations: Vec<Violation>,
+    thresholds: Thresholds,
+    packages: Vec<(String, Comparison)>,
+    teams: Vec<(String, Comparison)>,
+    markdown: Markdown,
+    numbers: Numbers,
+    json_numbers: Numbers,
+    write_options: WriteOptions,
+}
+
+/// The diff in the `--format`.
+fn render_diff(options: &Cli, outcome: &Outcome) -> Result<String, Box<dyn Error>> {
+    let Outcome {
+        inputs,
+        permalink,
+        report,
+        comparison,
+        findings,
+        violations,
+        thresholds,
+        packages,
+        teams,
+        markdown,
+        numbers,
+        json_numbers,
+        write_options,
+    } = outcome;
+    let (first, numbers) = (&inputs.first, *numbers);
+    Ok(match options.format {
+        Format::Lcov => {
+            let mut out = Vec::new();
+            write_lcov(
+                &mut out,
+                report.clone(),
+                &inputs.extras,
+                write_options,
+                inputs.order.as_ref(),
+            )?;
+            String::from_utf8(out)?
+        }
+        Format::Markdown => {
+            let mut out = markdown.render(comparison);
+            if !packages.is_empty() {
+                out += &render::markdown::groups_table("Crate", packages, &numbers);
+            }
+            if !teams.is_empty() {
+                out += &render::markdown::groups_table("Owner", teams, &numbers);
+            }
+            if options.function_table {
+                let deltas = functions::function_deltas_with_ends(
+                    &inputs.second,
+                    first,
+                    &inputs.second_ends,
+                    &inputs.first_ends,
+                );
+                out += &render::markdown::functions_table(&deltas, options.max_files, &numbers);
+            }
+            out
+        }
+        Format::Github => render::github::render(findings, violations),
+        Format::Teamcity => render::teamcity::render(comparison, findings, json_numbers),
+        Format::Sarif => {
+            render::sarif::render(findings, violations, permalink.as_ref()).pretty() + "\n"
+        }
+        Format::Cobertura => Cobertura {
+            source: options.src.clone(),
+            ..Cobertura::default()
+        }
+        .render(report),
+        Format::Coveralls => {
+            Coveralls {
+                root: options.src.clone(),
+                repo_token: options.coveralls_token.clone(),
+                service_name: options.coveralls_service.clone(),
+                service_job_id: options.coveralls_job.clone(),
+                git: coveralls::Git::discover(&options.src),
+            }
+            .render(first)
+            .to_string()
+                + "\n"
+        }
+        Format::Codecov => Codecov {
+            fixes: options.codecov_fixes.clone(),
+            network: codecov::git_files(&options.src),
+            name: options.files[0].display().to_string(),
+        }
+        .render(first),
+        Format::Slack => {
+            Slack {
+                max_files: options.max_files,
+                report_url: options.report_url.clone(),
+                numbers,
+            }
+            .render(comparison)
+            .pretty()
+                + "\n"
+        }
+        Format::Teams => {
+            Teams {
+                max_files: options.max_files,
+                report_url: options.report_url.clone(),
+                numbers,
+            }
+            .render(comparison)
+            .pretty()
+                + "\n"
+        }
+        Format::Email => {
+            let to = options.email_to.join(", ");
+            let headers: Vec<(&str, &str)> = if to.is_empty() {
+                Vec::new()
+            } else {
+                vec![("To", to.as_str())]
+            };
+            Email {
+                max_files: options.max_files,
+                report_url: options.report_url.clone(),
+                numbers,
+            }
+            .mime(comparison, &options.email_subject, &headers)
+        }
+        Format::Pdf => Pdf {
+            title: options.email_subject.clone(),
+            max_files: None,
+            numbers,
+        }
+        .render(comparison),
+        Format::Csv | Format::Tsv => Table {
+            delimiter: if options.format == Format::Csv {
+                ','
+            } else {
+                '\t'
+            },
+            columns: if options.columns.is_empty() {
+                Column::ALL.to_vec()
+            } else {
+                options.columns.clone()
+            },
+        }
+        .render(comparison),
+        Format::Prometheus => Prometheus {
+            depth: options.metrics_depth,
+            labels: options.metric_labels.clone(),
+        }
+        .render(comparison),
+        Format::Junit => render::junit::render(comparison, thresholds),
+        Format::Patch => {
+            let original = inputs
+                .first_text
+                .as_deref()
+                .ok_or("--format patch needs an lcov file as the first file")?;
+            render::patch::render(original, &options.files[0], report)
+        }
+        Format::Azure => {
+            let summary = options
+                .summary_json
+                .clone()
+                .unwrap_or_else(|| PathBuf::from("coverage-summary.json"));
+            info!("Writing summary json to {:?}", summary);
+            fs::write(
+                summary,
+                render::azure::summary_json_with(comparison, json_numbers, permalink.as_ref())
+                    .pretty(),
+            )?;
+            Cobertura {
+                source: options.src.clone(),
+                ..Cobertura::default()
+            }
+            .render(first)
+        }
+        Format::Gitlab => {
+            // Job log line for the `coverage` regex, stdout could be taken by the xml
+            eprintln!("{}", render::gitlab::summary_line(comparison, json_numbers));
+            Cobertura {
+                source: options.src.clone(),
+                ..Cobertura::default()
+            }
+            .render(first)
+        }
+    })
+}
+
+/// Writes the rendered diff to `--output` or stdout, and the `--web` html report.
+fn write_output(options: &Cli, outcome: &Outcome, rendered: &str) -> Result<(), Box<dyn Error>> {
     match &options.output {
         Some(output) => {
-            let out_file = output.to_owned();
-            info!("Writing diff to file: {:?} ...", out_file);
-            let file = File::create(output).unwrap();
-            let mut writer = BufWriter::new(file);
-            report
-                .into_records()
-                .for_each(|rec| writeln!(writer, "{}", rec).unwrap());
-            writer.flush()?;
-
-            options.web.map(|rep_dir| {
-                let report_path = match &rep_dir {
-                    Some(dir) => dir.to_str().unwrap(),
-                    None => "web",
-                };
-                genhtml(&output, report_path);
-                Some(())
-            });
-        }
-        None => match &options.web {
-            Some(rep_dir) => {
+            info!("Writing diff to file: {:?} ...", output);
+            fs::write(output, rendered)?;
+        }
+        // The lcov diff goes to the html report only
+        None if options.web.is_some() && options.format == Format::Lcov => {}
+        None => {
+            info!("Writing diff to stdout");
+            print!("{}", rendered);
+        }
+    }
+
+    if let Some(rep_dir) = &options.web {
+        let report_path = match rep_dir {
+            Some(dir) => dir.to_str().unwrap(),
+            None => "web",
+        };
+        match &options.output {
+            Some(output) if options.format == Format::Lcov => {
+                genhtml(output, report_path);
+            }
+            _ => {
                 let file = NamedTempFile::new()?;
-                let mut writer = BufWriter::new(file.as_file());
-                report
-                    .into_records()
-                    .for_each(|rec| writeln!(writer, "{}", rec).unwrap());
-                writer.flush()?;
-
-                let report_path = match &rep_dir {
-                    Some(dir) => dir.to_str().unwrap(),
-                    None => "web",
-                };
+                write_lcov(
+                    file.as_file(),
+                    outcome.report.clone(),
+                    &Extras::default(),
+                    &outcome.write_options,
+                    outcome.inputs.order.as_ref(),
+                )?;
                 genhtml(file.path(), report_path);
             }
-            None => {
-                info!("Writing diff to stdout");
-                report.into_records().for_each(|rec| println!("{}", rec));
-            }
-        },
+        }
+    }
+    Ok(())
+}
+
+/// Posts the rendered diff to the `--chat-webhook` and the alert to the `--alert-webhook`.
+#[cfg(feature = "http")]
+fn post_webhooks(
+    options: &Cli,
+    outcome: &Outcome,
+    rendered: &str,
+    remote: &RemoteOptions,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(url) = &options.chat_webhook {
+        if !matches!(options.format, Format::Slack | Format::Teams) {
+            return Err("--chat-webhook requires slack or teams format".into());
+        }
+        webhook::post_json(url, rendered, remote)?;
+    }
+
+    if let Some(url) = options
+        .alert_webhook
+        .as_ref()
+        .filter(|_| !outcome.violations.is_empty())
+    {
+        let alert = Alert {
+            max_files: options.max_files,
+            report_url: options.report_url.clone(),
+            snippets: snippets(options),
+            numbers: outcome.json_numbers,
+            permalink: outcome.permalink.clone(),
+        };
+        webhook::post_json(
+            url,
+            &alert
+                .render(&outcome.comparison, &outcome.violations)
+                .to_string(),
+            remote,
+        )?;
+    }
+    Ok(())
+}
+
+/// Updates the sticky markdown comment of the pull request.
+#[cfg(feature = "github")]
+fn comment_pull_request(
+    options: &Cli,
+    outcome: &Outcome,
+    number: u64,
+    remote: &RemoteOptions,
+) -> Result<(), Box<dyn Error>> {
+    let pr = github::PullRequest {
+        repo: options
+            .github_repo
+            .clone()
+            .ok_or("--github-repo is required to post a comment")?,
+        number,
     };
+    github::post_sticky_comment(&pr, &outcome.markdown.render(&outcome.comparison), remote)?;
+    Ok(())
+}
+
+/// Exports the span and the metrics of the run to the OTLP endpoint.
+#[cfg(feature = "otel")]
+fn export_run(
+    options: &Cli,
+    outcome: &Outcome,
+    endpoint: &str,
+    mut run: otel::Run,
+    remote: &RemoteOptions,
+) -> Result<(), Box<dyn Error>> {
+    run.input_bytes = [
+        fs::metadata(&options.files[0]).map_or(0, |m| m.len()),
+        options
+            .files
+            .get(1)
+            .and_then(|path| fs::metadata(path).ok())
+            .map_or(0, |m| m.len()),
+    ];
+    run.sections = [
+        outcome.inputs.first.sections.len(),
+        outcome.inputs.second.sections.len(),
+    ];
+    run.diff_sections = outcome.report.sections.len();
+    run.violations = outcome.violations.len();
+    let endpoint = endpoint.trim_end_matches('/');
+    info!("Exporting the run span and metrics to {}", endpoint);
+    webhook::post_json(
+        &format!("{}/v1/traces", endpoint),
+        &run.traces(&outcome.comparison).pretty(),
+        remote,
+    )?;
+    webhook::post_json(
+        &format!("{}/v1/metrics", endpoint),
+        &run.metrics(&outcome.comparison).pretty(),
+        remote,
+    )?;
+    Ok(())
+}
+
+/// Failures of the uncovered lines the `--debt` file doesn't have. The file is created if it's
+/// missing and shrunk with `--update-debt`.
+fn debt_failures(
+    options: &Cli,
+    path: &Path,
+    first: &Report,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let current = Debt::of_report(first, &fs::canonicalize(&options.src)?);
+    let known = match fs::read_to_string(path) {
+        Ok(input) => Debt::parse(&input)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound && options.update_debt => {
+            info!("Creating debt file {:?} of {} lines", path, current.len());
+            fs::write(path, current.to_string())?;
+            return Ok(Vec::new());
+        }
+        Err(e) => return Err(format!("Can't read debt file {:?}: {}", path, e).into()),
+    };
+    let new_lines = known.new_lines(&current);
+    let suppressions = if options.ignore_suppressions {
+        Suppressions::default()
+    } else {
+        Suppressions::load(
+            &options.src,
+            new_lines.iter().map(|(file, _)| file.as_path()),
+        )
+    };
+    let mut failures = Vec::new();
+    for (file, line) in new_lines {
+        if suppressions.contains(&file, line) {
+            continue;
+        }
+        let message = format!(
+            "{}:{}: uncovered line is not in the debt file",
+            file.display(),
+            line
+        );
+        failures.push(("debt".to_owned(), message));
+    }
+    if options.update_debt {
+        let shrunk = known.shrink(&current);
+        info!(
+            "Coverage debt {} lines, {} paid off",
+            shrunk.len(),
+            known.len() - shrunk.len()
+        );
+        fs::write(path, shrunk.to_string())?;
+    }
+    Ok(failures)
+}
+
+/// Writes the test clusters, the dead code candidates and the minimal test set of the first
+/// file.
+fn write_test_reports(options: &Cli, first: &Report) -> Result<(), Box<dyn Error>> {
+    if let Some(path) = &options.test_clusters {
+        let tests = summary::by_test(first);
+        let found = clusters::clusters(&tests, options.cluster_threshold);
+        info!(
+            "Writing {} clusters of {} tests to {:?}",
+            found.len(),
+            tests.len(),
+            path
+        );
+        let json = clusters::to_json(&found, options.cluster_threshold);
+        fs::write(path, json.pretty() + "\n")?;
+    }
+
+    if let Some(path) = &options.dead_code {
+        let suites = options
+            .dead_code_suite
+            .iter()
+            .map(import::load_file)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut reports = vec![first];
+        reports.extend(&suites);
+        let dead = functions::dead_functions(&reports, |file| {
+            let file = file.to_string_lossy().replace('\\', "/");
+            options
+                .dead_code_exclude
+                .iter()
+                .any(|pattern| file.contains(pattern.as_str()))
+        });
+        info!("Writing {} dead code candidates to {:?}", dead.len(), path);
+        fs::write(path, functions::dead_markdown(&dead))?;
+    }
+
+    if let Some(path) = &options.minimal_tests {
+        let selection = selection::minimal_set(&summary::by_test(first));
+        info!(
+            "{} tests keep the coverage of {} lines, {} are redundant",
+            selection.selected.len(),
+            selection.lines,
+            selection.redundant.len()
+        );
+        fs::write(path, selection.to_json().pretty() + "\n")?;
+    }
+    Ok(())
+}
 
+/// Writes the sha256 manifest of the outputs which were written.
+fn write_checksums(options: &Cli, path: &Path) -> Result<(), Box<dyn Error>> {
+    let web = options
+        .web
+        .as_ref()
+        .map(|dir| dir.clone().unwrap_or_else(|| PathBuf::from("web")));
+    let artifacts: Vec<&PathBuf> = vec![
+        &options.output,
+        &options.side_by_side,
+        &web,
+        &options.summary_json,
+        &options.protobuf,
+        &options.blame,
+        &options.badge,
+        &options.badge_json,
+        &options.test_clusters,
+        &options.dead_code,
+        &options.minimal_tests,
+        &options.verdict,
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|artifact| artifact.exists())
+    .collect();
+    info!(
+        "Writing checksums of {} outputs to {:?}",
+        artifacts.len(),
+        path
+    );
+    checksum::write_manifest(path, &artifacts)?;
     Ok(())
 }
+
+/// Runs the diff, the exit code is a failure if a check failed.
+fn diff(options: Cli) -> Result<ExitCode, Box<dyn Error>> {
+    #[cfg(feature = "otel")]
+    let (started, timer) = (SystemTime::now(), Instant::now());
+    if let Some(path) = &options.verify_checksums {
+        verify_checksums(path)?;
+    }
+
+    let ci = CiEnv::detect();
+    if let Some(ci) = &ci {
+        debug!("Detected CI environment {:?}", ci);
+    }
+
+    #[cfg(any(feature = "http", feature = "github"))]
+    let remote = RemoteOptions {
+        retries: options.retries,
+        backoff: Duration::try_from_secs_f64(options.retry_backoff)?,
+        timeout: options
+            .timeout
+            .map(Duration::try_from_secs_f64)
+            .transpose()?,
+        proxy: options.proxy.clone(),
+    };
+
+    #[cfg(feature = "github")]
+    let mut options = options;
+    // The downloaded artifact is removed with the directory at the return
+    #[cfg(feature = "github")]
+    let _artifact_dir = download_baseline_artifact(&mut options, ci.as_ref(), &remote)?;
+
+    info!("Processing diff for two lcov files: {:?}", options.files);
+    // The baseline notes are verified once they're found
+    if let (Some(key), None) = (&options.baseline_public_key, &options.baseline_note) {
+        verify_baseline(&options, key)?;
+    }
+    let mut timings = Timings::new();
+
+    let mut write_options = WriteOptions::default();
+    for what in &options.lcov_omit {
+        write_options.omit(what)?;
+    }
+    let notes = Notes {
+        repo: options.src.clone(),
+        notes_ref: options.notes_ref.clone(),
+    };
+    let mut inputs = load_inputs(&options, &load_options_of(&options), &notes)?;
+    if options.save_note {
+        info!("Saving coverage note of {}", options.commit);
+        let pull_request = options
+            .pull_request
+            .or_else(|| ci.as_ref().and_then(|ci| ci.pull_request));
+        notes.save_with(&options.commit, &inputs.first, pull_request)?;
+    }
+    timings.lap(
+        "load",
+        timings::records(&inputs.first) + timings::records(&inputs.second),
+    );
+    let (merge_time, merged_records) = inputs.merge;
+    timings.split_last("merge", merge_time, merged_records);
+
+    remap(&options, &mut inputs)?;
+    let base = options.base.clone().or_else(|| ci.as_ref()?.base());
+    let changes = git_changes(&options, base.as_deref())?;
+    if let (true, Some(changes)) = (options.follow_renames, &changes) {
+        inputs.second = changes.follow_renames(&inputs.second);
+    }
+    timings.lap(
+        "remap",
+        timings::records(&inputs.first) + timings::records(&inputs.second),
+    );
+
+    if let Some(name) = &options.shard {
+        keep_shard(&options, name, &mut inputs)?;
+    }
+    empty_sections(&options, &mut inputs.first, "first file");
+    empty_sections(&options, &mut inputs.second, "second file");
+
+    let (numbers, json_numbers) = number_formats(&options)?;
+    if options.summary_only {
+        print!(
+            "{}",
+            Totals::new(&inputs.second, &inputs.first).text(&numbers)
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let shape = fingerprint(&inputs.first);
+    if shape == fingerprint(&inputs.second) {
+        info!("Both files have the same coverage (fingerprint {})", shape);
+    }
+    let diff_options = if options.large_input {
+        DiffOptions::large_input()
+    } else {
+        DiffOptions::default()
+    };
+    let mut processors: Vec<Box<dyn PostProcessor>> = Vec::new();
+    if options.drop_zeros {
+        processors.push(Box::new(DropZeros));
+    }
+    let mut report = diff_and_process(&inputs.first, &inputs.second, &diff_options, &processors)?;
+    empty_sections(&options, &mut report, "diff");
+    let diff_records = timings::records(&report);
+    timings.lap("diff", diff_records);
+
+    let permalink = resolve_permalink(&options, ci.as_ref())?;
+    if let Some(dir) = &options.side_by_side {
+        info!("Writing side-by-side html view to {:?}", dir);
+        let mut side_by_side = SideBySide::new(&inputs.second, &inputs.first)
+            .baseline_root(&options.baseline_src)
+            .current_root(&options.src)
+            .numbers(numbers);
+        if let Some(permalink) = &permalink {
+            side_by_side = side_by_side.permalink(permalink.clone());
+        }
+        if let Some(depth) = options.directory_depth {
+            side_by_side = side_by_side.directory_depth(depth);
+        }
+        side_by_side.write(dir)?;
+    }
+
+    let comparison = compare(&options, &inputs, changes.as_ref(), base.as_deref())?;
+    for (path, line) in &options.explain {
+        eprint!(
+            "{}",
+            explain::explain(
+                &inputs.second,
+                &inputs.first,
+                Some(&comparison),
+                path,
+                *line
+            )
+        );
+    }
+    let findings = collect_findings(&options, &inputs, &comparison);
+    let thresholds = Thresholds {
+        min_lines: options.min_lines,
+        max_drop: options.max_drop,
+    };
+    let violations = thresholds.check(&comparison);
+    timings.lap("post-process", findings.len() + violations.len());
+
+    let packages = crate_comparisons(&options, &inputs)?;
+    let teams = owner_comparisons(&options, &inputs)?;
+    write_artifacts(&options, &comparison, &violations)?;
+    let markdown = Markdown {
+        max_files: options.max_files,
+        max_lines: options.max_lines,
+        max_bytes: options.max_bytes,
+        threshold: options.file_threshold,
+        permalink: permalink.clone(),
+        directory_depth: options.directory_depth,
+        top: options.top,
+        rank: options.rank,
+        weighted: weighted_coverage(&options, &inputs)?,
+        snippets: snippets(&options),
+        numbers,
+    };
+    let outcome = Outcome {
+        inputs,
+        permalink,
+        report,
+        comparison,
+        findings,
+        violations,
+        thresholds,
+        packages,
+        teams,
+        markdown,
+        numbers,
+        json_numbers,
+        write_options,
+    };
+    let mut failures = check_failures(&options, &outcome);
+
+    let rendered = render_diff(&options, &outcome)?;
+    write_output(&options, &outcome, &rendered)?;
+    #[cfg(feature = "http")]
+    post_webhooks(&options, &outcome, &rendered, &remote)?;
+    #[cfg(feature = "github")]
+    if let Some(number) = options.github_pr {
+        comment_pull_request(&options, &outcome, number, &remote)?;
+    }
+    #[cfg(feature = "otel")]
+    if let Some(endpoint) = &options.otel_endpoint {
+        let traceparent = env::var("TRACEPARENT").ok();
+        let mut run = otel::Run::new(started, traceparent.as_deref());
+        run.duration = timer.elapsed();
+        export_run(&options, &outcome, endpoint, run, &remote)?;
+    }
+
+    if let Some(path) = &options.debt {
+        failures.extend(debt_failures(&options, path, &outcome.inputs.first)?);
+    }
+    write_test_reports(&options, &outcome.inputs.first)?;
+    if let Some(path) = &options.verdict {
+        info!("Writing verdict to {:?}", path);
+        let verdict = render::verdict::render(
+            &outcome.comparison,
+            &outcome.violations,
+            &failures,
+            &outcome.json_numbers,
+            outcome.permalink.as_ref(),
+        );
+        fs::write(path, verdict.pretty() + "\n")?;
+    }
+    if let Some(path) = &options.checksums {
+        write_checksums(&options, path)?;
+    }
+
+    timings.lap("write", diff_records);
+    for line in timings.to_string().lines() {
+        debug!("{}", line);
+    }
+
+    if !outcome.violations.is_empty() || !failures.is_empty() {
+        for violation in &outcome.violations {
+            error!("{}", violation.message(&outcome.numbers));
+        }
+        for (_, message) in &failures {
+            error!("{}", message);
+        }
+        // Returned rather than exiting, so the temporary directories are removed
+        return Ok(ExitCode::FAILURE);
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,102 @@
+//! Settings shared by the requests made with `curl` and `gh`: the retries with exponential
+//! backoff, the timeout of every attempt and the proxy.
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::warn;
+
+#[derive(Debug, Clone, Default)]
+pub struct RemoteOptions {
+    /// Attempts made after the failed first one.
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every next one.
+    pub backoff: Duration,
+    /// Time limit of every attempt, the command is killed after it.
+    pub timeout: Option<Duration>,
+    /// Proxy url, passed to the command as `HTTPS_PROXY` and `HTTP_PROXY`.
+    pub proxy: Option<String>,
+}
+
+impl RemoteOptions {
+    /// Runs the program with the input on stdin until it succeeds or the retries are spent,
+    /// returns the stdout. `what` names the request in the errors.
+    pub fn run(
+        &self,
+        what: &str,
+        program: &str,
+        args: &[&str],
+        input: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut attempt = 0;
+        loop {
+            match self.attempt(what, program, args, input) {
+                Ok(output) => return Ok(output),
+                Err(e) if attempt < self.retries => {
+                    let delay = self.backoff * 2u32.saturating_pow(attempt);
+                    warn!("{}, retrying in {:?}", e, delay);
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn attempt(
+        &self,
+        what: &str,
+        program: &str,
+        args: &[&str],
+        input: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped());
+        if let Some(proxy) = &self.proxy {
+            for var in ["HTTPS_PROXY", "HTTP_PROXY", "https_proxy", "http_proxy"] {
+                command.env(var, proxy);
+            }
+        }
+        let mut child = command.spawn()?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let input = input.to_vec();
+        let writer = thread::spawn(move || stdin.write_all(&input));
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let reader = thread::spawn(move || {
+            let mut output = Vec::new();
+            stdout.read_to_end(&mut output).map(|_| output)
+        });
+        let status = match self.timeout {
+            Some(timeout) => {
+                let deadline = Instant::now() + timeout;
+                loop {
+                    if let Some(status) = child.try_wait()? {
+                        break status;
+                    }
+                    if Instant::now() >= deadline {
+                        child.kill()?;
+                        child.wait()?;
+                        return Err(format!("{} timed out after {:?}", what, timeout).into());
+                    }
+                    thread::sleep(Duration::from_millis(50));
+                }
+            }
+            None => child.wait()?,
+        };
+        let written = writer.join().expect("stdin writer panicked");
+        let output = reader.join().expect("stdout reader panicked")?;
+        if !status.success() {
+            return Err(format!("{} failed: {}", what, status).into());
+        }
+        // A command which succeeds without reading all of its input closes the pipe early
+        match written {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(e.into()),
+            _ => Ok(output),
+        }
+    }
+}
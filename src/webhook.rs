@@ -0,0 +1,61 @@
+//! Json webhooks, requests are made with `curl`.
+//!
+//! The webhook url is the credential, so it's passed to `curl` with its config on stdin rather
+//! than the arguments other users see in `ps`, and only its host is logged.
+use std::error::Error;
+
+use log::{debug, info};
+
+use crate::remote::RemoteOptions;
+
+/// Posts the json body, fails on non-2xx responses.
+pub fn post_json(url: &str, body: &str, remote: &RemoteOptions) -> Result<(), Box<dyn Error>> {
+    info!("Posting to the webhook");
+    debug!("curl POST to {}", host(url));
+    // `data-raw` doesn't read a file of a body starting with `@`
+    let config = format!(
+        "url = \"{}\"\nheader = \"Content-Type: application/json\"\ndata-raw = \"{}\"\n",
+        quote(url),
+        quote(body)
+    );
+    remote.run(
+        "webhook request",
+        "curl",
+        &[
+            "--silent",
+            "--show-error",
+            "--fail",
+            "-X",
+            "POST",
+            "--config",
+            "-",
+        ],
+        config.as_bytes(),
+    )?;
+    Ok(())
+}
+
+/// Host of the url, without the user info.
+fn host(url: &str) -> &str {
+    let authority = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = authority.split(['/', '?', '#']).next().unwrap_or_default();
+    authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host)
+}
+
+/// Escapes the value for a double quoted parameter of a curl config.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted
+}